@@ -3,17 +3,25 @@ use std::{
     fmt::Display,
     fs::OpenOptions,
     num::NonZero,
+    ops::Range,
     os::raw::c_int,
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use config::{Config, FileFormat};
 use graft::{
+    core::PageCount,
+    local::mirror_storage::MirrorMode,
     remote::RemoteConfig,
+    rt::{event_log::EventLogDurability, runtime::CorruptionPolicy},
     setup::{GraftConfig, setup_graft},
 };
-use graft_sqlite::vfs::GraftVfs;
+use graft_sqlite::{
+    file::vol_file::{DEFAULT_IGNORED_HEADER_RANGES, DEFAULT_SYNC_TIMEOUT, IdleReadConsistency},
+    vfs::GraftVfs,
+};
 use graft_tracing::{SubscriberInitExt, TracingConsumer, setup_tracing_with_writer};
 use serde::Deserialize;
 use sqlite_plugin::{
@@ -28,6 +36,14 @@ fn default_data_dir() -> PathBuf {
         .data_dir
 }
 
+fn default_sync_timeout() -> u64 {
+    DEFAULT_SYNC_TIMEOUT.as_secs()
+}
+
+fn default_ignored_header_ranges() -> Vec<Range<usize>> {
+    DEFAULT_IGNORED_HEADER_RANGES.to_vec()
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ExtensionConfig {
     remote: RemoteConfig,
@@ -43,6 +59,107 @@ pub struct ExtensionConfig {
     /// if set, specifies the autosync interval in seconds
     #[serde(default = "Option::default")]
     autosync: Option<NonZero<u64>>,
+
+    /// if set, warn when the average number of pages per commit drops below
+    /// this threshold
+    #[serde(default = "Option::default")]
+    write_amp_threshold: Option<f64>,
+
+    /// if set, read ahead this many pages when a sequential scan is detected
+    #[serde(default = "Option::default")]
+    read_ahead: Option<NonZero<u32>>,
+
+    /// wait up to this many milliseconds for another connection to release
+    /// the reserved lock before returning `SQLITE_BUSY`; 0 returns busy
+    /// immediately
+    #[serde(default = "u64::default")]
+    lock_wait_ms: u64,
+
+    /// defer resolving the Volume behind a database file until `SQLite`
+    /// first locks or reads it
+    #[serde(default = "bool::default")]
+    lazy_open: bool,
+
+    /// pull and push pragmas fail with `SQLITE_BUSY` rather than waiting
+    /// longer than this many seconds on the remote; 0 waits indefinitely
+    #[serde(default = "default_sync_timeout")]
+    sync_timeout: u64,
+
+    /// header byte ranges which may change without causing a commit
+    #[serde(default = "default_ignored_header_ranges")]
+    ignored_header_ranges: Vec<Range<usize>>,
+
+    /// pin the snapshot observed by reads made without a lock until `SQLite`
+    /// next acquires a lock
+    #[serde(default = "bool::default")]
+    pin_idle_snapshot: bool,
+
+    /// if set, reads fail with `SQLITE_BUSY` rather than waiting longer than
+    /// this many seconds for a page to be fetched from the remote
+    #[serde(default = "Option::default")]
+    fetch_timeout: Option<NonZero<u64>>,
+
+    /// if set, fetch the remote log in batches of at most this many commits
+    #[serde(default = "Option::default")]
+    max_commits_per_fetch: Option<NonZero<usize>>,
+
+    /// controls how reads handle corrupt pages in the local cache
+    #[serde(default = "CorruptionPolicy::default")]
+    corruption_policy: CorruptionPolicy,
+
+    /// checkpoint a Volume once it accumulates more than this many commits
+    /// since its last checkpoint; 0 disables automatic checkpoints
+    #[serde(default = "u64::default")]
+    compact_after_commits: u64,
+
+    /// verify each pushed commit against the remote before clearing the
+    /// local commits
+    #[serde(default = "bool::default")]
+    verify_push: bool,
+
+    /// pad each Segment frame pushed to the remote to a multiple of this many
+    /// bytes; 0 disables padding
+    #[serde(default = "u64::default")]
+    frame_alignment: u64,
+
+    /// the byte which fills pages that were never written, such as the pages
+    /// skipped when a Volume is extended, unless the Volume has its own
+    #[serde(default = "u8::default")]
+    fill_byte: u8,
+
+    /// how many times hydrating a Volume retries after a failed fetch
+    #[serde(default = "u32::default")]
+    hydrate_retries: u32,
+
+    /// how many decoded pages to cache in memory and share between
+    /// connections; 0 disables the page cache
+    #[serde(default = "usize::default")]
+    page_cache_pages: usize,
+
+    /// the maximum number of pages in each Volume; 0 disables the limit
+    #[serde(default = "u32::default")]
+    max_pages: u32,
+
+    /// if set, append a record of every commit to this file
+    #[serde(default = "Option::default")]
+    event_log: Option<PathBuf>,
+
+    /// controls when records appended to the event log reach the disk
+    #[serde(default = "EventLogDurability::default")]
+    event_log_durability: EventLogDurability,
+
+    /// if set, mirror every local commit to a second data directory at this
+    /// path
+    #[serde(default = "Option::default")]
+    mirror_dir: Option<PathBuf>,
+
+    /// controls whether local commits wait for the mirror to be durable
+    #[serde(default = "MirrorMode::default")]
+    mirror_mode: MirrorMode,
+
+    /// store a single copy of identical pages in the data directory
+    #[serde(default = "bool::default")]
+    dedup_pages: bool,
 }
 
 impl ExtensionConfig {
@@ -51,6 +168,42 @@ impl ExtensionConfig {
             remote: self.remote.clone(),
             data_dir: self.data_dir.clone(),
             autosync: self.autosync,
+            write_amp_threshold: self.write_amp_threshold,
+            fetch_timeout: self.fetch_timeout,
+            max_commits_per_fetch: self.max_commits_per_fetch,
+            corruption_policy: self.corruption_policy,
+            compact_after_commits: self.compact_after_commits,
+            verify_push: self.verify_push,
+            frame_alignment: self.frame_alignment,
+            fill_byte: self.fill_byte,
+            hydrate_retries: self.hydrate_retries,
+            page_cache_pages: self.page_cache_pages,
+            max_pages: self.max_pages,
+            event_log: self.event_log.clone(),
+            event_log_durability: self.event_log_durability,
+            mirror_dir: self.mirror_dir.clone(),
+            mirror_mode: self.mirror_mode,
+            dedup_pages: self.dedup_pages,
+        }
+    }
+
+    pub fn read_ahead(&self) -> Option<PageCount> {
+        self.read_ahead.map(|window| PageCount::new(window.get()))
+    }
+
+    pub fn lock_wait(&self) -> Option<Duration> {
+        (self.lock_wait_ms > 0).then(|| Duration::from_millis(self.lock_wait_ms))
+    }
+
+    pub fn sync_timeout(&self) -> Option<Duration> {
+        (self.sync_timeout > 0).then(|| Duration::from_secs(self.sync_timeout))
+    }
+
+    pub fn idle_read_consistency(&self) -> IdleReadConsistency {
+        if self.pin_idle_snapshot {
+            IdleReadConsistency::Pinned
+        } else {
+            IdleReadConsistency::Latest
         }
     }
 }
@@ -95,7 +248,66 @@ fn write_err_msg(
     Ok(())
 }
 
-fn resolve_config() -> Result<ExtensionConfig, InitErr> {
+/// Where the extension loads its config from. Environment variables prefixed
+/// with `GRAFT_` are applied on top of every source.
+#[derive(Debug, Clone, Default)]
+pub enum ConfigSource {
+    /// Use the first config file found at `$GRAFT_CONFIG`, `./graft.toml`, or
+    /// `graft.toml` in the platform config directory. Missing files are
+    /// skipped.
+    #[default]
+    Search,
+
+    /// Load config from this TOML file, which must exist
+    Path(PathBuf),
+
+    /// Parse config from this TOML string
+    Toml(String),
+}
+
+/// The config source used the next time the extension is initialized
+static CONFIG_SOURCE: Mutex<ConfigSource> = Mutex::new(ConfigSource::Search);
+
+/// Sets where the extension loads its config from when it's initialized.
+/// Call this before loading or statically registering the extension.
+pub fn set_config_source(source: ConfigSource) {
+    *CONFIG_SOURCE.lock().expect("config source mutex poisoned") = source;
+}
+
+impl ConfigSource {
+    pub fn resolve(&self) -> Result<ExtensionConfig, config::ConfigError> {
+        let mut config = Config::builder();
+        match self {
+            ConfigSource::Search => {
+                if let Some(path) = search_config_path() {
+                    config = config
+                        .add_source(config::File::new(&path, FileFormat::Toml).required(true));
+                }
+            }
+            ConfigSource::Path(path) => {
+                config = config.add_source(
+                    config::File::from(path.as_path())
+                        .format(FileFormat::Toml)
+                        .required(true),
+                );
+            }
+            ConfigSource::Toml(toml) => {
+                config = config.add_source(config::File::from_str(toml, FileFormat::Toml));
+            }
+        }
+
+        config = config.add_source(
+            config::Environment::with_prefix("GRAFT")
+                .prefix_separator("_")
+                .separator("__"),
+        );
+
+        config.build()?.try_deserialize()
+    }
+}
+
+/// Returns the first config file found in the default search locations
+fn search_config_path() -> Option<String> {
     // a priority ordered list of config paths, the first path found will be used
     let paths = [
         std::env::var("GRAFT_CONFIG").ok().map(|s| s.into()),
@@ -105,27 +317,19 @@ fn resolve_config() -> Result<ExtensionConfig, InitErr> {
     ];
 
     // find the first path that is Some and resolves to a file
-    let path = paths
+    paths
         .into_iter()
         .flatten()
         .find(|p: &PathBuf| p.is_file())
-        .and_then(|p| p.to_str().map(|s| s.to_string()));
-
-    // build the config
-    let mut config = Config::builder();
-
-    // add the config file if it exists
-    if let Some(path) = path {
-        config = config.add_source(config::File::new(&path, FileFormat::Toml).required(true));
-    }
-
-    config = config.add_source(
-        config::Environment::with_prefix("GRAFT")
-            .prefix_separator("_")
-            .separator("__"),
-    );
+        .and_then(|p| p.to_str().map(|s| s.to_string()))
+}
 
-    Ok(config.build()?.try_deserialize()?)
+fn resolve_config() -> Result<ExtensionConfig, InitErr> {
+    let source = CONFIG_SOURCE
+        .lock()
+        .expect("config source mutex poisoned")
+        .clone();
+    Ok(source.resolve()?)
 }
 
 fn setup_logger(logger: SqliteLogger) {
@@ -161,7 +365,13 @@ fn dynamic_init(p_api: *mut sqlite_plugin::sqlite3_api_routines) -> Result<(), I
 
     // initialize graft
     let runtime = setup_graft(config.graft_config())?;
-    let vfs = GraftVfs::new(runtime);
+    let vfs = GraftVfs::new(runtime)
+        .with_read_ahead(config.read_ahead())
+        .with_lock_wait(config.lock_wait())
+        .with_lazy_open(config.lazy_open)
+        .with_sync_timeout(config.sync_timeout())
+        .with_idle_read_consistency(config.idle_read_consistency())
+        .with_ignored_header_ranges(config.ignored_header_ranges.clone())?;
     let opts = RegisterOpts { make_default: config.make_default };
 
     // Safety: `p_api` must be a valid, aligned pointer to a `sqlite3_api_routines` struct
@@ -204,7 +414,13 @@ fn graft_static_init_inner() -> Result<(), InitErr> {
 
     // initialize graft
     let runtime = setup_graft(config.graft_config())?;
-    let vfs = GraftVfs::new(runtime);
+    let vfs = GraftVfs::new(runtime)
+        .with_read_ahead(config.read_ahead())
+        .with_lock_wait(config.lock_wait())
+        .with_lazy_open(config.lazy_open)
+        .with_sync_timeout(config.sync_timeout())
+        .with_idle_read_consistency(config.idle_read_consistency())
+        .with_ignored_header_ranges(config.ignored_header_ranges.clone())?;
     let opts = RegisterOpts { make_default: config.make_default };
 
     // Safety: `p_api` must be a valid, aligned pointer to a `sqlite3_api_routines` struct
@@ -226,3 +442,53 @@ fn graft_static_init_inner() -> Result<(), InitErr> {
 pub unsafe extern "C" fn graft_static_init() -> c_int {
     graft_static_init_inner().map_or_else(|err| err.0, |_| 0)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::ConfigSource;
+
+    const TOML: &str = r#"
+        data_dir = "/tmp/graft-config-test"
+        autosync = 5
+
+        [remote]
+        type = "memory"
+    "#;
+
+    #[test]
+    fn test_config_from_toml() {
+        let config = ConfigSource::Toml(TOML.into()).resolve().unwrap();
+        assert_eq!(config.data_dir, PathBuf::from("/tmp/graft-config-test"));
+        assert_eq!(config.autosync.map(|s| s.get()), Some(5));
+    }
+
+    #[test]
+    fn test_config_from_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("custom.toml");
+        std::fs::write(&path, TOML).unwrap();
+
+        let config = ConfigSource::Path(path).resolve().unwrap();
+        assert_eq!(config.data_dir, PathBuf::from("/tmp/graft-config-test"));
+        assert_eq!(config.autosync.map(|s| s.get()), Some(5));
+
+        // an explicit path must exist
+        let missing = ConfigSource::Path(dir.path().join("missing.toml"));
+        assert!(missing.resolve().is_err());
+    }
+
+    #[test]
+    fn test_compact_after_commits() {
+        let config = ConfigSource::Toml(TOML.into()).resolve().unwrap();
+        assert_eq!(config.graft_config().compact_after_commits, 0);
+
+        // 0 disables automatic checkpoints rather than failing to parse
+        for (value, expected) in [(0, 0), (1000, 1000)] {
+            let toml = format!("compact_after_commits = {value}\n{TOML}");
+            let config = ConfigSource::Toml(toml).resolve().unwrap();
+            assert_eq!(config.graft_config().compact_after_commits, expected);
+        }
+    }
+}
@@ -8,6 +8,8 @@ use vol_file::VolFile;
 use crate::vfs::ErrCtx;
 
 pub mod mem_file;
+pub mod read_ahead;
+pub mod read_coalesce;
 pub mod vol_file;
 
 #[enum_dispatch]
@@ -0,0 +1,121 @@
+use std::ops::RangeInclusive;
+
+use graft::core::{PageCount, PageIdx};
+
+/// Detects sequential page reads and decides when to prefetch the next window
+/// of pages. `SQLite` issues one read per page while scanning a table, so
+/// loading the pages ahead of the scan in a single batch avoids a remote
+/// round trip per page.
+#[derive(Debug, Clone)]
+pub struct ReadAhead {
+    /// the number of pages to prefetch
+    window: PageCount,
+    /// the last page read
+    last_read: Option<PageIdx>,
+    /// the last page covered by the current read-ahead window
+    prefetched: Option<PageIdx>,
+}
+
+impl ReadAhead {
+    pub fn new(window: PageCount) -> Self {
+        Self {
+            window,
+            last_read: None,
+            prefetched: None,
+        }
+    }
+
+    /// Observes a read of `pageidx` from a Volume containing `page_count`
+    /// pages. Returns the range of pages to prefetch if this read continues a
+    /// sequential scan which has reached the end of the current window.
+    pub fn observe(
+        &mut self,
+        pageidx: PageIdx,
+        page_count: PageCount,
+    ) -> Option<RangeInclusive<PageIdx>> {
+        let last_read = self.last_read.replace(pageidx);
+        if last_read == Some(pageidx) {
+            // SQLite may read a page more than once, for example to read the
+            // database header
+            return None;
+        }
+        if last_read.map(PageIdx::saturating_next) != Some(pageidx) {
+            // random access resets the read-ahead window
+            self.prefetched = None;
+            return None;
+        }
+        if self
+            .prefetched
+            .is_some_and(|prefetched| pageidx < prefetched)
+        {
+            // we are still within the current window
+            return None;
+        }
+
+        let start = pageidx.saturating_next();
+        let end = pageidx
+            .saturating_add(self.window.to_u32())
+            .min(page_count.last_pageidx()?);
+        if start <= pageidx || start > end {
+            return None;
+        }
+        self.prefetched = Some(end);
+        Some(start..=end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graft::{core::PageCount, pageidx};
+
+    use super::ReadAhead;
+
+    #[test]
+    fn test_sequential_reads_trigger_prefetch() {
+        let page_count = PageCount::new(20);
+        let mut read_ahead = ReadAhead::new(PageCount::new(8));
+
+        // the first read is not yet sequential
+        assert_eq!(read_ahead.observe(pageidx!(1), page_count), None);
+
+        // the second consecutive read starts a window
+        assert_eq!(
+            read_ahead.observe(pageidx!(2), page_count),
+            Some(pageidx!(3)..=pageidx!(10))
+        );
+
+        // repeated and in-window reads don't prefetch
+        assert_eq!(read_ahead.observe(pageidx!(2), page_count), None);
+        for i in 3..10 {
+            assert_eq!(read_ahead.observe(pageidx!(i), page_count), None);
+        }
+
+        // reaching the end of the window prefetches the next window, which is
+        // clamped to the page count
+        assert_eq!(
+            read_ahead.observe(pageidx!(10), page_count),
+            Some(pageidx!(11)..=pageidx!(18))
+        );
+        for i in 11..18 {
+            assert_eq!(read_ahead.observe(pageidx!(i), page_count), None);
+        }
+        assert_eq!(
+            read_ahead.observe(pageidx!(18), page_count),
+            Some(pageidx!(19)..=pageidx!(20))
+        );
+
+        // there is nothing left to prefetch at the end of the volume
+        assert_eq!(read_ahead.observe(pageidx!(19), page_count), None);
+        assert_eq!(read_ahead.observe(pageidx!(20), page_count), None);
+    }
+
+    #[test]
+    fn test_random_reads_dont_prefetch() {
+        let page_count = PageCount::new(100);
+        let mut read_ahead = ReadAhead::new(PageCount::new(8));
+
+        for i in [7, 3, 42, 41, 90, 12, 57, 13, 99] {
+            assert_eq!(read_ahead.observe(pageidx!(i), page_count), None);
+        }
+    }
+}
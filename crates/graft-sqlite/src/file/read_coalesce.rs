@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeMap,
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
+
+use graft::{
+    GraftErr,
+    core::{PageCount, PageIdx, page::Page, pageidx::PageIdxRangeExt},
+    volume_reader::VolumeRead,
+};
+
+/// How many pages a coalesced read loads
+pub const COALESCE_PAGES: PageCount = PageCount::new(8);
+
+/// Reads of adjacent pages are only coalesced if they arrive within this
+/// window, and coalesced pages are discarded once it elapses
+pub const COALESCE_WINDOW: Duration = Duration::from_millis(10);
+
+/// Detects adjacent page reads issued in quick succession, and serves them
+/// from a single batched read. `SQLite`'s pager reads one page at a time, so
+/// without coalescing each read of a clustered access pattern pays for a
+/// separate storage lookup.
+///
+/// Pages loaded by a batch are held only until they are read or the window
+/// elapses, so coalescing never keeps pages around which `SQLite` didn't ask
+/// for.
+#[derive(Debug, Clone)]
+pub struct ReadCoalescer {
+    /// the last page read and when it was read
+    last_read: Option<(PageIdx, Instant)>,
+    /// pages loaded by the last batch which have not been read yet
+    batch: BTreeMap<PageIdx, Page>,
+    /// when the last batch was loaded
+    batched_at: Option<Instant>,
+}
+
+impl Default for ReadCoalescer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReadCoalescer {
+    pub fn new() -> Self {
+        Self {
+            last_read: None,
+            batch: BTreeMap::new(),
+            batched_at: None,
+        }
+    }
+
+    /// Discards the pages loaded by the last batch. Must be called whenever
+    /// the pages visible to the reader may have changed.
+    pub fn clear(&mut self) {
+        self.last_read = None;
+        self.batch.clear();
+        self.batched_at = None;
+    }
+
+    /// Reads `pageidx` from `reader`, coalescing it with the following pages
+    /// if it continues a run of adjacent reads.
+    pub fn read(
+        &mut self,
+        reader: &impl VolumeRead,
+        pageidx: PageIdx,
+        now: Instant,
+    ) -> Result<Page, GraftErr> {
+        if let Some(page) = self.take(pageidx, now) {
+            return Ok(page);
+        }
+        let Some(range) = self.observe(pageidx, reader.page_count(), now) else {
+            return reader.read_page(pageidx);
+        };
+        let batched = (pageidx.saturating_next()..=*range.end()).iter();
+        let mut pages = reader.read_pages(range)?.into_iter();
+        let page = pages
+            .next()
+            .expect("BUG: batch must contain the requested page");
+        self.batch = batched.zip(pages).collect();
+        self.batched_at = Some(now);
+        Ok(page)
+    }
+
+    /// Returns the page if it was loaded by a batch within the window.
+    fn take(&mut self, pageidx: PageIdx, now: Instant) -> Option<Page> {
+        let batched_at = self.batched_at?;
+        if now.saturating_duration_since(batched_at) > COALESCE_WINDOW {
+            self.batch.clear();
+            self.batched_at = None;
+            return None;
+        }
+        let page = self.batch.remove(&pageidx)?;
+        self.last_read = Some((pageidx, now));
+        Some(page)
+    }
+
+    /// Observes a read of `pageidx` from a Volume containing `page_count`
+    /// pages which missed the current batch. Returns the range of pages to
+    /// load in a single batch if this read continues a run of adjacent reads.
+    fn observe(
+        &mut self,
+        pageidx: PageIdx,
+        page_count: PageCount,
+        now: Instant,
+    ) -> Option<RangeInclusive<PageIdx>> {
+        let last_read = self.last_read.replace((pageidx, now));
+        let (last, at) = last_read?;
+        if last.saturating_next() != pageidx || now.saturating_duration_since(at) > COALESCE_WINDOW
+        {
+            return None;
+        }
+        let end = pageidx
+            .saturating_add(COALESCE_PAGES.to_u32() - 1)
+            .min(page_count.last_pageidx()?);
+        (pageidx < end).then_some(pageidx..=end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use graft::{core::page::PAGESIZE, snapshot::Snapshot};
+
+    use super::*;
+
+    /// A Volume whose pages are filled with their page index, which counts
+    /// how many lookups it served.
+    struct CountingReader {
+        snapshot: Snapshot,
+        lookups: Cell<usize>,
+    }
+
+    impl VolumeRead for CountingReader {
+        fn snapshot(&self) -> &Snapshot {
+            &self.snapshot
+        }
+
+        fn page_count(&self) -> PageCount {
+            self.snapshot.page_count
+        }
+
+        fn read_page(&self, pageidx: PageIdx) -> Result<Page, GraftErr> {
+            self.lookups.set(self.lookups.get() + 1);
+            Ok(filled(pageidx))
+        }
+
+        fn read_pages(&self, range: RangeInclusive<PageIdx>) -> Result<Vec<Page>, GraftErr> {
+            self.lookups.set(self.lookups.get() + 1);
+            Ok(range.iter().map(filled).collect())
+        }
+    }
+
+    fn filled(pageidx: PageIdx) -> Page {
+        Page::from(&[pageidx.to_u32() as u8; PAGESIZE.as_usize()])
+    }
+
+    fn counting_reader(pages: u32) -> CountingReader {
+        let mut snapshot = Snapshot::empty();
+        snapshot.page_count = PageCount::new(pages);
+        CountingReader { snapshot, lookups: Cell::new(0) }
+    }
+
+    fn pageidx(n: u32) -> PageIdx {
+        PageIdx::try_from(n).unwrap()
+    }
+
+    #[test]
+    fn test_adjacent_reads_are_coalesced() {
+        let reader = counting_reader(20);
+        let mut coalescer = ReadCoalescer::new();
+        let now = Instant::now();
+
+        for i in 1..=20 {
+            let page = coalescer.read(&reader, pageidx(i), now).unwrap();
+            assert_eq!(page, filled(pageidx(i)));
+        }
+
+        // the first two reads detect the run, after which each batch serves
+        // up to COALESCE_PAGES reads
+        assert_eq!(reader.lookups.get(), 4);
+    }
+
+    #[test]
+    fn test_random_and_slow_reads_are_not_coalesced() {
+        let reader = counting_reader(100);
+        let mut coalescer = ReadCoalescer::new();
+        let now = Instant::now();
+
+        for i in [7, 3, 42, 41, 90, 12, 57, 13, 99] {
+            let page = coalescer.read(&reader, pageidx(i), now).unwrap();
+            assert_eq!(page, filled(pageidx(i)));
+        }
+        assert_eq!(reader.lookups.get(), 9);
+
+        // adjacent reads outside of the window are read one at a time
+        reader.lookups.set(0);
+        let mut now = now;
+        for i in 20..30 {
+            now += COALESCE_WINDOW * 2;
+            coalescer.read(&reader, pageidx(i), now).unwrap();
+        }
+        assert_eq!(reader.lookups.get(), 10);
+    }
+
+    #[test]
+    fn test_batched_pages_expire() {
+        let reader = counting_reader(20);
+        let mut coalescer = ReadCoalescer::new();
+        let now = Instant::now();
+
+        coalescer.read(&reader, pageidx(1), now).unwrap();
+        coalescer.read(&reader, pageidx(2), now).unwrap();
+        assert_eq!(reader.lookups.get(), 2);
+        assert_eq!(coalescer.batch.len(), COALESCE_PAGES.to_usize() - 1);
+
+        // once the window elapses, batched pages are discarded rather than
+        // served
+        let later = now + COALESCE_WINDOW * 2;
+        coalescer.read(&reader, pageidx(3), later).unwrap();
+        assert_eq!(reader.lookups.get(), 3);
+        assert!(coalescer.batch.is_empty());
+
+        // clearing discards the batch
+        coalescer.read(&reader, pageidx(4), later).unwrap();
+        assert!(!coalescer.batch.is_empty());
+        coalescer.clear();
+        assert!(coalescer.batch.is_empty());
+    }
+}
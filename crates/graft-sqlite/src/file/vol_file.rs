@@ -3,7 +3,9 @@ use std::{
     fmt::Debug,
     hash::{DefaultHasher, Hash, Hasher},
     mem,
-    sync::Arc,
+    ops::Range,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
 };
 
 use bytes::BytesMut;
@@ -19,16 +21,146 @@ use graft::{
     volume_writer::{VolumeWrite, VolumeWriter},
 };
 use parking_lot::{Mutex, MutexGuard};
-use sqlite_plugin::flags::{LockLevel, OpenOpts};
+use sqlite_plugin::flags::{CreateMode, LockLevel, OpenMode, OpenOpts};
+use thiserror::Error;
 
 use crate::vfs::ErrCtx;
 
-use super::VfsFile;
+use super::{VfsFile, read_ahead::ReadAhead, read_coalesce::ReadCoalescer};
 
 // The byte offset of the SQLite file change counter in the database file
 const FILE_CHANGE_COUNTER_OFFSET: usize = 24;
 const VERSION_VALID_FOR_NUMBER_OFFSET: usize = 92;
 
+/// The header byte ranges which SQLite updates on every transaction: the file
+/// change counter and the version valid for number. A write to the header page
+/// which only changes these ranges is ignored.
+pub const DEFAULT_IGNORED_HEADER_RANGES: [Range<usize>; 2] = [
+    FILE_CHANGE_COUNTER_OFFSET..FILE_CHANGE_COUNTER_OFFSET + 4,
+    VERSION_VALID_FOR_NUMBER_OFFSET..VERSION_VALID_FOR_NUMBER_OFFSET + 4,
+];
+
+/// The size of the SQLite database header at the start of the first page
+pub const SQLITE_HEADER_SIZE: usize = 100;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error(
+    "invalid ignored header range {0:?}: ranges must be non-empty and end within the {SQLITE_HEADER_SIZE} byte database header"
+)]
+pub struct InvalidHeaderRange(pub Range<usize>);
+
+/// Sorts the ignored header ranges by their start offset and merges ranges
+/// which overlap or touch. Fails if any range is empty or extends past the
+/// database header, as ignoring bytes outside the header would drop writes to
+/// the first page's b-tree.
+pub fn normalize_header_ranges(
+    mut ranges: Vec<Range<usize>>,
+) -> Result<Vec<Range<usize>>, InvalidHeaderRange> {
+    if let Some(invalid) = ranges
+        .iter()
+        .find(|range| range.is_empty() || range.end > SQLITE_HEADER_SIZE)
+    {
+        return Err(InvalidHeaderRange(invalid.clone()));
+    }
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    Ok(merged)
+}
+
+/// How long pull and push pragmas wait on the remote by default
+pub const DEFAULT_SYNC_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How many pending commits a Volume may accumulate before a commit with
+/// backpressure enabled waits for autosync to push them
+pub const DEFAULT_BACKPRESSURE_PENDING_COMMITS: u64 = 8;
+
+/// Controls which snapshot `SQLite` observes when it reads the file without
+/// holding a lock, as it does when reading the database header before
+/// starting a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdleReadConsistency {
+    /// Every unlocked read observes the latest snapshot, so consecutive reads
+    /// may observe different snapshots if another connection commits in
+    /// between.
+    #[default]
+    Latest,
+
+    /// The first unlocked read pins the latest snapshot, which every
+    /// unlocked read observes until `SQLite` next acquires a lock. This
+    /// prevents a torn view of the header when a commit lands partway
+    /// through reading it.
+    Pinned,
+}
+
+/// The maximum supported Volume size in bytes. Offsets at or beyond this
+/// limit are rejected with `ErrCtx::PageIndexOverflow`.
+const MAX_VOLUME_SIZE: u64 = PAGESIZE.as_u64() * PageCount::MAX.to_u32() as u64;
+
+/// Resolves the Volume referenced by `tag`. If the file was opened with
+/// permission to create it, a new Volume is created and tagged when the tag
+/// doesn't exist yet.
+pub(crate) fn resolve_tag(
+    runtime: &Runtime,
+    tag: &str,
+    opts: OpenOpts,
+) -> Result<VolumeId, ErrCtx> {
+    let can_create = matches!(
+        opts.mode(),
+        OpenMode::ReadWrite {
+            create: CreateMode::Create | CreateMode::MustCreate
+        }
+    );
+
+    if let Some(vid) = runtime.tag_get(tag)? {
+        Ok(vid)
+    } else if can_create {
+        let volume = runtime.volume_open(None, None, None)?;
+        runtime.tag_replace(tag, volume.vid.clone())?;
+        Ok(volume.vid)
+    } else {
+        Err(ErrCtx::TagNotFound)
+    }
+}
+
+/// Returns the `PageIdx` containing the given byte offset
+fn offset_to_pageidx(offset: usize) -> Result<PageIdx, ErrCtx> {
+    if offset as u64 >= MAX_VOLUME_SIZE {
+        return Err(ErrCtx::PageIndexOverflow { offset });
+    }
+    ((offset / PAGESIZE.as_usize()) + 1)
+        .try_into()
+        .map_err(|_| ErrCtx::PageIndexOverflow { offset })
+}
+
+/// Returns true if `data` and `existing` are equal outside of the `ignored`
+/// byte ranges, which must be normalized by `normalize_header_ranges`.
+fn unchanged_ignoring(data: &[u8], existing: &[u8], ignored: &[Range<usize>]) -> bool {
+    let mut start = 0;
+    for range in ignored {
+        let end = range.start.min(data.len());
+        if start < end && data[start..end] != existing[start..end] {
+            return false;
+        }
+        start = start.max(range.end);
+    }
+    start >= data.len() || data[start..] == existing[start..]
+}
+
+/// Acquires the reserved lock, waiting up to `wait` if it's held by another
+/// connection.
+fn acquire_reserved(reserved: &Mutex<()>, wait: Option<Duration>) -> Option<MutexGuard<'_, ()>> {
+    match wait {
+        Some(wait) => reserved.try_lock_for(wait),
+        None => reserved.try_lock(),
+    }
+}
+
 enum VolFileState {
     Idle,
     Shared { reader: VolumeReader },
@@ -65,18 +197,51 @@ impl Debug for VolFileState {
 pub struct VolFile {
     runtime: Runtime,
     pub tag: String,
-    pub vid: VolumeId,
+    /// the Volume backing this file; unset until first use if the file was
+    /// opened lazily
+    vid: OnceLock<VolumeId>,
     opts: OpenOpts,
 
     reserved: Arc<Mutex<()>>,
     state: VolFileState,
+
+    /// detects sequential scans in order to prefetch pages; disabled by default
+    read_ahead: Option<ReadAhead>,
+    /// serves adjacent page reads from a single batched read
+    coalescer: ReadCoalescer,
+
+    /// how long to wait for another connection to release the reserved lock
+    /// before failing with `ErrCtx::Busy`
+    lock_wait: Option<Duration>,
+    /// the maximum duration this connection may hold the reserved lock
+    lock_timeout: Option<Duration>,
+    /// when this connection acquired the reserved lock
+    reserved_at: Option<Instant>,
+    /// true if the current transaction exceeded the lock timeout
+    lock_timed_out: bool,
+
+    /// writes to the header page which only change these byte ranges are
+    /// ignored; sorted by start offset
+    ignored_header_ranges: Arc<[Range<usize>]>,
+
+    /// how long pull and push pragmas wait on the remote before failing
+    sync_timeout: Option<Duration>,
+
+    /// if set, each commit waits until the Volume has at most this many
+    /// pending commits
+    commit_backpressure: Option<u64>,
+
+    idle_consistency: IdleReadConsistency,
+    /// the snapshot pinned by the first unlocked read when idle reads are
+    /// pinned; released when SQLite acquires or releases a lock
+    idle_reader: Option<VolumeReader>,
 }
 
 impl Debug for VolFile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("VolFile")
             .field("tag", &self.tag)
-            .field("vid", &self.vid)
+            .field("vid", &self.vid.get())
             .field("state", &self.state)
             .finish()
     }
@@ -89,6 +254,30 @@ impl VolFile {
         vid: VolumeId,
         opts: OpenOpts,
         reserved: Arc<Mutex<()>>,
+    ) -> Self {
+        Self::with_vid(runtime, tag, OnceLock::from(vid), opts, reserved)
+    }
+
+    /// Opens a file whose tag is resolved to a Volume on first use rather
+    /// than when it is opened, so opening a connection which is never used
+    /// doesn't touch storage. If the tag doesn't exist and `opts` doesn't
+    /// allow creating it, the first lock, read, or pragma fails with
+    /// `ErrCtx::TagNotFound` rather than the open.
+    pub fn new_lazy(
+        runtime: Runtime,
+        tag: String,
+        opts: OpenOpts,
+        reserved: Arc<Mutex<()>>,
+    ) -> Self {
+        Self::with_vid(runtime, tag, OnceLock::new(), opts, reserved)
+    }
+
+    fn with_vid(
+        runtime: Runtime,
+        tag: String,
+        vid: OnceLock<VolumeId>,
+        opts: OpenOpts,
+        reserved: Arc<Mutex<()>>,
     ) -> Self {
         Self {
             runtime,
@@ -97,12 +286,85 @@ impl VolFile {
             opts,
             reserved,
             state: VolFileState::Idle,
+            read_ahead: None,
+            coalescer: ReadCoalescer::new(),
+            lock_wait: None,
+            lock_timeout: None,
+            reserved_at: None,
+            lock_timed_out: false,
+            ignored_header_ranges: DEFAULT_IGNORED_HEADER_RANGES.into(),
+            sync_timeout: Some(DEFAULT_SYNC_TIMEOUT),
+            commit_backpressure: None,
+            idle_consistency: IdleReadConsistency::Latest,
+            idle_reader: None,
+        }
+    }
+
+    /// Enables read-ahead: once sequential page reads are detected, the next
+    /// `window` pages are prefetched in a single batch.
+    pub fn with_read_ahead(self, window: Option<PageCount>) -> Self {
+        Self {
+            read_ahead: window.map(ReadAhead::new),
+            ..self
+        }
+    }
+
+    /// Waits up to `wait` for another connection to release the reserved
+    /// lock rather than immediately failing with `ErrCtx::Busy`.
+    pub fn with_lock_wait(self, wait: Option<Duration>) -> Self {
+        Self { lock_wait: wait, ..self }
+    }
+
+    /// Ignores writes to the header page which only change the given byte
+    /// ranges, which must be sorted by their start offset. Defaults to
+    /// `DEFAULT_IGNORED_HEADER_RANGES`.
+    pub fn with_ignored_header_ranges(self, ranges: Arc<[Range<usize>]>) -> Self {
+        Self { ignored_header_ranges: ranges, ..self }
+    }
+
+    /// Bounds how long pull and push pragmas wait on the remote before
+    /// failing with a retryable timeout. `None` waits indefinitely. Defaults
+    /// to `DEFAULT_SYNC_TIMEOUT`.
+    pub fn with_sync_timeout(self, timeout: Option<Duration>) -> Self {
+        Self { sync_timeout: timeout, ..self }
+    }
+
+    pub fn sync_timeout(&self) -> Option<Duration> {
+        self.sync_timeout
+    }
+
+    /// Configures which snapshot reads observe while no lock is held.
+    /// Defaults to `IdleReadConsistency::Latest`.
+    pub fn with_idle_read_consistency(self, consistency: IdleReadConsistency) -> Self {
+        Self { idle_consistency: consistency, ..self }
+    }
+
+    /// Returns a reader for an unlocked read, pinning its snapshot if idle
+    /// reads are pinned.
+    fn idle_reader(&mut self) -> Result<Cow<'_, VolumeReader>, ErrCtx> {
+        if self.idle_consistency == IdleReadConsistency::Latest {
+            return Ok(Cow::Owned(self.runtime.volume_reader(self.vid()?.clone())?));
         }
+        if self.idle_reader.is_none() {
+            self.idle_reader = Some(self.runtime.volume_reader(self.vid()?.clone())?);
+        }
+        Ok(Cow::Borrowed(
+            self.idle_reader.as_ref().expect("idle reader is pinned"),
+        ))
+    }
+
+    /// Releases the snapshot pinned by unlocked reads, if any, so that the
+    /// next unlocked read pins the latest snapshot.
+    pub fn unpin_idle_snapshot(&mut self) {
+        self.idle_reader = None;
     }
 
     pub fn snapshot_or_latest(&self) -> Result<Snapshot, ErrCtx> {
         match &self.state {
-            VolFileState::Idle => Ok(self.runtime.volume_snapshot(&self.vid)?),
+            VolFileState::Idle => match &self.idle_reader {
+                Some(reader) => Ok(reader.snapshot().clone()),
+                None => Ok(self.runtime.volume_snapshot(self.vid()?)?),
+            },
             VolFileState::Shared { reader } => Ok(reader.snapshot().clone()),
             VolFileState::Reserved { writer } => Ok(writer.snapshot().clone()),
             VolFileState::Committing => Err(ErrCtx::InvalidVolumeState),
@@ -112,7 +374,7 @@ impl VolFile {
     pub fn page_count(&self) -> Result<PageCount, ErrCtx> {
         match &self.state {
             VolFileState::Idle => {
-                let snapshot = self.runtime.volume_snapshot(&self.vid)?;
+                let snapshot = self.snapshot_or_latest()?;
                 Ok(self.runtime.snapshot_pages(&snapshot)?)
             }
             VolFileState::Shared { reader } => Ok(reader.page_count()),
@@ -121,6 +383,77 @@ impl VolFile {
         }
     }
 
+    /// Prefetches the next window of pages if `pageidx` continues a
+    /// sequential scan. Prefetching is best effort; failures are logged and
+    /// the read proceeds normally.
+    fn maybe_read_ahead(&mut self, pageidx: PageIdx) {
+        let Some(read_ahead) = &mut self.read_ahead else {
+            return;
+        };
+        let snapshot = match &self.state {
+            VolFileState::Shared { reader } => reader.snapshot(),
+            VolFileState::Reserved { writer } => writer.snapshot(),
+            VolFileState::Idle | VolFileState::Committing => return,
+        };
+        if let Some(range) = read_ahead.observe(pageidx, snapshot.page_count) {
+            tracing::trace!(?range, "reading ahead");
+            if let Err(err) = self.runtime.snapshot_prefetch(snapshot.clone(), range) {
+                tracing::warn!("read ahead failed: {err}");
+            }
+        }
+    }
+
+    pub fn lock_wait(&self) -> Option<Duration> {
+        self.lock_wait
+    }
+
+    pub fn set_lock_wait(&mut self, wait: Option<Duration>) {
+        self.lock_wait = wait;
+    }
+
+    pub fn lock_timeout(&self) -> Option<Duration> {
+        self.lock_timeout
+    }
+
+    pub fn commit_backpressure(&self) -> Option<u64> {
+        self.commit_backpressure
+    }
+
+    /// Once enabled, each commit waits until autosync has pushed enough of
+    /// the Volume's commits to leave at most `max_pending` pending, for up to
+    /// the sync timeout. Commits don't wait if autosync is disabled.
+    pub fn set_commit_backpressure(&mut self, max_pending: Option<u64>) {
+        self.commit_backpressure = max_pending;
+    }
+
+    /// Limits how long this connection may hold the reserved lock. Once the
+    /// timeout elapses, the next read or write in the transaction fails with
+    /// `ErrCtx::Busy` and the transaction's changes are discarded.
+    pub fn set_lock_timeout(&mut self, timeout: Option<Duration>) {
+        self.lock_timeout = timeout;
+    }
+
+    /// Fails with `ErrCtx::Busy` the first time this connection is found to
+    /// have held the reserved lock for longer than the lock timeout. The
+    /// transaction is then marked as timed out, and its changes will be
+    /// discarded rather than committed. Subsequent operations are allowed so
+    /// that SQLite can roll back cleanly.
+    fn check_lock_timeout(&mut self) -> Result<(), ErrCtx> {
+        let (Some(timeout), Some(reserved_at)) = (self.lock_timeout, self.reserved_at) else {
+            return Ok(());
+        };
+        if !self.lock_timed_out && reserved_at.elapsed() > timeout {
+            tracing::warn!(
+                tag = self.tag,
+                ?timeout,
+                "reserved lock held longer than the lock timeout; the transaction must be rolled back"
+            );
+            self.lock_timed_out = true;
+            return Err(ErrCtx::Busy);
+        }
+        Ok(())
+    }
+
     pub fn is_idle(&self) -> bool {
         matches!(self.state, VolFileState::Idle)
     }
@@ -129,17 +462,32 @@ impl VolFile {
         self.opts
     }
 
+    /// Returns the Volume backing this file, resolving the file's tag if it
+    /// was opened lazily and this is its first use.
+    pub fn vid(&self) -> Result<&VolumeId, ErrCtx> {
+        if let Some(vid) = self.vid.get() {
+            return Ok(vid);
+        }
+        let vid = resolve_tag(&self.runtime, &self.tag, self.opts)?;
+        Ok(self.vid.get_or_init(|| vid))
+    }
+
     pub fn switch_volume(&mut self, vid: &VolumeId) -> Result<(), ErrCtx> {
         self.runtime.tag_replace(&self.tag, vid.clone())?;
-        self.vid = vid.clone();
+        self.vid = OnceLock::from(vid.clone());
+        self.coalescer.clear();
+        self.idle_reader = None;
         Ok(())
     }
 
     pub fn reader(&self) -> Result<VolumeReadRef<'_>, ErrCtx> {
         match &self.state {
-            VolFileState::Idle => Ok(VolumeReadRef::Reader(Cow::Owned(
-                self.runtime.volume_reader(self.vid.clone())?,
-            ))),
+            VolFileState::Idle => match &self.idle_reader {
+                Some(reader) => Ok(VolumeReadRef::Reader(Cow::Borrowed(reader))),
+                None => Ok(VolumeReadRef::Reader(Cow::Owned(
+                    self.runtime.volume_reader(self.vid()?.clone())?,
+                ))),
+            },
             VolFileState::Shared { reader, .. } => Ok(VolumeReadRef::Reader(Cow::Borrowed(reader))),
             VolFileState::Reserved { writer, .. } => Ok(VolumeReadRef::Writer(writer)),
             VolFileState::Committing => Err(ErrCtx::InvalidVolumeState),
@@ -157,6 +505,9 @@ impl VfsFile for VolFile {
     }
 
     fn lock(&mut self, level: LockLevel) -> Result<(), ErrCtx> {
+        // batched pages may not be visible to the next snapshot
+        self.coalescer.clear();
+        self.idle_reader = None;
         match level {
             LockLevel::Unlocked => {
                 // SQLite should never request an Unlocked lock
@@ -165,7 +516,7 @@ impl VfsFile for VolFile {
             LockLevel::Shared => {
                 if let VolFileState::Idle = self.state {
                     // Transition Idle -> Shared
-                    let reader = self.runtime.volume_reader(self.vid.clone())?;
+                    let reader = self.runtime.volume_reader(self.vid()?.clone())?;
                     self.state = VolFileState::Shared { reader };
                 } else {
                     tracing::error!("invalid lock request Shared in state {}", self.state.name());
@@ -184,8 +535,9 @@ impl VfsFile for VolFile {
                         return Err(ErrCtx::InvalidLockTransition);
                     }
 
-                    // try to acquire the reserved lock or fail if another thread has it
-                    let Some(reserved) = self.reserved.try_lock() else {
+                    // try to acquire the reserved lock or fail if another
+                    // thread holds it for longer than the lock wait
+                    let Some(reserved) = acquire_reserved(&self.reserved, self.lock_wait) else {
                         return Err(ErrCtx::Busy);
                     };
 
@@ -193,7 +545,7 @@ impl VfsFile for VolFile {
                     // has changed we can immediately reject the lock upgrade
                     if !self
                         .runtime
-                        .snapshot_is_latest(&self.vid, reader.snapshot())?
+                        .snapshot_is_latest(self.vid()?, reader.snapshot())?
                     {
                         tracing::trace!("unable to lock: Shared -> Reserved: snapshot changed");
                         return Err(ErrCtx::BusySnapshot);
@@ -204,6 +556,9 @@ impl VfsFile for VolFile {
                         writer: VolumeWriter::from(reader.clone()),
                     };
 
+                    self.reserved_at = Some(Instant::now());
+                    self.lock_timed_out = false;
+
                     // Explicitly leak the reserved lock
                     // SAFETY: we depend on SQLite to release the lock when it's done
                     MutexGuard::leak(reserved);
@@ -230,6 +585,8 @@ impl VfsFile for VolFile {
     }
 
     fn unlock(&mut self, level: LockLevel) -> Result<(), ErrCtx> {
+        self.coalescer.clear();
+        self.idle_reader = None;
         match level {
             LockLevel::Unlocked => match self.state {
                 VolFileState::Idle | VolFileState::Shared { .. } | VolFileState::Committing => {
@@ -248,8 +605,28 @@ impl VfsFile for VolFile {
                     // If we fail the commit, SQLite will subsequently issue an
                     // Unlocked request after handling the error
 
-                    // Commit the writer, downgrading to a reader
-                    let reader = writer.commit()?;
+                    self.reserved_at = None;
+                    let timed_out = mem::take(&mut self.lock_timed_out);
+                    let reader = if timed_out {
+                        // the lock timeout elapsed; discard the writer rather
+                        // than committing a partially rolled back transaction
+                        tracing::warn!(
+                            tag = self.tag,
+                            "discarding transaction which exceeded the lock timeout"
+                        );
+                        drop(writer);
+                        self.runtime.volume_reader(self.vid()?.clone())?
+                    } else if writer.is_unchanged() {
+                        // the transaction didn't change the Volume, for
+                        // example because it only wrote ignored header
+                        // ranges; don't record an empty commit
+                        tracing::trace!(tag = self.tag, "skipping commit of unchanged transaction");
+                        drop(writer);
+                        self.runtime.volume_reader(self.vid()?.clone())?
+                    } else {
+                        // Commit the writer, downgrading to a reader
+                        writer.commit()?
+                    };
                     self.state = VolFileState::Shared { reader };
 
                     // release the reserved lock
@@ -259,6 +636,19 @@ impl VfsFile for VolFile {
                     // SAFETY: we are in the Reserved state, thus we are holding the lock
                     // SAFETY: we depend on the connection not being passed
                     unsafe { self.reserved.force_unlock() };
+
+                    // apply backpressure after releasing the reserved lock,
+                    // so that other connections may continue to commit
+                    if !timed_out && let Some(max_pending) = self.commit_backpressure {
+                        let wait = self.runtime.wait_for_sync_capacity(
+                            self.vid()?,
+                            max_pending,
+                            self.sync_timeout,
+                        );
+                        if let Err(err) = wait {
+                            tracing::warn!(tag = self.tag, "commit backpressure failed: {err}");
+                        }
+                    }
                 } else {
                     tracing::error!(
                         "invalid unlock request Shared in state {}",
@@ -289,10 +679,10 @@ impl VfsFile for VolFile {
     }
 
     fn read(&mut self, offset: usize, data: &mut [u8]) -> Result<usize, ErrCtx> {
+        self.check_lock_timeout()?;
+
         // locate the page offset of the requested page
-        let pageidx: PageIdx = ((offset / PAGESIZE.as_usize()) + 1)
-            .try_into()
-            .expect("offset out of volume range");
+        let pageidx = offset_to_pageidx(offset)?;
         // local_offset is the offset *within* the requested page
         let local_offset = offset % PAGESIZE;
 
@@ -301,17 +691,23 @@ impl VfsFile for VolFile {
             "read must not cross page boundary"
         );
 
-        // load the page
+        self.maybe_read_ahead(pageidx);
+
+        // load the page. reads beyond the end of the Volume, including reads
+        // of an empty Volume, return a zeroed page.
         let page = match &mut self.state {
             VolFileState::Idle => {
                 // sqlite sometimes reads the database header without holding a
                 // lock, in this case we are expected to read from the latest
-                // snapshot
-                let reader = self.runtime.volume_reader(self.vid.clone())?;
-                reader.read_page(pageidx)?
+                // snapshot, or the snapshot pinned by the first such read
+                self.idle_reader()?.read_page(pageidx)?
+            }
+            VolFileState::Shared { reader } => {
+                self.coalescer.read(reader, pageidx, Instant::now())?
+            }
+            VolFileState::Reserved { writer } => {
+                self.coalescer.read(writer, pageidx, Instant::now())?
             }
-            VolFileState::Shared { reader } => reader.read_page(pageidx)?,
-            VolFileState::Reserved { writer } => writer.read_page(pageidx)?,
             VolFileState::Committing => return Err(ErrCtx::InvalidVolumeState),
         };
 
@@ -324,13 +720,19 @@ impl VfsFile for VolFile {
             && local_offset <= FILE_CHANGE_COUNTER_OFFSET
             && local_offset + data.len() >= FILE_CHANGE_COUNTER_OFFSET + 4
         {
+            let snapshot = self.snapshot_or_latest()?;
+            if snapshot.page_count.is_empty() {
+                // leave the header of an empty Volume zeroed so SQLite sees an
+                // uninitialized database
+                return Ok(data.len());
+            }
+
             // find the location of the file change counter within the out buffer
             let fcc_offset = FILE_CHANGE_COUNTER_OFFSET - local_offset.as_usize();
 
             // compute the file change counter by hashing the snapshot.
             // IMPORTANT: we use DefaultHasher which has a fixed seed/secret of
             // 0 to ensure that the same snapshot gives the same result
-            let snapshot = self.snapshot_or_latest()?;
             let mut hasher = DefaultHasher::new();
             snapshot.hash(&mut hasher);
             let hash = hasher.finish();
@@ -344,10 +746,12 @@ impl VfsFile for VolFile {
     }
 
     fn truncate(&mut self, size: usize) -> Result<(), ErrCtx> {
+        self.check_lock_timeout()?;
         let VolFileState::Reserved { writer, .. } = &mut self.state else {
             tracing::error!("must hold reserved lock to truncate");
             return Err(ErrCtx::InvalidVolumeState);
         };
+        self.coalescer.clear();
 
         assert_eq!(
             size % PAGESIZE.as_usize(),
@@ -355,24 +759,28 @@ impl VfsFile for VolFile {
             "size must be an even multiple of {PAGESIZE}"
         );
 
+        if size as u64 > MAX_VOLUME_SIZE {
+            return Err(ErrCtx::PageIndexOverflow { offset: size });
+        }
         let pages: PageCount = (size / PAGESIZE.as_usize())
             .try_into()
-            .expect("size too large");
+            .map_err(|_| ErrCtx::PageIndexOverflow { offset: size })?;
 
         writer.soft_truncate(pages)?;
         Ok(())
     }
 
     fn write(&mut self, offset: usize, data: &[u8]) -> Result<usize, ErrCtx> {
+        self.check_lock_timeout()?;
         let VolFileState::Reserved { writer, .. } = &mut self.state else {
             tracing::error!("must hold reserved lock to write");
             return Err(ErrCtx::InvalidVolumeState);
         };
+        // batched pages must not hide this write
+        self.coalescer.clear();
 
         // locate the requested page index
-        let page_idx: PageIdx = ((offset / PAGESIZE.as_usize()) + 1)
-            .try_into()
-            .expect("offset out of volume range");
+        let page_idx = offset_to_pageidx(offset)?;
         // local_offset is the offset *within* the requested page
         let local_offset = offset % PAGESIZE;
 
@@ -382,28 +790,17 @@ impl VfsFile for VolFile {
         );
 
         // if this is a write to the first page, and the write only changes the
-        // file change counter and the version valid for number, we can ignore this write
+        // ignored header ranges (by default the file change counter and the
+        // version valid for number), we can ignore this write
         if page_idx == PageIdx::FIRST && data.len() == PAGESIZE && local_offset == 0 {
             let existing: Page = writer.read_page(page_idx)?;
 
             debug_assert_eq!(data.len(), existing.len(), "page size mismatch");
 
-            let fcc = FILE_CHANGE_COUNTER_OFFSET..FILE_CHANGE_COUNTER_OFFSET + 4;
-            let vvf = VERSION_VALID_FOR_NUMBER_OFFSET..VERSION_VALID_FOR_NUMBER_OFFSET + 4;
-
-            // check the header page is unchanged while ignoring the file change
-            // counter and version valid for number
-            let unchanged =
-                // prefix [0,24)
-                data[..fcc.start]           == existing[..fcc.start] &&
-                // middle (28,92)
-                data[fcc.end..vvf.start]    == existing[fcc.end..vvf.start] &&
-                // suffix (96, end]
-                data[vvf.end..]             == existing[vvf.end..];
-
-            if unchanged {
+            if unchanged_ignoring(data, &existing, &self.ignored_header_ranges) {
                 tracing::trace!(
-                    "ignoring write to header page, as only file change counter and version valid for number changed"
+                    ranges = ?self.ignored_header_ranges,
+                    "ignoring write to header page, as only ignored header ranges changed"
                 );
                 return Ok(data.len());
             }
@@ -426,3 +823,129 @@ impl VfsFile for VolFile {
         Ok(data.len())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_to_pageidx() {
+        assert_eq!(offset_to_pageidx(0).unwrap(), PageIdx::FIRST);
+        assert_eq!(
+            offset_to_pageidx(PAGESIZE.as_usize()).unwrap(),
+            PageIdx::FIRST.saturating_next()
+        );
+
+        // the last byte of the largest supported Volume
+        let last = (MAX_VOLUME_SIZE - 1) as usize;
+        assert_eq!(offset_to_pageidx(last).unwrap(), PageIdx::LAST);
+
+        // reads beyond the max must fail cleanly rather than panic
+        for offset in [MAX_VOLUME_SIZE as usize, usize::MAX] {
+            assert!(matches!(
+                offset_to_pageidx(offset),
+                Err(ErrCtx::PageIndexOverflow { offset: o }) if o == offset
+            ));
+        }
+    }
+
+    #[test]
+    fn test_normalize_header_ranges() {
+        // the defaults are already normalized
+        let defaults = DEFAULT_IGNORED_HEADER_RANGES.to_vec();
+        assert_eq!(normalize_header_ranges(defaults.clone()).unwrap(), defaults);
+
+        // ranges are sorted, and overlapping or touching ranges are merged
+        assert_eq!(
+            normalize_header_ranges(vec![92..96, 40..44, 24..28, 26..30, 44..48]).unwrap(),
+            [24..30, 40..48, 92..96]
+        );
+        assert_eq!(
+            normalize_header_ranges(vec![0..100, 24..28]).unwrap(),
+            [0..100]
+        );
+        assert_eq!(normalize_header_ranges(vec![]).unwrap(), []);
+
+        // empty ranges and ranges outside the header are rejected
+        let empty = [Range { start: 28, end: 24 }, Range { start: 40, end: 40 }];
+        for invalid in empty.into_iter().chain([96..101, 100..104, 4096..4100]) {
+            assert_eq!(
+                normalize_header_ranges(vec![24..28, invalid.clone()]),
+                Err(InvalidHeaderRange(invalid))
+            );
+        }
+    }
+
+    #[test]
+    fn test_unchanged_ignoring() {
+        let existing = [0u8; 100];
+        let write = |offsets: &[usize]| {
+            let mut data = existing;
+            for &offset in offsets {
+                data[offset] = 1;
+            }
+            data
+        };
+
+        // writes only touching the file change counter and version valid for
+        // number are skipped
+        let defaults = DEFAULT_IGNORED_HEADER_RANGES;
+        assert!(unchanged_ignoring(&write(&[]), &existing, &defaults));
+        assert!(unchanged_ignoring(
+            &write(&[24, 27, 95]),
+            &existing,
+            &defaults
+        ));
+        for offset in [0, 23, 28, 40, 91, 96, 99] {
+            assert!(!unchanged_ignoring(&write(&[offset]), &existing, &defaults));
+        }
+
+        // adding a range also skips writes which touch it
+        let extended = [24..28, 72..80, 92..96];
+        assert!(unchanged_ignoring(
+            &write(&[24, 72, 79]),
+            &existing,
+            &extended
+        ));
+        for offset in [71, 80, 96] {
+            assert!(!unchanged_ignoring(
+                &write(&[72, offset]),
+                &existing,
+                &extended
+            ));
+        }
+
+        // a range reaching the end of the data ignores the suffix
+        assert!(unchanged_ignoring(&write(&[99]), &existing, &[90..200]));
+    }
+
+    #[test]
+    fn test_acquire_reserved_waits() {
+        let reserved = Arc::new(Mutex::new(()));
+
+        // without a lock wait, a held lock is immediately busy
+        let guard = reserved.lock();
+        assert!(acquire_reserved(&reserved, None).is_none());
+
+        // a long-held lock still times out
+        let start = Instant::now();
+        assert!(acquire_reserved(&reserved, Some(Duration::from_millis(50))).is_none());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // a short-held lock is acquired after waiting
+        let holder = std::thread::spawn({
+            let reserved = reserved.clone();
+            move || {
+                let _guard = reserved.lock();
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        });
+        drop(guard);
+        // give the holder a chance to take the lock
+        while !reserved.is_locked() && !holder.is_finished() {
+            std::thread::yield_now();
+        }
+        assert!(acquire_reserved(&reserved, Some(Duration::from_secs(10))).is_some());
+        holder.join().unwrap();
+    }
+}
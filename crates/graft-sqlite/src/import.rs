@@ -0,0 +1,63 @@
+use graft::{
+    GraftErr,
+    core::{PageIdx, VolumeId, page::PAGESIZE, page::Page},
+    rt::runtime::Runtime,
+    volume_writer::VolumeWrite,
+};
+use rusqlite::{Connection, DatabaseName};
+use thiserror::Error;
+
+// The byte offsets of the file format write and read versions in the SQLite
+// database header
+const WRITE_VERSION_OFFSET: usize = 18;
+const READ_VERSION_OFFSET: usize = 19;
+
+#[derive(Debug, Error)]
+pub enum ImportErr {
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("Graft error: {0}")]
+    Graft(#[from] GraftErr),
+
+    #[error("unsupported page size {0}; Graft requires a page size of {PAGESIZE}")]
+    UnsupportedPageSize(usize),
+}
+
+/// Imports the main database of an existing `SQLite` connection into a new
+/// Graft Volume, returning the new Volume's id.
+///
+/// The database is read within a single read transaction, so the import
+/// reflects a consistent snapshot even if the database is concurrently
+/// modified. The entire database is buffered in memory during the import.
+pub fn import_from_sqlite(runtime: &Runtime, conn: &Connection) -> Result<VolumeId, ImportErr> {
+    let page_size: u32 = conn.pragma_query_value(None, "page_size", |row| row.get(0))?;
+    if page_size as usize != PAGESIZE.as_usize() {
+        return Err(ImportErr::UnsupportedPageSize(page_size as usize));
+    }
+
+    // sqlite3_serialize reads every page within a single read transaction
+    let data = conn.serialize(DatabaseName::Main)?;
+
+    let volume = runtime.volume_open(None, None, None)?;
+    let mut writer = runtime.volume_writer(volume.vid.clone())?;
+    for (pageidx, chunk) in (1u32..).zip(data.chunks_exact(PAGESIZE.as_usize())) {
+        let mut page = chunk.to_vec();
+        if pageidx == 1 {
+            // Graft doesn't support WAL mode; switch the imported database
+            // back to the legacy rollback journal
+            page[WRITE_VERSION_OFFSET] = 1;
+            page[READ_VERSION_OFFSET] = 1;
+        }
+        let page = Page::try_from(page.as_slice()).expect("chunk is a full page");
+        writer.write_page(PageIdx::must_new(pageidx), page)?;
+    }
+    writer.commit()?;
+
+    tracing::debug!(
+        vid = ?volume.vid,
+        pages = data.len() / PAGESIZE.as_usize(),
+        "imported sqlite database"
+    );
+    Ok(volume.vid)
+}
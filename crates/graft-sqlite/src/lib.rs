@@ -3,9 +3,13 @@ pub mod pragma;
 pub mod vfs;
 
 mod dbg;
+mod table;
 
 #[cfg(feature = "register-static")]
 pub mod register;
 
+#[cfg(feature = "rusqlite")]
+pub mod import;
+
 #[cfg(feature = "register-static")]
-pub use register::register_static;
+pub use register::{default_vfs_name, register_named, register_static, registered_vfs_names};
@@ -1,13 +1,20 @@
 use std::{
     fmt::{Display, Write},
-    fs::File,
-    io::Write as IoWrite,
+    fs::{File, OpenOptions},
+    io::BufWriter,
+    num::NonZero,
+    ops::RangeInclusive,
     path::PathBuf,
     str::FromStr,
+    time::Duration,
 };
 
 use graft::core::{
-    LogId, PageIdx, VolumeId, commit::Commit, logref::LogRef, lsn::LSNRangeExt, page::PAGESIZE,
+    LogId, PageIdx, VolumeId,
+    commit::Commit,
+    logref::LogRef,
+    lsn::{LSN, LSNRangeExt},
+    page::PAGESIZE,
 };
 use graft::{rt::runtime::Runtime, volume::AheadStatus, volume_reader::VolumeRead};
 use indoc::{formatdoc, indoc, writedoc};
@@ -18,7 +25,12 @@ use sqlite_plugin::{
 use tryiter::TryIteratorExt;
 use zerocopy::FromBytes;
 
-use crate::{dbg::SqliteHeader, file::vol_file::VolFile, vfs::ErrCtx};
+use crate::{
+    dbg::SqliteHeader,
+    file::vol_file::{DEFAULT_BACKPRESSURE_PENDING_COMMITS, VolFile},
+    table::{ListFormat, Table},
+    vfs::ErrCtx,
+};
 
 /// Helper to create pragma errors concisely
 fn pragma_fail(msg: impl Display) -> PragmaErr {
@@ -54,11 +66,17 @@ impl<'a> PragmaExt<'a> for Pragma<'a> {
 }
 
 pub enum GraftPragma {
-    /// `pragma graft_volumes;`
-    Volumes,
+    /// `pragma graft_volumes [= "plain|tsv|table"];`
+    Volumes { format: ListFormat },
+
+    /// `pragma graft_tags [= "plain|tsv|table"];`
+    Tags { format: ListFormat },
 
-    /// `pragma graft_tags;`
-    Tags,
+    /// `pragma graft_aliases;`
+    Aliases,
+
+    /// `pragma graft_swap_tags = "a:b";`
+    SwapTags { a: String, b: String },
 
     /// `pragma graft_switch = "local_vid[:local[:remote]]";`
     Switch {
@@ -76,6 +94,9 @@ pub enum GraftPragma {
     /// `pragma graft_checkout = "remote:LSN";`
     Checkout { logref: LogRef },
 
+    /// `pragma graft_checkout_checkpoint = "remote:N";`
+    CheckoutCheckpoint { log: LogId, n: NonZero<usize> },
+
     /// `pragma graft_info;`
     Info,
 
@@ -85,6 +106,12 @@ pub enum GraftPragma {
     /// `pragma graft_snapshot;`
     Snapshot,
 
+    /// `pragma graft_range;`
+    Range,
+
+    /// `pragma graft_dot;`
+    Dot,
+
     /// `pragma graft_fetch;`
     Fetch,
 
@@ -94,26 +121,97 @@ pub enum GraftPragma {
     /// `pragma graft_push;`
     Push,
 
+    /// `pragma graft_squash_push;`
+    SquashPush,
+
+    /// `pragma graft_pending_heatmap;`
+    PendingHeatmap,
+
     /// `pragma graft_audit;`
     Audit,
 
-    /// `pragma graft_hydrate;`
-    Hydrate,
+    /// `pragma graft_check_page_count;`
+    CheckPageCount,
+
+    /// `pragma graft_checksum_range = "start..end";`
+    ChecksumRange { pages: RangeInclusive<PageIdx> },
+
+    /// `pragma graft_compare = "vid";`
+    Compare { other: VolumeId },
+
+    /// `pragma graft_hydrate [= "resume"];`
+    Hydrate { resume: bool },
+
+    /// `pragma graft_incomplete;`
+    Incomplete,
+
+    /// `pragma graft_reindex;`
+    Reindex,
+
+    /// `pragma graft_gc;`
+    Gc,
+
+    /// `pragma graft_resync = "confirm";`
+    Resync,
+
+    /// `pragma graft_reset;`
+    Reset,
 
     /// `pragma graft_version;`
     Version,
 
+    /// `pragma graft_whoami;`
+    Whoami,
+
+    /// `pragma graft_stats;`
+    Stats,
+
+    /// `pragma graft_write_amp;`
+    WriteAmp,
+
+    /// `pragma graft_sync_health;`
+    SyncHealth,
+
+    /// `pragma graft_metrics [= "reset"];`
+    Metrics { reset: bool },
+
+    /// `pragma graft_lock_contention [= "reset"];`
+    LockContention { reset: bool },
+
+    /// `pragma graft_simulate_remote_commit;`
+    /// Only available when the `testutil` feature is enabled.
+    #[cfg(feature = "testutil")]
+    SimulateRemoteCommit,
+
+    /// `pragma graft_lock_timeout [= ms];`
+    LockTimeout { ms: Option<u64> },
+
+    /// `pragma graft_lock_wait [= ms];`
+    LockWait { ms: Option<u64> },
+
+    /// `pragma graft_commit_backpressure [= "on|off|N"];`
+    CommitBackpressure { max_pending: Option<Option<u64>> },
+
+    /// `pragma graft_autosync [= "seconds|off"];`
+    Autosync { secs: Option<Option<NonZero<u64>>> },
+
     /// `pragma graft_import = "PATH";`
     Import(PathBuf),
 
     /// `pragma graft_export = "PATH";`
     Export(PathBuf),
 
+    /// `pragma graft_export_resume = "PATH";`
+    ExportResume(PathBuf),
+
     /// `pragma graft_dump_header;`
     DumpSqliteHeader,
 
     /// `pragma graft_dump_commit = "logid:LSN";`
     DumpCommit { logref: LogRef },
+
+    /// `pragma graft_dump_frames = "logid:LSN";`
+    DumpFrames { logref: LogRef },
 }
 
 impl TryFrom<&Pragma<'_>> for GraftPragma {
@@ -124,13 +222,35 @@ impl TryFrom<&Pragma<'_>> for GraftPragma {
             && prefix == "graft"
         {
             return match suffix {
-                "volumes" => Ok(GraftPragma::Volumes),
-                "tags" => Ok(GraftPragma::Tags),
+                "volumes" => {
+                    let format = parse_optional(p.arg.as_ref())?.unwrap_or_default();
+                    Ok(GraftPragma::Volumes { format })
+                }
+                "tags" => {
+                    let format = parse_optional(p.arg.as_ref())?.unwrap_or_default();
+                    Ok(GraftPragma::Tags { format })
+                }
+                "swap_tags" => {
+                    let Some((a, b)) = p.require_arg()?.split_once(':') else {
+                        return Err(pragma_fail("argument must be in the form: `a:b`"));
+                    };
+                    Ok(GraftPragma::SwapTags { a: a.to_string(), b: b.to_string() })
+                }
                 "clone" => {
                     let remote = p.arg.map(parse_or_fail).transpose()?;
                     Ok(GraftPragma::Clone { remote })
                 }
+                "aliases" => Ok(GraftPragma::Aliases),
                 "fork" => Ok(GraftPragma::Fork),
+                "checkout_checkpoint" => {
+                    let Some((log, n)) = p.require_arg()?.split_once(':') else {
+                        return Err(pragma_fail("argument must be in the form: `remote:N`"));
+                    };
+                    Ok(GraftPragma::CheckoutCheckpoint {
+                        log: parse_or_fail(log)?,
+                        n: parse_or_fail(n)?,
+                    })
+                }
                 "checkout" => {
                     Ok(GraftPragma::Checkout { logref: parse_or_fail(p.require_arg()?)? })
                 }
@@ -155,18 +275,95 @@ impl TryFrom<&Pragma<'_>> for GraftPragma {
                 "info" => Ok(GraftPragma::Info),
                 "status" => Ok(GraftPragma::Status),
                 "snapshot" => Ok(GraftPragma::Snapshot),
+                "range" => Ok(GraftPragma::Range),
+                "dot" => Ok(GraftPragma::Dot),
                 "fetch" => Ok(GraftPragma::Fetch),
                 "pull" => Ok(GraftPragma::Pull),
                 "push" => Ok(GraftPragma::Push),
+                "squash_push" => Ok(GraftPragma::SquashPush),
+                "pending_heatmap" => Ok(GraftPragma::PendingHeatmap),
                 "audit" => Ok(GraftPragma::Audit),
-                "hydrate" => Ok(GraftPragma::Hydrate),
+                "check_page_count" => Ok(GraftPragma::CheckPageCount),
+                "checksum_range" => {
+                    let Some((start, end)) = p.require_arg()?.split_once("..") else {
+                        return Err(pragma_fail("argument must be in the form: `start..end`"));
+                    };
+                    let pages = parse_or_fail(start)?..=parse_or_fail(end)?;
+                    if pages.is_empty() {
+                        return Err(pragma_fail("range start must not be after its end"));
+                    }
+                    Ok(GraftPragma::ChecksumRange { pages })
+                }
+                "compare" => Ok(GraftPragma::Compare { other: parse_or_fail(p.require_arg()?)? }),
+                "hydrate" => match p.arg {
+                    None => Ok(GraftPragma::Hydrate { resume: false }),
+                    Some("resume") => Ok(GraftPragma::Hydrate { resume: true }),
+                    Some(_) => Err(pragma_fail("argument must be `resume`")),
+                },
+                "incomplete" => Ok(GraftPragma::Incomplete),
+                "reindex" => Ok(GraftPragma::Reindex),
+                "gc" => Ok(GraftPragma::Gc),
+                "resync" => match p.arg {
+                    Some("confirm") => Ok(GraftPragma::Resync),
+                    _ => Err(pragma_fail(
+                        "resyncing discards all local pages; pass `confirm` to proceed",
+                    )),
+                },
+                "reset" => Ok(GraftPragma::Reset),
                 "version" => Ok(GraftPragma::Version),
+                "whoami" => Ok(GraftPragma::Whoami),
+                "stats" => Ok(GraftPragma::Stats),
+                "write_amp" => Ok(GraftPragma::WriteAmp),
+                "sync_health" => Ok(GraftPragma::SyncHealth),
+                "metrics" => match p.arg {
+                    None => Ok(GraftPragma::Metrics { reset: false }),
+                    Some("reset") => Ok(GraftPragma::Metrics { reset: true }),
+                    Some(_) => Err(pragma_fail("argument must be `reset`")),
+                },
+                "lock_contention" => match p.arg {
+                    None => Ok(GraftPragma::LockContention { reset: false }),
+                    Some("reset") => Ok(GraftPragma::LockContention { reset: true }),
+                    Some(_) => Err(pragma_fail("argument must be `reset`")),
+                },
+                #[cfg(feature = "testutil")]
+                "simulate_remote_commit" => Ok(GraftPragma::SimulateRemoteCommit),
+                "lock_timeout" => {
+                    let ms = p.arg.map(parse_or_fail).transpose()?;
+                    Ok(GraftPragma::LockTimeout { ms })
+                }
+                "lock_wait" => {
+                    let ms = p.arg.map(parse_or_fail).transpose()?;
+                    Ok(GraftPragma::LockWait { ms })
+                }
+                "commit_backpressure" => {
+                    let max_pending = match p.arg {
+                        None => None,
+                        Some("on") => Some(Some(DEFAULT_BACKPRESSURE_PENDING_COMMITS)),
+                        Some("off") => Some(None),
+                        Some(n) => Some(Some(parse_or_fail(n)?)),
+                    };
+                    Ok(GraftPragma::CommitBackpressure { max_pending })
+                }
+                "autosync" => {
+                    let secs = match p.arg {
+                        None => None,
+                        Some("off") => Some(None),
+                        Some(secs) => Some(Some(secs.parse().map_err(|_| {
+                            pragma_fail("argument must be `off` or a non-zero number of seconds")
+                        })?)),
+                    };
+                    Ok(GraftPragma::Autosync { secs })
+                }
                 "import" => Ok(GraftPragma::Import(PathBuf::from(p.require_arg()?))),
                 "export" => Ok(GraftPragma::Export(PathBuf::from(p.require_arg()?))),
+                "export_resume" => Ok(GraftPragma::ExportResume(PathBuf::from(p.require_arg()?))),
                 "dump_header" => Ok(GraftPragma::DumpSqliteHeader),
                 "dump_commit" => {
                     Ok(GraftPragma::DumpCommit { logref: parse_or_fail(p.require_arg()?)? })
                 }
+                "dump_frames" => {
+                    Ok(GraftPragma::DumpFrames { logref: parse_or_fail(p.require_arg()?)? })
+                }
                 _ => Err(pragma_fail(format!("invalid graft pragma `{}`", p.name))),
             };
         }
@@ -182,9 +379,17 @@ macro_rules! pragma_err {
 
 impl GraftPragma {
     pub fn eval(self, runtime: &Runtime, file: &mut VolFile) -> Result<Option<String>, ErrCtx> {
+        // pragmas observe the latest snapshot while idle, rather than one
+        // pinned by an earlier unlocked read which may predate a pull
+        file.unpin_idle_snapshot();
         match self {
-            GraftPragma::Volumes => Ok(Some(format_volumes(runtime, file)?)),
-            GraftPragma::Tags => Ok(Some(format_tags(runtime, file)?)),
+            GraftPragma::Volumes { format } => Ok(Some(format_volumes(runtime, file, format)?)),
+            GraftPragma::Tags { format } => Ok(Some(format_tags(runtime, file, format)?)),
+            GraftPragma::SwapTags { a, b } => {
+                runtime.swap_tags(&a, &b)?;
+                Ok(Some(format!("Swapped the Volumes of tags `{a}` and `{b}`")))
+            }
+            GraftPragma::Aliases => Ok(Some(format_aliases(runtime, file)?)),
 
             GraftPragma::Clone { remote } => {
                 if !file.is_idle() {
@@ -193,7 +398,7 @@ impl GraftPragma {
 
                 let remote = match remote {
                     Some(remote) => remote,
-                    None => runtime.volume_get(&file.vid)?.remote,
+                    None => runtime.volume_get(file.vid()?)?.remote,
                 };
                 let volume = runtime.volume_open(None, None, Some(remote))?;
                 file.switch_volume(&volume.vid)?;
@@ -236,7 +441,30 @@ impl GraftPragma {
 
                 Ok(Some(format!(
                     "Checked out Volume {} at Log {} LSN {}",
-                    file.vid, logref.log, logref.lsn,
+                    file.vid()?,
+                    logref.log,
+                    logref.lsn,
+                )))
+            }
+
+            GraftPragma::CheckoutCheckpoint { log, n } => {
+                if !file.is_idle() {
+                    return pragma_err!("cannot checkout while there is an open transaction");
+                }
+
+                runtime.fetch_log(log.clone(), None)?;
+                let Some(lsn) = runtime.nth_checkpoint(&log, n.get() - 1)? else {
+                    return pragma_err!(format!("remote Log {log} has fewer than {n} checkpoints"));
+                };
+                let Some(volume) = runtime.volume_from_logref(LogRef::new(log.clone(), lsn))?
+                else {
+                    return pragma_err!("logref not found");
+                };
+                file.switch_volume(&volume.vid)?;
+
+                Ok(Some(format!(
+                    "Checked out Volume {} at checkpoint {n} (Log {log} LSN {lsn})",
+                    file.vid()?,
                 )))
             }
 
@@ -261,28 +489,136 @@ impl GraftPragma {
                 let snapshot = file.snapshot_or_latest()?;
                 Ok(Some(format!("{snapshot:?}")))
             }
+            GraftPragma::Range => Ok(Some(format_reachable_range(file)?)),
+            GraftPragma::Dot => Ok(Some(format_commit_dot(runtime, file)?)),
 
             GraftPragma::Fetch => Ok(Some(fetch_or_pull(runtime, file, false)?)),
             GraftPragma::Pull => Ok(Some(fetch_or_pull(runtime, file, true)?)),
 
-            GraftPragma::Push => Ok(Some(push(runtime, file)?)),
+            GraftPragma::Push => Ok(Some(push(runtime, file, false)?)),
+            GraftPragma::SquashPush => Ok(Some(push(runtime, file, true)?)),
 
+            GraftPragma::PendingHeatmap => Ok(Some(format_pending_heatmap(runtime, file)?)),
             GraftPragma::Audit => Ok(Some(format_volume_audit(runtime, file)?)),
+            GraftPragma::CheckPageCount => Ok(Some(format_page_count_check(runtime, file)?)),
+            GraftPragma::ChecksumRange { pages } => {
+                Ok(Some(format_checksum_range(runtime, file, pages)?))
+            }
+            GraftPragma::Compare { other } => {
+                let diff = runtime.compare_volumes(file.vid()?, &other)?;
+                Ok(Some(diff.to_string()))
+            }
 
-            GraftPragma::Hydrate => {
+            GraftPragma::Hydrate { resume: false } => {
                 let snapshot = file.snapshot_or_latest()?;
                 runtime.snapshot_hydrate(snapshot)?;
                 Ok(None)
             }
+            GraftPragma::Hydrate { resume: true } => Ok(Some(resume_hydrate(runtime, file)?)),
+
+            GraftPragma::Incomplete => Ok(Some(format_incomplete_volumes(runtime)?)),
+
+            GraftPragma::Reindex => {
+                if !file.is_idle() {
+                    return pragma_err!("cannot reindex while there is an open transaction");
+                }
+                Ok(Some(reindex(runtime, file)?))
+            }
+            GraftPragma::Gc => Ok(Some(gc(runtime)?)),
+            GraftPragma::Resync => {
+                if !file.is_idle() {
+                    return pragma_err!("cannot resync while there is an open transaction");
+                }
+                Ok(Some(resync(runtime, file)?))
+            }
+            GraftPragma::Reset => {
+                if !file.is_idle() {
+                    return pragma_err!("cannot reset while there is an open transaction");
+                }
+                Ok(Some(reset(runtime, file)?))
+            }
+
+            GraftPragma::Version => Ok(Some(format_version()?)),
+            GraftPragma::Whoami => Ok(Some(format_whoami(runtime)?)),
+
+            GraftPragma::Stats => Ok(Some(format_stats(runtime)?)),
+            GraftPragma::WriteAmp => Ok(Some(runtime.write_amp_stats(file.vid()?).to_string())),
+            GraftPragma::SyncHealth => Ok(Some(runtime.sync_health(file.vid()?).to_string())),
+            GraftPragma::Metrics { reset } => {
+                if reset {
+                    runtime.reset_volume_metrics(file.vid()?);
+                    return Ok(Some("Metrics reset".into()));
+                }
+                Ok(Some(runtime.volume_metrics(file.vid()?).to_string()))
+            }
+            GraftPragma::LockContention { reset } => {
+                if reset {
+                    runtime.reset_lock_contention();
+                    return Ok(Some("Lock contention reset".into()));
+                }
+                Ok(Some(format_lock_contention(runtime)?))
+            }
+
+            #[cfg(feature = "testutil")]
+            GraftPragma::SimulateRemoteCommit => {
+                let lsn = runtime.testonly_simulate_remote_commit(file.vid()?)?;
+                Ok(Some(format!(
+                    "Simulated a remote commit at LSN {lsn}. Use 'pragma graft_status' to view the Volume's status."
+                )))
+            }
+
+            GraftPragma::LockTimeout { ms } => {
+                if let Some(ms) = ms {
+                    // a timeout of zero disables the lock timeout
+                    let timeout = if ms == 0 {
+                        None
+                    } else {
+                        Some(Duration::from_millis(ms))
+                    };
+                    file.set_lock_timeout(timeout);
+                }
+                Ok(Some(match file.lock_timeout() {
+                    Some(timeout) => format!("Lock timeout: {}ms", timeout.as_millis()),
+                    None => "Lock timeout: disabled".into(),
+                }))
+            }
+
+            GraftPragma::LockWait { ms } => {
+                if let Some(ms) = ms {
+                    // a wait of zero returns busy immediately
+                    let wait = if ms == 0 {
+                        None
+                    } else {
+                        Some(Duration::from_millis(ms))
+                    };
+                    file.set_lock_wait(wait);
+                }
+                Ok(Some(match file.lock_wait() {
+                    Some(wait) => format!("Lock wait: {}ms", wait.as_millis()),
+                    None => "Lock wait: disabled".into(),
+                }))
+            }
+
+            GraftPragma::CommitBackpressure { max_pending } => {
+                if let Some(max_pending) = max_pending {
+                    file.set_commit_backpressure(max_pending);
+                }
+                Ok(Some(match file.commit_backpressure() {
+                    Some(max_pending) => {
+                        format!("Commit backpressure: enabled (max pending commits: {max_pending})")
+                    }
+                    None => "Commit backpressure: disabled".into(),
+                }))
+            }
 
-            GraftPragma::Version => {
-                const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
-                const GITHUB_SHA: Option<&str> = option_env!("GITHUB_SHA");
-                let mut out = format!("Graft Version: {PKG_VERSION}");
-                if let Some(sha) = GITHUB_SHA {
-                    writeln!(&mut out, "\nGit Commit: {sha}")?;
+            GraftPragma::Autosync { secs } => {
+                if let Some(secs) = secs {
+                    runtime.set_autosync_interval(secs.map(|s| Duration::from_secs(s.get())));
                 }
-                Ok(Some(out))
+                Ok(Some(match runtime.autosync_interval() {
+                    Some(interval) => format!("Autosync: every {}s", interval.as_secs()),
+                    None => "Autosync: disabled".into(),
+                }))
             }
 
             GraftPragma::Import(_) => {
@@ -292,9 +628,10 @@ impl GraftPragma {
             }
 
             GraftPragma::Export(path) => volume_export(runtime, file, path).map(Some),
+            GraftPragma::ExportResume(path) => volume_export_resume(file, path).map(Some),
 
             GraftPragma::DumpSqliteHeader => {
-                let reader = runtime.volume_reader(file.vid.clone())?;
+                let reader = runtime.volume_reader(file.vid()?.clone())?;
                 let page = reader.read_page(PageIdx::FIRST)?;
                 let header = SqliteHeader::read_from_bytes(&page[..100])
                     .expect("failed to parse SQLite header");
@@ -310,6 +647,7 @@ impl GraftPragma {
                         commit_hash,
                         segment_idx,
                         checkpoints,
+                        note,
                     } = commit;
                     Ok(Some(formatdoc!(
                         "
@@ -318,12 +656,20 @@ impl GraftPragma {
                             commit_hash: {commit_hash:?}
                             segment_idx: {segment_idx:#?}
                             checkpoints: {checkpoints:?}
+                            note: {note:?}
                         "
                     )))
                 } else {
                     pragma_err!("commit not found")
                 }
             }
+            GraftPragma::DumpFrames { logref } => {
+                if let Some(commit) = runtime.get_commit(&logref.log, logref.lsn)? {
+                    Ok(Some(format_frames(&commit)?))
+                } else {
+                    pragma_err!("commit not found")
+                }
+            }
         }
     }
 }
@@ -335,7 +681,7 @@ macro_rules! pluralize {
 }
 
 fn format_volume_info(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
-    let state = runtime.volume_get(&file.vid)?;
+    let state = runtime.volume_get(file.vid()?)?;
     let sync = state.sync().map_or_else(
         || "Never synced".into(),
         |sync| match sync.local_watermark {
@@ -369,7 +715,7 @@ fn format_volume_status(runtime: &Runtime, file: &VolFile) -> Result<String, Err
     let tag = &file.tag;
     writeln!(&mut f, "On tag {tag}")?;
 
-    let status = runtime.volume_status(&file.vid)?;
+    let status = runtime.volume_status(file.vid()?)?;
     let local_changes = status.local_status.changes();
     let remote_changes = status.remote_status.changes();
 
@@ -424,12 +770,136 @@ fn format_volume_status(runtime: &Runtime, file: &VolFile) -> Result<String, Err
     Ok(f)
 }
 
+fn format_lock_contention(runtime: &Runtime) -> Result<String, ErrCtx> {
+    let contention = runtime.lock_contention();
+    let mut f = String::new();
+    writeln!(
+        &mut f,
+        "Acquired the storage lock {} {}",
+        contention.acquisitions,
+        pluralize!(contention.acquisitions, "time")
+    )?;
+    writeln!(
+        &mut f,
+        "Contended: {} (waited {}ms in total)",
+        contention.contended,
+        contention.wait.as_millis()
+    )?;
+    Ok(f)
+}
+
+fn format_checksum_range(
+    runtime: &Runtime,
+    file: &VolFile,
+    pages: RangeInclusive<PageIdx>,
+) -> Result<String, ErrCtx> {
+    let snapshot = file.snapshot_or_latest()?;
+    // make sure every page in the range is available locally
+    runtime.snapshot_prefetch(snapshot.clone(), pages.clone())?;
+    let checksum = runtime.snapshot_checksum_range(&snapshot, pages.clone())?;
+    Ok(format!(
+        "Checksum of pages {}..{}: {checksum}",
+        pages.start(),
+        pages.end()
+    ))
+}
+
+fn format_reachable_range(file: &VolFile) -> Result<String, ErrCtx> {
+    let snapshot = file.snapshot_or_latest()?;
+    let Some((log, lsns)) = snapshot.head_range() else {
+        return Ok("Volume is empty\n".into());
+    };
+    let mut f = String::new();
+    writeln!(
+        &mut f,
+        "Head: Log {log} LSNs {}..={}",
+        lsns.start(),
+        lsns.end()
+    )?;
+    writeln!(&mut f, "Layers:")?;
+    for layer in snapshot.iter() {
+        writeln!(
+            &mut f,
+            "  Log {} LSNs {}..={}",
+            layer.log,
+            layer.lsns.start(),
+            layer.lsns.end()
+        )?;
+    }
+    Ok(f)
+}
+
+/// Renders the commits reachable by the Volume's snapshot as a Graphviz
+/// digraph, with an edge from each commit to the commit built on top of it.
+/// Checkpoints are filled, and a dashed edge connects the last local commit
+/// pushed to the remote with the remote commit the Volume is synced to.
+fn format_commit_dot(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
+    let volume = runtime.volume_get(file.vid()?)?;
+    let snapshot = file.snapshot_or_latest()?;
+    // commits are ordered from newest to oldest
+    let commits = runtime.snapshot_commits(&snapshot)?;
+    let node_id = |log: &LogId, lsn: LSN| format!("\"{log}:{lsn}\"");
+
+    // the last pushed local commit is no longer part of the snapshot
+    let sync = volume.sync().cloned();
+    let pushed = match sync.as_ref().and_then(|sync| sync.local_watermark) {
+        Some(watermark) => runtime.get_commit(&volume.local, watermark)?,
+        None => None,
+    };
+
+    let mut f = String::new();
+    writeln!(&mut f, "digraph \"{}\" {{", volume.vid)?;
+    writeln!(&mut f, "  rankdir=LR;")?;
+    writeln!(&mut f, "  node [shape=box];")?;
+    for commit in commits.iter().chain(&pushed) {
+        let kind = if commit.log() == &volume.local {
+            "local"
+        } else {
+            "remote"
+        };
+        let page_count = commit.page_count();
+        let style = if commit.is_checkpoint() {
+            ", style=filled, fillcolor=lightblue"
+        } else {
+            ""
+        };
+        writeln!(
+            &mut f,
+            "  {} [label=\"{kind} LSN {}\\n{page_count} {}\"{style}];",
+            node_id(commit.log(), commit.lsn()),
+            commit.lsn(),
+            pluralize!(page_count.to_usize(), "page"),
+        )?;
+    }
+    for pair in commits.windows(2) {
+        let (child, parent) = (&pair[0], &pair[1]);
+        writeln!(
+            &mut f,
+            "  {} -> {};",
+            node_id(parent.log(), parent.lsn()),
+            node_id(child.log(), child.lsn()),
+        )?;
+    }
+    if let Some(sync) = sync
+        && let Some(pushed) = pushed
+    {
+        writeln!(
+            &mut f,
+            "  {} -> {} [style=dashed, label=\"sync\"];",
+            node_id(pushed.log(), pushed.lsn()),
+            node_id(&volume.remote, sync.remote),
+        )?;
+    }
+    writeln!(&mut f, "}}")?;
+    Ok(f)
+}
+
 fn format_volume_audit(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
     let snapshot = file.snapshot_or_latest()?;
-    let missing_pages = runtime.snapshot_missing_pages(&snapshot)?;
-    let pages = file.page_count()?.to_usize();
-    if missing_pages.is_empty() {
-        let checksum = runtime.snapshot_checksum(&snapshot)?;
+    let report = runtime.audit_report(&snapshot)?;
+    let pages = report.total_pages.to_usize();
+    let have = report.cached_pages.to_usize();
+    if let Some(checksum) = report.checksum {
         Ok(formatdoc!(
             "
                 Cached {pages} of {pages} {} (100%%) from the remote Log.
@@ -438,27 +908,26 @@ fn format_volume_audit(runtime: &Runtime, file: &VolFile) -> Result<String, ErrC
             pluralize!(pages, "page"),
         ))
     } else {
-        let missing = missing_pages.cardinality().to_usize();
-        let have = pages - missing;
-        let pct = (have as f64) / (pages as f64) * 100.0;
         Ok(formatdoc!(
             "
-                Cached {have} of {pages} {} ({pct:.02}%%) from the remote Log.
+                Cached {have} of {pages} {} ({:.02}%%) from the remote Log.
                   (use 'pragma graft_hydrate' to fetch missing pages)
             ",
             pluralize!(pages, "page"),
+            report.pct_cached,
         ))
     }
 }
 
 fn fetch_or_pull(runtime: &Runtime, file: &mut VolFile, pull: bool) -> Result<String, ErrCtx> {
-    let pre = runtime.volume_status(&file.vid)?;
+    let pre = runtime.volume_status(file.vid()?)?;
+    let timeout = file.sync_timeout();
     if pull {
-        runtime.volume_pull(file.vid.clone())?;
+        runtime.volume_pull_timeout(file.vid()?.clone(), timeout)?;
     } else {
-        runtime.fetch_log(pre.remote, None)?;
+        runtime.fetch_log_timeout(pre.remote, None, timeout)?;
     }
-    let post = runtime.volume_status(&file.vid)?;
+    let post = runtime.volume_status(file.vid()?)?;
 
     let mut f = String::new();
 
@@ -477,17 +946,31 @@ fn fetch_or_pull(runtime: &Runtime, file: &mut VolFile, pull: bool) -> Result<St
     Ok(f)
 }
 
-fn push(runtime: &Runtime, file: &mut VolFile) -> Result<String, ErrCtx> {
-    let pre = runtime.volume_status(&file.vid)?;
+fn push(runtime: &Runtime, file: &mut VolFile, squash: bool) -> Result<String, ErrCtx> {
+    let pre = runtime.volume_status(file.vid()?)?;
     if let Some(changes) = pre.local_status.changes()
         && !changes.is_empty()
     {
-        runtime.volume_push(file.vid.clone())?;
-        let post = runtime.volume_status(&file.vid)?;
+        let mut f = String::new();
+        if squash {
+            let pages = runtime.coalesce_pending_changes(file.vid()?)?.cardinality();
+            writeln!(
+                &mut f,
+                "Squashed {} {} into a single commit touching at most {pages} {}",
+                changes.len(),
+                pluralize!(changes.len(), "commit"),
+                pluralize!(pages.to_usize(), "page"),
+            )?;
+            runtime.volume_squash_push_timeout(file.vid()?.clone(), file.sync_timeout())?;
+        } else {
+            runtime.volume_push_timeout(file.vid()?.clone(), file.sync_timeout())?;
+        }
+        let post = runtime.volume_status(file.vid()?)?;
 
         let pushed = AheadStatus::new(post.local_status.base, pre.local_status.base).changes();
 
-        Ok(formatdoc!(
+        writedoc!(
+            &mut f,
             "
                 Pushed LSNs {} from local Log {}
                 to remote Log {} @ {}
@@ -498,53 +981,320 @@ fn push(runtime: &Runtime, file: &mut VolFile) -> Result<String, ErrCtx> {
             post.remote_status
                 .base
                 .map_or("unknown".into(), |l| l.to_string())
-        ))
+        )?;
+        Ok(f)
     } else {
         Ok("Everything up-to-date".to_string())
     }
 }
 
-fn format_tags(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
+fn format_version() -> Result<String, ErrCtx> {
+    const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
+    const GITHUB_SHA: Option<&str> = option_env!("GITHUB_SHA");
+    let mut out = format!("Graft Version: {PKG_VERSION}");
+    if let Some(sha) = GITHUB_SHA {
+        writeln!(&mut out, "\nGit Commit: {sha}")?;
+    }
+    Ok(out)
+}
+
+fn format_whoami(runtime: &Runtime) -> Result<String, ErrCtx> {
+    let mut volumes = 0;
+    let mut iter = runtime.volume_iter();
+    while iter.try_next()?.is_some() {
+        volumes += 1;
+    }
+    let autosync = runtime.autosync_interval().map_or_else(
+        || "disabled".into(),
+        |interval| format!("every {}s", interval.as_secs()),
+    );
+
+    let mut f = format_version()?.trim_end().to_string();
+    writedoc!(
+        &mut f,
+        "
+
+            Data dir: {}
+            Remote: {}
+            Autosync: {autosync}
+            Volumes: {volumes}
+        ",
+        runtime.data_dir().display(),
+        runtime.remote_config(),
+    )?;
+    Ok(f)
+}
+
+fn format_stats(runtime: &Runtime) -> Result<String, ErrCtx> {
+    let stats = runtime.storage_stats()?;
+    let mut f = formatdoc!(
+        "
+            Disk usage: {}
+            Tags: {}
+            Volumes: {}
+            Commits: {}
+            Cached pages: {}
+            Keyspaces:
+        ",
+        stats.disk_space,
+        stats.tags,
+        stats.volumes,
+        stats.commits,
+        stats.pages,
+    );
+    for (name, size) in stats.keyspaces {
+        writeln!(&mut f, "  {name}: {size}")?;
+    }
+    if let Some(diverged) = runtime.mirror_diverged_commits() {
+        writeln!(&mut f, "Mirror diverged commits: {diverged}")?;
+    }
+    Ok(f)
+}
+
+fn reindex(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
+    let entries = runtime.rebuild_page_versions(file.vid()?)?;
+    Ok(format!(
+        "Rebuilt the page version index with {entries} {}",
+        pluralize!(entries, "page version")
+    ))
+}
+
+fn resync(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
+    runtime.resync_from_remote(file.vid()?)?;
+    let pages = file.page_count()?.to_u32();
+    Ok(format!(
+        "Resynced {pages} {} from the remote",
+        pluralize!(pages, "page")
+    ))
+}
+
+fn reset(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
+    let discarded = runtime.volume_reset_to_remote(file.vid()?)?;
+    let volume = runtime.volume_get(file.vid()?)?;
+    let remote_lsn = volume
+        .remote_commit()
+        .expect("BUG: reset volume has no remote commit");
+    Ok(match discarded {
+        Some(lsns) => format!(
+            "Discarded local LSNs {} and reset to remote LSN {remote_lsn}",
+            lsns.to_string()
+        ),
+        None => format!("No local changes to discard; reset to remote LSN {remote_lsn}"),
+    })
+}
+
+fn format_page_count_check(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
+    let Some(check) = runtime.check_page_count(file.vid()?)? else {
+        return Ok("The Volume has never synced with the remote".into());
+    };
+    let lsn = check.remote_lsn;
+    Ok(match check.remote {
+        Some(remote) if check.agrees() => {
+            format!("Page counts agree: remote LSN {lsn} has {remote} pages")
+        }
+        Some(remote) => format!(
+            "Page count mismatch at remote LSN {lsn}: local storage records {} pages, but the remote reports {remote} pages",
+            check.local
+        ),
+        None => format!(
+            "Remote LSN {lsn} is missing from the remote; local storage records {} pages",
+            check.local
+        ),
+    })
+}
+
+fn format_pending_heatmap(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
+    let heatmap = runtime.pending_change_heatmap(file.vid()?)?;
+    if heatmap.is_empty() {
+        return Ok("No pending local changes".into());
+    }
+    let mut f = format!("{:>10}  {:>7}\n", "Page", "Commits");
+    for (pageidx, count) in heatmap {
+        writeln!(&mut f, "{:>10}  {count:>7}", pageidx.to_string())?;
+    }
+    Ok(f)
+}
+
+fn format_frames(commit: &Commit) -> Result<String, ErrCtx> {
+    let Some(segment_idx) = commit.segment_idx() else {
+        return Ok(format!("Commit {} has no segment", commit.logref()));
+    };
+    if segment_idx.frames.is_empty() {
+        return Ok(format!(
+            "Segment {} has no frames; it has not been pushed",
+            segment_idx.sid()
+        ));
+    }
+
+    let mut f = format!(
+        "{:>5}  {:>21}  {:>10}  {:>5}\n",
+        "Frame", "Bytes", "Last Page", "Pages"
+    );
+    let ranges = segment_idx.iter_frames(|_| true);
+    for (i, (frame, range)) in segment_idx.frames.iter().zip(ranges).enumerate() {
+        let bytes = format!("{}..{}", range.bytes.start, range.bytes.end);
+        writeln!(
+            &mut f,
+            "{i:>5}  {bytes:>21}  {:>10}  {:>5}",
+            frame.last_pageidx().to_string(),
+            range.pageset.cardinality(),
+        )?;
+    }
+    Ok(f)
+}
+
+fn format_tags(runtime: &Runtime, file: &VolFile, format: ListFormat) -> Result<String, ErrCtx> {
     let mut f = String::new();
+    let mut table = Table::new(["Tag", "Volume", "Local", "Remote", "Status"]);
     let mut tags = runtime.tag_iter();
     while let Some((tag, vid)) = tags.try_next()? {
         let status = runtime.volume_status(&vid)?;
         let local = &status.local;
         let remote = &status.remote;
+        let current = if tag == file.tag { " (current)" } else { "" };
 
-        writedoc!(
-            &mut f,
-            "
-                Tag: {tag}{}
-                  Volume: {vid}
-                    Local: {local}
-                    Remote: {remote}
-                    Status: {status}
-            ",
-            if tag == file.tag { " (current)" } else { "" }
-        )?;
+        if format == ListFormat::Plain {
+            writedoc!(
+                &mut f,
+                "
+                    Tag: {tag}{current}
+                      Volume: {vid}
+                        Local: {local}
+                        Remote: {remote}
+                        Status: {status}
+                "
+            )?;
+        } else {
+            table.push_row([
+                format!("{tag}{current}"),
+                vid.to_string(),
+                local.to_string(),
+                remote.to_string(),
+                status.to_string(),
+            ]);
+        }
+    }
+    Ok(match format {
+        ListFormat::Plain => f,
+        ListFormat::Tsv => table.to_tsv(),
+        ListFormat::Table => table.to_table(),
+    })
+}
+
+fn format_aliases(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
+    let tags = runtime.tags_for_volume(file.vid()?)?;
+    let mut f = format!(
+        "Volume {} is referenced by {} {}\n",
+        file.vid()?,
+        tags.len(),
+        pluralize!(tags.len(), "tag")
+    );
+    for tag in tags {
+        let current = if tag == file.tag { " (current)" } else { "" };
+        writeln!(&mut f, "  {tag}{current}")?;
     }
     Ok(f)
 }
 
-fn format_volumes(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
+fn format_volumes(runtime: &Runtime, file: &VolFile, format: ListFormat) -> Result<String, ErrCtx> {
     let mut f = String::new();
+    let mut table = Table::new(["Volume", "Local", "Remote", "Status"]);
+    let current_vid = file.vid()?;
     let mut volumes = runtime.volume_iter();
     while let Some(volume) = volumes.try_next()? {
         let vid = volume.vid;
         let status = runtime.volume_status(&vid)?;
         let local = volume.local;
         let remote = volume.remote;
+        let current = if &vid == current_vid {
+            " (current)"
+        } else {
+            ""
+        };
+
+        if format == ListFormat::Plain {
+            writedoc!(
+                &mut f,
+                "
+                    Volume: {vid}{current}
+                      Local: {local}
+                      Remote: {remote}
+                      Status: {status}
+                "
+            )?;
+        } else {
+            table.push_row([
+                format!("{vid}{current}"),
+                local.to_string(),
+                remote.to_string(),
+                status.to_string(),
+            ]);
+        }
+    }
+    Ok(match format {
+        ListFormat::Plain => f,
+        ListFormat::Tsv => table.to_tsv(),
+        ListFormat::Table => table.to_table(),
+    })
+}
 
-        writedoc!(
-            &mut f,
-            "
-                Volume: {vid}{}
-                  Local: {local}
-                  Remote: {remote}
-                  Status: {status}
-            ",
-            if vid == file.vid { " (current)" } else { "" }
+/// Fetches the pages still missing from an interrupted hydrate and reports
+/// progress. A failed fetch is reported rather than returned, as the pages
+/// fetched so far are kept.
+fn resume_hydrate(runtime: &Runtime, file: &VolFile) -> Result<String, ErrCtx> {
+    let snapshot = file.snapshot_or_latest()?;
+    let before = runtime.snapshot_missing_pages(&snapshot)?.cardinality();
+    let result = runtime.snapshot_hydrate(snapshot.clone());
+    let after = runtime.snapshot_missing_pages(&snapshot)?.cardinality();
+    let fetched = before.to_usize() - after.to_usize();
+
+    let mut f = String::new();
+    writeln!(&mut f, "Fetched {fetched} {}.", pluralize!(fetched, "page"))?;
+    match result {
+        Ok(()) => writeln!(&mut f, "The Volume is fully hydrated.")?,
+        Err(err) => {
+            let missing = after.to_usize();
+            writeln!(
+                &mut f,
+                indoc! {"
+                    Hydrate was interrupted with {} {} still missing: {}
+                      (use 'pragma graft_hydrate = \"resume\"' to resume)
+                "},
+                missing,
+                pluralize!(missing, "page"),
+                err
+            )?;
+        }
+    }
+    Ok(f)
+}
+
+fn gc(runtime: &Runtime) -> Result<String, ErrCtx> {
+    let report = runtime.gc_orphan_segments()?;
+    if report.segments.is_empty() {
+        return Ok("No orphaned segments to remove".into());
+    }
+    let segments = report.segments.len();
+    Ok(format!(
+        "Removed {segments} orphaned {}: {} {}, reclaiming {}",
+        pluralize!(segments, "segment"),
+        report.pages,
+        pluralize!(report.pages, "page"),
+        report.bytes
+    ))
+}
+
+fn format_incomplete_volumes(runtime: &Runtime) -> Result<String, ErrCtx> {
+    let incomplete = runtime.incomplete_volumes()?;
+    if incomplete.is_empty() {
+        return Ok("All Volumes are fully hydrated".into());
+    }
+    let mut f = String::new();
+    for (vid, missing) in incomplete {
+        writeln!(
+            f,
+            "{vid}: {missing} missing {}",
+            pluralize!(missing, "page")
         )?;
     }
     Ok(f)
@@ -554,16 +1304,9 @@ fn volume_export(_runtime: &Runtime, file: &VolFile, path: PathBuf) -> Result<St
     // Get a reader based on the current state of the VolFile
     let reader = file.reader()?;
 
-    let page_count = reader.page_count();
-    let total_pages = page_count.to_usize();
-
-    let mut output_file = File::create(&path)?;
-
-    // Iterate over all pages and write them to the output file
-    for page_idx in page_count.iter() {
-        let page = reader.read_page(page_idx)?;
-        output_file.write_all(page.as_ref())?;
-    }
+    let total_pages = reader.page_count().to_usize();
+    let output_file = BufWriter::new(File::create(&path)?);
+    reader.export_to_writer(output_file)?;
 
     Ok(format!(
         "exported {} {}",
@@ -571,3 +1314,21 @@ fn volume_export(_runtime: &Runtime, file: &VolFile, path: PathBuf) -> Result<St
         pluralize!(total_pages, "page")
     ))
 }
+
+fn volume_export_resume(file: &VolFile, path: PathBuf) -> Result<String, ErrCtx> {
+    let reader = file.reader()?;
+
+    let output_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)?;
+    let written = reader.resume_export(output_file)? / PAGESIZE.as_usize();
+
+    Ok(format!(
+        "exported {} remaining {}",
+        written,
+        pluralize!(written, "page")
+    ))
+}
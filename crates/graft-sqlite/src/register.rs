@@ -1,7 +1,11 @@
-use std::ffi::CString;
+use std::{ffi::CString, sync::Mutex};
 
 use graft::setup::{GraftConfig, InitErr, setup_graft};
 
+/// The Graft VFS instances registered in this process, along with whether
+/// each was made the default VFS
+static REGISTERED: Mutex<Vec<(String, bool)>> = Mutex::new(Vec::new());
+
 /// Statically register the Graft SQLite extension with `SQLite`.
 ///
 /// `vfs_name`: The name of the VFS to register with SQLite. Use `graft` if you're not sure.
@@ -12,16 +16,60 @@ pub fn register_static(
     make_default: bool,
     config: GraftConfig,
 ) -> Result<(), InitErr> {
-    let vfs_name = CString::new(vfs_name).expect("VFS name must not contain nul (0) bytes");
+    let mut registered = REGISTERED.lock().expect("registry mutex poisoned");
+    if registered.iter().any(|(name, _)| name == vfs_name) {
+        return Err(std::io::Error::other(format!(
+            "a Graft VFS named `{vfs_name}` is already registered"
+        ))
+        .into());
+    }
+
+    let c_name = CString::new(vfs_name).expect("VFS name must not contain nul (0) bytes");
     let runtime = setup_graft(config)?;
     let vfs = crate::vfs::GraftVfs::new(runtime);
     let opts = sqlite_plugin::vfs::RegisterOpts { make_default };
 
-    sqlite_plugin::vfs::register_static(vfs_name, vfs, opts).map_err(|err| {
+    sqlite_plugin::vfs::register_static(c_name, vfs, opts).map_err(|err| {
         std::io::Error::other(format!(
             "failed to register Graft VFS, received error code {err}"
         ))
     })?;
 
+    if make_default {
+        for (_, is_default) in registered.iter_mut() {
+            *is_default = false;
+        }
+    }
+    registered.push((vfs_name.to_string(), make_default));
+
     Ok(())
 }
+
+/// Statically register an additional named Graft VFS with `SQLite`, without
+/// making it the default VFS.
+///
+/// Each registered VFS has its own Graft runtime, so multiple instances with
+/// different configs (e.g. different remotes or data directories) can coexist
+/// in one process. Select an instance by passing its name as the VFS when
+/// opening a database.
+///
+/// Fails if a Graft VFS with the same name has already been registered.
+pub fn register_named(vfs_name: &str, config: GraftConfig) -> Result<(), InitErr> {
+    register_static(vfs_name, false, config)
+}
+
+/// Returns the names of the Graft VFS instances registered in this process,
+/// in registration order.
+pub fn registered_vfs_names() -> Vec<String> {
+    let registered = REGISTERED.lock().expect("registry mutex poisoned");
+    registered.iter().map(|(name, _)| name.clone()).collect()
+}
+
+/// Returns the name of the Graft VFS registered as the default VFS, if any.
+pub fn default_vfs_name() -> Option<String> {
+    let registered = REGISTERED.lock().expect("registry mutex poisoned");
+    registered
+        .iter()
+        .find(|(_, is_default)| *is_default)
+        .map(|(name, _)| name.clone())
+}
@@ -0,0 +1,136 @@
+use std::{fmt::Write, str::FromStr};
+
+/// The output format of the listing pragmas, e.g. `pragma graft_volumes = "table";`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListFormat {
+    /// Indented text blocks, one per row
+    #[default]
+    Plain,
+
+    /// Tab separated values with a header row
+    Tsv,
+
+    /// Aligned columns surrounded by box-drawing borders
+    Table,
+}
+
+impl FromStr for ListFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(ListFormat::Plain),
+            "tsv" => Ok(ListFormat::Tsv),
+            "table" => Ok(ListFormat::Table),
+            other => Err(format!(
+                "invalid format `{other}`; expected one of: plain, tsv, table"
+            )),
+        }
+    }
+}
+
+/// A small helper for rendering rows of text as tsv or as a table
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: impl Into<Vec<&'static str>>) -> Self {
+        Self { headers: headers.into(), rows: vec![] }
+    }
+
+    pub fn push_row(&mut self, row: impl Into<Vec<String>>) {
+        let row = row.into();
+        debug_assert_eq!(row.len(), self.headers.len(), "row width mismatch");
+        self.rows.push(row);
+    }
+
+    pub fn to_tsv(&self) -> String {
+        let mut f = self.headers.join("\t");
+        f.push('\n');
+        for row in &self.rows {
+            f.push_str(&row.join("\t"));
+            f.push('\n');
+        }
+        f
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let border = |left: char, mid: char, right: char| {
+            let mut line = String::from(left);
+            for (i, width) in widths.iter().enumerate() {
+                if i > 0 {
+                    line.push(mid);
+                }
+                line.extend(std::iter::repeat_n('─', width + 2));
+            }
+            line.push(right);
+            line.push('\n');
+            line
+        };
+        let write_row = |f: &mut String, cells: &mut dyn Iterator<Item = &str>| {
+            f.push('│');
+            for (cell, width) in cells.zip(&widths) {
+                // write! to a String is infallible
+                let _ = write!(f, " {cell:<width$} │");
+            }
+            f.push('\n');
+        };
+
+        let mut f = border('┌', '┬', '┐');
+        write_row(&mut f, &mut self.headers.iter().copied());
+        f.push_str(&border('├', '┼', '┤'));
+        for row in &self.rows {
+            write_row(&mut f, &mut row.iter().map(String::as_str));
+        }
+        f.push_str(&border('└', '┴', '┘'));
+        f
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Table;
+
+    #[test]
+    fn test_table_aligns_columns() {
+        let mut table = Table::new(["Name", "Status"]);
+        table.push_row(["a".to_string(), "ok".to_string()]);
+        table.push_row(["longer name".to_string(), "diverged".to_string()]);
+        table.push_row(["ünïcode".to_string(), "".to_string()]);
+
+        let out = table.to_table();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "┌─────────────┬──────────┐",
+                "│ Name        │ Status   │",
+                "├─────────────┼──────────┤",
+                "│ a           │ ok       │",
+                "│ longer name │ diverged │",
+                "│ ünïcode     │          │",
+                "└─────────────┴──────────┘",
+            ]
+        );
+
+        // every line has the same display width
+        let widths: Vec<usize> = lines.iter().map(|l| l.chars().count()).collect();
+        assert!(widths.iter().all(|w| *w == widths[0]));
+    }
+
+    #[test]
+    fn test_tsv() {
+        let mut table = Table::new(["Name", "Status"]);
+        table.push_row(["a".to_string(), "ok".to_string()]);
+        assert_eq!(table.to_tsv(), "Name\tStatus\na\tok\n");
+    }
+}
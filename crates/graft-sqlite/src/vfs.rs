@@ -1,19 +1,26 @@
-use std::{borrow::Cow, collections::HashMap, fmt::Debug, sync::Arc};
+use std::{borrow::Cow, collections::HashMap, fmt::Debug, ops::Range, sync::Arc, time::Duration};
 
-use graft::{GraftErr, LogicalErr, rt::runtime::Runtime};
+use graft::{GraftErr, LogicalErr, core::PageCount, rt::runtime::Runtime};
 use parking_lot::Mutex;
 use sqlite_plugin::{
-    flags::{AccessFlags, CreateMode, LockLevel, OpenKind, OpenMode, OpenOpts},
+    flags::{AccessFlags, LockLevel, OpenKind, OpenOpts},
     vars::{
-        self, SQLITE_BUSY, SQLITE_BUSY_SNAPSHOT, SQLITE_CANTOPEN, SQLITE_INTERNAL, SQLITE_IOERR,
-        SQLITE_NOTFOUND,
+        self, SQLITE_BUSY, SQLITE_BUSY_SNAPSHOT, SQLITE_CANTOPEN, SQLITE_FULL, SQLITE_INTERNAL,
+        SQLITE_IOERR, SQLITE_NOTFOUND, SQLITE_TOOBIG,
     },
     vfs::{Pragma, PragmaErr, SqliteErr, Vfs, VfsResult},
 };
 use thiserror::Error;
 
 use crate::{
-    file::{FileHandle, VfsFile, mem_file::MemFile, vol_file::VolFile},
+    file::{
+        FileHandle, VfsFile,
+        mem_file::MemFile,
+        vol_file::{
+            DEFAULT_IGNORED_HEADER_RANGES, DEFAULT_SYNC_TIMEOUT, IdleReadConsistency,
+            InvalidHeaderRange, VolFile, normalize_header_ranges, resolve_tag,
+        },
+    },
     pragma::GraftPragma,
 };
 
@@ -43,6 +50,9 @@ pub enum ErrCtx {
     #[error("Invalid volume state")]
     InvalidVolumeState,
 
+    #[error("Offset {offset} exceeds the maximum supported volume size")]
+    PageIndexOverflow { offset: usize },
+
     #[error(transparent)]
     IoErr(#[from] std::io::Error),
 
@@ -65,6 +75,7 @@ impl ErrCtx {
             ErrCtx::TagNotFound => SQLITE_CANTOPEN,
             ErrCtx::Busy => SQLITE_BUSY,
             ErrCtx::BusySnapshot => SQLITE_BUSY_SNAPSHOT,
+            ErrCtx::PageIndexOverflow { .. } => SQLITE_FULL,
             ErrCtx::Graft(err) => Self::map_graft_err(err),
             _ => SQLITE_INTERNAL,
         }
@@ -73,13 +84,20 @@ impl ErrCtx {
     fn map_graft_err(err: &GraftErr) -> SqliteErr {
         match err {
             GraftErr::Storage(_) => SQLITE_IOERR,
+            GraftErr::Remote(err) if err.is_timeout() => SQLITE_BUSY,
             GraftErr::Remote(_) => SQLITE_IOERR,
+            GraftErr::Io(_) => SQLITE_IOERR,
             GraftErr::Logical(err) => match err {
-                LogicalErr::VolumeNotFound(_) => SQLITE_IOERR,
+                LogicalErr::VolumeNotFound(_) | LogicalErr::TagNotFound(_) => SQLITE_IOERR,
                 LogicalErr::VolumeConcurrentWrite(_) => SQLITE_BUSY_SNAPSHOT,
                 LogicalErr::VolumeNeedsRecovery(_)
                 | LogicalErr::VolumeDiverged(_)
-                | LogicalErr::VolumeRemoteMismatch { .. } => SQLITE_INTERNAL,
+                | LogicalErr::VolumeRemoteMismatch { .. }
+                | LogicalErr::PushVerificationFailed { .. }
+                | LogicalErr::VolumeHasLocalChanges(_)
+                | LogicalErr::VolumeNeverSynced(_) => SQLITE_INTERNAL,
+                LogicalErr::VolumeTooLarge { .. } => SQLITE_FULL,
+                LogicalErr::CommitNoteTooLong { .. } => SQLITE_TOOBIG,
             },
         }
     }
@@ -89,11 +107,90 @@ pub struct GraftVfs {
     runtime: Runtime,
     // VolFile locks keyed by tag
     locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    // the number of pages to read ahead during sequential scans
+    read_ahead: Option<PageCount>,
+    // how long to wait for the reserved lock before returning busy
+    lock_wait: Option<Duration>,
+    // header byte ranges which don't cause a commit when they are the only
+    // change to the header page
+    ignored_header_ranges: Arc<[Range<usize>]>,
+    // how long pull and push pragmas wait on the remote
+    sync_timeout: Option<Duration>,
+    // which snapshot reads observe while no lock is held
+    idle_consistency: IdleReadConsistency,
+    // defer resolving a file's Volume until it is first used
+    lazy_open: bool,
 }
 
 impl GraftVfs {
     pub fn new(runtime: Runtime) -> Self {
-        Self { runtime, locks: Default::default() }
+        Self {
+            runtime,
+            locks: Default::default(),
+            read_ahead: None,
+            lock_wait: None,
+            ignored_header_ranges: DEFAULT_IGNORED_HEADER_RANGES.into(),
+            sync_timeout: Some(DEFAULT_SYNC_TIMEOUT),
+            idle_consistency: IdleReadConsistency::Latest,
+            lazy_open: false,
+        }
+    }
+
+    /// Enables read-ahead on all Volume files opened by this VFS
+    pub fn with_read_ahead(self, window: Option<PageCount>) -> Self {
+        Self { read_ahead: window, ..self }
+    }
+
+    /// Configures how long Volume files opened by this VFS wait for another
+    /// connection to release the reserved lock before returning
+    /// `SQLITE_BUSY`. `None` returns busy immediately.
+    pub fn with_lock_wait(self, wait: Option<Duration>) -> Self {
+        Self { lock_wait: wait, ..self }
+    }
+
+    /// Defers resolving the Volume behind each database file opened by this
+    /// VFS until `SQLite` first locks or reads it, so connections which are
+    /// opened but never used don't touch storage. A missing tag is then
+    /// reported by the first operation rather than the open. Defaults to
+    /// false, resolving the Volume when the file is opened.
+    pub fn with_lazy_open(self, lazy: bool) -> Self {
+        Self { lazy_open: lazy, ..self }
+    }
+
+    /// Configures how long pull and push pragmas on Volume files opened by
+    /// this VFS wait on the remote before failing with `SQLITE_BUSY`. `None`
+    /// waits indefinitely. Defaults to `DEFAULT_SYNC_TIMEOUT`.
+    pub fn with_sync_timeout(self, timeout: Option<Duration>) -> Self {
+        Self { sync_timeout: timeout, ..self }
+    }
+
+    /// Configures which snapshot Volume files opened by this VFS observe
+    /// when SQLite reads them without holding a lock. Defaults to
+    /// `IdleReadConsistency::Latest`.
+    pub fn with_idle_read_consistency(self, consistency: IdleReadConsistency) -> Self {
+        Self { idle_consistency: consistency, ..self }
+    }
+
+    /// Configures which byte ranges of the SQLite header may change without
+    /// causing a commit, for SQLite builds which rewrite additional header
+    /// fields on otherwise unchanged transactions. Defaults to
+    /// `DEFAULT_IGNORED_HEADER_RANGES`.
+    ///
+    /// A write to the header page which only changes these ranges is dropped
+    /// entirely, so other connections never observe the new values. Only
+    /// extend this set with fields that SQLite doesn't rely on to detect
+    /// changes made by other connections.
+    ///
+    /// Overlapping ranges are merged. Fails if a range is empty or extends
+    /// past the 100 byte database header.
+    pub fn with_ignored_header_ranges(
+        self,
+        ranges: Vec<Range<usize>>,
+    ) -> Result<Self, InvalidHeaderRange> {
+        Ok(Self {
+            ignored_header_ranges: normalize_header_ranges(ranges)?.into(),
+            ..self
+        })
     }
 }
 
@@ -131,38 +228,36 @@ impl Vfs for GraftVfs {
             if opts.kind() == OpenKind::MainDb
                 && let Some(tag) = path
             {
-                let can_create = matches!(
-                    opts.mode(),
-                    OpenMode::ReadWrite {
-                        create: CreateMode::Create | CreateMode::MustCreate
-                    }
-                );
-
-                let vid = if can_create {
-                    // create the volume if needed
-                    if let Some(vid) = self.runtime.tag_get(tag)? {
-                        vid
-                    } else {
-                        let volume = self.runtime.volume_open(None, None, None)?;
-                        self.runtime.tag_replace(tag, volume.vid.clone())?;
-                        volume.vid
-                    }
+                // resolve the Volume before registering a lock, as a failed
+                // open is never closed
+                let vid = if self.lazy_open {
+                    None
                 } else {
-                    // just get the existing volume
-                    self.runtime.tag_get(tag)?.ok_or(ErrCtx::TagNotFound)?
+                    Some(resolve_tag(&self.runtime, tag, opts)?)
                 };
 
                 // get or create a reserved lock for this Volume
                 let reserved_lock = self.locks.lock().entry(tag.to_owned()).or_default().clone();
 
-                return Ok(VolFile::new(
-                    self.runtime.clone(),
-                    tag.to_owned(),
-                    vid,
-                    opts,
-                    reserved_lock,
-                )
-                .into());
+                let file = match vid {
+                    Some(vid) => VolFile::new(
+                        self.runtime.clone(),
+                        tag.to_owned(),
+                        vid,
+                        opts,
+                        reserved_lock,
+                    ),
+                    None => {
+                        VolFile::new_lazy(self.runtime.clone(), tag.to_owned(), opts, reserved_lock)
+                    }
+                };
+                return Ok(file
+                    .with_read_ahead(self.read_ahead)
+                    .with_lock_wait(self.lock_wait)
+                    .with_ignored_header_ranges(self.ignored_header_ranges.clone())
+                    .with_sync_timeout(self.sync_timeout)
+                    .with_idle_read_consistency(self.idle_consistency)
+                    .into());
             }
 
             // all other files use in-memory storage
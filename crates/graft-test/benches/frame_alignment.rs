@@ -0,0 +1,93 @@
+//! Compares the size of pushed segments and the time to scan a cloned
+//! database, whose pages all live on the remote, across frame alignments.
+//!
+//! Run with `cargo bench -p graft-test --bench frame_alignment`.
+
+use std::{num::NonZero, path::Path, sync::Arc, time::Instant};
+
+use graft::{core::LogId, remote::RemoteConfig};
+use graft_test::GraftTestRuntime;
+
+/// rows in the scanned table; each row fills roughly an eighth of a page
+const ROWS: i64 = 20_000;
+
+/// frame alignments to compare, in bytes
+const ALIGNMENTS: [u64; 4] = [0, 512, 4096, 65536];
+
+fn main() {
+    graft_test::ensure_test_env();
+
+    for alignment in ALIGNMENTS {
+        // use a filesystem remote so that fetches have a real cost
+        let remote_dir = tempfile::tempdir().unwrap();
+        let remote = Arc::new(
+            RemoteConfig::Fs {
+                root: remote_dir.path().to_str().unwrap().to_string(),
+            }
+            .build()
+            .unwrap()
+            .with_frame_alignment(NonZero::new(alignment))
+            .unwrap(),
+        );
+
+        // populate and push the database
+        let log = LogId::random();
+        let mut writer = GraftTestRuntime::with_remote(remote.clone());
+        let sqlite = writer.open_sqlite("main", Some(log.clone()));
+        sqlite
+            .execute_batch(&format!(
+                r#"
+                CREATE TABLE t (id INTEGER PRIMARY KEY, data BLOB NOT NULL);
+                WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < {ROWS})
+                INSERT INTO t SELECT n, randomblob(500) FROM seq;
+                "#
+            ))
+            .unwrap();
+        let start = Instant::now();
+        sqlite.graft_pragma("push").unwrap();
+        let push = start.elapsed();
+        drop(sqlite);
+        writer.shutdown().unwrap();
+        let segment_bytes = dir_size(&remote_dir.path().join("segments"));
+
+        // scan from a fresh runtime so no pages are local
+        let mut runtime = GraftTestRuntime::with_remote(remote.clone());
+        let sqlite = runtime.open_sqlite("main", Some(log));
+        sqlite.graft_pragma("pull").unwrap();
+
+        let start = Instant::now();
+        let bytes: i64 = sqlite
+            .query_row("SELECT sum(length(data)) FROM t", [], |row| row.get(0))
+            .unwrap();
+        let scan = start.elapsed();
+        assert_eq!(bytes, ROWS * 500);
+
+        println!(
+            "frame_alignment {:>5}: {segment_bytes:>10} segment bytes, push {push:>10.2?}, scan {scan:>10.2?}",
+            if alignment == 0 {
+                "off".to_string()
+            } else {
+                alignment.to_string()
+            },
+        );
+
+        drop(sqlite);
+        runtime.shutdown().unwrap();
+    }
+}
+
+/// returns the total size of the files in `dir`
+fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            let meta = entry.metadata().unwrap();
+            if meta.is_dir() {
+                dir_size(&entry.path())
+            } else {
+                meta.len()
+            }
+        })
+        .sum()
+}
@@ -0,0 +1,105 @@
+//! Compares committing to and reading a large Log with and without KV
+//! separation of the log keyspace. See `StorageOptions::log_kv_separation`.
+//!
+//! Run with `cargo bench -p graft-test --bench log_kv_separation`.
+
+use std::{path::Path, sync::Arc};
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use graft::{
+    core::{PageIdx, VolumeId, page::Page},
+    local::fjall_storage::{FjallStorage, StorageOptions},
+    remote::RemoteConfig,
+    rt::runtime::Runtime,
+    volume_writer::VolumeWrite,
+};
+
+/// commits in the Log read by the `read_log` benchmark
+const COMMITS: u32 = 2_000;
+
+/// pages written by each commit, spread over the Volume so that each commit's
+/// segment index isn't a single contiguous run
+const PAGES_PER_COMMIT: u32 = 16;
+
+/// commits write pages among the first `PAGES` pages of the Volume
+const PAGES: u32 = 4096;
+
+/// a Runtime over storage in `dir`, alongside the tokio runtime it uses
+struct Bench {
+    _tokio: tokio::runtime::Runtime,
+    storage: Arc<FjallStorage>,
+    runtime: Runtime,
+    vid: VolumeId,
+}
+
+impl Bench {
+    fn open(dir: &Path, log_kv_separation: bool, vid: Option<VolumeId>) -> Self {
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let remote = Arc::new(RemoteConfig::Memory.build().unwrap());
+        let opts = StorageOptions { log_kv_separation, ..Default::default() };
+        let storage = Arc::new(FjallStorage::open_with_options(dir, opts).unwrap());
+        let runtime = Runtime::new(tokio.handle().clone(), remote, storage.clone(), None);
+        let vid = runtime.volume_open(vid, None, None).unwrap().vid;
+        Self { _tokio: tokio, storage, runtime, vid }
+    }
+
+    /// persists storage, so that it can be reopened, returning the Volume
+    fn close(self) -> VolumeId {
+        self.storage.persist().unwrap();
+        self.vid
+    }
+
+    fn commit(&self, n: u32) {
+        let mut writer = self.runtime.volume_writer(self.vid.clone()).unwrap();
+        for i in 0..PAGES_PER_COMMIT {
+            let pageidx = PageIdx::must_new(1 + (n * 31 + i * 257) % PAGES);
+            writer
+                .write_page(pageidx, Page::test_filled(n as u8))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+    }
+}
+
+fn log_kv_separation(c: &mut Criterion) {
+    graft_test::ensure_test_env();
+
+    let mut group = c.benchmark_group("log_kv_separation");
+    for enabled in [false, true] {
+        // commit to a Log which grows with every iteration
+        let dir = tempfile::tempdir().unwrap();
+        let bench = Bench::open(dir.path(), enabled, None);
+        let mut n = 0;
+        group.bench_function(BenchmarkId::new("commit", enabled), |b| {
+            b.iter(|| {
+                n += 1;
+                bench.commit(n);
+            })
+        });
+        drop(bench);
+
+        // read every commit of a large Log, after reopening storage so that
+        // the Log is read from disk rather than from memory
+        let dir = tempfile::tempdir().unwrap();
+        let bench = Bench::open(dir.path(), enabled, None);
+        for n in 1..=COMMITS {
+            bench.commit(n);
+        }
+        let vid = bench.close();
+        let bench = Bench::open(dir.path(), enabled, Some(vid));
+        let snapshot = bench.runtime.volume_snapshot(&bench.vid).unwrap();
+        group.bench_function(BenchmarkId::new("read_log", enabled), |b| {
+            b.iter(|| {
+                let commits = bench.runtime.snapshot_commits(&snapshot).unwrap();
+                assert_eq!(commits.len(), COMMITS as usize);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, log_kv_separation);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+//! Compares random point lookups from several connections sharing a database
+//! with and without the page cache shared across connections.
+//!
+//! Run with `cargo bench -p graft-test --bench page_cache`.
+
+use std::{num::NonZero, time::Instant};
+
+use graft_test::GraftTestRuntime;
+
+/// rows in the queried table; each row fills roughly an eighth of a page
+const ROWS: i64 = 20_000;
+
+/// connections reading concurrently, each on its own thread
+const CONNECTIONS: i64 = 4;
+
+/// point lookups made by each connection
+const LOOKUPS: i64 = 20_000;
+
+/// page cache capacities to compare, in pages
+const CAPACITIES: [Option<usize>; 3] = [None, Some(256), Some(4096)];
+
+fn main() {
+    graft_test::ensure_test_env();
+
+    for capacity in CAPACITIES {
+        let mut runtime = GraftTestRuntime::with_memory_remote();
+        runtime.set_page_cache_capacity(capacity.and_then(NonZero::new));
+
+        // populate the database; every page stays local
+        let sqlite = runtime.open_sqlite("main", None);
+        sqlite
+            .execute_batch(&format!(
+                r#"
+                CREATE TABLE t (id INTEGER PRIMARY KEY, data BLOB NOT NULL);
+                WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < {ROWS})
+                INSERT INTO t SELECT n, randomblob(500) FROM seq;
+                "#
+            ))
+            .unwrap();
+        drop(sqlite);
+
+        // keep SQLite's own per-connection cache small so that reads reach
+        // the VFS
+        let conns: Vec<_> = (0..CONNECTIONS)
+            .map(|_| {
+                let sqlite = runtime.open_sqlite("main", None);
+                sqlite.execute_batch("PRAGMA cache_size = 16").unwrap();
+                sqlite
+            })
+            .collect();
+
+        let start = Instant::now();
+        std::thread::scope(|s| {
+            for (i, sqlite) in conns.into_iter().enumerate() {
+                s.spawn(move || {
+                    let mut stmt = sqlite
+                        .prepare("SELECT length(data) FROM t WHERE id = ?")
+                        .unwrap();
+                    for n in 0..LOOKUPS {
+                        // each connection visits the rows in a different order
+                        let id = (n * 7919 + i as i64 * 104_729) % ROWS + 1;
+                        let len: i64 = stmt.query_row([id], |row| row.get(0)).unwrap();
+                        assert_eq!(len, 500);
+                    }
+                });
+            }
+        });
+        let elapsed = start.elapsed();
+
+        let stats = runtime.page_cache_stats();
+        println!(
+            "page_cache {:>5}: {elapsed:>10.2?}, {} hits, {} misses",
+            capacity.map_or("off".to_string(), |c| c.to_string()),
+            stats.map_or(0, |s| s.hits),
+            stats.map_or(0, |s| s.misses),
+        );
+
+        runtime.shutdown().unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+//! Compares a full-table scan of a cloned database, whose pages all live on
+//! the remote, with and without read-ahead.
+//!
+//! Run with `cargo bench -p graft-test --bench read_ahead`.
+
+use std::{sync::Arc, time::Instant};
+
+use graft::{
+    core::{LogId, PageCount},
+    remote::RemoteConfig,
+};
+use graft_test::GraftTestRuntime;
+
+/// rows in the scanned table; each row fills roughly an eighth of a page
+const ROWS: i64 = 20_000;
+
+/// read-ahead windows to compare, in pages
+const WINDOWS: [Option<u32>; 4] = [None, Some(8), Some(32), Some(128)];
+
+fn main() {
+    graft_test::ensure_test_env();
+
+    // use a filesystem remote so that fetches have a real cost
+    let remote_dir = tempfile::tempdir().unwrap();
+    let remote = Arc::new(
+        RemoteConfig::Fs {
+            root: remote_dir.path().to_str().unwrap().to_string(),
+        }
+        .build()
+        .unwrap(),
+    );
+
+    // populate and push the database
+    let log = LogId::random();
+    let mut writer = GraftTestRuntime::with_remote(remote.clone());
+    let sqlite = writer.open_sqlite("main", Some(log.clone()));
+    sqlite
+        .execute_batch(&format!(
+            r#"
+            CREATE TABLE t (id INTEGER PRIMARY KEY, data BLOB NOT NULL);
+            WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < {ROWS})
+            INSERT INTO t SELECT n, randomblob(500) FROM seq;
+            "#
+        ))
+        .unwrap();
+    sqlite.graft_pragma("push").unwrap();
+    drop(sqlite);
+    writer.shutdown().unwrap();
+
+    for window in WINDOWS {
+        // each scan starts from a fresh runtime so no pages are local
+        let mut runtime = GraftTestRuntime::with_remote(remote.clone());
+        runtime.configure_vfs(|vfs| vfs.with_read_ahead(window.map(PageCount::new)));
+        let sqlite = runtime.open_sqlite("main", Some(log.clone()));
+        sqlite.graft_pragma("pull").unwrap();
+
+        let start = Instant::now();
+        let bytes: i64 = sqlite
+            .query_row("SELECT sum(length(data)) FROM t", [], |row| row.get(0))
+            .unwrap();
+        let elapsed = start.elapsed();
+        assert_eq!(bytes, ROWS * 500);
+
+        let vid = runtime.tag_get("main").unwrap().unwrap();
+        let metrics = runtime.volume_metrics(&vid);
+        println!(
+            "read_ahead {:>4}: {elapsed:>10.2?}, {} pages fetched, {} storage reads",
+            window.map_or("off".to_string(), |w| w.to_string()),
+            metrics.pages_fetched,
+            metrics.storage_reads,
+        );
+
+        drop(sqlite);
+        runtime.shutdown().unwrap();
+    }
+}
@@ -175,6 +175,22 @@ fn main_inner() -> Result<(), TestErr> {
         remote,
         data_dir: data_dir.clone(),
         autosync: None,
+        write_amp_threshold: None,
+        fetch_timeout: None,
+        max_commits_per_fetch: None,
+        corruption_policy: Default::default(),
+        compact_after_commits: 0,
+        verify_push: false,
+        frame_alignment: 0,
+        fill_byte: 0,
+        hydrate_retries: 0,
+        page_cache_pages: 0,
+        max_pages: 0,
+        event_log: None,
+        event_log_durability: Default::default(),
+        mirror_dir: None,
+        mirror_mode: Default::default(),
+        dedup_pages: false,
     })?;
 
     // initialize the main tag if needed
@@ -113,6 +113,12 @@ impl GraftTestRuntime {
 
     /// Ensures the VFS is registered and returns its name.
     pub fn ensure_vfs(&mut self) -> &str {
+        self.configure_vfs(|vfs| vfs)
+    }
+
+    /// Ensures the VFS is registered and returns its name. If the VFS hasn't
+    /// been registered yet, `configure` customizes it first.
+    pub fn configure_vfs(&mut self, configure: impl FnOnce(GraftVfs) -> GraftVfs) -> &str {
         let vfs_id = self.vfs_id.get_or_insert_with(|| {
             // generate a 16 byte random ascii CString
             let vfs_id = {
@@ -124,7 +130,7 @@ impl GraftTestRuntime {
             };
             register_static(
                 vfs_id.clone(),
-                GraftVfs::new(self.runtime.clone()),
+                configure(GraftVfs::new(self.runtime.clone())),
                 RegisterOpts { make_default: false },
             )
             .expect("failed to register vfs");
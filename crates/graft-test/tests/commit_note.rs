@@ -0,0 +1,46 @@
+use graft::{
+    GraftErr, LogicalErr,
+    core::{LogId, commit::MAX_NOTE_LEN, page::Page},
+    lsn, pageidx,
+    volume_writer::VolumeWrite,
+};
+use graft_test::GraftTestRuntime;
+
+#[test]
+fn test_commit_note() -> anyhow::Result<()> {
+    graft_test::ensure_test_env();
+
+    let remote = LogId::random();
+    let runtime = GraftTestRuntime::with_memory_remote();
+    let volume = runtime.volume_open(None, None, Some(remote.clone()))?;
+    let vid = volume.vid;
+
+    // commit with a note
+    let mut writer = runtime.volume_writer(vid.clone())?;
+    writer.write_page(pageidx!(1), Page::test_filled(1))?;
+    writer.commit_with_note("initial import")?;
+
+    // the note round trips through local storage
+    let commit = runtime.get_commit(&volume.local, lsn!(1))?.unwrap();
+    assert_eq!(commit.note().map(|n| &**n), Some("initial import"));
+
+    // notes which are too long are rejected
+    let mut writer = runtime.volume_writer(vid.clone())?;
+    writer.write_page(pageidx!(2), Page::test_filled(2))?;
+    assert!(matches!(
+        writer.commit_with_note("x".repeat(MAX_NOTE_LEN + 1)),
+        Err(GraftErr::Logical(LogicalErr::CommitNoteTooLong { .. }))
+    ));
+
+    // the note is replicated to the remote
+    runtime.volume_push(vid.clone())?;
+    let peer = runtime.spawn_peer();
+    peer.fetch_log(remote.clone(), None)?;
+    let commit = peer.get_commit(&remote, lsn!(1))?.unwrap();
+    assert_eq!(commit.note().map(|n| &**n), Some("initial import"));
+
+    runtime.shutdown().unwrap();
+    peer.shutdown().unwrap();
+
+    Ok(())
+}
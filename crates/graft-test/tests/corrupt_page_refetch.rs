@@ -0,0 +1,63 @@
+use graft::{
+    core::{LogId, page::Page},
+    lsn, pageidx,
+    rt::runtime::CorruptionPolicy,
+    volume_reader::VolumeRead,
+    volume_writer::VolumeWrite,
+};
+use graft_test::GraftTestRuntime;
+
+#[test]
+fn test_corrupt_page_refetch() -> anyhow::Result<()> {
+    graft_test::ensure_test_env();
+
+    let remote = LogId::random();
+    let runtime = GraftTestRuntime::with_memory_remote();
+    let vid = runtime.volume_open(None, None, Some(remote.clone()))?.vid;
+
+    // push a page to the remote
+    let mut writer = runtime.volume_writer(vid.clone())?;
+    writer.write_page(pageidx!(1), Page::test_filled(1))?;
+    writer.commit()?;
+    runtime.volume_push(vid.clone())?;
+
+    // cache the page in a peer
+    let peer = runtime.spawn_peer();
+    let vid2 = peer.volume_open(None, None, Some(remote.clone()))?.vid;
+    peer.volume_pull(vid2.clone())?;
+    peer.snapshot_hydrate(peer.volume_snapshot(&vid2)?)?;
+
+    // corrupt the peer's cached copy of the page
+    let commit = peer.get_commit(&remote, lsn!(1))?.unwrap();
+    let sid = commit.segment_idx().unwrap().sid().clone();
+    peer.testonly_corrupt_page(sid, pageidx!(1))?;
+
+    // by default, the read fails
+    let reader = peer.volume_reader(vid2.clone())?;
+    assert!(reader.read_page(pageidx!(1)).is_err());
+
+    // refetching transparently recovers the page from the remote
+    peer.set_corruption_policy(CorruptionPolicy::Refetch);
+    assert_eq!(reader.read_page(pageidx!(1))?, Page::test_filled(1));
+
+    // the recovered page is cached again
+    peer.set_corruption_policy(CorruptionPolicy::Fail);
+    assert_eq!(reader.read_page(pageidx!(1))?, Page::test_filled(1));
+
+    // a corrupt page which was never pushed can't be refetched
+    let mut writer = peer.volume_writer(vid2.clone())?;
+    writer.write_page(pageidx!(2), Page::test_filled(2))?;
+    writer.commit()?;
+    let local = peer.volume_get(&vid2)?.local;
+    let commit = peer.get_commit(&local, lsn!(1))?.unwrap();
+    let sid = commit.segment_idx().unwrap().sid().clone();
+    peer.testonly_corrupt_page(sid, pageidx!(2))?;
+    peer.set_corruption_policy(CorruptionPolicy::Refetch);
+    let reader = peer.volume_reader(vid2)?;
+    assert!(reader.read_page(pageidx!(2)).is_err());
+
+    runtime.shutdown().unwrap();
+    peer.shutdown().unwrap();
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use graft::{
+    core::{PageIdx, page::Page},
+    volume_writer::VolumeWrite,
+};
+use graft_test::GraftTestRuntime;
+
+#[test]
+fn test_hydrate_resume() -> anyhow::Result<()> {
+    graft_test::ensure_test_env();
+
+    let runtime = GraftTestRuntime::with_memory_remote();
+    let peer = runtime.spawn_peer();
+
+    // push two commits so the pages live in separate segments, which are
+    // fetched by separate requests
+    let vid = runtime.volume_open(None, None, None)?.vid;
+    for pages in [1..=64, 65..=128] {
+        let mut writer = runtime.volume_writer(vid.clone())?;
+        for i in pages {
+            writer.write_page(PageIdx::must_new(i), Page::test_filled(i as u8))?;
+        }
+        writer.commit()?;
+        runtime.volume_push(vid.clone())?;
+    }
+    let remote = runtime.volume_get(&vid)?.remote;
+
+    let missing = |peer: &GraftTestRuntime, vid| {
+        let snapshot = peer.volume_snapshot(vid).unwrap();
+        peer.snapshot_missing_pages(&snapshot)
+            .unwrap()
+            .cardinality()
+            .to_usize()
+    };
+
+    // the network drops partway through hydrating
+    let vid2 = peer.volume_open(None, None, Some(remote.clone()))?.vid;
+    peer.volume_pull(vid2.clone())?;
+    assert_eq!(missing(&peer, &vid2), 128);
+    precept::fault::get_fault_by_name("FetchSegment: network loss")
+        .unwrap()
+        .set_pending(1);
+    assert!(peer.snapshot_hydrate(peer.volume_snapshot(&vid2)?).is_err());
+
+    // pages fetched before the failure are kept
+    assert_eq!(missing(&peer, &vid2), 64);
+
+    // resuming only fetches the remaining pages
+    peer.snapshot_hydrate(peer.volume_snapshot(&vid2)?)?;
+    assert_eq!(missing(&peer, &vid2), 0);
+
+    // with retries enabled, a single interruption is recovered automatically
+    let retry_peer = runtime.spawn_peer();
+    retry_peer.set_hydrate_retries(1);
+    let vid3 = retry_peer.volume_open(None, None, Some(remote))?.vid;
+    retry_peer.volume_pull(vid3.clone())?;
+    precept::fault::get_fault_by_name("FetchSegment: network loss")
+        .unwrap()
+        .set_pending(1);
+    retry_peer.snapshot_hydrate(retry_peer.volume_snapshot(&vid3)?)?;
+    assert_eq!(missing(&retry_peer, &vid3), 0);
+
+    runtime.shutdown().unwrap();
+    peer.shutdown().unwrap();
+    retry_peer.shutdown().unwrap();
+
+    Ok(())
+}
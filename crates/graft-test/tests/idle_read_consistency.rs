@@ -0,0 +1,82 @@
+use graft::{core::page::Page, pageidx, rt::runtime::Runtime, volume_writer::VolumeWrite};
+use graft_sqlite::{
+    file::{
+        VfsFile,
+        vol_file::{IdleReadConsistency, VolFile},
+    },
+    pragma::GraftPragma,
+};
+use graft_test::GraftTestRuntime;
+use sqlite_plugin::{
+    flags::{LockLevel, OpenOpts},
+    vars::{SQLITE_OPEN_MAIN_DB, SQLITE_OPEN_READWRITE},
+};
+
+#[test]
+fn test_idle_read_consistency() -> anyhow::Result<()> {
+    graft_test::ensure_test_env();
+
+    let runtime = GraftTestRuntime::with_memory_remote();
+    let vid = runtime.volume_open(None, None, None)?.vid;
+    let mut writer = runtime.volume_writer(vid.clone())?;
+    writer.write_page(pageidx!(1), Page::test_filled(1))?;
+    writer.commit()?;
+
+    let open_file = |consistency| {
+        VolFile::new(
+            Runtime::clone(&runtime),
+            "main".into(),
+            vid.clone(),
+            OpenOpts::from(SQLITE_OPEN_MAIN_DB | SQLITE_OPEN_READWRITE),
+            Default::default(),
+        )
+        .with_idle_read_consistency(consistency)
+    };
+    let mut pinned = open_file(IdleReadConsistency::Pinned);
+    let mut latest = open_file(IdleReadConsistency::Latest);
+
+    // read the start of the header, before the file change counter which
+    // reads overwrite with a counter derived from the snapshot
+    let read_header = |file: &mut VolFile| {
+        let mut header = [0; 16];
+        file.read(0, &mut header).unwrap();
+        header
+    };
+    let original_size = pinned.file_size()?;
+    assert_eq!(read_header(&mut pinned), [1; 16]);
+    assert_eq!(read_header(&mut latest), [1; 16]);
+
+    // another connection commits while neither file holds a lock
+    let mut writer = runtime.volume_writer(vid.clone())?;
+    writer.write_page(pageidx!(1), Page::test_filled(2))?;
+    writer.write_page(pageidx!(2), Page::test_filled(2))?;
+    writer.commit()?;
+
+    // the pinned file keeps observing the snapshot it first read
+    assert_eq!(read_header(&mut pinned), [1; 16]);
+    assert_eq!(pinned.file_size()?, original_size);
+    assert_eq!(read_header(&mut latest), [2; 16]);
+    assert_eq!(latest.file_size()?, original_size * 2);
+
+    // acquiring a lock releases the pinned snapshot
+    pinned.lock(LockLevel::Shared)?;
+    pinned.unlock(LockLevel::Unlocked)?;
+    assert_eq!(read_header(&mut pinned), [2; 16]);
+    assert_eq!(pinned.file_size()?, original_size * 2);
+
+    // pragmas report the latest snapshot rather than the pinned one
+    let mut writer = runtime.volume_writer(vid.clone())?;
+    writer.write_page(pageidx!(3), Page::test_filled(3))?;
+    writer.commit()?;
+    assert_eq!(read_header(&mut pinned), [2; 16]);
+    let latest = format!("{:?}", runtime.volume_snapshot(&vid)?);
+    assert_eq!(
+        GraftPragma::Snapshot.eval(&runtime, &mut pinned)?,
+        Some(latest)
+    );
+    assert_eq!(pinned.file_size()?, original_size * 3);
+
+    runtime.shutdown().unwrap();
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use graft::{
+    core::page::{PAGESIZE, Page},
+    pageidx,
+    rt::runtime::Runtime,
+    volume_reader::VolumeRead,
+    volume_writer::VolumeWrite,
+};
+use graft_sqlite::file::{VfsFile, vol_file::VolFile};
+use graft_test::GraftTestRuntime;
+use sqlite_plugin::{
+    flags::{LockLevel, OpenOpts},
+    vars::{SQLITE_OPEN_MAIN_DB, SQLITE_OPEN_READWRITE},
+};
+
+#[test]
+fn test_ignored_header_ranges() -> anyhow::Result<()> {
+    graft_test::ensure_test_env();
+
+    let runtime = GraftTestRuntime::with_memory_remote();
+    let vid = runtime.volume_open(None, None, None)?.vid;
+    let mut writer = runtime.volume_writer(vid.clone())?;
+    writer.write_page(pageidx!(1), Page::test_filled(1))?;
+    writer.commit()?;
+    assert_eq!(runtime.pending_commits(&vid)?, 1);
+
+    // the file ignores the default ranges: the file change counter and the
+    // version valid for number
+    let mut file = VolFile::new(
+        Runtime::clone(&runtime),
+        "main".into(),
+        vid.clone(),
+        OpenOpts::from(SQLITE_OPEN_MAIN_DB | SQLITE_OPEN_READWRITE),
+        Default::default(),
+    );
+
+    // rewrites the header page with the given bytes changed, as SQLite does
+    // at the end of each write transaction
+    let mut write_header = |offsets: &[usize]| -> anyhow::Result<()> {
+        let mut header = [1; PAGESIZE.as_usize()];
+        for &offset in offsets {
+            header[offset] = 2;
+        }
+        file.lock(LockLevel::Shared)?;
+        file.lock(LockLevel::Reserved)?;
+        file.lock(LockLevel::Exclusive)?;
+        file.write(0, &header)?;
+        file.unlock(LockLevel::Shared)?;
+        file.unlock(LockLevel::Unlocked)?;
+        Ok(())
+    };
+
+    // a write which only changes ignored ranges doesn't commit
+    write_header(&[24, 27, 92, 95])?;
+    assert_eq!(runtime.pending_commits(&vid)?, 1);
+    let reader = runtime.volume_reader(vid.clone())?;
+    assert_eq!(reader.read_page(pageidx!(1))?, Page::test_filled(1));
+
+    // a write which also changes the schema cookie commits
+    write_header(&[24, 40])?;
+    assert_eq!(runtime.pending_commits(&vid)?, 2);
+    let reader = runtime.volume_reader(vid.clone())?;
+    let page = reader.read_page(pageidx!(1))?;
+    assert_eq!(page.as_ref()[24], 2);
+    assert_eq!(page.as_ref()[40], 2);
+
+    runtime.shutdown().unwrap();
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use graft::{
+    core::{LogId, page::Page},
+    pageidx,
+    volume_reader::VolumeRead,
+    volume_writer::VolumeWrite,
+};
+use graft_test::GraftTestRuntime;
+
+#[test]
+fn test_incomplete_volumes() -> anyhow::Result<()> {
+    graft_test::ensure_test_env();
+
+    let runtime = GraftTestRuntime::with_memory_remote();
+    let peer = runtime.spawn_peer();
+
+    // push two remote volumes, each with its pages in separate segments so
+    // that they can be fetched independently
+    let remotes = [LogId::random(), LogId::random()];
+    for remote in &remotes {
+        let vid = runtime.volume_open(None, None, Some(remote.clone()))?.vid;
+        for (pageidx, fill) in [(pageidx!(1), 1), (pageidx!(2), 2)] {
+            let mut writer = runtime.volume_writer(vid.clone())?;
+            writer.write_page(pageidx, Page::test_filled(fill))?;
+            writer.commit()?;
+            runtime.volume_push(vid.clone())?;
+        }
+    }
+
+    // a volume with only local changes is fully hydrated
+    let local = runtime.volume_open(None, None, None)?.vid;
+    let mut writer = runtime.volume_writer(local)?;
+    writer.write_page(pageidx!(1), Page::test_filled(3))?;
+    writer.commit()?;
+    assert!(runtime.incomplete_volumes()?.is_empty());
+
+    // pull both volumes into the peer without fetching any pages
+    let [hydrated, partial] = remotes.map(|remote| {
+        let vid = peer.volume_open(None, None, Some(remote)).unwrap().vid;
+        peer.volume_pull(vid.clone()).unwrap();
+        vid
+    });
+    let mut incomplete = peer.incomplete_volumes()?;
+    incomplete.sort();
+    let mut expected = vec![(hydrated.clone(), 2), (partial.clone(), 2)];
+    expected.sort();
+    assert_eq!(incomplete, expected);
+
+    // hydrating a volume removes it from the list
+    peer.snapshot_hydrate(peer.volume_snapshot(&hydrated)?)?;
+    assert_eq!(peer.incomplete_volumes()?, [(partial.clone(), 2)]);
+
+    // reading a page fetches it, partially hydrating the volume
+    let reader = peer.volume_reader(partial.clone())?;
+    assert_eq!(reader.read_page(pageidx!(1))?, Page::test_filled(1));
+    assert_eq!(peer.incomplete_volumes()?, [(partial.clone(), 1)]);
+
+    // once offline, the hydrated page is still readable but the missing page
+    // isn't, and the volume remains incomplete
+    precept::fault::get_fault_by_name("FetchSegment: network loss")
+        .unwrap()
+        .set_pending(1);
+    assert_eq!(reader.read_page(pageidx!(1))?, Page::test_filled(1));
+    assert!(reader.read_page(pageidx!(2)).is_err());
+    assert_eq!(peer.incomplete_volumes()?, [(partial.clone(), 1)]);
+
+    // back online, reading the missing page completes the volume
+    assert_eq!(reader.read_page(pageidx!(2))?, Page::test_filled(2));
+    assert!(peer.incomplete_volumes()?.is_empty());
+
+    runtime.shutdown().unwrap();
+    peer.shutdown().unwrap();
+
+    Ok(())
+}
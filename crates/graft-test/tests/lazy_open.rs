@@ -0,0 +1,61 @@
+use graft::{core::page::Page, pageidx, rt::runtime::Runtime, volume_writer::VolumeWrite};
+use graft_sqlite::{
+    file::{VfsFile, vol_file::VolFile},
+    vfs::ErrCtx,
+};
+use graft_test::GraftTestRuntime;
+use sqlite_plugin::{
+    flags::{LockLevel, OpenOpts},
+    vars::{SQLITE_OPEN_CREATE, SQLITE_OPEN_MAIN_DB, SQLITE_OPEN_READONLY, SQLITE_OPEN_READWRITE},
+};
+
+#[test]
+fn test_lazy_open() -> anyhow::Result<()> {
+    graft_test::ensure_test_env();
+
+    let runtime = GraftTestRuntime::with_memory_remote();
+    let open_lazy = |tag: &str, flags| {
+        VolFile::new_lazy(
+            Runtime::clone(&runtime),
+            tag.into(),
+            OpenOpts::from(SQLITE_OPEN_MAIN_DB | flags),
+            Default::default(),
+        )
+    };
+
+    // opening the file touches no storage
+    let mut file = open_lazy("main", SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE);
+    assert_eq!(runtime.tag_get("main")?, None);
+    assert_eq!(runtime.volume_iter().count(), 0);
+
+    // the first lock creates and tags the Volume
+    file.lock(LockLevel::Shared)?;
+    let vid = runtime.tag_get("main")?.expect("tag should exist");
+    assert_eq!(file.vid()?, &vid);
+    assert_eq!(runtime.volume_iter().count(), 1);
+    file.unlock(LockLevel::Unlocked)?;
+
+    // a file which may not create its Volume only fails once it is used
+    let mut missing = open_lazy("missing", SQLITE_OPEN_READONLY);
+    // read before the file change counter, which reads overwrite
+    let mut header = [0; 16];
+    assert!(matches!(
+        missing.read(0, &mut header),
+        Err(ErrCtx::TagNotFound)
+    ));
+    assert_eq!(runtime.tag_get("missing")?, None);
+
+    // a lazy file resolves an existing tag to its Volume on first read
+    let mut writer = runtime.volume_writer(vid.clone())?;
+    writer.write_page(pageidx!(1), Page::test_filled(1))?;
+    writer.commit()?;
+    let mut existing = open_lazy("main", SQLITE_OPEN_READONLY);
+    existing.read(0, &mut header)?;
+    assert_eq!(header, [1; 16]);
+    assert_eq!(existing.vid()?, &vid);
+    assert_eq!(runtime.volume_iter().count(), 1);
+
+    runtime.shutdown().unwrap();
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use graft::{remote::RemoteConfig, setup::GraftConfig};
+use graft_sqlite::{register_named, registered_vfs_names};
+use rusqlite::{Connection, OpenFlags};
+
+fn config(data_dir: &std::path::Path) -> GraftConfig {
+    GraftConfig {
+        remote: RemoteConfig::Memory,
+        data_dir: data_dir.to_path_buf(),
+        autosync: None,
+        write_amp_threshold: None,
+        fetch_timeout: None,
+        max_commits_per_fetch: None,
+        corruption_policy: Default::default(),
+        compact_after_commits: 0,
+        verify_push: false,
+        frame_alignment: 0,
+        fill_byte: 0,
+        hydrate_retries: 0,
+        page_cache_pages: 0,
+        max_pages: 0,
+        event_log: None,
+        event_log_durability: Default::default(),
+        mirror_dir: None,
+        mirror_mode: Default::default(),
+        dedup_pages: false,
+    }
+}
+
+#[test]
+fn test_register_named() {
+    graft_test::ensure_test_env();
+
+    let dir_a = tempfile::tempdir().unwrap();
+    let dir_b = tempfile::tempdir().unwrap();
+    register_named("graft_named_a", config(dir_a.path())).unwrap();
+    register_named("graft_named_b", config(dir_b.path())).unwrap();
+
+    // names must be unique
+    assert!(register_named("graft_named_a", config(dir_b.path())).is_err());
+
+    let names = registered_vfs_names();
+    assert!(names.iter().any(|n| n == "graft_named_a"));
+    assert!(names.iter().any(|n| n == "graft_named_b"));
+
+    // write a different value into a database under each VFS
+    let flags = OpenFlags::default();
+    let db_a = Connection::open_with_flags_and_vfs("main", flags, "graft_named_a").unwrap();
+    let db_b = Connection::open_with_flags_and_vfs("main", flags, "graft_named_b").unwrap();
+    for (db, value) in [(&db_a, 1), (&db_b, 2)] {
+        db.execute_batch("CREATE TABLE t (v INTEGER)").unwrap();
+        db.execute("INSERT INTO t VALUES (?)", [value]).unwrap();
+    }
+
+    // each VFS is backed by its own data dir, so the databases are distinct
+    for (db, expected) in [(&db_a, 1), (&db_b, 2)] {
+        let value: i64 = db
+            .query_row("SELECT v FROM t", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(value, expected);
+    }
+    let whoami = |db: &Connection| -> String {
+        db.query_row("pragma graft_whoami", [], |row| row.get(0))
+            .unwrap()
+    };
+    assert!(whoami(&db_a).contains(&dir_a.path().display().to_string()));
+    assert!(whoami(&db_b).contains(&dir_b.path().display().to_string()));
+}
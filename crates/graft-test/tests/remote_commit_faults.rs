@@ -1,6 +1,7 @@
 use std::panic::{AssertUnwindSafe, catch_unwind};
 
 use graft::{
+    GraftErr, LogicalErr,
     core::{LogId, PageIdx, page::Page},
     volume_reader::VolumeRead,
     volume_writer::VolumeWrite,
@@ -54,6 +55,54 @@ fn test_skip_segment_cache() {
     assert_eq!(page, Page::test_filled(123));
 }
 
+#[test]
+fn test_segment_upload_failure() {
+    graft_test::ensure_test_env();
+
+    let runtime = GraftTestRuntime::with_memory_remote();
+
+    let vid = runtime.volume_open(None, None, None).unwrap().vid;
+    let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+    writer
+        .write_page(PageIdx::FIRST, Page::test_filled(123))
+        .unwrap();
+    writer.commit().unwrap();
+
+    // the push fails while uploading the segment
+    let fault = precept::fault::get_fault_by_name("RemoteCommit: segment upload failed").unwrap();
+    fault.set_pending(1);
+    let err = runtime.volume_push(vid.clone()).unwrap_err();
+    tracing::info!("push failed as expected: {err}");
+
+    // nothing was prepared, so there is no pending commit to recover and the
+    // local commit is still waiting to be pushed
+    let volume = runtime.volume_get(&vid).unwrap();
+    assert!(volume.pending_commit().is_none());
+    assert_eq!(runtime.volume_status(&vid).unwrap().to_string(), "+1 r_");
+    let reader = runtime.volume_reader(vid.clone()).unwrap();
+    assert_eq!(
+        reader.read_page(PageIdx::FIRST).unwrap(),
+        Page::test_filled(123)
+    );
+
+    // a subsequent push succeeds
+    runtime.volume_push(vid.clone()).unwrap();
+    assert_eq!(runtime.volume_status(&vid).unwrap().to_string(), "1 r1");
+
+    // make sure we can pull the page to a peer
+    let peer = runtime.spawn_peer();
+    let vid2 = peer
+        .volume_open(None, None, Some(volume.remote))
+        .unwrap()
+        .vid;
+    peer.volume_pull(vid2.clone()).unwrap();
+    let reader = peer.volume_reader(vid2).unwrap();
+    assert_eq!(
+        reader.read_page(PageIdx::FIRST).unwrap(),
+        Page::test_filled(123)
+    );
+}
+
 #[test]
 fn test_bank_balance_skip_seg_cache() {
     graft_test::ensure_test_env();
@@ -139,3 +188,67 @@ fn test_crash_after_commit_recovery() {
     // final validate should be ok
     bank_validate(&mut env).unwrap();
 }
+
+#[test]
+fn test_verify_push_mismatch() {
+    graft_test::ensure_test_env();
+
+    let runtime = GraftTestRuntime::with_memory_remote();
+    runtime.set_verify_push(true);
+
+    // verification succeeds when the remote matches
+    let vid = runtime.volume_open(None, None, None).unwrap().vid;
+    let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+    writer
+        .write_page(PageIdx::FIRST, Page::test_filled(1))
+        .unwrap();
+    writer.commit().unwrap();
+    runtime.volume_push(vid.clone()).unwrap();
+    assert_eq!(runtime.volume_status(&vid).unwrap().to_string(), "1 r1");
+
+    // simulate the remote returning a different commit hash
+    let fault = precept::fault::get_fault_by_name("RemoteCommit: verify push mismatch").unwrap();
+    fault.set_pending(1);
+
+    let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+    writer
+        .write_page(PageIdx::FIRST, Page::test_filled(2))
+        .unwrap();
+    writer.commit().unwrap();
+    let err = runtime.volume_push(vid.clone()).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            GraftErr::Logical(LogicalErr::PushVerificationFailed { .. })
+        ),
+        "unexpected error: {err}"
+    );
+
+    // the local commits were not cleared
+    let volume = runtime.volume_get(&vid).unwrap();
+    assert!(volume.pending_commit().is_some());
+    let reader = runtime.volume_reader(vid.clone()).unwrap();
+    assert_eq!(
+        reader.read_page(PageIdx::FIRST).unwrap(),
+        Page::test_filled(2)
+    );
+
+    // a subsequent push recovers by checking the remote again
+    runtime.volume_push(vid.clone()).unwrap();
+    let volume = runtime.volume_get(&vid).unwrap();
+    assert!(volume.pending_commit().is_none());
+    assert_eq!(runtime.volume_status(&vid).unwrap().to_string(), "2 r2");
+
+    // a peer sees the pushed page
+    let peer = runtime.spawn_peer();
+    let vid2 = peer
+        .volume_open(None, None, Some(volume.remote))
+        .unwrap()
+        .vid;
+    peer.volume_pull(vid2.clone()).unwrap();
+    let reader = peer.volume_reader(vid2).unwrap();
+    assert_eq!(
+        reader.read_page(PageIdx::FIRST).unwrap(),
+        Page::test_filled(2)
+    );
+}
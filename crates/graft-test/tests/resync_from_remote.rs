@@ -0,0 +1,65 @@
+use graft::{
+    GraftErr, LogicalErr,
+    core::{LogId, PageIdx, page::Page},
+    lsn, pageidx,
+    volume_reader::VolumeRead,
+    volume_writer::VolumeWrite,
+};
+use graft_test::GraftTestRuntime;
+
+#[test]
+fn test_resync_from_remote() -> anyhow::Result<()> {
+    graft_test::ensure_test_env();
+
+    let remote = LogId::random();
+    let runtime = GraftTestRuntime::with_memory_remote();
+    let vid = runtime.volume_open(None, None, Some(remote.clone()))?.vid;
+
+    // push some pages to the remote
+    let mut writer = runtime.volume_writer(vid.clone())?;
+    for i in 1..=8 {
+        writer.write_page(PageIdx::must_new(i), Page::test_filled(i as u8))?;
+    }
+    writer.commit()?;
+    runtime.volume_push(vid.clone())?;
+    let expected = runtime.snapshot_checksum(&runtime.volume_snapshot(&vid)?)?;
+
+    // cache the pages in a peer
+    let peer = runtime.spawn_peer();
+    let vid2 = peer.volume_open(None, None, Some(remote.clone()))?.vid;
+    peer.volume_pull(vid2.clone())?;
+    peer.snapshot_hydrate(peer.volume_snapshot(&vid2)?)?;
+
+    // corrupt some of the peer's cached pages
+    let commit = peer.get_commit(&remote, lsn!(1))?.unwrap();
+    let sid = commit.segment_idx().unwrap().sid().clone();
+    peer.testonly_corrupt_page(sid.clone(), pageidx!(1))?;
+    peer.testonly_corrupt_page(sid, pageidx!(5))?;
+    assert!(
+        peer.snapshot_checksum(&peer.volume_snapshot(&vid2)?)
+            .is_err()
+    );
+
+    // resyncing downloads every page again
+    peer.resync_from_remote(&vid2)?;
+    let snapshot = peer.volume_snapshot(&vid2)?;
+    assert!(peer.snapshot_missing_pages(&snapshot)?.is_empty());
+    assert_eq!(peer.snapshot_checksum(&snapshot)?, expected);
+    assert_eq!(peer.volume_status(&vid2)?.to_string(), "_ r1");
+
+    // resyncing refuses to discard unpushed local commits
+    let mut writer = peer.volume_writer(vid2.clone())?;
+    writer.write_page(pageidx!(9), Page::test_filled(9))?;
+    writer.commit()?;
+    assert!(matches!(
+        peer.resync_from_remote(&vid2),
+        Err(GraftErr::Logical(LogicalErr::VolumeHasLocalChanges(_)))
+    ));
+    let reader = peer.volume_reader(vid2)?;
+    assert_eq!(reader.read_page(pageidx!(9))?, Page::test_filled(9));
+
+    runtime.shutdown().unwrap();
+    peer.shutdown().unwrap();
+
+    Ok(())
+}
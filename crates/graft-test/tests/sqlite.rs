@@ -1,6 +1,9 @@
-use graft::core::{LogId, PageCount};
+use std::time::Duration;
+
+use graft::core::{LogId, PageCount, page::Page};
+use graft::{lsn, pageidx, volume_reader::VolumeRead, volume_writer::VolumeWrite};
 use graft_test::GraftTestRuntime;
-use rusqlite::Connection;
+use rusqlite::{Connection, ErrorCode};
 
 #[test]
 fn test_sync_and_reset() {
@@ -294,3 +297,527 @@ fn test_sqlite_query_only_fetches_needed_pages() {
         0
     );
 }
+
+#[test]
+fn test_checkout_checkpoint() {
+    graft_test::ensure_test_env();
+
+    let log = LogId::random();
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let vid = runtime
+        .volume_open(None, None, Some(log.clone()))
+        .unwrap()
+        .vid;
+
+    // push a mix of checkpoint (all pages) and non-checkpoint commits
+    let commits: [&[_]; 4] = [
+        &[(pageidx!(1), 1), (pageidx!(2), 1)],
+        &[(pageidx!(1), 2)],
+        &[(pageidx!(1), 3), (pageidx!(2), 3)],
+        &[(pageidx!(2), 4)],
+    ];
+    for pages in commits {
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        for &(pageidx, fill) in pages {
+            writer.write_page(pageidx, Page::test_filled(fill)).unwrap();
+        }
+        writer.commit().unwrap();
+        runtime.volume_push(vid.clone()).unwrap();
+    }
+    assert_eq!(runtime.nth_checkpoint(&log, 0).unwrap(), Some(lsn!(3)));
+    assert_eq!(runtime.nth_checkpoint(&log, 1).unwrap(), Some(lsn!(1)));
+    assert_eq!(runtime.nth_checkpoint(&log, 2).unwrap(), None);
+
+    let sqlite = runtime.open_sqlite("main", None);
+
+    // checkout the second most recent checkpoint
+    sqlite
+        .graft_pragma_arg("checkout_checkpoint", format!("{log}:2"))
+        .unwrap();
+    let checked_out = runtime.tag_get("main").unwrap().unwrap();
+    let reader = runtime.volume_reader(checked_out).unwrap();
+    assert_eq!(reader.page_count(), PageCount::new(2));
+    assert_eq!(reader.read_page(pageidx!(1)).unwrap(), Page::test_filled(1));
+    assert_eq!(reader.read_page(pageidx!(2)).unwrap(), Page::test_filled(1));
+
+    // checkout the latest checkpoint
+    sqlite
+        .graft_pragma_arg("checkout_checkpoint", format!("{log}:1"))
+        .unwrap();
+    let checked_out = runtime.tag_get("main").unwrap().unwrap();
+    let reader = runtime.volume_reader(checked_out).unwrap();
+    assert_eq!(reader.read_page(pageidx!(1)).unwrap(), Page::test_filled(3));
+    assert_eq!(reader.read_page(pageidx!(2)).unwrap(), Page::test_filled(3));
+
+    // there are only two checkpoints
+    let result = sqlite.graft_pragma_arg("checkout_checkpoint", format!("{log}:3"));
+    assert!(
+        result.is_err(),
+        "checkout should fail with too few checkpoints"
+    );
+
+    runtime.shutdown().unwrap();
+}
+
+#[test]
+fn test_dump_frames() {
+    graft_test::ensure_test_env();
+
+    let log = LogId::random();
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let vid = runtime
+        .volume_open(None, None, Some(log.clone()))
+        .unwrap()
+        .vid;
+
+    // write enough pages to span three frames: 64 + 64 + 2
+    let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+    for pageidx in PageCount::new(130).iter() {
+        writer
+            .write_page(pageidx, Page::test_filled(pageidx.to_u32() as u8))
+            .unwrap();
+    }
+    writer.commit().unwrap();
+    runtime.volume_push(vid).unwrap();
+
+    let commit = runtime.get_commit(&log, lsn!(1)).unwrap().unwrap();
+    let ranges: Vec<_> = commit
+        .segment_idx()
+        .unwrap()
+        .iter_frames(|_| true)
+        .collect();
+    let page_counts: Vec<_> = ranges.iter().map(|r| r.pageset.cardinality()).collect();
+    assert_eq!(
+        page_counts,
+        [PageCount::new(64), PageCount::new(64), PageCount::new(2)]
+    );
+
+    let sqlite = runtime.open_sqlite("main", None);
+    let output: String = sqlite
+        .pragma(None, "graft_dump_frames", format!("{log}:1"), |row| {
+            row.get(0)
+        })
+        .unwrap();
+    let rows: Vec<Vec<&str>> = output
+        .lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    assert_eq!(rows.len(), 3);
+    for (i, (row, range)) in rows.iter().zip(&ranges).enumerate() {
+        let bytes = format!("{}..{}", range.bytes.start, range.bytes.end);
+        let pages = range.pageset.cardinality().to_string();
+        assert_eq!(row[0], i.to_string());
+        assert_eq!(row[1], bytes);
+        assert_eq!(row[3], pages);
+    }
+    assert_eq!(rows[2][2], "130");
+
+    runtime.shutdown().unwrap();
+}
+
+#[test]
+fn test_lock_timeout() {
+    graft_test::ensure_test_env();
+
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let sqlite = runtime.open_sqlite("main", None);
+    sqlite
+        .execute_batch("CREATE TABLE t (v INTEGER); INSERT INTO t VALUES (1);")
+        .unwrap();
+
+    sqlite.graft_pragma_arg("lock_timeout", 50).unwrap();
+
+    // a transaction which finishes within the timeout commits normally
+    sqlite
+        .execute_batch("BEGIN IMMEDIATE; INSERT INTO t VALUES (2); COMMIT;")
+        .unwrap();
+
+    // a transaction which holds the reserved lock for too long is rejected
+    sqlite
+        .execute_batch("BEGIN IMMEDIATE; INSERT INTO t VALUES (3);")
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    let err = sqlite.execute_batch("COMMIT").unwrap_err();
+    assert_eq!(err.sqlite_error_code(), Some(ErrorCode::DatabaseBusy));
+    sqlite.execute_batch("ROLLBACK").unwrap();
+
+    // the rejected transaction's changes were discarded
+    let count: i64 = sqlite
+        .query_row("SELECT COUNT(*) FROM t", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 2);
+
+    // disabling the timeout allows long transactions again
+    sqlite.graft_pragma_arg("lock_timeout", 0).unwrap();
+    sqlite
+        .execute_batch("BEGIN IMMEDIATE; INSERT INTO t VALUES (4);")
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    sqlite.execute_batch("COMMIT").unwrap();
+
+    runtime.shutdown().unwrap();
+}
+
+#[test]
+fn test_import_from_sqlite() {
+    graft_test::ensure_test_env();
+
+    // populate an in-memory SQLite database
+    let source = Connection::open_in_memory().unwrap();
+    source
+        .execute_batch(
+            r#"
+            CREATE TABLE t (id INTEGER PRIMARY KEY, data BLOB);
+            WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < 200)
+            INSERT INTO t SELECT n, randomblob(512) FROM seq;
+            "#,
+        )
+        .unwrap();
+
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let vid = graft_sqlite::import::import_from_sqlite(&runtime, &source).unwrap();
+
+    // every page of the Volume matches the source database, except the
+    // journal mode bytes in the header
+    let expected = source
+        .serialize(rusqlite::DatabaseName::Main)
+        .unwrap()
+        .to_vec();
+    let reader = runtime.volume_reader(vid.clone()).unwrap();
+    assert_eq!(reader.page_count().to_usize() * 4096, expected.len());
+    for (pageidx, chunk) in reader.page_count().iter().zip(expected.chunks(4096)) {
+        let page = reader.read_page(pageidx).unwrap();
+        if pageidx == pageidx!(1) {
+            assert_eq!(page[..18], chunk[..18]);
+            assert_eq!(page[20..], chunk[20..]);
+        } else {
+            assert_eq!(&page[..], chunk);
+        }
+    }
+
+    // the imported Volume is readable through the Graft VFS
+    runtime.tag_replace("imported", vid).unwrap();
+    let sqlite = runtime.open_sqlite("imported", None);
+    let (count, sum): (i64, i64) = sqlite
+        .query_row("SELECT COUNT(*), SUM(length(data)) FROM t", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    assert_eq!(count, 200);
+    assert_eq!(sum, 200 * 512);
+
+    runtime.shutdown().unwrap();
+}
+
+#[test]
+fn test_read_empty_volume() {
+    graft_test::ensure_test_env();
+
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let vid = runtime.volume_open(None, None, None).unwrap().vid;
+    runtime.tag_replace("main", vid.clone()).unwrap();
+
+    // reading the header of a fresh volume returns a zeroed page
+    let reader = runtime.volume_reader(vid).unwrap();
+    assert_eq!(reader.page_count(), PageCount::ZERO);
+    assert_eq!(reader.read_page(pageidx!(1)).unwrap(), Page::EMPTY);
+
+    // SQLite sees an empty but valid database
+    let sqlite = runtime.open_sqlite("main", None);
+    let tables: i64 = sqlite
+        .query_row("select count(*) from sqlite_schema", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(tables, 0);
+    sqlite.execute("create table t (x)", []).unwrap();
+}
+
+#[test]
+fn test_whoami() {
+    graft_test::ensure_test_env();
+
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let sqlite = runtime.open_sqlite("main", None);
+
+    let output: String = sqlite
+        .query_row("pragma graft_whoami", [], |row| row.get(0))
+        .unwrap();
+    let data_dir = format!("Data dir: {}", runtime.data_dir().display());
+    assert!(output.contains(&data_dir), "missing data dir: {output}");
+    assert!(
+        output.contains("Remote: memory"),
+        "missing remote: {output}"
+    );
+    assert!(output.contains("Autosync: disabled"), "{output}");
+
+    runtime.shutdown().unwrap();
+}
+
+#[test]
+fn test_gc() {
+    graft_test::ensure_test_env();
+
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let sqlite = runtime.open_sqlite("main", None);
+    sqlite.execute("create table t (x)", []).unwrap();
+    sqlite.execute("insert into t values (1)", []).unwrap();
+
+    // a deleted volume leaves its pages behind
+    let deleted = runtime.volume_open(None, None, None).unwrap().vid;
+    let mut writer = runtime.volume_writer(deleted.clone()).unwrap();
+    writer
+        .write_page(pageidx!(1), Page::test_filled(1))
+        .unwrap();
+    writer.commit().unwrap();
+    runtime.volume_delete(&deleted).unwrap();
+
+    let output: String = sqlite
+        .query_row("pragma graft_gc", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(
+        output,
+        "Removed 1 orphaned segment: 1 page, reclaiming 4 KB"
+    );
+    let output: String = sqlite
+        .query_row("pragma graft_gc", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(output, "No orphaned segments to remove");
+
+    // the current volume's pages are kept
+    let count: i64 = sqlite
+        .query_row("select count(*) from t", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 1);
+
+    runtime.shutdown().unwrap();
+}
+
+#[test]
+fn test_reset() {
+    graft_test::ensure_test_env();
+
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let sqlite = runtime.open_sqlite("main", None);
+
+    // a volume which never synced has nothing to reset to
+    sqlite.execute("create table t (x)", []).unwrap();
+    let err = sqlite.graft_pragma("reset").unwrap_err();
+    assert!(err.to_string().contains("never synced"), "{err}");
+
+    sqlite.graft_pragma("push").unwrap();
+    sqlite.execute("insert into t values (1)", []).unwrap();
+    sqlite.execute("insert into t values (2)", []).unwrap();
+
+    // resetting refuses to run within a transaction
+    sqlite.execute_batch("BEGIN; SELECT * FROM t;").unwrap();
+    assert!(sqlite.graft_pragma("reset").is_err());
+    sqlite.execute_batch("COMMIT").unwrap();
+
+    let output: String = sqlite
+        .query_row("pragma graft_reset", [], |row| row.get(0))
+        .unwrap();
+    assert!(output.starts_with("Discarded local LSNs"), "{output}");
+    assert!(output.ends_with("reset to remote LSN 1"), "{output}");
+
+    // the local inserts are gone and the volume matches the remote
+    let count: i64 = sqlite
+        .query_row("select count(*) from t", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 0);
+    let status: String = sqlite
+        .query_row("pragma graft_status", [], |row| row.get(0))
+        .unwrap();
+    assert!(status.contains("up to date"), "{status}");
+
+    let output: String = sqlite
+        .query_row("pragma graft_reset", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(output, "No local changes to discard; reset to remote LSN 1");
+
+    runtime.shutdown().unwrap();
+}
+
+#[test]
+fn test_check_page_count() {
+    graft_test::ensure_test_env();
+
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let sqlite = runtime.open_sqlite("main", None);
+    let check = || -> String {
+        sqlite
+            .query_row("pragma graft_check_page_count", [], |row| row.get(0))
+            .unwrap()
+    };
+    assert_eq!(check(), "The Volume has never synced with the remote");
+
+    sqlite.execute("create table t (x)", []).unwrap();
+    sqlite.graft_pragma("push").unwrap();
+    let output = check();
+    assert!(output.starts_with("Page counts agree"), "{output}");
+
+    runtime.shutdown().unwrap();
+}
+
+#[test]
+fn test_autosync_pragma() {
+    graft_test::ensure_test_env();
+
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let sqlite = runtime.open_sqlite("main", None);
+    let autosync = |arg: &str| -> rusqlite::Result<String> {
+        let sql = if arg.is_empty() {
+            "pragma graft_autosync".to_string()
+        } else {
+            format!("pragma graft_autosync = {arg}")
+        };
+        sqlite.query_row(&sql, [], |row| row.get(0))
+    };
+
+    assert_eq!(autosync("").unwrap(), "Autosync: disabled");
+    assert_eq!(autosync("30").unwrap(), "Autosync: every 30s");
+    assert_eq!(runtime.autosync_interval(), Some(Duration::from_secs(30)));
+    assert_eq!(autosync("").unwrap(), "Autosync: every 30s");
+
+    // zero isn't a valid interval
+    assert!(autosync("0").is_err());
+    assert!(autosync("soon").is_err());
+    assert_eq!(autosync("").unwrap(), "Autosync: every 30s");
+
+    assert_eq!(autosync("off").unwrap(), "Autosync: disabled");
+    assert_eq!(runtime.autosync_interval(), None);
+
+    runtime.shutdown().unwrap();
+}
+
+#[test]
+fn test_simulate_remote_commit() {
+    graft_test::ensure_test_env();
+
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let sqlite = runtime.open_sqlite("main", None);
+    sqlite.execute("create table t (x)", []).unwrap();
+    sqlite.graft_pragma("push").unwrap();
+
+    let status: String = sqlite
+        .query_row("pragma graft_status", [], |row| row.get(0))
+        .unwrap();
+    assert!(status.contains("up to date"), "{status}");
+
+    sqlite.graft_pragma("simulate_remote_commit").unwrap();
+    let status: String = sqlite
+        .query_row("pragma graft_status", [], |row| row.get(0))
+        .unwrap();
+    assert!(
+        status.contains("The Volume is behind the remote by 1 commit."),
+        "{status}"
+    );
+
+    // local changes on top of the simulated commit cause a divergence
+    sqlite.execute("insert into t values (1)", []).unwrap();
+    let status: String = sqlite
+        .query_row("pragma graft_status", [], |row| row.get(0))
+        .unwrap();
+    assert!(status.contains("have diverged"), "{status}");
+
+    runtime.shutdown().unwrap();
+}
+
+#[test]
+fn test_adjacent_reads_are_coalesced() {
+    graft_test::ensure_test_env();
+
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let sqlite = runtime.open_sqlite("main", None);
+    let vid = runtime.tag_get("main").unwrap().unwrap();
+
+    // each row overflows into a run of adjacent pages
+    sqlite
+        .execute_batch(
+            r#"
+            CREATE TABLE t (d);
+            WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 16)
+            INSERT INTO t SELECT printf('%0*d', 16384, i) FROM n;
+            "#,
+        )
+        .unwrap();
+    drop(sqlite);
+    let snapshot = runtime.volume_snapshot(&vid).unwrap();
+    let pages = runtime.snapshot_pages(&snapshot).unwrap().to_usize() as u64;
+
+    // scan the table from a fresh connection, so every page is read through
+    // the VFS
+    runtime.reset_volume_metrics(&vid);
+    let sqlite = runtime.open_sqlite("main", None);
+    let total: i64 = sqlite
+        .query_row("SELECT sum(length(d)) FROM t", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(total, 16 * 16384);
+    let row: String = sqlite
+        .query_row("SELECT d FROM t WHERE rowid = 7", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(row, format!("{:016384}", 7));
+
+    // adjacent reads are served by batches rather than a lookup per page
+    let storage_reads = runtime.volume_metrics(&vid).storage_reads;
+    assert!(
+        storage_reads * 2 < pages,
+        "{storage_reads} storage reads for {pages} pages"
+    );
+
+    runtime.shutdown().unwrap();
+}
+
+#[test]
+fn test_commit_dot() {
+    graft_test::ensure_test_env();
+
+    let remote = LogId::random();
+    let mut runtime = GraftTestRuntime::with_memory_remote();
+    let volume = runtime
+        .volume_open(None, None, Some(remote.clone()))
+        .unwrap();
+    let vid = volume.vid;
+
+    // push a checkpoint, then commit locally without pushing
+    for (pageidx, fill) in [(pageidx!(1), 1), (pageidx!(2), 2)] {
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer.write_page(pageidx, Page::test_filled(fill)).unwrap();
+        writer.commit().unwrap();
+        if fill == 1 {
+            runtime.volume_push(vid.clone()).unwrap();
+        }
+    }
+    runtime.tag_replace("main", vid.clone()).unwrap();
+
+    let sqlite = runtime.open_sqlite("main", None);
+    let output: String = sqlite
+        .query_row("pragma graft_dot", [], |row| row.get(0))
+        .unwrap();
+
+    let mut lines = output.lines();
+    assert_eq!(lines.next(), Some(format!("digraph \"{vid}\" {{").as_str()));
+    assert_eq!(lines.next_back(), Some("}"));
+    for line in lines {
+        assert!(line.starts_with("  ") && line.ends_with(';'), "{output}");
+    }
+
+    let local = volume.local;
+    let nodes = [
+        format!("\"{remote}:1\" [label=\"remote LSN 1\\n1 page\", style=filled"),
+        format!("\"{local}:2\" [label=\"local LSN 2\\n2 pages\"]"),
+        // the pushed local commit
+        format!("\"{local}:1\" [label=\"local LSN 1\\n1 page\", style=filled"),
+    ];
+    for node in nodes {
+        assert!(output.contains(&node), "missing {node}: {output}");
+    }
+    let edges = [
+        format!("\"{remote}:1\" -> \"{local}:2\";"),
+        format!("\"{local}:1\" -> \"{remote}:1\" [style=dashed, label=\"sync\"];"),
+    ];
+    for edge in edges {
+        assert!(output.contains(&edge), "missing {edge}: {output}");
+    }
+
+    runtime.shutdown().unwrap();
+}
@@ -0,0 +1,65 @@
+use graft::{
+    core::{LogId, page::Page},
+    lsn, pageidx,
+    volume_writer::VolumeWrite,
+};
+use graft_test::GraftTestRuntime;
+
+#[test]
+fn test_squash_push() -> anyhow::Result<()> {
+    graft_test::ensure_test_env();
+
+    let remote = LogId::random();
+    let runtime = GraftTestRuntime::with_memory_remote();
+    let vid = runtime.volume_open(None, None, Some(remote.clone()))?.vid;
+
+    // establish a base in the remote
+    let mut writer = runtime.volume_writer(vid.clone())?;
+    writer.write_page(pageidx!(1), Page::test_filled(1))?;
+    writer.write_page(pageidx!(2), Page::test_filled(2))?;
+    writer.commit()?;
+    runtime.volume_push(vid.clone())?;
+
+    // change page 1 and then change it back, and also write a new page
+    for (pageidx, fill) in [(pageidx!(1), 9), (pageidx!(1), 1), (pageidx!(3), 3)] {
+        let mut writer = runtime.volume_writer(vid.clone())?;
+        writer.write_page(pageidx, Page::test_filled(fill))?;
+        writer.commit()?;
+    }
+    assert_eq!(runtime.volume_status(&vid)?.to_string(), "4+3 r1");
+
+    let pending = runtime.coalesce_pending_changes(&vid)?;
+    assert_eq!(
+        pending.iter().collect::<Vec<_>>(),
+        [pageidx!(1), pageidx!(3)]
+    );
+
+    let snapshot = runtime.volume_snapshot(&vid)?;
+    let expected = runtime.snapshot_checksum(&snapshot)?;
+
+    // squash the three local commits into a single remote commit
+    runtime.volume_squash_push(vid.clone())?;
+    assert_eq!(runtime.volume_status(&vid)?.to_string(), "4 r2");
+    assert!(runtime.coalesce_pending_changes(&vid)?.is_empty());
+
+    // only the net-changed page should have been pushed
+    let commit = runtime.get_commit(&remote, lsn!(2))?.unwrap();
+    let pageset = &commit.segment_idx().unwrap().pageset;
+    assert_eq!(pageset.iter().collect::<Vec<_>>(), [pageidx!(3)]);
+
+    let snapshot = runtime.volume_snapshot(&vid)?;
+    assert_eq!(runtime.snapshot_checksum(&snapshot)?, expected);
+
+    // verify that a peer sees the same volume
+    let peer = runtime.spawn_peer();
+    let vid2 = peer.volume_open(None, None, Some(remote))?.vid;
+    peer.volume_pull(vid2.clone())?;
+    let snapshot = peer.volume_snapshot(&vid2)?;
+    peer.snapshot_hydrate(snapshot.clone())?;
+    assert_eq!(peer.snapshot_checksum(&snapshot)?, expected);
+
+    runtime.shutdown().unwrap();
+    peer.shutdown().unwrap();
+
+    Ok(())
+}
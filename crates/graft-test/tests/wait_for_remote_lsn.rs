@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use graft::{
+    core::{LogId, page::Page},
+    lsn, pageidx,
+    volume_reader::VolumeRead,
+    volume_writer::VolumeWrite,
+};
+use graft_test::GraftTestRuntime;
+
+#[test]
+fn test_wait_for_remote_lsn() -> anyhow::Result<()> {
+    graft_test::ensure_test_env();
+
+    // create two nodes connected to the same remote
+    let remote = LogId::random();
+    let writer_rt = GraftTestRuntime::with_memory_remote();
+    let reader_rt = writer_rt.spawn_peer();
+
+    let writer_vid = writer_rt.volume_open(None, None, Some(remote.clone()))?.vid;
+    let reader_vid = reader_rt.volume_open(None, None, Some(remote.clone()))?.vid;
+
+    // nothing has been pushed yet, so waiting should time out
+    let timeout = Duration::from_secs(1);
+    assert!(!reader_rt.wait_for_remote_lsn(&reader_vid, lsn!(1), timeout)?);
+
+    // commit and push from the writer
+    let mut writer = writer_rt.volume_writer(writer_vid.clone())?;
+    writer.write_page(pageidx!(1), Page::test_filled(1))?;
+    writer.commit()?;
+    writer_rt.volume_push(writer_vid.clone())?;
+
+    // the reader should observe the LSN and then see the new data
+    assert!(reader_rt.wait_for_remote_lsn(&reader_vid, lsn!(1), timeout)?);
+    let reader = reader_rt.volume_reader(reader_vid.clone())?;
+    assert_eq!(reader.read_page(pageidx!(1))?, Page::test_filled(1));
+
+    // waiting for an LSN which hasn't been written yet times out
+    assert!(!reader_rt.wait_for_remote_lsn(&reader_vid, lsn!(2), timeout)?);
+
+    // a waiter is woken by another pull receiving the commit
+    let waiter = {
+        let runtime = (*reader_rt).clone();
+        let vid = reader_vid.clone();
+        std::thread::spawn(move || {
+            runtime.wait_for_remote_lsn(&vid, lsn!(2), Duration::from_secs(3600))
+        })
+    };
+    let mut writer = writer_rt.volume_writer(writer_vid.clone())?;
+    writer.write_page(pageidx!(2), Page::test_filled(2))?;
+    writer.commit()?;
+    writer_rt.volume_push(writer_vid.clone())?;
+    reader_rt.volume_pull(reader_vid.clone())?;
+    assert!(waiter.join().unwrap()?);
+    let reader = reader_rt.volume_reader(reader_vid.clone())?;
+    assert_eq!(reader.read_page(pageidx!(2))?, Page::test_filled(2));
+
+    writer_rt.shutdown().unwrap();
+    reader_rt.shutdown().unwrap();
+
+    Ok(())
+}
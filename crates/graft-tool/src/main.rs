@@ -1,5 +1,23 @@
-use clap::{Parser, Subcommand};
-use graft::core::{LogId, SegmentId, VolumeId};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::{ArgAction, Parser, Subcommand};
+use graft::{
+    core::{
+        LogId, SegmentId, VolumeId,
+        lsn::{InvalidLSN, LSN},
+    },
+    local::fjall_storage::{FjallStorage, VolumeInspection},
+    rt::event_log::{EventKind, EventRecord, read_event_log},
+};
+use graft_tracing::{
+    LevelFilter, SubscriberInitExt, TracingConsumer, setup_tracing_with_writer_with_level,
+};
 
 #[derive(Subcommand)]
 enum Tool {
@@ -9,6 +27,35 @@ enum Tool {
     Log,
     /// Generate a Segment ID (sid)
     Sid,
+    /// Print the type, creation time, and short form of a serialized ID
+    Decode {
+        /// A serialized Volume, Log, or Segment ID
+        gid: String,
+    },
+    /// Reconstruct the commit timeline from an event log
+    ReplayEvents {
+        /// Path to the event log
+        path: PathBuf,
+    },
+    /// Print the local state of a Volume in a client's data directory, without
+    /// modifying it
+    Inspect {
+        /// The client's data directory
+        #[arg(long)]
+        data_dir: PathBuf,
+        /// The Volume to inspect
+        #[arg(long)]
+        vid: VolumeId,
+    },
+    /// Convert an LSN between decimal and the fixed-width hex used in keys
+    Lsn {
+        /// A hex LSN to print in decimal
+        #[arg(long, conflicts_with = "dec", required_unless_present = "dec")]
+        hex: Option<String>,
+        /// A decimal LSN to print as fixed-width hex
+        #[arg(long)]
+        dec: Option<String>,
+    },
 }
 
 #[derive(Parser)]
@@ -17,13 +64,409 @@ enum Tool {
 struct Cli {
     #[command(subcommand)]
     tool: Tool,
+    /// Log more verbosely: -v logs info, -vv debug, and -vvv trace. Ignored
+    /// when GRAFT_LOG_LEVEL is set
+    /// Log more verbosely: -v logs info, -vv debug, and -vvv trace
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+}
+
+/// Maps the number of `-v` flags to a log level, or `None` to keep the
+/// default level.
+fn log_level(verbose: u8) -> Option<LevelFilter> {
+    match verbose {
+        0 => None,
+        1 => Some(LevelFilter::INFO),
+        2 => Some(LevelFilter::DEBUG),
+        _ => Some(LevelFilter::TRACE),
+    }
 }
 
-fn main() {
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    // log to stderr to keep stdout free for the tool's output
+    setup_tracing_with_writer_with_level(
+        TracingConsumer::Tool,
+        std::io::stderr,
+        None,
+        log_level(cli.verbose),
+    )
+    .init();
+
     match cli.tool {
         Tool::Vid => println!("{}", VolumeId::random()),
         Tool::Log => println!("{}", LogId::random()),
         Tool::Sid => println!("{}", SegmentId::random()),
+        Tool::Decode { gid } => match DecodedGid::parse(&gid) {
+            Ok(decoded) => {
+                let created = decoded
+                    .created
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                println!("type: {}", decoded.kind);
+                println!(
+                    "created: {}.{:03}",
+                    created.as_secs(),
+                    created.subsec_millis()
+                );
+                println!("short: {}", decoded.short);
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        Tool::ReplayEvents { path } => match read_event_log(&path) {
+            Ok(records) => print!("{}", format_replay(&records)),
+            Err(err) => {
+                eprintln!("failed to read {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        Tool::Inspect { data_dir, vid } => {
+            match open_storage(&data_dir).and_then(|storage| inspect_volume(&storage, &vid)) {
+                Ok(inspection) => print!("{}", format_inspection(&inspection)),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        Tool::Lsn { hex, dec } => {
+            let converted = match (hex, dec) {
+                (Some(hex), _) => hex_to_dec(&hex),
+                (None, Some(dec)) => dec_to_hex(&dec),
+                (None, None) => unreachable!("clap requires --hex or --dec"),
+            };
+            match converted {
+                Ok(out) => println!("{out}"),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// A serialized ID along with the type of ID it parsed as.
+#[derive(Debug)]
+struct DecodedGid {
+    kind: &'static str,
+    created: SystemTime,
+    short: String,
+}
+
+impl DecodedGid {
+    /// Parses `s` as each kind of ID in turn, as the prefix determines which
+    /// kind it is.
+    fn parse(s: &str) -> Result<Self, String> {
+        if let Ok(vid) = s.parse::<VolumeId>() {
+            Ok(Self {
+                kind: "Volume",
+                created: vid.as_time(),
+                short: vid.short(),
+            })
+        } else if let Ok(log) = s.parse::<LogId>() {
+            Ok(Self {
+                kind: "Log",
+                created: log.as_time(),
+                short: log.short(),
+            })
+        } else {
+            match s.parse::<SegmentId>() {
+                Ok(sid) => Ok(Self {
+                    kind: "Segment",
+                    created: sid.as_time(),
+                    short: sid.short(),
+                }),
+                Err(err) => Err(format!("`{s}` is not a Volume, Log, or Segment ID: {err}")),
+            }
+        }
+    }
+}
+
+/// Opens the storage in a client's data directory without modifying it. Only
+/// existing storage is opened, as opening a missing directory would initialize
+/// new storage, and storage in an older format isn't migrated.
+fn open_storage(data_dir: &Path) -> Result<FjallStorage, String> {
+    if !data_dir.is_dir() {
+        return Err(format!("{} is not a directory", data_dir.display()));
+    }
+    FjallStorage::open_read_only(data_dir).map_err(|err| {
+        if err.is_locked() {
+            format!(
+                "{} is in use by another process; stop it before inspecting the data directory",
+                data_dir.display()
+            )
+        } else {
+            format!("failed to open {}: {err}", data_dir.display())
+        }
+    })
+}
+
+fn inspect_volume(storage: &FjallStorage, vid: &VolumeId) -> Result<VolumeInspection, String> {
+    storage
+        .inspect_volume(vid)
+        .map_err(|err| format!("failed to inspect Volume {vid}: {err}"))
+}
+
+fn format_inspection(inspection: &VolumeInspection) -> String {
+    let volume = &inspection.volume;
+    let lsn = |lsn: Option<LSN>| lsn.map_or("-".into(), |lsn| lsn.to_string());
+    let sync = volume.sync().map_or_else(
+        || "never synced".into(),
+        |sync| match sync.local_watermark {
+            Some(local) => format!("local {local}, remote {}", sync.remote),
+            None => format!("remote {}", sync.remote),
+        },
+    );
+    [
+        format!("Volume: {}", volume.vid),
+        format!("Local Log: {}", volume.local),
+        format!("Remote Log: {}", volume.remote),
+        format!("Latest local LSN: {}", lsn(inspection.latest_local)),
+        format!("Latest remote LSN: {}", lsn(inspection.latest_remote)),
+        format!("Sync point: {sync}"),
+        format!("Page count: {}\n", inspection.page_count),
+    ]
+    .join("\n")
+}
+
+/// Converts a hex LSN to decimal.
+fn hex_to_dec(hex: &str) -> Result<String, String> {
+    let hex = hex.trim_start_matches("0x");
+    // LSN::from_hex panics on zero, so reject it before parsing
+    if u64::from_str_radix(hex, 16).is_ok_and(|n| n == 0) {
+        return Err(InvalidLSN.to_string());
+    }
+    LSN::from_hex(hex)
+        .map(|lsn| lsn.to_u64().to_string())
+        .map_err(|err| format!("invalid hex LSN `{hex}`: {err}"))
+}
+
+/// Converts a decimal LSN to fixed-width hex.
+fn dec_to_hex(dec: &str) -> Result<String, String> {
+    let n: u64 = dec
+        .parse()
+        .map_err(|err| format!("invalid decimal LSN `{dec}`: {err}"))?;
+    let lsn = LSN::try_from(n).map_err(|err| err.to_string())?;
+    Ok(lsn.format_fixed_hex())
+}
+
+/// Formats every commit in the order it happened, followed by the final state
+/// of each Volume.
+fn format_replay(records: &[EventRecord]) -> String {
+    // the latest local and pushed commit for each Volume
+    let mut volumes: BTreeMap<&VolumeId, (Option<&EventRecord>, Option<&EventRecord>)> =
+        BTreeMap::new();
+
+    let mut out = String::new();
+    for record in records {
+        let timestamp = format!("{}.{:03}", record.timestamp / 1000, record.timestamp % 1000);
+        let state = volumes.entry(&record.vid).or_default();
+        match record.kind {
+            EventKind::Local => {
+                writeln!(
+                    out,
+                    "{timestamp} {} committed locally at lsn {} with {} pages",
+                    record.vid, record.lsn, record.page_count
+                )
+                .unwrap();
+                state.0 = Some(record);
+            }
+            EventKind::Push => {
+                let hash = record.hash.as_ref().map_or("-".into(), |h| h.to_string());
+                writeln!(
+                    out,
+                    "{timestamp} {} pushed lsn {} with {} pages, hash {hash}",
+                    record.vid, record.lsn, record.page_count
+                )
+                .unwrap();
+                state.1 = Some(record);
+            }
+        }
+    }
+
+    writeln!(out).unwrap();
+    for (vid, (local, pushed)) in volumes {
+        let local = local.map_or("-".into(), |r| r.lsn.to_string());
+        let pushed = pushed.map_or("-".into(), |r| r.lsn.to_string());
+        writeln!(
+            out,
+            "{vid}: latest local lsn {local}, latest pushed lsn {pushed}"
+        )
+        .unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use graft::{
+        core::{PageCount, commit_hash::CommitHash},
+        rt::event_log::{EventLog, EventLogDurability},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_decode_gid() {
+        let before = SystemTime::now() - Duration::from_secs(1);
+        let cases = [
+            ("Volume", VolumeId::random().serialize()),
+            ("Log", LogId::random().serialize()),
+            ("Segment", SegmentId::random().serialize()),
+        ];
+        for (kind, gid) in cases {
+            let decoded = DecodedGid::parse(&gid).unwrap();
+            assert_eq!(decoded.kind, kind);
+            assert!(gid.contains(&decoded.short));
+            assert!(decoded.created >= before);
+            assert!(decoded.created <= SystemTime::now());
+        }
+
+        assert!(DecodedGid::parse("").is_err());
+        assert!(DecodedGid::parse("not a gid").is_err());
+    }
+
+    #[test]
+    fn test_inspect_errors() {
+        let dir = std::env::temp_dir().join(format!("graft-tool-missing-{}", VolumeId::random()));
+        let err = open_storage(&dir).unwrap_err();
+        assert!(err.contains("is not a directory"), "{err}");
+        // the missing directory isn't created
+        assert!(!dir.exists());
+
+        let storage = FjallStorage::open_temporary().unwrap();
+        let vid = VolumeId::random();
+        let err = inspect_volume(&storage, &vid).unwrap_err();
+        assert!(err.contains(&vid.to_string()), "{err}");
+        assert!(err.contains("not found"), "{err}");
+    }
+
+    #[test]
+    fn test_inspect_locked() {
+        let dir = std::env::temp_dir().join(format!("graft-tool-locked-{}", VolumeId::random()));
+        let storage = FjallStorage::open(&dir).unwrap();
+        storage.persist().unwrap();
+
+        // a data directory held open by a running client can't be inspected
+        let err = open_storage(&dir).unwrap_err();
+        assert!(err.contains("is in use by another process"), "{err}");
+
+        drop(storage);
+        open_storage(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replay_events() {
+        let path = std::env::temp_dir().join(format!("graft-tool-events-{}", VolumeId::random()));
+        let (a, b) = (VolumeId::random(), VolumeId::random());
+        let record = |timestamp, kind, vid: &VolumeId, lsn, pages, hash| EventRecord {
+            timestamp,
+            kind,
+            vid: vid.clone(),
+            lsn: LSN::new(lsn),
+            page_count: PageCount::new(pages),
+            hash,
+        };
+        let records = [
+            record(1_000, EventKind::Local, &a, 1, 1, None),
+            record(1_500, EventKind::Local, &b, 1, 4, None),
+            record(2_001, EventKind::Local, &a, 2, 3, None),
+            record(2_250, EventKind::Push, &a, 1, 3, Some(CommitHash::ZERO)),
+        ];
+
+        // replay the records after round tripping them through the log
+        let mut log = EventLog::open(&path, EventLogDurability::Strict).unwrap();
+        for record in &records {
+            log.append(record).unwrap();
+        }
+        drop(log);
+        let replayed = read_event_log(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replayed, records);
+
+        let (first, second) = if a < b { (&a, &b) } else { (&b, &a) };
+        let latest = |vid: &VolumeId| {
+            if vid == &a {
+                format!("{a}: latest local lsn 2, latest pushed lsn 1")
+            } else {
+                format!("{b}: latest local lsn 1, latest pushed lsn -")
+            }
+        };
+        let hash = CommitHash::ZERO;
+        assert_eq!(
+            format_replay(&replayed),
+            [
+                format!("1.000 {a} committed locally at lsn 1 with 1 pages"),
+                format!("1.500 {b} committed locally at lsn 1 with 4 pages"),
+                format!("2.001 {a} committed locally at lsn 2 with 3 pages"),
+                format!("2.250 {a} pushed lsn 1 with 3 pages, hash {hash}"),
+                String::new(),
+                latest(first),
+                latest(second),
+                String::new(),
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_lsn_conversion() {
+        assert_eq!(dec_to_hex("1").unwrap(), "0000000000000001");
+        assert_eq!(hex_to_dec("0000000000000001").unwrap(), "1");
+        assert_eq!(
+            dec_to_hex(&LSN::LAST.to_string()).unwrap(),
+            "ffffffffffffffff"
+        );
+        assert_eq!(
+            hex_to_dec("ffffffffffffffff").unwrap(),
+            u64::MAX.to_string()
+        );
+        assert_eq!(hex_to_dec("0x2a").unwrap(), "42");
+
+        // conversions round trip
+        for lsn in [LSN::FIRST, LSN::new(42), LSN::LAST] {
+            let hex = dec_to_hex(&lsn.to_string()).unwrap();
+            assert_eq!(hex, lsn.format_fixed_hex());
+            assert_eq!(hex_to_dec(&hex).unwrap(), lsn.to_string());
+        }
+
+        // zero is not a valid LSN
+        let invalid = InvalidLSN.to_string();
+        assert_eq!(dec_to_hex("0").unwrap_err(), invalid);
+        assert_eq!(hex_to_dec("0000000000000000").unwrap_err(), invalid);
+
+        for bad in ["", "-1", "18446744073709551616", "abc"] {
+            assert!(dec_to_hex(bad).is_err(), "{bad}");
+        }
+        for bad in ["", "xyz", "10000000000000000"] {
+            assert!(hex_to_dec(bad).is_err(), "{bad}");
+        }
+
+        // exactly one of --hex or --dec is required
+        assert!(Cli::try_parse_from(["graft-tool", "lsn"]).is_err());
+        assert!(Cli::try_parse_from(["graft-tool", "lsn", "--hex", "1", "--dec", "1"]).is_err());
+        assert!(Cli::try_parse_from(["graft-tool", "lsn", "--dec", "1"]).is_ok());
+    }
+
+    #[test]
+    fn test_verbose_flags() {
+        let cases = [
+            (&["graft-tool", "vid"][..], None),
+            (&["graft-tool", "-v", "vid"], Some(LevelFilter::INFO)),
+            (&["graft-tool", "vid", "-vv"], Some(LevelFilter::DEBUG)),
+            (&["graft-tool", "-vvvv", "sid"], Some(LevelFilter::TRACE)),
+        ];
+        for (args, level) in cases {
+            let cli = Cli::try_parse_from(args).unwrap();
+            assert_eq!(log_level(cli.verbose), level, "{args:?}");
+        }
     }
 }
@@ -3,21 +3,28 @@
 //! This crate provides functionality for initializing and configuring
 //! [tracing](https://docs.rs/tracing) in different environments (test, server, tool).
 
-use std::time::Instant;
+use std::{
+    fmt,
+    io::{self, IsTerminal},
+    time::{Duration, Instant, UNIX_EPOCH},
+};
 use tracing_subscriber::{
     fmt::{MakeWriter, time::SystemTime},
     layer::SubscriberExt,
 };
 
-use tracing::level_filters::LevelFilter;
+use tracing::{Event, Metadata, Subscriber};
 use tracing_subscriber::{
     EnvFilter,
     fmt::{
-        format::{FmtSpan, Writer},
+        FmtContext, FormatEvent, FormatFields,
+        format::{FmtSpan, Format, Json, JsonFields, Writer},
         time::FormatTime,
     },
+    registry::LookupSpan,
 };
 
+pub use tracing::level_filters::LevelFilter;
 pub use tracing_subscriber::util::SubscriberInitExt;
 
 /// Checks if the application is running in the Antithesis testing environment.
@@ -36,14 +43,134 @@ pub enum TracingConsumer {
     Tool,
 }
 
+/// Determines whether output should be colored. Color defaults to on only
+/// when writing to a terminal. A non-empty `NO_COLOR` always disables color,
+/// while a non-empty `FORCE_COLOR` enables it even when the output isn't a
+/// terminal.
+fn color_enabled(is_terminal: bool, env: impl Fn(&str) -> Option<String>) -> bool {
+    let is_set = |key: &str| env(key).is_some_and(|s| !s.is_empty());
+    if env("ANTITHESIS_OUTPUT_DIR").is_some() || is_set("NO_COLOR") {
+        false
+    } else {
+        is_terminal || is_set("FORCE_COLOR")
+    }
+}
+
+/// Determines the default log level, which applies to every target without
+/// an explicit directive in `RUST_LOG`. The `GRAFT_LOG_LEVEL` environment
+/// variable takes precedence over a level passed directly, which takes
+/// precedence over the consumer's default. Invalid values of
+/// `GRAFT_LOG_LEVEL` are ignored.
+fn default_level(
+    consumer: &TracingConsumer,
+    level: Option<LevelFilter>,
+    env: impl Fn(&str) -> Option<String>,
+) -> LevelFilter {
+    env("GRAFT_LOG_LEVEL")
+        .and_then(|s| s.trim().parse().ok())
+        .or(level)
+        .unwrap_or(match consumer {
+            TracingConsumer::Test => LevelFilter::INFO,
+            TracingConsumer::Server => LevelFilter::INFO,
+            TracingConsumer::Tool => LevelFilter::WARN,
+        })
+}
+
+/// Determines which span lifecycle events are logged. Tests and Antithesis
+/// log when spans are created and closed by default, other consumers log no
+/// span events. The `GRAFT_SPAN_EVENTS` environment variable (`none`, `new`,
+/// `close`, or `full`) overrides the default. Invalid values are ignored.
+fn span_events(lifecycle: bool, env: impl Fn(&str) -> Option<String>) -> FmtSpan {
+    let configured =
+        env("GRAFT_SPAN_EVENTS").and_then(|s| match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Some(FmtSpan::NONE),
+            "new" => Some(FmtSpan::NEW),
+            "close" => Some(FmtSpan::CLOSE),
+            "full" => Some(FmtSpan::FULL),
+            _ => None,
+        });
+    configured.unwrap_or(if lifecycle {
+        FmtSpan::NEW | FmtSpan::CLOSE
+    } else {
+        FmtSpan::NONE
+    })
+}
+
+/// Output options controlled by the environment.
+#[derive(Default)]
+struct OutputOpts {
+    /// Emit one compact JSON object per line (NDJSON) rather than text.
+    /// Enabled by `GRAFT_LOG_FORMAT=json`.
+    json: bool,
+    /// Flush the writer after every event rather than leaving it to the
+    /// writer's own buffering. Enabled by `GRAFT_LOG_FLUSH=line`.
+    flush_per_event: bool,
+    /// Omit timestamps entirely. Enabled by a non-empty `NO_TIME`.
+    no_time: bool,
+    /// Timestamp tool output with the wall clock time rather than the time
+    /// since startup. Enabled by `GRAFT_LOG_WALLCLOCK=1`.
+    wallclock: bool,
+}
+
+fn output_opts(env: impl Fn(&str) -> Option<String>) -> OutputOpts {
+    let is = |key: &str, expected: &str| {
+        env(key).is_some_and(|s| s.trim().eq_ignore_ascii_case(expected))
+    };
+    OutputOpts {
+        json: is("GRAFT_LOG_FORMAT", "json"),
+        flush_per_event: is("GRAFT_LOG_FLUSH", "line"),
+        no_time: env("NO_TIME").is_some_and(|s| !s.is_empty()),
+        wallclock: is("GRAFT_LOG_WALLCLOCK", "1"),
+    }
+}
+
 /// Initializes tracing with stdout as the output.
+///
+/// Output is colored if stdout is a terminal, subject to the `NO_COLOR` and
+/// `FORCE_COLOR` environment variables. Set `GRAFT_LOG_FORMAT=json` to emit
+/// newline delimited JSON, and `GRAFT_LOG_FLUSH=line` to flush after every
+/// event. `GRAFT_SPAN_EVENTS` (`none`, `new`, `close`, or `full`) controls
+/// which span lifecycle events are logged. Tools timestamp events with the
+/// time since startup, or the wall clock time if `GRAFT_LOG_WALLCLOCK=1`.
+///
+/// The `GRAFT_LOG_LEVEL` environment variable overrides the default log level
+/// for the consumer. Per-target directives in `RUST_LOG` take precedence.
 #[must_use]
 pub fn setup_tracing(consumer: TracingConsumer, prefix: Option<String>) -> impl SubscriberExt {
-    setup_tracing_with_writer(consumer, std::io::stdout, prefix)
+    setup_tracing_with_level(consumer, prefix, None)
+}
+
+/// Initializes tracing with stdout as the output, like [`setup_tracing`].
+///
+/// `level` overrides the default log level for the consumer, for example to
+/// raise it from a command line flag. `GRAFT_LOG_LEVEL` still takes
+/// precedence when it's set.
+#[must_use]
+pub fn setup_tracing_with_level(
+    consumer: TracingConsumer,
+    prefix: Option<String>,
+    level: Option<LevelFilter>,
+) -> impl SubscriberExt {
+    let env = |key: &str| std::env::var(key).ok();
+    let color = color_enabled(std::io::stdout().is_terminal(), env);
+    let level = default_level(&consumer, level, env);
+    build_subscriber(
+        consumer,
+        std::io::stdout,
+        prefix,
+        level,
+        color,
+        output_opts(env),
+    )
 }
 
 /// Initializes tracing with a custom writer for output.
 ///
+/// Output to a custom writer is never colored, as the writer is typically a
+/// file or another logger rather than a terminal. The `GRAFT_LOG_FORMAT`,
+/// `GRAFT_LOG_FLUSH`, `GRAFT_LOG_LEVEL`, `GRAFT_LOG_WALLCLOCK`, and
+/// `GRAFT_SPAN_EVENTS` environment variables apply as in [`setup_tracing`].
+///
 /// # Parameters
 /// * `consumer` - The type of application consuming the tracing output
 /// * `writer` - Custom writer implementation for tracing output
@@ -56,16 +183,47 @@ pub fn setup_tracing_with_writer<W>(
     writer: W,
     prefix: Option<String>,
 ) -> impl SubscriberExt
+where
+    W: for<'writer> MakeWriter<'writer> + 'static + Send + Sync,
+{
+    setup_tracing_with_writer_with_level(consumer, writer, prefix, None)
+}
+
+/// Initializes tracing with a custom writer for output, like
+/// [`setup_tracing_with_writer`].
+///
+/// `level` overrides the default log level for the consumer, for example to
+/// raise it from a command line flag. `GRAFT_LOG_LEVEL` still takes
+/// precedence when it's set.
+#[must_use]
+pub fn setup_tracing_with_writer_with_level<W>(
+    consumer: TracingConsumer,
+    writer: W,
+    prefix: Option<String>,
+    level: Option<LevelFilter>,
+) -> impl SubscriberExt
+where
+    W: for<'writer> MakeWriter<'writer> + 'static + Send + Sync,
+{
+    let env = |key: &str| std::env::var(key).ok();
+    let level = default_level(&consumer, level, env);
+    build_subscriber(consumer, writer, prefix, level, false, output_opts(env))
+}
+
+fn build_subscriber<W>(
+    consumer: TracingConsumer,
+    writer: W,
+    prefix: Option<String>,
+    default_level: LevelFilter,
+    color: bool,
+    opts: OutputOpts,
+) -> impl SubscriberExt
 where
     W: for<'writer> MakeWriter<'writer> + 'static + Send + Sync,
 {
     let antithesis = running_in_antithesis();
     let testing = consumer == TracingConsumer::Test;
 
-    // determine if color output should be enabled
-    let color = !antithesis && !std::env::var("NO_COLOR").is_ok_and(|s| !s.is_empty());
-    let no_time = std::env::var("NO_TIME").is_ok_and(|s| !s.is_empty());
-
     // allow a log prefix to be injected from the environment if it's not
     // provided directly
     let prefix = prefix.or_else(|| {
@@ -74,49 +232,117 @@ where
             .and_then(|s| (!s.trim().is_empty()).then_some(s.trim().to_string()))
     });
 
-    let default_level = match consumer {
-        TracingConsumer::Test => LevelFilter::INFO,
-        TracingConsumer::Server => LevelFilter::INFO,
-        TracingConsumer::Tool => LevelFilter::WARN,
-    };
-
     let mut filter = EnvFilter::builder()
         .with_default_directive(default_level.into())
         .from_env()
         .unwrap();
 
-    let mut span_events = FmtSpan::NONE;
+    let span_events = span_events(antithesis || testing, |key| std::env::var(key).ok());
 
     if antithesis || testing {
-        span_events = FmtSpan::NEW | FmtSpan::CLOSE;
         filter = filter
             .add_directive("graft=debug".parse().unwrap())
             .add_directive("graft_test=trace".parse().unwrap())
             .add_directive("graft_sqlite=debug".parse().unwrap())
     }
 
-    let time = if antithesis || no_time {
+    let time = if antithesis || opts.no_time {
         TimeFormat::None
     } else if consumer == TracingConsumer::Server {
         TimeFormat::Long(SystemTime)
+    } else if consumer == TracingConsumer::Tool && opts.wallclock {
+        TimeFormat::LongMillis
     } else {
         TimeFormat::Offset { start: Instant::now() }
     };
 
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_thread_names(antithesis || consumer == TracingConsumer::Server)
-        .with_span_events(span_events)
-        .with_ansi(color)
-        .with_timer(TimeAndPrefix::new(prefix, time))
-        .with_writer(writer)
-        .finish()
+    let thread_names = antithesis || consumer == TracingConsumer::Server;
+    let writer = FlushWriter {
+        inner: writer,
+        flush_per_event: opts.flush_per_event,
+    };
+
+    // exactly one of the text and json layers is enabled
+    let (text, json) = if opts.json {
+        let format = tracing_subscriber::fmt::format()
+            .json()
+            .with_thread_names(thread_names);
+        // omit the timestamp field rather than leaving it empty
+        let format = match time {
+            TimeFormat::None => format
+                .without_time()
+                .with_timer(TimeAndPrefix::new(None, TimeFormat::None)),
+            time => format.with_timer(TimeAndPrefix::new(None, time)),
+        };
+        let layer = tracing_subscriber::fmt::layer()
+            .with_span_events(span_events)
+            .fmt_fields(JsonFields::new())
+            .event_format(PrefixedJson { inner: format, prefix })
+            .with_writer(writer);
+        (None, Some(layer))
+    } else {
+        let layer = tracing_subscriber::fmt::layer()
+            .with_thread_names(thread_names)
+            .with_span_events(span_events)
+            .with_ansi(color)
+            .with_timer(TimeAndPrefix::new(prefix, time))
+            .with_writer(writer);
+        (Some(layer), None)
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(text)
+        .with(json)
 }
 
 enum TimeFormat {
     None,
     Long(SystemTime),
-    Offset { start: Instant },
+    /// the current UTC time with millisecond precision, which can be
+    /// correlated with the logs of other processes
+    LongMillis,
+    Offset {
+        start: Instant,
+    },
+}
+
+/// Splits a duration into whole seconds and milliseconds, rounded to the
+/// nearest millisecond.
+fn round_millis(d: Duration) -> (u64, u32) {
+    let millis = (d.as_nanos() + 500_000) / 1_000_000;
+    ((millis / 1000) as u64, (millis % 1000) as u32)
+}
+
+/// Writes `time` as an RFC 3339 UTC timestamp with millisecond precision,
+/// such as `2024-01-02T15:04:05.123Z`.
+fn write_wallclock(w: &mut impl fmt::Write, time: std::time::SystemTime) -> fmt::Result {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (secs, millis) = round_millis(since_epoch);
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let secs = secs % 86_400;
+    write!(
+        w,
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{millis:03}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Converts days since the unix epoch into a `(year, month, day)` date, see
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
 }
 
 struct TimeAndPrefix {
@@ -132,12 +358,10 @@ impl TimeAndPrefix {
         match self.time {
             TimeFormat::None => Ok(()),
             TimeFormat::Long(inner) => inner.format_time(w),
+            TimeFormat::LongMillis => write_wallclock(w, std::time::SystemTime::now()),
             TimeFormat::Offset { start } => {
-                let e = start.elapsed();
-                let nanos = e.subsec_nanos();
-                // round nanos to the nearest millisecond
-                let millis = (nanos as f64 / 1_000_000.0).round();
-                write!(w, "{:03}.{:03}s", e.as_secs(), millis)
+                let (secs, millis) = round_millis(start.elapsed());
+                write!(w, "{secs:03}.{millis:03}s")
             }
         }
     }
@@ -155,3 +379,416 @@ impl FormatTime for TimeAndPrefix {
         }
     }
 }
+
+/// Formats each event with tracing-subscriber's JSON format, which writes a
+/// single line containing one compact JSON object, suitable for log shippers
+/// which consume newline delimited JSON. The log prefix, if any, is added as
+/// a top-level `prefix` key.
+///
+/// ```json
+/// {"prefix":"node1","timestamp":"...","level":"INFO","fields":{"message":"hi"},"target":"graft::rt","span":{"name":"push"},"spans":[{"name":"push"}]}
+/// ```
+struct PrefixedJson {
+    inner: Format<Json, TimeAndPrefix>,
+    prefix: Option<String>,
+}
+
+impl<S, N> FormatEvent<S, N> for PrefixedJson
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let Some(prefix) = &self.prefix else {
+            return self.inner.format_event(ctx, writer, event);
+        };
+        let mut line = String::new();
+        self.inner
+            .format_event(ctx, Writer::new(&mut line), event)?;
+        // splice the prefix in as the first key of the object
+        let rest = line.strip_prefix('{').ok_or(fmt::Error)?;
+        let prefix = serde_json::to_string(prefix).map_err(|_| fmt::Error)?;
+        write!(writer, "{{\"prefix\":{prefix},{rest}")
+    }
+}
+
+/// Wraps a [`MakeWriter`], optionally flushing after every event so that
+/// each line is handed to the underlying sink as soon as it's formatted.
+struct FlushWriter<W> {
+    inner: W,
+    flush_per_event: bool,
+}
+
+impl<'a, W: MakeWriter<'a>> MakeWriter<'a> for FlushWriter<W> {
+    type Writer = FlushOnWrite<W::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        FlushOnWrite {
+            inner: self.inner.make_writer(),
+            flush: self.flush_per_event,
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        FlushOnWrite {
+            inner: self.inner.make_writer_for(meta),
+            flush: self.flush_per_event,
+        }
+    }
+}
+
+/// The fmt layer writes each formatted event with a single `write_all`, so
+/// flushing there flushes once per event.
+struct FlushOnWrite<W> {
+    inner: W,
+    flush: bool,
+}
+
+impl<W: io::Write> io::Write for FlushOnWrite<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.inner.write_all(buf)?;
+        if self.flush {
+            self.inner.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct BufWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for BufWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl MakeWriter<'_> for BufWriter {
+        type Writer = Self;
+
+        fn make_writer(&self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_span_events() {
+        let lifecycle = FmtSpan::NEW | FmtSpan::CLOSE;
+        assert_eq!(span_events(true, env(&[])), lifecycle);
+        assert_eq!(span_events(false, env(&[])), FmtSpan::NONE);
+
+        // the env overrides the default in test mode and otherwise
+        for (value, expected) in [
+            ("none", FmtSpan::NONE),
+            ("new", FmtSpan::NEW),
+            ("close", FmtSpan::CLOSE),
+            (" FULL ", FmtSpan::FULL),
+        ] {
+            let vars = [("GRAFT_SPAN_EVENTS", value)];
+            assert_eq!(span_events(true, env(&vars)), expected);
+            assert_eq!(span_events(false, env(&vars)), expected);
+        }
+
+        // invalid values keep the default
+        let vars = [("GRAFT_SPAN_EVENTS", "verbose")];
+        assert_eq!(span_events(true, env(&vars)), lifecycle);
+    }
+
+    #[test]
+    fn test_color_enabled() {
+        assert!(color_enabled(true, env(&[])));
+        assert!(!color_enabled(false, env(&[])));
+
+        // NO_COLOR wins over a terminal and FORCE_COLOR
+        assert!(!color_enabled(true, env(&[("NO_COLOR", "1")])));
+        assert!(!color_enabled(
+            false,
+            env(&[("NO_COLOR", "1"), ("FORCE_COLOR", "1")])
+        ));
+
+        // FORCE_COLOR enables color for non-terminals
+        assert!(color_enabled(false, env(&[("FORCE_COLOR", "1")])));
+
+        // empty values are ignored
+        assert!(color_enabled(true, env(&[("NO_COLOR", "")])));
+        assert!(!color_enabled(false, env(&[("FORCE_COLOR", "")])));
+
+        // antithesis never uses color
+        assert!(!color_enabled(true, env(&[("ANTITHESIS_OUTPUT_DIR", "")])));
+    }
+
+    #[test]
+    fn test_custom_writer_is_not_colored() {
+        let writer = BufWriter::default();
+        let subscriber = setup_tracing_with_writer(TracingConsumer::Tool, writer.clone(), None);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::error!(answer = 42, "hello");
+        });
+
+        let output = String::from_utf8(writer.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("hello"), "unexpected output: {output}");
+        assert!(
+            !output.contains('\x1b'),
+            "output contains ANSI codes: {output}"
+        );
+    }
+
+    #[test]
+    fn test_default_level() {
+        let tool = TracingConsumer::Tool;
+        assert_eq!(default_level(&tool, None, env(&[])), LevelFilter::WARN);
+        assert_eq!(
+            default_level(&TracingConsumer::Server, None, env(&[])),
+            LevelFilter::INFO
+        );
+
+        // GRAFT_LOG_LEVEL overrides the consumer default
+        let debug = env(&[("GRAFT_LOG_LEVEL", "debug")]);
+        assert_eq!(default_level(&tool, None, &debug), LevelFilter::DEBUG);
+
+        // a level passed directly overrides the consumer default, but not
+        // GRAFT_LOG_LEVEL
+        assert_eq!(
+            default_level(&tool, Some(LevelFilter::ERROR), env(&[])),
+            LevelFilter::ERROR
+        );
+        assert_eq!(
+            default_level(&tool, Some(LevelFilter::ERROR), &debug),
+            LevelFilter::DEBUG
+        );
+
+        // invalid values are ignored
+        let invalid = env(&[("GRAFT_LOG_LEVEL", "loud")]);
+        assert_eq!(default_level(&tool, None, &invalid), LevelFilter::WARN);
+        assert_eq!(
+            default_level(&tool, Some(LevelFilter::ERROR), &invalid),
+            LevelFilter::ERROR
+        );
+
+        // debug events are only emitted once the level is raised
+        for (vars, expected) in [
+            (&[][..], false),
+            (&[("GRAFT_LOG_LEVEL", "debug")][..], true),
+        ] {
+            let writer = BufWriter::default();
+            let subscriber = build_subscriber(
+                TracingConsumer::Tool,
+                writer.clone(),
+                None,
+                default_level(&tool, None, env(vars)),
+                false,
+                OutputOpts::default(),
+            );
+            tracing::subscriber::with_default(subscriber, || {
+                tracing::debug!("debug event");
+            });
+            let output = String::from_utf8(writer.0.lock().unwrap().clone()).unwrap();
+            assert_eq!(output.contains("debug event"), expected, "{output}");
+        }
+    }
+
+    #[test]
+    fn test_output_opts() {
+        assert!(!output_opts(env(&[])).json);
+        assert!(output_opts(env(&[("GRAFT_LOG_FORMAT", "JSON")])).json);
+        assert!(!output_opts(env(&[("GRAFT_LOG_FORMAT", "text")])).json);
+        assert!(output_opts(env(&[("GRAFT_LOG_FLUSH", "line")])).flush_per_event);
+        assert!(!output_opts(env(&[("GRAFT_LOG_FLUSH", "")])).flush_per_event);
+        assert!(output_opts(env(&[("NO_TIME", "1")])).no_time);
+        assert!(!output_opts(env(&[("NO_TIME", "")])).no_time);
+        assert!(output_opts(env(&[("GRAFT_LOG_WALLCLOCK", "1")])).wallclock);
+        assert!(!output_opts(env(&[("GRAFT_LOG_WALLCLOCK", "0")])).wallclock);
+    }
+
+    #[test]
+    fn test_write_wallclock() {
+        let format = |millis| {
+            let mut s = String::new();
+            write_wallclock(&mut s, UNIX_EPOCH + Duration::from_millis(millis)).unwrap();
+            s
+        };
+        assert_eq!(format(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(format(1_704_207_845_123), "2024-01-02T15:04:05.123Z");
+        // leap day
+        assert_eq!(format(951_782_400_000), "2000-02-29T00:00:00.000Z");
+
+        // rounding up to the next second carries into the seconds, like the
+        // offset format
+        let time = UNIX_EPOCH + Duration::from_nanos(59_999_600_000);
+        let mut s = String::new();
+        write_wallclock(&mut s, time).unwrap();
+        assert_eq!(s, "1970-01-01T00:01:00.000Z");
+        assert_eq!(round_millis(Duration::from_nanos(59_999_600_000)), (60, 0));
+        assert_eq!(round_millis(Duration::from_micros(1_234_400)), (1, 234));
+    }
+
+    #[test]
+    fn test_json_no_time() {
+        for no_time in [false, true] {
+            let writer = BufWriter::default();
+            let opts = OutputOpts {
+                json: true,
+                no_time,
+                ..Default::default()
+            };
+            let subscriber = build_subscriber(
+                TracingConsumer::Tool,
+                writer.clone(),
+                None,
+                LevelFilter::WARN,
+                false,
+                opts,
+            );
+            tracing::subscriber::with_default(subscriber, || {
+                tracing::warn!("event");
+            });
+
+            let output = String::from_utf8(writer.0.lock().unwrap().clone()).unwrap();
+            let line: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+            // the timestamp is omitted rather than empty, as is the prefix
+            assert_eq!(line.get("timestamp").is_some(), !no_time, "{output}");
+            assert!(line.get("prefix").is_none(), "{output}");
+            assert!(line.get("span").is_none(), "{output}");
+        }
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let writer = BufWriter::default();
+        let opts = OutputOpts { json: true, ..Default::default() };
+        let subscriber = build_subscriber(
+            TracingConsumer::Tool,
+            writer.clone(),
+            Some("node1".into()),
+            LevelFilter::WARN,
+            false,
+            opts,
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::warn_span!("outer", vid = "abc");
+            let _guard = span.enter();
+            tracing::error!(
+                answer = 42,
+                ratio = 0.5,
+                ok = true,
+                "hello \"world\"\nagain"
+            );
+            tracing::warn!(nan = f64::NAN, err = ?Some("x"), "tab\tand control \u{1}");
+        });
+
+        let output = String::from_utf8(writer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| {
+                let value: serde_json::Value = serde_json::from_str(line)
+                    .unwrap_or_else(|err| panic!("invalid json line {line:?}: {err}"));
+                assert!(value.is_object(), "not an object: {line}");
+                value
+            })
+            .collect();
+        assert_eq!(lines.len(), 2, "unexpected output: {output}");
+
+        let first = &lines[0];
+        assert_eq!(first["level"], "ERROR");
+        assert_eq!(first["prefix"], "node1");
+        assert_eq!(first["span"]["name"], "outer");
+        assert_eq!(
+            first["spans"],
+            serde_json::json!([{ "name": "outer", "vid": "abc" }])
+        );
+        assert_eq!(first["fields"]["message"], "hello \"world\"\nagain");
+        assert_eq!(first["fields"]["answer"], 42);
+        assert_eq!(first["fields"]["ratio"], 0.5);
+        assert_eq!(first["fields"]["ok"], true);
+
+        let second = &lines[1];
+        assert_eq!(second["fields"]["message"], "tab\tand control \u{1}");
+        assert!(second["fields"]["nan"].is_null());
+        assert_eq!(second["fields"]["err"], "Some(\"x\")");
+    }
+
+    #[test]
+    fn test_flush_per_event() {
+        #[derive(Clone, Default)]
+        struct CountFlushes(Arc<Mutex<usize>>);
+
+        impl io::Write for CountFlushes {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                *self.0.lock().unwrap() += 1;
+                Ok(())
+            }
+        }
+
+        impl MakeWriter<'_> for CountFlushes {
+            type Writer = Self;
+
+            fn make_writer(&self) -> Self::Writer {
+                self.clone()
+            }
+        }
+
+        for flush_per_event in [false, true] {
+            let writer = CountFlushes::default();
+            let opts = OutputOpts {
+                json: true,
+                flush_per_event,
+                ..Default::default()
+            };
+            let subscriber = build_subscriber(
+                TracingConsumer::Tool,
+                writer.clone(),
+                None,
+                LevelFilter::WARN,
+                false,
+                opts,
+            );
+            tracing::subscriber::with_default(subscriber, || {
+                for i in 0..3 {
+                    tracing::warn!(i, "event");
+                }
+            });
+            let expected = if flush_per_event { 3 } else { 0 };
+            assert_eq!(*writer.0.lock().unwrap(), expected);
+        }
+    }
+}
@@ -2,6 +2,10 @@ use std::{fmt::Display, iter::Sum};
 
 use zerocopy::{ByteEq, ByteHash, FromBytes, Immutable, IntoBytes, KnownLayout};
 
+use crate::derive_zerocopy_encoding;
+
+const CHECKSUM_SIZE: usize = 64;
+
 #[derive(Clone, Debug, Default, IntoBytes, FromBytes, ByteEq, ByteHash, Immutable, KnownLayout)]
 #[repr(C)]
 pub struct Checksum {
@@ -15,6 +19,14 @@ pub struct Checksum {
     bytes: u128,
 }
 
+static_assertions::assert_eq_size!(Checksum, [u8; CHECKSUM_SIZE]);
+
+derive_zerocopy_encoding!(
+    encode type (Checksum)
+    with size (CHECKSUM_SIZE)
+    with empty (Checksum::default())
+);
+
 impl Display for Checksum {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // hash the checksum using blake3 to make it easier to read/compare
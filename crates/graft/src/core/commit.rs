@@ -1,6 +1,7 @@
 use std::ops::{Deref, DerefMut, Range, RangeInclusive};
 
 use bilrost::Message;
+use bytestring::ByteString;
 use itertools::Itertools;
 use splinter_rs::Splinter;
 use thin_vec::ThinVec;
@@ -45,8 +46,16 @@ pub struct Commit {
     /// If this commit is a checkpoint, it will store its own LSN in this field.
     #[bilrost(7)]
     pub checkpoints: ThinVec<LSN>,
+
+    /// An optional application-supplied note describing this Commit.
+    /// At most `MAX_NOTE_LEN` bytes.
+    #[bilrost(8)]
+    pub note: Option<ByteString>,
 }
 
+/// The maximum length of a `Commit` note in bytes
+pub const MAX_NOTE_LEN: usize = 256;
+
 impl Commit {
     /// Creates a new Commit for the given snapshot info
     pub fn new(log: LogId, lsn: LSN, page_count: PageCount) -> Self {
@@ -57,6 +66,7 @@ impl Commit {
             commit_hash: None,
             segment_idx: None,
             checkpoints: Default::default(),
+            note: None,
         }
     }
 
@@ -85,6 +95,11 @@ impl Commit {
         Self { checkpoints, ..self }
     }
 
+    /// Attaches an application-supplied note to this commit.
+    pub fn with_note(self, note: Option<ByteString>) -> Self {
+        Self { note, ..self }
+    }
+
     pub fn log(&self) -> &LogId {
         &self.log
     }
@@ -120,6 +135,10 @@ impl Commit {
     pub fn is_checkpoint(&self) -> bool {
         self.checkpoints.contains(&self.lsn)
     }
+
+    pub fn note(&self) -> Option<&ByteString> {
+        self.note.as_ref()
+    }
 }
 
 #[derive(Clone, Message, PartialEq, Eq)]
@@ -34,7 +34,7 @@ macro_rules! lsn {
     ($v:expr) => {{
         // force $v to be u64
         const V: u64 = $v;
-        static_assertions::const_assert!(V > 0 && V <= u64::MAX);
+        $crate::static_assertions::const_assert!(V > 0 && V <= u64::MAX);
         // SAFETY: V is checked at compile time to be > 0
         unsafe { $crate::core::lsn::LSN::new_unchecked(V) }
     }};
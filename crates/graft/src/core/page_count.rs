@@ -34,6 +34,8 @@ pub struct PageCount(u32);
 impl PageCount {
     pub const ZERO: Self = Self(0);
     pub const ONE: Self = Self(1);
+    /// The maximum number of pages in a Volume, which bounds the maximum
+    /// supported Volume size to `PAGESIZE * u32::MAX` bytes (~16 TB).
     pub const MAX: Self = Self(u32::MAX);
 
     #[inline]
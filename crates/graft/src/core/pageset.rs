@@ -1,9 +1,9 @@
 use std::ops::{BitOrAssign, RangeBounds, RangeInclusive};
 
 use bytes::Bytes;
-use splinter_rs::{CowSplinter, Cut, PartitionRead, PartitionWrite, Splinter};
+use splinter_rs::{CowSplinter, Cut, PartitionRead, PartitionWrite, Splinter, SplinterRef};
 
-use crate::core::{PageCount, PageIdx};
+use crate::core::{PageCount, PageIdx, pageidx::ConvertToPageIdxErr};
 use crate::derive_newtype_proxy;
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -60,6 +60,25 @@ impl PageSet {
             .map(|n| unsafe { PageIdx::new_unchecked(n) })
     }
 
+    /// Returns the number of pages in the set which precede `pageidx`, which
+    /// is the position of `pageidx` within the set if it is present.
+    #[inline]
+    pub fn rank(&self, pageidx: PageIdx) -> usize {
+        // Splinter's rank counts values less than or equal to its argument;
+        // PageIdx is never zero so subtracting one can't underflow
+        self.splinter.rank(pageidx.to_u32() - 1)
+    }
+
+    /// Returns the `n`th page in the set, counting from zero.
+    #[inline]
+    pub fn select(&self, n: usize) -> Option<PageIdx> {
+        self.splinter
+            .select(n)
+            // SAFETY: The PageSet type verifies that `0` is not contained by the
+            // Splinter at creation time.
+            .map(|n| unsafe { PageIdx::new_unchecked(n) })
+    }
+
     #[inline]
     pub fn insert(&mut self, pageidx: PageIdx) -> bool {
         self.splinter.insert(pageidx.to_u32())
@@ -114,6 +133,16 @@ impl PageSet {
         self.splinter.to_mut().cut(&rhs.splinter).into()
     }
 
+    /// Returns the pages in self which are not in rhs. Splinter compares the
+    /// two sets partition by partition, so neither set is expanded into a full
+    /// bitmap.
+    pub fn difference(&self, rhs: &PageSet) -> PageSet {
+        let mut difference = self.clone();
+        // cut removes the intersection, leaving only the difference behind
+        difference.cut(rhs);
+        difference
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = PageIdx> {
         self.splinter.iter().map(|v| {
             // SAFETY: The PageSet type verifies that `0` is not contained by the
@@ -142,6 +171,32 @@ impl From<Splinter> for PageSet {
     }
 }
 
+impl TryFrom<CowSplinter<Bytes>> for PageSet {
+    type Error = ConvertToPageIdxErr;
+
+    /// Builds a `PageSet` from a Splinter of page indices, failing if it
+    /// contains the invalid page index `0`.
+    fn try_from(splinter: CowSplinter<Bytes>) -> Result<Self, Self::Error> {
+        if splinter.contains(0) {
+            return Err(ConvertToPageIdxErr::Zero);
+        }
+        Ok(Self { splinter })
+    }
+}
+
+/// Decodes encoded Splinters of page indices, such as the pages changed by a
+/// commit, directly into a [`PageSet`].
+pub trait SplinterPageSetExt {
+    fn to_pageset(&self) -> Result<PageSet, ConvertToPageIdxErr>;
+}
+
+impl SplinterPageSetExt for SplinterRef<Bytes> {
+    #[inline]
+    fn to_pageset(&self) -> Result<PageSet, ConvertToPageIdxErr> {
+        PageSet::try_from(CowSplinter::Ref(self.clone()))
+    }
+}
+
 impl From<PageSet> for Splinter {
     fn from(value: PageSet) -> Self {
         value.splinter.into_owned()
@@ -157,10 +212,10 @@ derive_newtype_proxy!(
         self.splinter.encode_to_bytes()
     }
     from_proxy(&mut self, proxy) {
-        *self = PageSet::new(
-            CowSplinter::from_bytes(proxy)
-                .map_err(|_| bilrost::DecodeErrorKind::InvalidValue)?
-        );
+        let splinter = CowSplinter::from_bytes(proxy)
+            .map_err(|_| bilrost::DecodeErrorKind::InvalidValue)?;
+        *self = PageSet::try_from(splinter)
+            .map_err(|_| bilrost::DecodeErrorKind::InvalidValue)?;
         Ok(())
     }
 );
@@ -170,3 +225,115 @@ impl BitOrAssign<Self> for PageSet {
         self.splinter.to_mut().bitor_assign(rhs.splinter);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use assert_matches::assert_matches;
+    use rand::RngExt;
+
+    use super::*;
+    use crate::pageidx;
+
+    #[test]
+    fn pageset_difference() {
+        let mut rng = rand::rng();
+        for _ in 0..100 {
+            // vary the density so that both sparse and full partitions are
+            // compared
+            let max = PageIdx::must_new(rng.random_range(1..=100_000));
+            let random_set = |rng: &mut rand::rngs::ThreadRng| {
+                let len = rng.random_range(0..=2_000);
+                let pages: BTreeSet<PageIdx> = (0..len)
+                    .map(|_| rng.random_range(PageIdx::FIRST..=max))
+                    .collect();
+                let mut set = PageSet::EMPTY;
+                for &pageidx in &pages {
+                    set.insert(pageidx);
+                }
+                (set, pages)
+            };
+            let (lhs, lhs_pages) = random_set(&mut rng);
+            let (rhs, rhs_pages) = random_set(&mut rng);
+
+            let diff = lhs.difference(&rhs);
+            assert!(diff.iter().eq(lhs_pages.difference(&rhs_pages).copied()));
+            assert!(lhs.difference(&lhs).is_empty());
+            assert!(lhs.difference(&PageSet::EMPTY).iter().eq(lhs.iter()));
+        }
+
+        // the difference of encoded sets matches the difference of owned sets
+        let lhs = PageSet::from_range(pageidx!(1)..=pageidx!(1000));
+        let rhs = PageSet::from_range(pageidx!(500)..=pageidx!(2000));
+        let encoded = SplinterRef::from_bytes(lhs.splinter().encode_to_bytes())
+            .unwrap()
+            .to_pageset()
+            .unwrap();
+        assert!(
+            encoded
+                .difference(&rhs)
+                .iter()
+                .eq(PageSet::from_range(pageidx!(1)..=pageidx!(499)).iter())
+        );
+    }
+
+    #[test]
+    fn pageset_rank_select() {
+        let empty = PageSet::EMPTY;
+        assert_eq!(empty.rank(pageidx!(1)), 0);
+        assert_eq!(empty.rank(PageIdx::LAST), 0);
+        assert_eq!(empty.select(0), None);
+
+        let mut rng = rand::rng();
+        for _ in 0..100 {
+            let max = PageIdx::must_new(rng.random_range(1..=100_000));
+            let len = rng.random_range(1..=2_000);
+            let oracle: Vec<PageIdx> = (0..len)
+                .map(|_| rng.random_range(PageIdx::FIRST..=max))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            let mut pages = PageSet::EMPTY;
+            for &pageidx in &oracle {
+                pages.insert(pageidx);
+            }
+
+            for (n, &pageidx) in oracle.iter().enumerate() {
+                assert_eq!(pages.select(n), Some(pageidx));
+                assert_eq!(pages.rank(pageidx), n);
+            }
+            // positions and pages past the end of the set
+            assert_eq!(pages.select(oracle.len()), None);
+            assert_eq!(pages.rank(PageIdx::LAST), oracle.len());
+
+            for _ in 0..100 {
+                let pageidx = rng.random_range(PageIdx::FIRST..=PageIdx::LAST);
+                let expected = oracle.partition_point(|&p| p < pageidx);
+                assert_eq!(pages.rank(pageidx), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn splinter_to_pageset_roundtrip() {
+        let mut pages = PageSet::from_range(pageidx!(3)..=pageidx!(9));
+        pages.insert(pageidx!(1));
+        pages.insert(pageidx!(1000));
+
+        let encoded = pages.splinter().encode_to_bytes();
+        let decoded = SplinterRef::from_bytes(encoded)
+            .unwrap()
+            .to_pageset()
+            .unwrap();
+        assert!(decoded.iter().eq(pages.iter()));
+        assert_eq!(decoded.cardinality(), 9);
+
+        // a Splinter containing page index 0 is rejected
+        let invalid = Splinter::from_iter([0u32, 1, 2]).encode_to_bytes();
+        assert_matches!(
+            SplinterRef::from_bytes(invalid).unwrap().to_pageset(),
+            Err(ConvertToPageIdxErr::Zero)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::core::{LogId, VolumeId};
+use crate::core::{LogId, VolumeId, lsn::LSN};
 use crate::{local::fjall_storage::FjallStorageErr, remote::RemoteErr};
 
 #[derive(Debug, thiserror::Error)]
@@ -11,6 +11,9 @@ pub enum GraftErr {
 
     #[error(transparent)]
     Logical(#[from] LogicalErr),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 impl From<FjallStorageErr> for GraftErr {
@@ -30,6 +33,9 @@ pub enum LogicalErr {
     #[error("Volume {0} not found")]
     VolumeNotFound(VolumeId),
 
+    #[error("Tag `{0}` not found")]
+    TagNotFound(String),
+
     #[error("Volume {0} has a pending commit and needs recovery")]
     VolumeNeedsRecovery(VolumeId),
 
@@ -44,4 +50,21 @@ pub enum LogicalErr {
         expected: LogId,
         actual: LogId,
     },
+
+    #[error("Commit note is {len} bytes, which exceeds the maximum of {max} bytes")]
+    CommitNoteTooLong { len: usize, max: usize },
+
+    #[error("Volume {vid} can't grow by {pages} pages without exceeding the maximum page count")]
+    VolumeTooLarge { vid: VolumeId, pages: u32 },
+
+    #[error("Commit {lsn} pushed for Volume {vid} does not match the remote")]
+    PushVerificationFailed { vid: VolumeId, lsn: LSN },
+
+    #[error("Volume {0} has local commits which haven't been pushed")]
+    VolumeHasLocalChanges(VolumeId),
+
+    #[error(
+        "Volume {0} has never synced with the remote, so there is no remote commit to reset to"
+    )]
+    VolumeNeverSynced(VolumeId),
 }
@@ -1,9 +1,16 @@
 pub mod local {
     pub mod fjall_storage;
+    pub mod mirror_storage;
 }
 
 pub mod rt {
+    pub mod event_log;
+    pub mod observer;
+    pub mod page_cache;
     pub mod runtime;
+    pub mod sync_health;
+    pub mod volume_metrics;
+    pub mod write_amp;
 
     mod action;
     mod task;
@@ -1,33 +1,43 @@
-use std::{collections::BTreeMap, fmt::Debug, ops::RangeInclusive, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Debug,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
 
 use crate::{
     core::{
         LogId, PageCount, PageIdx, SegmentId, VolumeId,
+        byte_unit::ByteUnit,
         checksum::{Checksum, ChecksumBuilder},
-        commit::{Commit, SegmentIdx, SegmentRangeRef},
+        commit::{Commit, MAX_NOTE_LEN, SegmentIdx, SegmentRangeRef},
         commit_hash::CommitHash,
         logref::LogRef,
         lsn::{LSN, LSNRangeExt, LSNSet},
-        page::Page,
+        page::{PAGESIZE, Page},
         pageset::PageSet,
     },
     local::fjall_storage::{
+        fjall_repr::FjallRepr,
         fjall_typed::{ReadableExt, TypedIter, TypedKeyspace, TypedValIter, WriteBatchExt},
-        keys::PageVersion,
+        keys::{PageContentRef, PageHash, PageVersion},
     },
 };
 use bytestring::ByteString;
-use fjall::{Database, KeyspaceCreateOptions, KvSeparationOptions, OwnedWriteBatch};
+use fjall::{Database, KeyspaceCreateOptions, KvSeparationOptions, OwnedWriteBatch, PersistMode};
 use parking_lot::{Mutex, MutexGuard};
 use splinter_rs::Splinter;
 use thin_vec::thin_vec;
 use tryiter::TryIteratorExt;
+use zerocopy::{BE, U32};
 
 use crate::{
     LogicalErr,
     local::fjall_storage::keys::PageKey,
     snapshot::Snapshot,
-    volume::{PendingCommit, SyncPoint, Volume},
+    volume::{ConflictReport, OpenPolicy, PendingCommit, SyncPoint, Volume, VolumeSettings},
 };
 
 mod fjall_repr;
@@ -49,11 +59,63 @@ pub enum FjallStorageErr {
     #[error("batch commit precondition failed")]
     BatchPreconditionErr,
 
+    #[error(
+        "incompatible storage format: found version {found}, but this version of Graft supports up to version {supported}"
+    )]
+    IncompatibleFormat { found: u32, supported: u32 },
+
+    #[error(
+        "outdated storage format: found version {found}, which must be migrated to version {supported} by opening it with Graft"
+    )]
+    OutdatedFormat { found: u32, supported: u32 },
+
+    #[error("storage has no format version, so it was never initialized by Graft")]
+    MissingFormat,
+
+    #[error(
+        "commit {log}:{lsn} references page {pageidx}, which is beyond its page count of {page_count}"
+    )]
+    CommitPageOutOfBounds {
+        log: LogId,
+        lsn: LSN,
+        pageidx: PageIdx,
+        page_count: PageCount,
+    },
+
     #[error(transparent)]
     LogicalErr(#[from] LogicalErr),
 }
 
+impl FjallStorageErr {
+    /// Returns true if storage couldn't be opened because another process
+    /// holds it open.
+    pub fn is_locked(&self) -> bool {
+        let err = match self {
+            FjallStorageErr::FjallErr(fjall::Error::Io(err)) | FjallStorageErr::IoErr(err) => err,
+            _ => return false,
+        };
+        matches!(
+            err.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::ResourceBusy
+        )
+    }
+}
+
+/// The version of the on-disk storage format written by this version of Graft.
+/// Must be incremented whenever the format changes incompatibly.
+///
+/// Version 2 added deduplicated pages, stored in the `page_refs`,
+/// `page_contents` and `page_content_refs` keyspaces.
+const FORMAT_VERSION: u32 = 2;
+
+/// The key in the meta keyspace which stores the storage format version
+const FORMAT_VERSION_KEY: &str = "format_version";
+
 struct Keyspaces {
+    /// This keyspace stores metadata about storage itself, such as the
+    /// format version
+    meta: TypedKeyspace<ByteString, U32<BE>>,
+
     /// This keyspace maps tags to volumes
     tags: TypedKeyspace<ByteString, VolumeId>,
 
@@ -73,34 +135,178 @@ struct Keyspaces {
 
     /// This keyspace stores Pages
     pages: TypedKeyspace<PageKey, Page>,
+
+    /// This keyspace maps pages to their content hash when page
+    /// deduplication is enabled
+    page_refs: TypedKeyspace<PageKey, PageHash>,
+
+    /// This keyspace stores deduplicated Pages keyed by their content hash
+    page_contents: TypedKeyspace<PageHash, Page>,
+
+    /// This keyspace is an index of the pages referencing each entry in
+    /// `page_contents`, used to remove content once nothing references it
+    page_content_refs: TypedKeyspace<PageContentRef, ()>,
+
+    /// whether new pages are written to `page_refs` and `page_contents`
+    /// rather than `pages`
+    dedup_pages: bool,
 }
 
 impl Keyspaces {
-    fn open(db: &fjall::Database) -> Result<Self, FjallStorageErr> {
+    /// Returns the name and disk space used by each keyspace
+    fn disk_space(&self) -> Vec<(&'static str, ByteUnit)> {
+        vec![
+            ("tags", ByteUnit::new(self.tags.disk_space())),
+            ("volumes", ByteUnit::new(self.volumes.disk_space())),
+            ("checkpoints", ByteUnit::new(self.checkpoints.disk_space())),
+            ("log", ByteUnit::new(self.log.disk_space())),
+            (
+                "page_versions",
+                ByteUnit::new(self.page_versions.disk_space()),
+            ),
+            ("pages", ByteUnit::new(self.pages.disk_space())),
+            ("page_refs", ByteUnit::new(self.page_refs.disk_space())),
+            (
+                "page_contents",
+                ByteUnit::new(self.page_contents.disk_space()),
+            ),
+            (
+                "page_content_refs",
+                ByteUnit::new(self.page_content_refs.disk_space()),
+            ),
+        ]
+    }
+
+    fn open(db: &fjall::Database, opts: &StorageOptions) -> Result<Self, FjallStorageErr> {
         Ok(Self {
+            meta: TypedKeyspace::open(db, "meta", Default::default)?,
             tags: TypedKeyspace::open(db, "tags", Default::default)?,
             volumes: TypedKeyspace::open(db, "volumes", Default::default)?,
             checkpoints: TypedKeyspace::open(db, "checkpoints", Default::default)?,
-            log: TypedKeyspace::open(db, "log", Default::default)?,
+            log: TypedKeyspace::open(db, "log", || {
+                let kv_separation = opts.log_kv_separation.then(KvSeparationOptions::default);
+                KeyspaceCreateOptions::default().with_kv_separation(kv_separation)
+            })?,
             page_versions: TypedKeyspace::open(db, "page_versions", Default::default)?,
             pages: TypedKeyspace::open(db, "pages", || {
                 KeyspaceCreateOptions::default()
                     .with_kv_separation(Some(KvSeparationOptions::default()))
             })?,
+            page_refs: TypedKeyspace::open(db, "page_refs", Default::default)?,
+            page_contents: TypedKeyspace::open(db, "page_contents", || {
+                KeyspaceCreateOptions::default()
+                    .with_kv_separation(Some(KvSeparationOptions::default()))
+            })?,
+            page_content_refs: TypedKeyspace::open(db, "page_content_refs", Default::default)?,
+            dedup_pages: opts.dedup_pages,
         })
     }
 }
 
+/// Options which tune how `FjallStorage` lays out data on disk. Options only
+/// take effect when storage is first created.
+#[derive(Debug, Clone, Default)]
+pub struct StorageOptions {
+    /// Store commits in the log keyspace using KV separation. KV separation
+    /// benefits large commits, such as those with segments containing many
+    /// pages, but adds overhead for workloads with many small commits.
+    /// Disabled by default.
+    pub log_kv_separation: bool,
+
+    /// Store a single copy of identical pages, such as repeated empty pages,
+    /// keyed by their content hash. Saves space for databases with many
+    /// duplicate pages at the cost of an extra lookup per read. Unlike other
+    /// options, this may be changed when reopening storage: it only affects
+    /// how new pages are written, and pages written either way remain
+    /// readable. Disabled by default.
+    pub dedup_pages: bool,
+}
+
+/// A summary of the contents of `FjallStorage`
+#[derive(Debug, Clone)]
+pub struct StorageStats {
+    /// Total disk space used by all keyspaces
+    pub disk_space: ByteUnit,
+    pub tags: usize,
+    pub volumes: usize,
+    pub commits: usize,
+    /// The number of pages cached locally, including deduplicated pages
+    pub pages: usize,
+    /// Disk space used by each keyspace, by name
+    pub keyspaces: Vec<(&'static str, ByteUnit)>,
+}
+
+/// The result of removing orphaned Segments from storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcReport {
+    /// The Segments whose pages were removed, in ascending order
+    pub segments: Vec<SegmentId>,
+    /// The number of pages removed, including deduplicated pages
+    pub pages: usize,
+    /// The disk space reclaimed by removing page content. Deduplicated
+    /// content only counts once it is no longer shared by any other page.
+    pub bytes: ByteUnit,
+}
+
+/// The local state of a single Volume, for debugging a data directory
+#[derive(Debug, Clone)]
+pub struct VolumeInspection {
+    pub volume: Volume,
+    /// The latest LSN in the Volume's local Log
+    pub latest_local: Option<LSN>,
+    /// The latest LSN in the Volume's remote Log, as of the last fetch
+    pub latest_remote: Option<LSN>,
+    /// The page count as of the latest commit visible to the Volume
+    pub page_count: PageCount,
+}
+
+/// Counters describing contention on the storage lock, which serializes all
+/// read + write transactions such as commits.
+#[derive(Debug, Default)]
+struct LockStats {
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+    wait_nanos: AtomicU64,
+}
+
+/// A snapshot of contention on the storage lock since storage was opened or
+/// the counters were last reset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockContention {
+    /// The number of times the lock was acquired
+    pub acquisitions: u64,
+    /// The number of acquisitions which had to wait for another holder
+    pub contended: u64,
+    /// The total time spent waiting to acquire the lock
+    pub wait: Duration,
+}
+
 pub struct FjallStorage {
     db: fjall::Database,
     ks: Keyspaces,
 
+    /// the directory containing the database
+    path: PathBuf,
+
     /// Must be held while performing read+write transactions.
     /// Read-only and write-only transactions don't need to hold the lock as
     /// long as they are safe:
     /// To make read-only txns safe, use the same snapshot for all reads
     /// To make write-only txns safe, they must be monotonic
     lock: Mutex<()>,
+
+    /// contention on `lock`
+    lock_stats: LockStats,
+
+    /// Must be held while committing deduplicated page content, and while
+    /// removing content which is no longer referenced, so that content isn't
+    /// removed as a new page starts referencing it. When both are needed,
+    /// `lock` must be acquired first.
+    contents_lock: Mutex<()>,
+
+    /// Segments whose pages are being cached by an in-progress push, before
+    /// any commit references them. These are never considered orphaned.
+    pinned_segments: Mutex<HashSet<SegmentId>>,
 }
 
 impl Debug for FjallStorage {
@@ -111,20 +317,123 @@ impl Debug for FjallStorage {
 
 impl FjallStorage {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, FjallStorageErr> {
-        Self::open_from_builder(Database::builder(path))
+        Self::open_with_options(path, StorageOptions::default())
+    }
+
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        opts: StorageOptions,
+    ) -> Result<Self, FjallStorageErr> {
+        let path = path.as_ref().to_path_buf();
+        Self::open_from_builder(Database::builder(&path), path, &opts)
     }
 
     pub fn open_temporary() -> Result<Self, FjallStorageErr> {
         let path = tempfile::tempdir()?.keep();
-        Self::open_from_builder(Database::builder(path).temporary(true))
+        Self::open_from_builder(
+            Database::builder(&path).temporary(true),
+            path,
+            &StorageOptions::default(),
+        )
+    }
+
+    /// Opens existing storage without initializing or migrating it, for tools
+    /// which inspect a data directory without modifying it. Fails with
+    /// `FjallStorageErr::MissingFormat` or `FjallStorageErr::OutdatedFormat`
+    /// rather than writing the current format version.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self, FjallStorageErr> {
+        let path = path.as_ref().to_path_buf();
+        let storage =
+            Self::open_unchecked(Database::builder(&path), path, &StorageOptions::default())?;
+        let found = storage
+            .db
+            .snapshot()
+            .get(&storage.ks.meta, FORMAT_VERSION_KEY)?;
+        match found.map(|v| v.get()) {
+            Some(found) if found > FORMAT_VERSION => {
+                Err(FjallStorageErr::IncompatibleFormat { found, supported: FORMAT_VERSION })
+            }
+            Some(FORMAT_VERSION) => Ok(storage),
+            Some(found) => {
+                Err(FjallStorageErr::OutdatedFormat { found, supported: FORMAT_VERSION })
+            }
+            None => Err(FjallStorageErr::MissingFormat),
+        }
     }
 
     fn open_from_builder(
         builder: fjall::DatabaseBuilder<Database>,
+        path: PathBuf,
+        opts: &StorageOptions,
+    ) -> Result<Self, FjallStorageErr> {
+        let storage = Self::open_unchecked(builder, path, opts)?;
+        storage.check_format_version()?;
+        Ok(storage)
+    }
+
+    fn open_unchecked(
+        builder: fjall::DatabaseBuilder<Database>,
+        path: PathBuf,
+        opts: &StorageOptions,
     ) -> Result<Self, FjallStorageErr> {
         let db = builder.open()?;
-        let ks = Keyspaces::open(&db)?;
-        Ok(Self { db, ks, lock: Default::default() })
+        let ks = Keyspaces::open(&db, opts)?;
+        Ok(Self {
+            db,
+            ks,
+            path,
+            lock: Default::default(),
+            lock_stats: Default::default(),
+            contents_lock: Default::default(),
+            pinned_segments: Default::default(),
+        })
+    }
+
+    /// Returns the directory containing the database
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Verifies that storage was not written by a newer, incompatible version
+    /// of Graft. Marks new storage with the current format version, and
+    /// migrates storage written by an older version.
+    fn check_format_version(&self) -> Result<(), FjallStorageErr> {
+        let snapshot = self.db.snapshot();
+        let found = snapshot.get(&self.ks.meta, FORMAT_VERSION_KEY)?;
+        match found.map(|v| v.get()) {
+            Some(found) if found > FORMAT_VERSION => {
+                Err(FjallStorageErr::IncompatibleFormat { found, supported: FORMAT_VERSION })
+            }
+            Some(FORMAT_VERSION) => Ok(()),
+            Some(found) => {
+                tracing::info!(
+                    found,
+                    supported = FORMAT_VERSION,
+                    "migrating storage format"
+                );
+                let mut batch = self.db.batch();
+                // version 1 has no deduplicated pages, but index any that
+                // were written anyway so their content can be removed later
+                let mut refs = snapshot.iter(&self.ks.page_refs);
+                while let Some((key, hash)) = refs.try_next()? {
+                    batch.insert_typed(
+                        &self.ks.page_content_refs,
+                        PageContentRef::new(hash, key),
+                        (),
+                    );
+                }
+                batch.insert_typed(
+                    &self.ks.meta,
+                    FORMAT_VERSION_KEY.into(),
+                    U32::new(FORMAT_VERSION),
+                );
+                Ok(batch.commit()?)
+            }
+            None => self
+                .ks
+                .meta
+                .insert(FORMAT_VERSION_KEY.into(), U32::new(FORMAT_VERSION)),
+        }
     }
 
     pub(crate) fn read(&self) -> ReadGuard<'_> {
@@ -135,6 +444,18 @@ impl FjallStorage {
         WriteBatch::open(self)
     }
 
+    fn remove_pages(&self) -> PageRemoval<'_> {
+        PageRemoval::open(self)
+    }
+
+    /// Prevents the Segment from being considered orphaned until the
+    /// returned guard is dropped. Must be taken before caching any of the
+    /// Segment's pages.
+    pub(crate) fn pin_segment(&self, sid: SegmentId) -> SegmentPin<'_> {
+        self.pinned_segments.lock().insert(sid.clone());
+        SegmentPin { storage: self, sid }
+    }
+
     /// Open a read + write txn on storage.
     /// The returned object holds a lock, any subsequent calls to `read_write`
     /// will block.
@@ -142,17 +463,75 @@ impl FjallStorage {
         ReadWriteGuard::open(self)
     }
 
+    /// Acquires the read + write lock, recording whether it was contended.
+    fn acquire_lock(&self) -> MutexGuard<'_, ()> {
+        let stats = &self.lock_stats;
+        stats.acquisitions.fetch_add(1, Ordering::Relaxed);
+        if let Some(permit) = self.lock.try_lock() {
+            return permit;
+        }
+
+        let start = Instant::now();
+        let permit = self.lock.lock();
+        let wait = start.elapsed();
+        stats.contended.fetch_add(1, Ordering::Relaxed);
+        stats
+            .wait_nanos
+            .fetch_add(wait.as_nanos() as u64, Ordering::Relaxed);
+        tracing::debug!(wait_us = wait.as_micros() as u64, "storage lock contended");
+        permit
+    }
+
+    /// Returns contention on the lock which serializes read + write
+    /// transactions, such as commits.
+    pub fn lock_contention(&self) -> LockContention {
+        let stats = &self.lock_stats;
+        LockContention {
+            acquisitions: stats.acquisitions.load(Ordering::Relaxed),
+            contended: stats.contended.load(Ordering::Relaxed),
+            wait: Duration::from_nanos(stats.wait_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Zeroes the lock contention counters.
+    pub fn reset_lock_contention(&self) {
+        let stats = &self.lock_stats;
+        stats.acquisitions.store(0, Ordering::Relaxed);
+        stats.contended.store(0, Ordering::Relaxed);
+        stats.wait_nanos.store(0, Ordering::Relaxed);
+    }
+
     pub fn write_page(
         &self,
         sid: SegmentId,
         pageidx: PageIdx,
         page: Page,
     ) -> Result<(), FjallStorageErr> {
-        self.ks.pages.insert(PageKey::new(sid, pageidx), page)
+        let mut batch = self.batch();
+        batch.write_page(sid, pageidx, page);
+        batch.commit()
     }
 
+    /// Removes a cached page. The content of a deduplicated page is removed
+    /// once no other page shares it.
     pub fn remove_page(&self, sid: SegmentId, pageidx: PageIdx) -> Result<(), FjallStorageErr> {
-        self.ks.pages.remove(PageKey::new(sid, pageidx))
+        let key = PageKey::new(sid, pageidx);
+        let mut removal = self.remove_pages();
+        removal.remove_range(key.clone()..=key)?;
+        removal.commit()?;
+        Ok(())
+    }
+
+    /// Overwrites a cached page with bytes which don't decode as a Page
+    #[cfg(any(test, feature = "testutil"))]
+    pub fn testonly_corrupt_page(
+        &self,
+        sid: SegmentId,
+        pageidx: PageIdx,
+    ) -> Result<(), FjallStorageErr> {
+        self.ks
+            .pages
+            .testonly_insert_raw(PageKey::new(sid, pageidx), b"corrupt")
     }
 
     pub fn remove_page_range(
@@ -160,22 +539,55 @@ impl FjallStorage {
         sid: &SegmentId,
         pages: RangeInclusive<PageIdx>,
     ) -> Result<(), FjallStorageErr> {
-        // PageKeys are stored in descending order
+        // PageKeys encode the page index big endian, so they are stored in
+        // ascending order
         let keyrange =
-            PageKey::new(sid.clone(), *pages.end())..=PageKey::new(sid.clone(), *pages.start());
-        let mut batch = self.db.batch();
-        let mut iter = self.db.snapshot().range(&self.ks.pages, keyrange);
-        while let Some((key, _)) = iter.try_next()? {
-            batch.remove_typed(&self.ks.pages, key);
-        }
-        batch.commit()?;
+            PageKey::new(sid.clone(), *pages.start())..=PageKey::new(sid.clone(), *pages.end());
+        let mut removal = self.remove_pages();
+        removal.remove_range(keyrange)?;
+        removal.commit()?;
         Ok(())
     }
 
+    /// Blocks until all previous writes have been durably persisted to disk.
+    pub fn persist(&self) -> Result<(), FjallStorageErr> {
+        Ok(self.db.persist(PersistMode::SyncAll)?)
+    }
+
     pub fn tag_delete(&self, tag: &str) -> Result<(), FjallStorageErr> {
         self.ks.tags.remove(tag.into())
     }
 
+    /// Gathers statistics about storage. Counts are computed by scanning
+    /// each keyspace, so this is proportional to the size of storage.
+    pub fn stats(&self) -> Result<StorageStats, FjallStorageErr> {
+        let reader = self.read();
+        let keyspaces = self.ks.disk_space();
+        Ok(StorageStats {
+            disk_space: keyspaces
+                .iter()
+                .fold(ByteUnit::ZERO, |total, (_, size)| total + *size),
+            tags: count_keys(reader.snapshot.iter(&self.ks.tags))?,
+            volumes: count_keys(reader.snapshot.iter(&self.ks.volumes))?,
+            commits: count_keys(reader.snapshot.iter(&self.ks.log))?,
+            pages: count_keys(reader.snapshot.iter(&self.ks.pages))?
+                + count_keys(reader.snapshot.iter(&self.ks.page_refs))?,
+            keyspaces,
+        })
+    }
+
+    /// Loads the local state of a Volume without modifying storage.
+    pub fn inspect_volume(&self, vid: &VolumeId) -> Result<VolumeInspection, FjallStorageErr> {
+        let reader = self.read();
+        let volume = reader.volume(vid)?;
+        Ok(VolumeInspection {
+            latest_local: reader.latest_lsn(&volume.local)?,
+            latest_remote: reader.latest_lsn(&volume.remote)?,
+            page_count: reader.snapshot(vid)?.page_count,
+            volume,
+        })
+    }
+
     pub fn volume_delete(&self, vid: &VolumeId) -> Result<(), FjallStorageErr> {
         self.ks.volumes.remove(vid.clone())
     }
@@ -206,6 +618,15 @@ impl FjallStorage {
     }
 }
 
+fn count_keys<K: FjallRepr, V: FjallRepr>(iter: TypedIter<K, V>) -> Result<usize, FjallStorageErr> {
+    let mut keys = iter.keys();
+    let mut count = 0;
+    while keys.try_next()?.is_some() {
+        count += 1;
+    }
+    Ok(count)
+}
+
 pub struct ReadGuard<'a> {
     storage: &'a FjallStorage,
     snapshot: fjall::Snapshot,
@@ -257,6 +678,38 @@ impl<'a> ReadGuard<'a> {
             .map(|lr| lr.lsn))
     }
 
+    /// Counts the commits in the snapshot which are newer than its most recent
+    /// checkpoint, or all of its commits if it contains no checkpoint.
+    pub fn commits_since_checkpoint(&self, snapshot: &Snapshot) -> Result<u64, FjallStorageErr> {
+        let mut count = 0;
+        for entry in snapshot.iter() {
+            let end = *entry.lsns.end();
+            match self.checkpoint_for(&entry.log, end)? {
+                Some(checkpoint) if entry.lsns.contains(&checkpoint) => {
+                    return Ok(count + end.since(checkpoint).expect("checkpoint <= end"));
+                }
+                _ => count += entry.lsns.len(),
+            }
+        }
+        Ok(count)
+    }
+
+    /// Retrieve the LSN of the nth most recent checkpoint in the log, where
+    /// `n == 0` is the latest checkpoint.
+    pub fn nth_checkpoint(&self, log: &LogId, n: usize) -> Result<Option<LSN>, FjallStorageErr> {
+        // The checkpoint index orders LSNs in reverse, thus the newest
+        // checkpoint comes first
+        let mut checkpoints = self.snapshot.prefix(&self.ks().checkpoints, log).keys();
+        let mut remaining = n;
+        while let Some(logref) = checkpoints.try_next()? {
+            if remaining == 0 {
+                return Ok(Some(logref.lsn));
+            }
+            remaining -= 1;
+        }
+        Ok(None)
+    }
+
     pub fn iter_volumes(&self) -> TypedValIter<VolumeId, Volume> {
         self.snapshot.iter(&self.ks().volumes).values()
     }
@@ -396,34 +849,54 @@ impl<'a> ReadGuard<'a> {
     pub fn iter_visible_pages(
         &self,
         snapshot: &Snapshot,
+    ) -> impl Iterator<Item = Result<(SegmentIdx, PageSet), FjallStorageErr>> {
+        self.iter_visible_pages_in(snapshot, PageIdx::FIRST..=PageIdx::LAST)
+    }
+
+    /// Like `iter_visible_pages`, but only covers visible pages within the
+    /// specified range. Stops scanning commits once every page in the range
+    /// has been found.
+    pub fn iter_visible_pages_in(
+        &self,
+        snapshot: &Snapshot,
+        range: RangeInclusive<PageIdx>,
     ) -> impl Iterator<Item = Result<(SegmentIdx, PageSet), FjallStorageErr>> {
         // the set of pages we are searching for.
         // we remove pages from this set as we iterate through commits.
-        let mut pages = PageSet::from_range(PageIdx::FIRST..=PageIdx::LAST);
-
-        self.commits(snapshot).try_filter_map(move |commit| {
-            // if we have found all pages, we are done
-            if pages.is_empty() {
-                return Ok(None);
+        let mut pages = match snapshot.page_count.last_pageidx() {
+            Some(last) if *range.start() <= last.min(*range.end()) => {
+                PageSet::from_range(*range.start()..=last.min(*range.end()))
             }
+            _ => PageSet::EMPTY,
+        };
 
-            if let Some(idx) = commit.segment_idx {
-                let mut commit_pages = idx.pageset.clone();
-
-                if commit_pages.last().map(|idx| idx.pages()) > Some(snapshot.page_count) {
-                    // truncate any pages in this commit that extend beyond the page count
-                    commit_pages.truncate(snapshot.page_count);
-                }
-
-                // figure out which pages we need from this commit
-                let outstanding = pages.cut(&commit_pages);
-
-                if !outstanding.is_empty() {
-                    return Ok(Some((idx, outstanding)));
+        let mut commits = self.commits(snapshot);
+        std::iter::from_fn(move || {
+            // once we have found all pages we are done, which allows a
+            // checkpoint to short-circuit scanning older commits
+            while !pages.is_empty() {
+                let commit = match commits.next()? {
+                    Ok(commit) => commit,
+                    Err(err) => return Some(Err(err)),
+                };
+
+                if let Some(idx) = commit.segment_idx {
+                    let mut commit_pages = idx.pageset.clone();
+
+                    if commit_pages.last().map(|idx| idx.pages()) > Some(snapshot.page_count) {
+                        // truncate any pages in this commit that extend beyond the page count
+                        commit_pages.truncate(snapshot.page_count);
+                    }
+
+                    // figure out which pages we need from this commit
+                    let outstanding = pages.cut(&commit_pages);
+
+                    if !outstanding.is_empty() {
+                        return Some(Ok((idx, outstanding)));
+                    }
                 }
             }
-
-            Ok(None)
+            None
         })
     }
 
@@ -472,8 +945,9 @@ impl<'a> ReadGuard<'a> {
     }
 
     pub fn has_page(&self, sid: SegmentId, pageidx: PageIdx) -> Result<bool, FjallStorageErr> {
-        self.snapshot
-            .contains_key(&self.ks().pages, &PageKey::new(sid, pageidx))
+        let key = PageKey::new(sid, pageidx);
+        Ok(self.snapshot.contains_key(&self.ks().pages, &key)?
+            || self.snapshot.contains_key(&self.ks().page_refs, &key)?)
     }
 
     pub fn read_page(
@@ -481,8 +955,15 @@ impl<'a> ReadGuard<'a> {
         sid: SegmentId,
         pageidx: PageIdx,
     ) -> Result<Option<Page>, FjallStorageErr> {
-        self.snapshot
-            .get_owned(&self.ks().pages, PageKey::new(sid, pageidx))
+        let key = PageKey::new(sid, pageidx);
+        if let Some(page) = self.snapshot.get(&self.ks().pages, &key)? {
+            return Ok(Some(page));
+        }
+        // fall back to resolving a deduplicated page through its content hash
+        match self.snapshot.get(&self.ks().page_refs, &key)? {
+            Some(hash) => self.snapshot.get(&self.ks().page_contents, &hash),
+            None => Ok(None),
+        }
     }
 
     /// Retrieve the `PageCount` of a Volume at a particular LSN.
@@ -491,12 +972,21 @@ impl<'a> ReadGuard<'a> {
     }
 
     pub fn checksum(&self, snapshot: &Snapshot) -> Result<Checksum, FjallStorageErr> {
+        self.checksum_range(snapshot, PageIdx::FIRST..=PageIdx::LAST)
+    }
+
+    /// Like `checksum`, but only includes visible pages within the specified
+    /// range.
+    pub fn checksum_range(
+        &self,
+        snapshot: &Snapshot,
+        range: RangeInclusive<PageIdx>,
+    ) -> Result<Checksum, FjallStorageErr> {
         let mut builder = ChecksumBuilder::new();
-        let mut iter = self.iter_visible_pages(snapshot);
+        let mut iter = self.iter_visible_pages_in(snapshot, range);
         while let Some((idx, pageset)) = iter.try_next()? {
             for pageidx in pageset.iter() {
-                let key = PageKey::new(idx.sid.clone(), pageidx);
-                if let Some(page) = self.snapshot.get(&self.ks().pages, &key)? {
+                if let Some(page) = self.read_page(idx.sid.clone(), pageidx)? {
                     builder.write(&page);
                 }
             }
@@ -507,22 +997,34 @@ impl<'a> ReadGuard<'a> {
     pub fn find_missing_frames(
         &self,
         snapshot: &Snapshot,
+    ) -> Result<Vec<SegmentRangeRef>, FjallStorageErr> {
+        self.find_missing_frames_in(snapshot, PageIdx::FIRST..=PageIdx::LAST)
+    }
+
+    /// Like `find_missing_frames`, but only considers frames which contain
+    /// visible pages within the specified range.
+    pub fn find_missing_frames_in(
+        &self,
+        snapshot: &Snapshot,
+        range: RangeInclusive<PageIdx>,
     ) -> Result<Vec<SegmentRangeRef>, FjallStorageErr> {
         let mut missing_frames = vec![];
         let mut iter = self.iter_visible_pages(snapshot);
         while let Some((idx, pageset)) = iter.try_next()? {
-            // find candidate frames (intersects with the visible pageset)
-            let frames = idx.iter_frames(|pages| pageset.contains_any(pages));
+            // find candidate frames (intersects with the visible pageset
+            // within the requested range)
+            let frames = idx.iter_frames(|pages| {
+                let start = *pages.start().max(range.start());
+                let end = *pages.end().min(range.end());
+                start <= end && pageset.contains_any(&(start..=end))
+            });
 
             // find frames for which we are missing the first page.
             // since we always download entire segment frames, if we are missing
             // the first page, we are missing all the pages (in the frame)
             for frame in frames {
                 if let Some(first_page) = frame.pageset.first()
-                    && !self.snapshot.contains_key(
-                        &self.ks().pages,
-                        &PageKey::new(frame.sid.clone(), first_page),
-                    )?
+                    && !self.has_page(frame.sid.clone(), first_page)?
                 {
                     missing_frames.push(frame);
                 }
@@ -530,24 +1032,142 @@ impl<'a> ReadGuard<'a> {
         }
         Ok(missing_frames)
     }
+
+    /// Returns the commits in `log` within `lsns` along with the union of the
+    /// pages they changed. Commits are returned in ascending LSN order.
+    fn changes(
+        &self,
+        log: &LogId,
+        lsns: RangeInclusive<LSN>,
+    ) -> Result<(Vec<Commit>, PageSet), FjallStorageErr> {
+        // the page count is irrelevant when only iterating commits
+        let snapshot = Snapshot::new(log.clone(), lsns, PageCount::ZERO);
+        let mut commits = self.commits(&snapshot).collect::<Result<Vec<_>, _>>()?;
+        commits.reverse();
+        let mut changed = PageSet::EMPTY;
+        for idx in commits.iter().filter_map(Commit::segment_idx) {
+            changed |= idx.pageset().clone();
+        }
+        Ok((commits, changed))
+    }
+
+    /// Describes how the Volume's unpushed local commits conflict with the
+    /// remote commits it hasn't pulled yet. Returns `None` unless the Volume
+    /// has diverged from its remote Log, as of the last fetch.
+    pub fn conflict_report(
+        &self,
+        vid: &VolumeId,
+    ) -> Result<Option<ConflictReport>, FjallStorageErr> {
+        let volume = self.volume(vid)?;
+        let latest_local = self.latest_lsn(&volume.local)?;
+        let latest_remote = self.latest_lsn(&volume.remote)?;
+        let (Some(local_lsns), Some(remote_lsns)) = (
+            volume.local_changes(latest_local),
+            volume.remote_changes(latest_remote),
+        ) else {
+            return Ok(None);
+        };
+
+        let (local_commits, mut local_changed) = self.changes(&volume.local, local_lsns)?;
+        let (remote_commits, remote_changed) = self.changes(&volume.remote, remote_lsns)?;
+        Ok(Some(ConflictReport {
+            fork_point: volume.remote_commit(),
+            local_commits,
+            remote_commits,
+            overlapping: local_changed.cut(&remote_changed),
+            vid: volume.vid,
+        }))
+    }
+
+    /// Returns the Segments with pages in storage which aren't referenced by
+    /// any commit in a Volume's local or remote Log, in ascending order.
+    /// Every commit in those Logs is considered, including commits which are
+    /// no longer visible to the Volume's latest snapshot. Segments being
+    /// pushed, including the Segment of a Volume's pending commit, are not
+    /// orphaned even though no commit references them yet.
+    pub fn orphan_segments(&self) -> Result<Vec<SegmentId>, FjallStorageErr> {
+        // segments being pushed are cached before any commit references them
+        let mut referenced = self.storage.pinned_segments.lock().clone();
+
+        let mut logs = HashSet::new();
+        let mut volumes = self.iter_volumes();
+        while let Some(volume) = volumes.try_next()? {
+            if let Some(sid) = volume.pending_commit.and_then(|pending| pending.sid) {
+                referenced.insert(sid);
+            }
+            logs.insert(volume.local);
+            logs.insert(volume.remote);
+        }
+
+        for log in &logs {
+            let mut commits = self.snapshot.prefix(&self.ks().log, log).values();
+            while let Some(commit) = commits.try_next()? {
+                if let Some(idx) = commit.segment_idx {
+                    referenced.insert(idx.sid().clone());
+                }
+            }
+        }
+
+        let mut orphans = BTreeSet::new();
+        let mut keys = self.snapshot.iter(&self.ks().pages).keys();
+        while let Some(key) = keys.try_next()? {
+            if !referenced.contains(key.sid()) {
+                orphans.insert(key.sid().clone());
+            }
+        }
+        let mut keys = self.snapshot.iter(&self.ks().page_refs).keys();
+        while let Some(key) = keys.try_next()? {
+            if !referenced.contains(key.sid()) {
+                orphans.insert(key.sid().clone());
+            }
+        }
+        Ok(orphans.into_iter().collect())
+    }
 }
 
 pub struct WriteBatch<'a> {
     ks: &'a Keyspaces,
+    contents_lock: &'a Mutex<()>,
     batch: OwnedWriteBatch,
+    /// whether the batch writes deduplicated page content
+    writes_contents: bool,
 }
 
 impl<'a> WriteBatch<'a> {
     fn open(storage: &'a FjallStorage) -> Self {
         let ks = &storage.ks;
         let batch = storage.db.batch();
-        Self { ks, batch }
+        Self {
+            ks,
+            contents_lock: &storage.contents_lock,
+            batch,
+            writes_contents: false,
+        }
     }
 
     pub fn write_tag(&mut self, tag: &str, vid: VolumeId) {
         self.batch.insert_typed(&self.ks.tags, tag.into(), vid);
     }
 
+    /// Writes a commit received from a remote. Rejects the commit if its
+    /// segment index references pages beyond its page count, as a well formed
+    /// commit never contains such pages.
+    pub fn write_remote_commit(&mut self, commit: Commit) -> Result<(), FjallStorageErr> {
+        let page_count = commit.page_count();
+        if let Some(pageidx) = commit.segment_idx().and_then(|idx| idx.pageset.last())
+            && !page_count.contains(pageidx)
+        {
+            return Err(FjallStorageErr::CommitPageOutOfBounds {
+                log: commit.log,
+                lsn: commit.lsn,
+                pageidx,
+                page_count,
+            });
+        }
+        self.write_commit(commit);
+        Ok(())
+    }
+
     pub fn write_commit(&mut self, commit: Commit) {
         // keep the checkpoint index up to date
         for &checkpoint in commit.checkpoints() {
@@ -579,15 +1199,135 @@ impl<'a> WriteBatch<'a> {
     }
 
     pub fn write_page(&mut self, sid: SegmentId, pageidx: PageIdx, page: Page) {
-        self.batch
-            .insert_typed(&self.ks.pages, PageKey::new(sid, pageidx), page);
+        let key = PageKey::new(sid, pageidx);
+        if self.ks.dedup_pages {
+            // identical pages share a single entry in page_contents
+            let hash = PageHash::of(&page);
+            self.batch.insert_typed(
+                &self.ks.page_content_refs,
+                PageContentRef::new(hash.clone(), key.clone()),
+                (),
+            );
+            self.batch
+                .insert_typed(&self.ks.page_refs, key, hash.clone());
+            self.batch.insert_typed(&self.ks.page_contents, hash, page);
+            self.writes_contents = true;
+        } else {
+            self.batch.insert_typed(&self.ks.pages, key, page);
+        }
     }
 
     pub fn commit(self) -> Result<(), FjallStorageErr> {
+        // content must not be removed while this batch starts referencing it
+        let _contents = self.writes_contents.then(|| self.contents_lock.lock());
         Ok(self.batch.commit()?)
     }
 }
 
+/// Keeps a Segment from being considered orphaned while it is alive
+pub(crate) struct SegmentPin<'a> {
+    storage: &'a FjallStorage,
+    sid: SegmentId,
+}
+
+impl Drop for SegmentPin<'_> {
+    fn drop(&mut self) {
+        self.storage.pinned_segments.lock().remove(&self.sid);
+    }
+}
+
+/// Removes cached pages from storage, along with any deduplicated content
+/// which is no longer referenced by a page once the removal commits.
+struct PageRemoval<'a> {
+    ks: &'a Keyspaces,
+    _contents: MutexGuard<'a, ()>,
+    /// taken after acquiring `contents_lock`, so it sees every reference to
+    /// deduplicated content
+    snapshot: fjall::Snapshot,
+    batch: OwnedWriteBatch,
+    /// the removed references to deduplicated content
+    refs: HashSet<PageContentRef>,
+    pages: usize,
+    /// the number of removed pages which weren't deduplicated
+    plain: usize,
+}
+
+/// The outcome of a committed `PageRemoval`
+struct RemovedPages {
+    /// pages removed, including deduplicated pages
+    pages: usize,
+    /// page contents removed, both plain pages and deduplicated content
+    /// which is no longer shared
+    contents: usize,
+}
+
+impl<'a> PageRemoval<'a> {
+    fn open(storage: &'a FjallStorage) -> Self {
+        let _contents = storage.contents_lock.lock();
+        Self {
+            ks: &storage.ks,
+            _contents,
+            snapshot: storage.db.snapshot(),
+            batch: storage.db.batch(),
+            refs: HashSet::new(),
+            pages: 0,
+            plain: 0,
+        }
+    }
+
+    fn remove_range(&mut self, keyrange: RangeInclusive<PageKey>) -> Result<(), FjallStorageErr> {
+        let mut keys = self.snapshot.range(&self.ks.pages, keyrange.clone()).keys();
+        while let Some(key) = keys.try_next()? {
+            self.batch.remove_typed(&self.ks.pages, key);
+            self.pages += 1;
+            self.plain += 1;
+        }
+        let mut iter = self.snapshot.range(&self.ks.page_refs, keyrange);
+        while let Some((key, hash)) = iter.try_next()? {
+            self.batch.remove_typed(&self.ks.page_refs, key.clone());
+            self.refs.insert(PageContentRef::new(hash, key));
+            self.pages += 1;
+        }
+        Ok(())
+    }
+
+    fn remove_segment(&mut self, sid: &SegmentId) -> Result<(), FjallStorageErr> {
+        self.remove_range(
+            PageKey::new(sid.clone(), PageIdx::FIRST)..=PageKey::new(sid.clone(), PageIdx::LAST),
+        )
+    }
+
+    /// Commits the removal, returning what was removed.
+    fn commit(mut self) -> Result<RemovedPages, FjallStorageErr> {
+        let mut contents = self.plain;
+        let hashes: HashSet<PageHash> = self.refs.iter().map(|r| r.hash().clone()).collect();
+        for r in &self.refs {
+            self.batch
+                .remove_typed(&self.ks.page_content_refs, r.clone());
+        }
+        for hash in hashes {
+            // content is shared by every page in its index entry
+            let mut shared = false;
+            let mut iter = self
+                .snapshot
+                .prefix(&self.ks.page_content_refs, &hash)
+                .keys();
+            while let Some(r) = iter.try_next()? {
+                if !self.refs.contains(&r) {
+                    shared = true;
+                    break;
+                }
+            }
+            if !shared {
+                self.batch.remove_typed(&self.ks.page_contents, hash);
+                contents += 1;
+            }
+        }
+        self.batch.commit()?;
+        Ok(RemovedPages { pages: self.pages, contents })
+    }
+}
+
 pub struct ReadWriteGuard<'a> {
     _permit: MutexGuard<'a, ()>,
     read: ReadGuard<'a>,
@@ -596,7 +1336,7 @@ pub struct ReadWriteGuard<'a> {
 impl<'a> ReadWriteGuard<'a> {
     fn open(storage: &'a FjallStorage) -> Self {
         // TODO: consider adding some kind of deadlock detection
-        let _permit = storage.lock.lock();
+        let _permit = storage.acquire_lock();
         // IMPORTANT: take the read snapshot after taking the lock
         let read = storage.read();
         Self { _permit, read }
@@ -616,23 +1356,45 @@ impl<'a> ReadWriteGuard<'a> {
         Ok(out)
     }
 
+    /// points each tag at the Volume the other tag points at, in a single
+    /// batch. fails with `LogicalErr::TagNotFound` if either tag is missing.
+    pub fn tag_swap(self, a: &str, b: &str) -> Result<(), FjallStorageErr> {
+        let vid_a = self
+            .read
+            .get_tag(a)?
+            .ok_or_else(|| LogicalErr::TagNotFound(a.into()))?;
+        let vid_b = self
+            .read
+            .get_tag(b)?
+            .ok_or_else(|| LogicalErr::TagNotFound(b.into()))?;
+        let mut batch = self.read.storage.batch();
+        batch.write_tag(a, vid_b);
+        batch.write_tag(b, vid_a);
+        batch.commit()
+    }
+
     /// opens a volume. if any id is missing, it will be randomly
-    /// generated. If the volume already exists, this function will fail if its
-    /// remote Log doesn't match.
+    /// generated. If the volume already exists and its remote Log doesn't
+    /// match, the `OpenPolicy` determines whether we fail or adopt the
+    /// requested remote.
     pub fn volume_open(
         self,
         vid: Option<VolumeId>,
         local: Option<LogId>,
         remote: Option<LogId>,
+        policy: OpenPolicy,
     ) -> Result<Volume, FjallStorageErr> {
         // generate the VolumeId if it's not specified
         let vid = vid.unwrap_or_else(VolumeId::random);
 
         // lookup the volume if specified
+        let mut local = local;
+        let mut settings = VolumeSettings::default();
         if let Some(volume) = self.read.snapshot.get(&self.ks().volumes, &vid)? {
-            if let Some(remote) = remote
-                && volume.remote != remote
-            {
+            let Some(remote) = remote.filter(|remote| &volume.remote != remote) else {
+                return Ok(volume);
+            };
+            if policy != OpenPolicy::AdoptRemote || self.has_unpushed_changes(&volume)? {
                 return Err(LogicalErr::VolumeRemoteMismatch {
                     vid: volume.vid,
                     expected: remote,
@@ -640,7 +1402,16 @@ impl<'a> ReadWriteGuard<'a> {
                 }
                 .into());
             }
-            return Ok(volume);
+            tracing::warn!(
+                vid = ?volume.vid,
+                old_remote = ?volume.remote,
+                new_remote = ?remote,
+                "adopting new remote log for volume"
+            );
+            // the existing local log has already been pushed to the old
+            // remote; start over with a fresh local log
+            local = local.filter(|local| local != &volume.local);
+            settings = volume.settings;
         }
 
         // determine the local and remote LogIds
@@ -658,7 +1429,7 @@ impl<'a> ReadWriteGuard<'a> {
             });
 
         // create the new volume
-        let volume = Volume::new(vid.clone(), local, remote, sync, None);
+        let volume = Volume::new(vid.clone(), local, remote, sync, None).with_settings(settings);
         self.ks().volumes.insert(vid, volume.clone())?;
 
         tracing::debug!(
@@ -671,6 +1442,13 @@ impl<'a> ReadWriteGuard<'a> {
         Ok(volume)
     }
 
+    /// Returns true if the volume has local commits which haven't been pushed
+    /// or a pending remote commit.
+    fn has_unpushed_changes(&self, volume: &Volume) -> Result<bool, FjallStorageErr> {
+        let latest_local = self.read.latest_lsn(&volume.local)?;
+        Ok(volume.pending_commit().is_some() || volume.local_changes(latest_local).is_some())
+    }
+
     /// Attempt to execute a local commit to the specified Volume's local Log.
     ///
     /// Returns the resulting `Snapshot` on success
@@ -681,6 +1459,81 @@ impl<'a> ReadWriteGuard<'a> {
         page_count: PageCount,
         pages: BTreeMap<PageIdx, Page>,
     ) -> Result<Snapshot, FjallStorageErr> {
+        self.commit_with_note(vid, snapshot, page_count, pages, None)
+    }
+
+    /// Commits to the Volume's local Log, attaching the optional note to the
+    /// commit.
+    pub fn commit_with_note(
+        self,
+        vid: &VolumeId,
+        snapshot: Snapshot,
+        page_count: PageCount,
+        pages: BTreeMap<PageIdx, Page>,
+        note: Option<ByteString>,
+    ) -> Result<Snapshot, FjallStorageErr> {
+        let mut batch = self.read.storage.batch();
+        self.stage_commit(&mut batch, vid, snapshot, page_count, pages, note)?;
+        batch.commit()?;
+
+        // open a new ReadGuard to read an updated snapshot
+        // since we are holding a read_write lock, we know that no other thread
+        // is concurrently committing to the volume, so we know this snapshot
+        // will reflect the commit we just executed
+        self.read.storage.read().snapshot(vid)
+    }
+
+    /// Atomically commits to several Volumes' local Logs in a single batch.
+    /// Either every Volume advances or none of them do.
+    ///
+    /// Fails with `LogicalErr::VolumeConcurrentWrite` if any commit was not
+    /// constructed using the latest snapshot of its Volume, or if a Volume
+    /// appears more than once.
+    ///
+    /// Returns the resulting `Snapshot` for each Volume, in order.
+    pub fn multi_commit(
+        self,
+        commits: Vec<(VolumeId, Snapshot, PageCount, BTreeMap<PageIdx, Page>)>,
+    ) -> Result<Vec<Snapshot>, FjallStorageErr> {
+        // two commits to the same Volume would both be based on the same
+        // snapshot, so the second is a concurrent write
+        let mut seen = HashSet::with_capacity(commits.len());
+        for (vid, ..) in &commits {
+            if !seen.insert(vid) {
+                return Err(LogicalErr::VolumeConcurrentWrite(vid.clone()).into());
+            }
+        }
+
+        let vids: Vec<VolumeId> = commits.iter().map(|(vid, ..)| vid.clone()).collect();
+        let mut batch = self.read.storage.batch();
+        for (vid, snapshot, page_count, pages) in commits {
+            self.stage_commit(&mut batch, &vid, snapshot, page_count, pages, None)?;
+        }
+        batch.commit()?;
+
+        let read = self.read.storage.read();
+        vids.iter().map(|vid| read.snapshot(vid)).collect()
+    }
+
+    /// Validates a local commit and writes it into the batch, without
+    /// committing the batch.
+    fn stage_commit(
+        &self,
+        batch: &mut WriteBatch<'_>,
+        vid: &VolumeId,
+        snapshot: Snapshot,
+        page_count: PageCount,
+        pages: BTreeMap<PageIdx, Page>,
+        note: Option<ByteString>,
+    ) -> Result<(), FjallStorageErr> {
+        if let Some(note) = &note
+            && note.len() > MAX_NOTE_LEN
+        {
+            return Err(
+                LogicalErr::CommitNoteTooLong { len: note.len(), max: MAX_NOTE_LEN }.into(),
+            );
+        }
+
         // Verify that the commit was constructed using the latest snapshot for
         // the volume.
         if !self.read.is_latest_snapshot(vid, &snapshot)? {
@@ -711,21 +1564,84 @@ impl<'a> ReadWriteGuard<'a> {
         // build the commit
         let commit = Commit::new(volume.local.clone(), commit_lsn, page_count)
             .with_checkpoints(maybe_checkpoint)
-            .with_segment_idx(Some(segment));
+            .with_segment_idx(Some(segment))
+            .with_note(note);
 
-        // write out the segment and commit to storage
-        let mut batch = self.read.storage.batch();
+        // write out the segment and commit
         for (pageidx, page) in pages {
             batch.write_page(sid.clone(), pageidx, page);
         }
         batch.write_commit(commit);
+        Ok(())
+    }
+
+    /// Removes every page of the Segments returned by `orphan_segments`,
+    /// reporting what was removed. The content of deduplicated pages is
+    /// removed once no remaining page shares it.
+    pub fn gc_orphan_segments(self) -> Result<GcReport, FjallStorageErr> {
+        // holding the lock prevents a commit from referencing one of the
+        // orphans before they are removed
+        let segments = self.read.orphan_segments()?;
+        let mut removal = self.read.storage.remove_pages();
+        for sid in &segments {
+            removal.remove_segment(sid)?;
+        }
+        let RemovedPages { pages, contents } = removal.commit()?;
+        let bytes = contents as u64 * PAGESIZE.as_u64();
+        tracing::debug!(
+            orphans = segments.len(),
+            pages,
+            bytes,
+            "removed orphaned segments"
+        );
+        Ok(GcReport {
+            segments,
+            pages,
+            bytes: ByteUnit::new(bytes),
+        })
+    }
+
+    /// Regenerates the page version index for the Volume's local and remote
+    /// Logs from the commits in storage, removing any stale entries.
+    ///
+    /// Returns the number of index entries written.
+    pub fn rebuild_page_versions(self, vid: &VolumeId) -> Result<usize, FjallStorageErr> {
+        let volume = self.read.volume(vid)?;
+        let ks = self.ks();
+
+        // removing and inserting the same key in a single batch is ambiguous,
+        // so only remove entries which are not part of the rebuilt index
+        let mut batch = self.read.storage.db.batch();
+        let mut rebuilt = 0;
+        for log in [&volume.local, &volume.remote] {
+            let mut expected = BTreeSet::new();
+            let mut commits = self.read.snapshot.prefix(&ks.log, log).values();
+            while let Some(commit) = commits.try_next()? {
+                if let Some(idx) = commit.segment_idx() {
+                    expected.extend(idx.pageset.iter().map(|pageidx| (pageidx, commit.lsn)));
+                }
+            }
+
+            let mut versions = self.read.snapshot.prefix(&ks.page_versions, log).keys();
+            while let Some(pv) = versions.try_next()? {
+                if !expected.contains(&(pv.pageidx, pv.lsn)) {
+                    batch.remove_typed(&ks.page_versions, pv);
+                }
+            }
+
+            rebuilt += expected.len();
+            for (pageidx, lsn) in expected {
+                batch.insert_typed(
+                    &ks.page_versions,
+                    PageVersion::new(log.clone(), pageidx, lsn),
+                    (),
+                );
+            }
+        }
         batch.commit()?;
 
-        // open a new ReadGuard to read an updated snapshot
-        // since we are holding a read_write lock, we know that no other thread
-        // is concurrently committing to the volume, so we know this snapshot
-        // will reflect the commit we just executed
-        self.read.storage.read().snapshot(&volume.vid)
+        tracing::debug!(?vid, rebuilt, "rebuilt page version index");
+        Ok(rebuilt)
     }
 
     /// Verify we are ready to make a remote commit and update the volume
@@ -811,6 +1727,20 @@ impl<'a> ReadWriteGuard<'a> {
         Ok(())
     }
 
+    /// Updates the Volume's settings using `f`, returning the updated Volume.
+    pub fn update_volume_settings(
+        self,
+        vid: &VolumeId,
+        f: impl FnOnce(&mut VolumeSettings),
+    ) -> Result<Volume, FjallStorageErr> {
+        let mut volume = self.read.volume(vid)?;
+        f(&mut volume.settings);
+        self.ks()
+            .volumes
+            .insert(volume.vid.clone(), volume.clone())?;
+        Ok(volume)
+    }
+
     /// Drop a pending commit without applying it. This should only be called
     /// after receiving a rejection from the remote.
     pub fn drop_pending_commit(&self, vid: &VolumeId) -> Result<(), FjallStorageErr> {
@@ -872,6 +1802,208 @@ impl<'a> ReadWriteGuard<'a> {
         }
     }
 
+    /// Moves the Volume's unpushed local changes on top of the latest commit
+    /// in its remote Log, which must already be fetched. The changes are
+    /// rewritten into a single commit on a fresh local Log.
+    ///
+    /// Fails with `LogicalErr::VolumeDiverged` if the new remote commits
+    /// changed any of the same pages, or if one side shrank the Volume while
+    /// the other changed its page count. Returns whether a rebased commit was
+    /// made, which isn't the case if the Volume hasn't diverged.
+    pub fn rebase_onto_remote(self, vid: &VolumeId) -> Result<bool, FjallStorageErr> {
+        let volume = self.read.volume(vid)?;
+        if volume.pending_commit().is_some() {
+            return Err(LogicalErr::VolumeNeedsRecovery(volume.vid).into());
+        }
+
+        let latest_local = self.read.latest_lsn(&volume.local)?;
+        let latest_remote = self.read.latest_lsn(&volume.remote)?;
+        let (Some(local_lsns), Some(remote_lsns)) = (
+            volume.local_changes(latest_local),
+            volume.remote_changes(latest_remote),
+        ) else {
+            // the volume hasn't diverged
+            return Ok(false);
+        };
+
+        let local_pages = self
+            .read
+            .page_count(&volume.local, *local_lsns.end())?
+            .expect("BUG: no page count for commit");
+        let remote_pages = self
+            .read
+            .page_count(&volume.remote, *remote_lsns.end())?
+            .expect("BUG: no page count for remote commit");
+        let base_pages = match volume.sync() {
+            Some(sync) => self
+                .read
+                .page_count(&volume.remote, sync.remote)?
+                .expect("BUG: no page count for remote commit"),
+            None => PageCount::ZERO,
+        };
+        let page_count = if local_pages == base_pages {
+            remote_pages
+        } else if remote_pages == base_pages {
+            local_pages
+        } else if local_pages > base_pages && remote_pages > base_pages {
+            // both sides grew the volume
+            local_pages.max(remote_pages)
+        } else {
+            tracing::debug!(
+                vid = ?volume.vid,
+                %base_pages,
+                %local_pages,
+                %remote_pages,
+                "can't rebase volume; page count shrank on one side and changed on the other"
+            );
+            return Err(LogicalErr::VolumeDiverged(volume.vid).into());
+        };
+
+        // collect the pages changed by the remote
+        let (_, remote_changed) = self.read.changes(&volume.remote, remote_lsns)?;
+
+        // collect the latest version of each page changed locally
+        let local_snapshot = Snapshot::new(volume.local.clone(), local_lsns, page_count);
+        let mut pages = BTreeMap::new();
+        let mut visible = self.read.iter_visible_pages(&local_snapshot);
+        while let Some((idx, pageset)) = visible.try_next()? {
+            for pageidx in pageset.iter() {
+                if remote_changed.contains(pageidx) {
+                    tracing::debug!(
+                        vid = ?volume.vid,
+                        %pageidx,
+                        "can't rebase volume; page changed locally and remotely"
+                    );
+                    return Err(LogicalErr::VolumeDiverged(volume.vid).into());
+                }
+                let page = self
+                    .read
+                    .read_page(idx.sid().clone(), pageidx)?
+                    .expect("BUG: local page missing from storage");
+                pages.insert(pageidx, page);
+            }
+        }
+
+        let local = LogId::random();
+        let remote_lsn = latest_remote.expect("BUG: remote changes without a remote lsn");
+        tracing::debug!(
+            vid = ?volume.vid,
+            old_local = ?volume.local,
+            new_local = ?local,
+            %remote_lsn,
+            pages = pages.len(),
+            "rebasing volume onto remote"
+        );
+
+        let maybe_checkpoint = if page_count.to_usize() == pages.len() {
+            thin_vec![LSN::FIRST]
+        } else {
+            thin_vec![]
+        };
+        let pageset = PageSet::from(Splinter::from_iter(pages.keys().map(|&k| k.to_u32())));
+        let sid = SegmentId::random();
+        let commit = Commit::new(local.clone(), LSN::FIRST, page_count)
+            .with_checkpoints(maybe_checkpoint)
+            .with_segment_idx(Some(SegmentIdx::new(sid.clone(), pageset)));
+
+        let mut batch = self.read.storage.batch();
+        for (pageidx, page) in pages {
+            batch.write_page(sid.clone(), pageidx, page);
+        }
+        batch.write_commit(commit);
+        batch.write_volume(Volume {
+            local,
+            sync: Some(SyncPoint {
+                remote: remote_lsn,
+                local_watermark: None,
+            }),
+            pending_commit: None,
+            ..volume
+        });
+        batch.commit()?;
+        Ok(true)
+    }
+
+    /// Removes the locally stored pages of every Segment visible to the
+    /// Volume's latest snapshot, so they are fetched again from the remote.
+    /// Returns the snapshot.
+    ///
+    /// Fails with `LogicalErr::VolumeHasLocalChanges` if the Volume has
+    /// commits which haven't been pushed, as their pages only exist locally.
+    /// The check and the removal both happen under the write lock, so a
+    /// local commit can't land in between.
+    pub fn remove_snapshot_pages(self, vid: &VolumeId) -> Result<Snapshot, FjallStorageErr> {
+        let volume = self.read.volume(vid)?;
+        if volume.pending_commit().is_some()
+            || volume
+                .local_changes(self.read.latest_lsn(&volume.local)?)
+                .is_some()
+        {
+            return Err(LogicalErr::VolumeHasLocalChanges(volume.vid).into());
+        }
+
+        let snapshot = self.read.snapshot(vid)?;
+        let mut removal = self.read.storage.remove_pages();
+        let mut visible = self.read.iter_visible_pages(&snapshot);
+        while let Some((idx, _)) = visible.try_next()? {
+            let pageset = idx.pageset();
+            if let (Some(first), Some(last)) = (pageset.first(), pageset.last()) {
+                removal.remove_range(
+                    PageKey::new(idx.sid().clone(), first)..=PageKey::new(idx.sid().clone(), last),
+                )?;
+            }
+        }
+        drop(visible);
+        removal.commit()?;
+        Ok(snapshot)
+    }
+
+    /// Discards the Volume's local commits which haven't been pushed and
+    /// attaches it to the latest commit in its remote Log. Returns the range
+    /// of discarded local LSNs, if any.
+    ///
+    /// The Volume switches to a new, empty local Log, leaving the discarded
+    /// commits unreferenced so their pages can be removed by
+    /// `gc_orphan_segments`.
+    pub fn reset_volume_to_remote(
+        self,
+        vid: &VolumeId,
+    ) -> Result<Option<RangeInclusive<LSN>>, FjallStorageErr> {
+        let volume = self.read.volume(vid)?;
+        if volume.pending_commit().is_some() {
+            return Err(LogicalErr::VolumeNeedsRecovery(volume.vid).into());
+        }
+        let Some(remote_lsn) = self.read.latest_lsn(&volume.remote)? else {
+            return Err(LogicalErr::VolumeNeverSynced(volume.vid).into());
+        };
+
+        let latest_local = self.read.latest_lsn(&volume.local)?;
+        let discarded = volume.local_changes(latest_local);
+        let local = LogId::random();
+        tracing::debug!(
+            vid = ?volume.vid,
+            old_local = ?volume.local,
+            new_local = ?local,
+            discarded = ?discarded,
+            %remote_lsn,
+            "resetting volume to remote"
+        );
+
+        self.ks().volumes.insert(
+            volume.vid.clone(),
+            Volume {
+                local,
+                sync: Some(SyncPoint {
+                    remote: remote_lsn,
+                    local_watermark: None,
+                }),
+                pending_commit: None,
+                ..volume
+            },
+        )?;
+        Ok(discarded)
+    }
+
     pub fn sync_remote_to_local(self, vid: VolumeId) -> Result<(), FjallStorageErr> {
         let volume = self.read.volume(&vid)?;
 
@@ -927,3 +2059,712 @@ impl<'a> ReadWriteGuard<'a> {
             .insert(volume.vid.clone(), volume.with_sync(Some(new_sync)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        ops::RangeInclusive,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
+
+    use test_log::test;
+
+    use zerocopy::U32;
+
+    use crate::{
+        LogicalErr,
+        core::{
+            PageCount, PageIdx, SegmentId, VolumeId,
+            byte_unit::ByteUnit,
+            commit_hash::CommitHash,
+            page::{PAGESIZE, Page},
+        },
+        local::fjall_storage::{
+            FORMAT_VERSION, FORMAT_VERSION_KEY, FjallStorage, FjallStorageErr, StorageOptions,
+            count_keys,
+            fjall_typed::ReadableExt,
+            keys::{PageContentRef, PageHash, PageKey, PageVersion},
+        },
+        lsn, pageidx,
+        volume::{OpenPolicy, PendingCommit},
+    };
+
+    #[test]
+    fn tag_swap() {
+        let storage = Arc::new(FjallStorage::open_temporary().unwrap());
+        let open = || {
+            storage
+                .read_write()
+                .volume_open(None, None, None, OpenPolicy::Strict)
+                .unwrap()
+                .vid
+        };
+        let (blue, green) = (open(), open());
+        storage
+            .read_write()
+            .tag_replace("live", blue.clone())
+            .unwrap();
+        storage
+            .read_write()
+            .tag_replace("staging", green.clone())
+            .unwrap();
+
+        // readers never observe both tags pointing at the same volume
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = std::thread::spawn({
+            let storage = storage.clone();
+            let done = done.clone();
+            move || {
+                while !done.load(Ordering::Relaxed) {
+                    let read = storage.read();
+                    let live = read.get_tag("live").unwrap().unwrap();
+                    let staging = read.get_tag("staging").unwrap().unwrap();
+                    assert_ne!(live, staging);
+                }
+            }
+        });
+        for _ in 0..99 {
+            storage.read_write().tag_swap("live", "staging").unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        reader.join().unwrap();
+
+        // an odd number of swaps leaves each tag at the other's volume
+        let read = storage.read();
+        assert_eq!(read.get_tag("live").unwrap(), Some(green));
+        assert_eq!(read.get_tag("staging").unwrap(), Some(blue.clone()));
+
+        // swapping with a missing tag fails without changing anything
+        assert!(matches!(
+            storage.read_write().tag_swap("live", "missing"),
+            Err(FjallStorageErr::LogicalErr(LogicalErr::TagNotFound(tag))) if tag == "missing"
+        ));
+        assert_eq!(storage.read().get_tag("staging").unwrap(), Some(blue));
+    }
+
+    #[test]
+    fn lock_contention() {
+        let storage = Arc::new(FjallStorage::open_temporary().unwrap());
+
+        // serial transactions never wait on the lock
+        for _ in 0..4 {
+            storage
+                .read_write()
+                .volume_open(None, None, None, OpenPolicy::Strict)
+                .unwrap();
+        }
+        let contention = storage.lock_contention();
+        assert_eq!(contention.acquisitions, 4);
+        assert_eq!(contention.contended, 0);
+        assert_eq!(contention.wait, Duration::ZERO);
+
+        // a transaction started while another holds the lock must wait
+        let guard = storage.read_write();
+        let handle = std::thread::spawn({
+            let storage = storage.clone();
+            move || {
+                storage
+                    .read_write()
+                    .volume_open(None, None, None, OpenPolicy::Strict)
+                    .unwrap();
+            }
+        });
+        // the waiter counts its acquisition before trying the lock, so once
+        // it's counted the waiter is guaranteed to find the lock held
+        while storage.lock_contention().acquisitions < 6 {
+            std::thread::yield_now();
+        }
+        drop(guard);
+        handle.join().unwrap();
+
+        let contention = storage.lock_contention();
+        assert_eq!(contention.acquisitions, 6);
+        assert_eq!(contention.contended, 1);
+        assert!(contention.wait > Duration::ZERO);
+
+        storage.reset_lock_contention();
+        assert_eq!(storage.lock_contention().acquisitions, 0);
+    }
+
+    #[test]
+    fn incompatible_format_version() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // new storage is marked with the current format version, and can be
+        // reopened
+        let storage = FjallStorage::open(dir.path()).unwrap();
+        let vid = storage
+            .read_write()
+            .volume_open(None, None, None, OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+        drop(storage);
+        let storage = FjallStorage::open(dir.path()).unwrap();
+        assert!(storage.read().volume_exists(&vid).unwrap());
+
+        // simulate storage written by a newer version of Graft
+        storage
+            .ks
+            .meta
+            .insert(FORMAT_VERSION_KEY.into(), U32::new(FORMAT_VERSION + 1))
+            .unwrap();
+        storage.persist().unwrap();
+        drop(storage);
+
+        let err = FjallStorage::open(dir.path()).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                FjallStorageErr::IncompatibleFormat { found, supported }
+                    if found == FORMAT_VERSION + 1 && supported == FORMAT_VERSION
+            ),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn open_read_only() {
+        // storage which was never initialized isn't initialized by a
+        // read-only open
+        let dir = tempfile::tempdir().unwrap();
+        for _ in 0..2 {
+            let err = FjallStorage::open_read_only(dir.path()).unwrap_err();
+            assert!(
+                matches!(err, FjallStorageErr::MissingFormat),
+                "unexpected error: {err}"
+            );
+        }
+
+        // initialized storage opens read-only
+        let storage = FjallStorage::open(dir.path()).unwrap();
+        let vid = storage
+            .read_write()
+            .volume_open(None, None, None, OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+        storage.persist().unwrap();
+        drop(storage);
+        let storage = FjallStorage::open_read_only(dir.path()).unwrap();
+        assert!(storage.read().volume_exists(&vid).unwrap());
+
+        // storage held open by another process is reported as locked
+        let err = FjallStorage::open_read_only(dir.path()).unwrap_err();
+        assert!(err.is_locked(), "unexpected error: {err}");
+    }
+
+    #[test]
+    fn storage_stats() {
+        let storage = FjallStorage::open_temporary().unwrap();
+        let stats = storage.stats().unwrap();
+        assert_eq!(
+            (stats.tags, stats.volumes, stats.commits, stats.pages),
+            (0, 0, 0, 0)
+        );
+
+        let vid = storage
+            .read_write()
+            .volume_open(None, None, None, OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+        storage
+            .read_write()
+            .tag_replace("main", vid.clone())
+            .unwrap();
+
+        // write three commits, each containing two pages
+        let mut snapshot = storage.read().snapshot(&vid).unwrap();
+        for i in 1..=3u32 {
+            let pages = BTreeMap::from([
+                (PageIdx::must_new(i), Page::test_filled(i as u8)),
+                (PageIdx::must_new(i + 10), Page::test_filled(i as u8)),
+            ]);
+            snapshot = storage
+                .read_write()
+                .commit(&vid, snapshot, PageCount::new(i + 10), pages)
+                .unwrap();
+        }
+
+        let stats = storage.stats().unwrap();
+        assert_eq!(stats.tags, 1);
+        assert_eq!(stats.volumes, 1);
+        assert_eq!(stats.commits, 3);
+        assert_eq!(stats.pages, 6);
+        assert_eq!(stats.keyspaces.len(), 8);
+
+        // pages only count towards disk usage once flushed out of memory
+        storage.ks.pages.testonly_flush().unwrap();
+        let stats = storage.stats().unwrap();
+        let disk_space = |name| {
+            stats
+                .keyspaces
+                .iter()
+                .find_map(|&(ks, size)| (ks == name).then_some(size))
+                .unwrap()
+        };
+        assert!(disk_space("pages") > ByteUnit::ZERO);
+        assert_eq!(
+            stats.disk_space,
+            stats
+                .keyspaces
+                .iter()
+                .fold(ByteUnit::ZERO, |total, (_, size)| total + *size)
+        );
+        assert!(stats.disk_space >= disk_space("pages"));
+    }
+
+    #[test]
+    fn log_kv_separation_roundtrip() {
+        for log_kv_separation in [false, true] {
+            let dir = tempfile::tempdir().unwrap();
+            let opts = StorageOptions { log_kv_separation, ..Default::default() };
+            let storage = FjallStorage::open_with_options(dir.path(), opts.clone()).unwrap();
+            let vid = storage
+                .read_write()
+                .volume_open(None, None, None, OpenPolicy::Strict)
+                .unwrap()
+                .vid;
+
+            // write many small commits
+            let mut snapshot = storage.read().snapshot(&vid).unwrap();
+            for i in 1..=20u32 {
+                let pages = BTreeMap::from([(PageIdx::must_new(i), Page::test_filled(i as u8))]);
+                snapshot = storage
+                    .read_write()
+                    .commit(&vid, snapshot, PageCount::new(i), pages)
+                    .unwrap();
+            }
+            storage.persist().unwrap();
+            drop(storage);
+
+            // every commit and page survives reopening storage
+            let storage = FjallStorage::open_with_options(dir.path(), opts).unwrap();
+            let reader = storage.read();
+            let snapshot = reader.snapshot(&vid).unwrap();
+            assert_eq!(snapshot.page_count, 20);
+            let commits = reader
+                .commits(&snapshot)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(commits.len(), 20);
+            for pageidx in PageCount::new(20).iter() {
+                let commit = reader.search_page(&snapshot, pageidx).unwrap().unwrap();
+                assert_eq!(commit.lsn, pageidx.to_u32() as u64);
+                let page = reader
+                    .read_page(commit.segment_idx().unwrap().sid().clone(), pageidx)
+                    .unwrap();
+                assert_eq!(page, Some(Page::test_filled(pageidx.to_u32() as u8)));
+            }
+        }
+    }
+
+    #[test]
+    fn rebuild_page_versions() {
+        let storage = FjallStorage::open_temporary().unwrap();
+        let volume = storage
+            .read_write()
+            .volume_open(None, None, None, OpenPolicy::Strict)
+            .unwrap();
+        let vid = volume.vid;
+
+        let mut snapshot = storage.read().snapshot(&vid).unwrap();
+        for pages in [&[1, 2][..], &[2], &[3]] {
+            let pages = pages
+                .iter()
+                .map(|&i| (PageIdx::must_new(i), Page::test_filled(i as u8)))
+                .collect::<BTreeMap<_, _>>();
+            snapshot = storage
+                .read_write()
+                .commit(&vid, snapshot, PageCount::new(3), pages)
+                .unwrap();
+        }
+
+        let page_lsns = |storage: &FjallStorage| {
+            let reader = storage.read();
+            PageCount::new(3)
+                .iter()
+                .map(|pageidx| {
+                    reader
+                        .search_page(&snapshot, pageidx)
+                        .unwrap()
+                        .map(|commit| commit.lsn)
+                })
+                .collect::<Vec<_>>()
+        };
+        let expected = vec![Some(lsn!(1)), Some(lsn!(2)), Some(lsn!(3))];
+        assert_eq!(page_lsns(&storage), expected);
+
+        // clear the index, and add an entry referencing the wrong commit
+        let versions: Vec<_> = storage
+            .read()
+            .snapshot
+            .prefix(&storage.ks.page_versions, &volume.local)
+            .keys()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(versions.len(), 4);
+        for pv in versions {
+            storage.ks.page_versions.remove(pv).unwrap();
+        }
+        storage
+            .ks
+            .page_versions
+            .insert(
+                PageVersion::new(volume.local.clone(), pageidx!(1), lsn!(3)),
+                (),
+            )
+            .unwrap();
+        assert_eq!(page_lsns(&storage), vec![Some(lsn!(3)), None, None]);
+
+        let rebuilt = storage.read_write().rebuild_page_versions(&vid).unwrap();
+        assert_eq!(rebuilt, 4);
+        assert_eq!(page_lsns(&storage), expected);
+    }
+
+    #[test]
+    fn iter_visible_pages_in() {
+        let storage = FjallStorage::open_temporary().unwrap();
+        let vid = storage
+            .read_write()
+            .volume_open(None, None, None, OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+
+        let commit = |pages: RangeInclusive<u32>| {
+            let snapshot = storage.read().snapshot(&vid).unwrap();
+            let pages: BTreeMap<_, _> = pages
+                .map(|i| (PageIdx::must_new(i), Page::test_filled(i as u8)))
+                .collect();
+            storage
+                .read_write()
+                .commit(&vid, snapshot, PageCount::new(10), pages)
+                .unwrap()
+        };
+        commit(1..=10);
+        let snapshot = commit(8..=8);
+
+        let visible = |range: RangeInclusive<PageIdx>| {
+            storage
+                .read()
+                .iter_visible_pages_in(&snapshot, range)
+                .map(|entry| entry.map(|(_, pages)| pages.iter().collect::<Vec<_>>()))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        // only pages within the range are returned
+        assert_eq!(
+            visible(pageidx!(5)..=pageidx!(6)),
+            [[pageidx!(5), pageidx!(6)]]
+        );
+        // the scan stops at the newest commit once it covers the range
+        assert_eq!(visible(pageidx!(8)..=pageidx!(8)), [[pageidx!(8)]]);
+        // pages past the page count are never visible
+        assert!(visible(pageidx!(11)..=pageidx!(20)).is_empty());
+    }
+
+    #[test]
+    fn dedup_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = StorageOptions { dedup_pages: true, ..Default::default() };
+        let storage = FjallStorage::open_with_options(dir.path(), opts).unwrap();
+        let vid = storage
+            .read_write()
+            .volume_open(None, None, None, OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+
+        // a sparse volume: mostly empty pages with a few distinct pages
+        let expected = |pageidx: PageIdx| match pageidx.to_u32() {
+            i @ (7 | 42) => Page::test_filled(i as u8),
+            _ => Page::EMPTY,
+        };
+        let pages: BTreeMap<_, _> = PageCount::new(100)
+            .iter()
+            .map(|pageidx| (pageidx, expected(pageidx)))
+            .collect();
+        let snapshot = storage.read().snapshot(&vid).unwrap();
+        let snapshot = storage
+            .read_write()
+            .commit(&vid, snapshot, PageCount::new(100), pages)
+            .unwrap();
+
+        // only one copy of each distinct page is stored
+        let reader = storage.read();
+        assert_eq!(
+            count_keys(reader.snapshot.iter(&storage.ks.page_contents)).unwrap(),
+            3
+        );
+        assert_eq!(
+            count_keys(reader.snapshot.iter(&storage.ks.pages)).unwrap(),
+            0
+        );
+        assert_eq!(storage.stats().unwrap().pages, 100);
+
+        // every page reads back its own content
+        let commit = reader.search_page(&snapshot, pageidx!(1)).unwrap().unwrap();
+        let sid = commit.segment_idx().unwrap().sid().clone();
+        for pageidx in PageCount::new(100).iter() {
+            assert!(reader.has_page(sid.clone(), pageidx).unwrap());
+            assert_eq!(
+                reader.read_page(sid.clone(), pageidx).unwrap(),
+                Some(expected(pageidx))
+            );
+        }
+
+        // removing a page leaves content shared with other pages readable
+        storage.remove_page(sid.clone(), pageidx!(1)).unwrap();
+        let reader = storage.read();
+        assert!(!reader.has_page(sid.clone(), pageidx!(1)).unwrap());
+        assert_eq!(
+            reader.read_page(sid.clone(), pageidx!(2)).unwrap(),
+            Some(Page::EMPTY)
+        );
+
+        // content is removed along with the last page referencing it
+        storage.remove_page(sid.clone(), pageidx!(7)).unwrap();
+        storage
+            .remove_page_range(&sid, pageidx!(2)..=pageidx!(100))
+            .unwrap();
+        let reader = storage.read();
+        assert_eq!(
+            count_keys(reader.snapshot.iter(&storage.ks.page_contents)).unwrap(),
+            0
+        );
+        assert_eq!(
+            count_keys(reader.snapshot.iter(&storage.ks.page_content_refs)).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn gc_dedup_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = StorageOptions { dedup_pages: true, ..Default::default() };
+        let storage = FjallStorage::open_with_options(dir.path(), opts).unwrap();
+        let vid = storage
+            .read_write()
+            .volume_open(None, None, None, OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+
+        // a committed empty page shares its content with the orphan below
+        let snapshot = storage.read().snapshot(&vid).unwrap();
+        storage
+            .read_write()
+            .commit(
+                &vid,
+                snapshot,
+                PageCount::new(1),
+                BTreeMap::from([(pageidx!(1), Page::EMPTY)]),
+            )
+            .unwrap();
+
+        // an orphaned segment with one shared and one distinct page
+        let stray = SegmentId::random();
+        storage
+            .write_page(stray.clone(), pageidx!(1), Page::EMPTY)
+            .unwrap();
+        storage
+            .write_page(stray.clone(), pageidx!(2), Page::test_filled(2))
+            .unwrap();
+
+        let report = storage.read_write().gc_orphan_segments().unwrap();
+        assert_eq!(report.segments, [stray]);
+        assert_eq!(report.pages, 2);
+        // only the distinct content is reclaimed
+        assert_eq!(report.bytes.as_u64(), PAGESIZE.as_u64());
+
+        let reader = storage.read();
+        assert_eq!(
+            count_keys(reader.snapshot.iter(&storage.ks.page_contents)).unwrap(),
+            1
+        );
+        assert_eq!(
+            count_keys(reader.snapshot.iter(&storage.ks.page_content_refs)).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn orphan_segments_skip_pushes() {
+        let storage = FjallStorage::open_temporary().unwrap();
+        let vid = storage
+            .read_write()
+            .volume_open(None, None, None, OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+
+        // a push caches its segment before any commit references it
+        let pushing = SegmentId::random();
+        let pin = storage.pin_segment(pushing.clone());
+        storage
+            .write_page(pushing.clone(), pageidx!(1), Page::test_filled(1))
+            .unwrap();
+        assert_eq!(storage.read().orphan_segments().unwrap(), []);
+
+        // the pending commit keeps the segment once the push is interrupted
+        storage
+            .read_write()
+            .remote_commit_prepare(
+                &vid,
+                PendingCommit {
+                    local: lsn!(1),
+                    commit: lsn!(1),
+                    commit_hash: CommitHash::testonly_random(),
+                    sid: Some(pushing.clone()),
+                },
+            )
+            .unwrap();
+        drop(pin);
+
+        // a push which failed before preparing its commit leaves an orphan
+        let abandoned = SegmentId::random();
+        let pin = storage.pin_segment(abandoned.clone());
+        storage
+            .write_page(abandoned.clone(), pageidx!(1), Page::test_filled(2))
+            .unwrap();
+        drop(pin);
+
+        let report = storage.read_write().gc_orphan_segments().unwrap();
+        assert_eq!(report.segments, [abandoned]);
+        assert!(storage.read().has_page(pushing, pageidx!(1)).unwrap());
+    }
+
+    #[test]
+    fn migrate_format_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = StorageOptions { dedup_pages: true, ..Default::default() };
+        let storage = FjallStorage::open_with_options(dir.path(), opts.clone()).unwrap();
+        let sid = SegmentId::random();
+        storage
+            .write_page(sid.clone(), pageidx!(1), Page::test_filled(1))
+            .unwrap();
+
+        // simulate storage written by version 1, which has no index of the
+        // pages referencing deduplicated content
+        let key = PageKey::new(sid.clone(), pageidx!(1));
+        let hash = PageHash::of(&Page::test_filled(1));
+        storage
+            .ks
+            .page_content_refs
+            .remove(PageContentRef::new(hash, key))
+            .unwrap();
+        storage
+            .ks
+            .meta
+            .insert(FORMAT_VERSION_KEY.into(), U32::new(1))
+            .unwrap();
+        storage.persist().unwrap();
+        drop(storage);
+
+        // opening read-only reports the old version without migrating it
+        for _ in 0..2 {
+            let err = FjallStorage::open_read_only(dir.path()).unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    FjallStorageErr::OutdatedFormat { found: 1, supported }
+                        if supported == FORMAT_VERSION
+                ),
+                "unexpected error: {err}"
+            );
+        }
+
+        // reopening migrates storage to the current version
+        let storage = FjallStorage::open_with_options(dir.path(), opts).unwrap();
+        let reader = storage.read();
+        assert_eq!(
+            reader
+                .snapshot
+                .get(&storage.ks.meta, FORMAT_VERSION_KEY)
+                .unwrap()
+                .map(|v| v.get()),
+            Some(FORMAT_VERSION)
+        );
+        assert_eq!(
+            count_keys(reader.snapshot.iter(&storage.ks.page_content_refs)).unwrap(),
+            1
+        );
+
+        // so the page's content can be removed along with it
+        storage.remove_page(sid, pageidx!(1)).unwrap();
+        assert_eq!(
+            count_keys(storage.read().snapshot.iter(&storage.ks.page_contents)).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn remove_page_range() {
+        for dedup_pages in [false, true] {
+            let dir = tempfile::tempdir().unwrap();
+            let opts = StorageOptions { dedup_pages, ..Default::default() };
+            let storage = FjallStorage::open_with_options(dir.path(), opts).unwrap();
+            let sid = SegmentId::random();
+            for pageidx in PageCount::new(10).iter() {
+                storage
+                    .write_page(
+                        sid.clone(),
+                        pageidx,
+                        Page::test_filled(pageidx.to_u32() as u8),
+                    )
+                    .unwrap();
+            }
+
+            storage
+                .remove_page_range(&sid, pageidx!(3)..=pageidx!(7))
+                .unwrap();
+
+            // only the pages within the range are removed, and the rest
+            // still read back their own content
+            let reader = storage.read();
+            for pageidx in PageCount::new(10).iter() {
+                let expected = (!(3..=7).contains(&pageidx.to_u32()))
+                    .then(|| Page::test_filled(pageidx.to_u32() as u8));
+                assert_eq!(
+                    reader.read_page(sid.clone(), pageidx).unwrap(),
+                    expected,
+                    "dedup_pages={dedup_pages} pageidx={pageidx}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn inspect_volume() {
+        let storage = FjallStorage::open_temporary().unwrap();
+        let vid = storage
+            .read_write()
+            .volume_open(None, None, None, OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+
+        let inspection = storage.inspect_volume(&vid).unwrap();
+        assert_eq!(inspection.latest_local, None);
+        assert_eq!(inspection.latest_remote, None);
+        assert_eq!(inspection.page_count, PageCount::ZERO);
+
+        let snapshot = storage.read().snapshot(&vid).unwrap();
+        let pages = BTreeMap::from([(pageidx!(1), Page::test_filled(1))]);
+        storage
+            .read_write()
+            .commit(&vid, snapshot, PageCount::new(4), pages)
+            .unwrap();
+        let inspection = storage.inspect_volume(&vid).unwrap();
+        assert_eq!(inspection.volume.vid, vid);
+        assert_eq!(inspection.latest_local, Some(lsn!(1)));
+        assert_eq!(inspection.latest_remote, None);
+        assert!(inspection.volume.sync().is_none());
+        assert_eq!(inspection.page_count, PageCount::new(4));
+
+        assert!(matches!(
+            storage.inspect_volume(&VolumeId::random()),
+            Err(FjallStorageErr::LogicalErr(LogicalErr::VolumeNotFound(_)))
+        ));
+    }
+}
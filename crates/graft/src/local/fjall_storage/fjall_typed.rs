@@ -40,6 +40,17 @@ where
         &self.keyspace
     }
 
+    /// Returns the disk space used by this Keyspace's on-disk segments
+    pub fn disk_space(&self) -> u64 {
+        self.keyspace.disk_space()
+    }
+
+    /// Flushes the active memtable to an on-disk segment
+    #[cfg(test)]
+    pub fn testonly_flush(&self) -> Result<()> {
+        Ok(self.keyspace.rotate_memtable_and_wait()?)
+    }
+
     /// Insert a key and value into this Keyspace
     pub fn insert(&self, key: K, value: V) -> Result<()> {
         Ok(self.keyspace.insert(key.into_slice(), value.into_slice())?)
@@ -49,6 +60,12 @@ where
     pub fn remove(&self, key: K) -> Result<()> {
         Ok(self.keyspace.remove(key.into_slice())?)
     }
+
+    /// Insert raw bytes under a key, bypassing value encoding
+    #[cfg(any(test, feature = "testutil"))]
+    pub fn testonly_insert_raw(&self, key: K, value: &[u8]) -> Result<()> {
+        Ok(self.keyspace.insert(key.into_slice(), value)?)
+    }
 }
 
 pub trait WriteBatchExt<K, V>
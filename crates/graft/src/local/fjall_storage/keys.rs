@@ -1,11 +1,14 @@
 use crate::core::{
-    LogId, PageIdx, SegmentId, cbe::CBE64, logref::LogRef, lsn::LSN, zerocopy_ext::TryFromBytesExt,
+    LogId, PageIdx, SegmentId, cbe::CBE64, logref::LogRef, lsn::LSN, page::Page,
+    zerocopy_ext::TryFromBytesExt,
 };
 use fjall::Slice;
-use zerocopy::{BigEndian, Immutable, IntoBytes, KnownLayout, TryFromBytes, U32, Unaligned};
+use zerocopy::{
+    BigEndian, FromBytes, Immutable, IntoBytes, KnownLayout, TryFromBytes, U32, Unaligned,
+};
 
 use crate::{
-    local::fjall_storage::fjall_repr::{FjallRepr, FjallReprRef},
+    local::fjall_storage::fjall_repr::{DecodeErr, FjallRepr, FjallReprRef},
     proxy_to_fjall_repr,
 };
 
@@ -59,6 +62,11 @@ impl PageKey {
     pub fn new(sid: SegmentId, pageidx: PageIdx) -> Self {
         Self { sid, pageidx }
     }
+
+    #[inline]
+    pub fn sid(&self) -> &SegmentId {
+        &self.sid
+    }
 }
 
 #[derive(IntoBytes, TryFromBytes, KnownLayout, Immutable, Unaligned)]
@@ -95,6 +103,91 @@ proxy_to_fjall_repr!(
     }
 );
 
+/// The content hash of a Page. Key for the `page_contents` keyspace.
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, IntoBytes, FromBytes, KnownLayout, Immutable, Unaligned,
+)]
+#[repr(transparent)]
+pub struct PageHash([u8; 32]);
+
+impl PageHash {
+    pub fn of(page: &Page) -> Self {
+        Self(*blake3::hash(page.as_ref()).as_bytes())
+    }
+}
+
+impl FjallReprRef for PageHash {
+    #[inline]
+    fn as_slice(&self) -> impl AsRef<[u8]> {
+        self.as_bytes()
+    }
+}
+
+impl FjallRepr for PageHash {
+    fn try_from_slice(slice: Slice) -> Result<Self, DecodeErr> {
+        Ok(Self::try_ref_from_unaligned_bytes(&slice)?.clone())
+    }
+}
+
+/// Key for the `page_content_refs` keyspace, which indexes the pages sharing
+/// each deduplicated page content
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PageContentRef {
+    hash: PageHash,
+    key: PageKey,
+}
+
+impl PageContentRef {
+    #[inline]
+    pub fn new(hash: PageHash, key: PageKey) -> Self {
+        Self { hash, key }
+    }
+
+    #[inline]
+    pub fn hash(&self) -> &PageHash {
+        &self.hash
+    }
+}
+
+impl FjallKeyPrefix for PageContentRef {
+    type Prefix = PageHash;
+}
+
+#[derive(IntoBytes, TryFromBytes, KnownLayout, Immutable, Unaligned)]
+#[repr(C)]
+struct SerializedPageContentRef {
+    hash: PageHash,
+    sid: SegmentId,
+    pageidx: U32<BigEndian>,
+}
+
+impl AsRef<[u8]> for SerializedPageContentRef {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+proxy_to_fjall_repr!(
+    encode (PageContentRef) using proxy (SerializedPageContentRef)
+    into_proxy(me) {
+        SerializedPageContentRef {
+            hash: me.hash,
+            sid: me.key.sid,
+            pageidx: me.key.pageidx.into(),
+        }
+    }
+    from_proxy(proxy) {
+        Ok(PageContentRef {
+            hash: proxy.hash.clone(),
+            key: PageKey {
+                sid: proxy.sid.clone(),
+                pageidx: PageIdx::try_from(proxy.pageidx)?,
+            },
+        })
+    }
+);
+
 /// A reference to a specific version of a page in a log
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PageVersion {
@@ -103,6 +196,10 @@ pub struct PageVersion {
     pub lsn: LSN,
 }
 
+impl FjallKeyPrefix for PageVersion {
+    type Prefix = LogId;
+}
+
 impl PageVersion {
     #[inline]
     pub fn new(log: LogId, pageidx: PageIdx, lsn: LSN) -> Self {
@@ -199,6 +296,36 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_page_hash() {
+        test_roundtrip(PageHash::of(&Page::test_filled(1)));
+        test_roundtrip(PageHash::of(&Page::EMPTY));
+        test_invalid::<PageHash>(b"short");
+        test_invalid::<PageHash>(b"");
+    }
+
+    #[test]
+    fn test_page_content_ref() {
+        let hash = PageHash::of(&Page::test_filled(1));
+        test_roundtrip(PageContentRef::new(
+            hash.clone(),
+            PageKey::new(SegmentId::random(), pageidx!(42)),
+        ));
+
+        // zero page index is invalid
+        test_invalid::<PageContentRef>(
+            SerializedPageContentRef {
+                hash: hash.clone(),
+                sid: SegmentId::random(),
+                pageidx: 0.into(),
+            }
+            .as_bytes(),
+        );
+
+        test_invalid::<PageContentRef>(b"short");
+        test_invalid::<PageContentRef>(b"");
+    }
+
     #[test]
     fn test_page_key() {
         test_roundtrip(PageKey::new(SegmentId::random(), pageidx!(42)));
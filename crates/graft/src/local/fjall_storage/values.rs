@@ -1,8 +1,10 @@
 use crate::core::{commit::Commit, page::Page};
 use bilrost::{Message, OwnedMessage};
 use bytes::Bytes;
+use zerocopy::{BE, IntoBytes, U32};
 
 use crate::{
+    core::zerocopy_ext::TryFromBytesExt,
     local::fjall_storage::fjall_repr::{FjallRepr, FjallReprRef},
     volume::Volume,
 };
@@ -86,6 +88,7 @@ mod tests {
     use crate::core::VolumeId;
     use crate::core::{LogId, PageCount, page::PAGESIZE};
     use crate::lsn;
+    use crate::volume::{ConflictPolicy, VolumeSettings};
 
     use crate::local::fjall_storage::fjall_repr::testutil::{
         test_empty_default, test_invalid, test_roundtrip,
@@ -107,6 +110,11 @@ mod tests {
             None,
             None,
         ));
+        test_roundtrip(Volume::new_random().with_settings(VolumeSettings {
+            fill_byte: Some(0xAB),
+            conflict_policy: ConflictPolicy::LastWriterWins,
+            max_pages: Some(PageCount::new(16)),
+        }));
         test_empty_default::<Volume>();
         test_invalid::<Volume>(&b"abc".repeat(123));
     }
@@ -118,3 +126,16 @@ mod tests {
         test_invalid::<Commit>(&b"abc".repeat(123));
     }
 }
+
+impl FjallReprRef for U32<BE> {
+    #[inline]
+    fn as_slice(&self) -> impl AsRef<[u8]> {
+        self.as_bytes()
+    }
+}
+
+impl FjallRepr for U32<BE> {
+    fn try_from_slice(slice: fjall::Slice) -> Result<Self, DecodeErr> {
+        Ok(*U32::<BE>::try_ref_from_unaligned_bytes(&slice)?)
+    }
+}
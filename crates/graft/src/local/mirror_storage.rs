@@ -0,0 +1,250 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use bytestring::ByteString;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{PageCount, PageIdx, VolumeId, page::Page},
+    local::fjall_storage::{FjallStorage, FjallStorageErr},
+    snapshot::Snapshot,
+};
+
+/// Controls how `MirrorStorage` treats the secondary store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorMode {
+    /// A commit only succeeds once it has been durably written to both the
+    /// primary and the secondary.
+    #[default]
+    Durable,
+
+    /// The secondary is written without waiting for it to become durable.
+    /// Failures to write to the secondary are logged and otherwise ignored.
+    BestEffort,
+}
+
+/// `MirrorStorage` wraps a primary and a secondary `FjallStorage`, writing
+/// every local commit to both. The secondary is typically located on a
+/// different disk to provide local redundancy.
+///
+/// All reads are served by the primary. Only local commits are mirrored.
+///
+/// The primary is written first, so a commit which fails to reach the
+/// secondary leaves the two stores diverged. Such commits are counted by
+/// `diverged_commits`.
+#[derive(Debug)]
+pub struct MirrorStorage {
+    primary: Arc<FjallStorage>,
+    secondary: Arc<FjallStorage>,
+    mode: MirrorMode,
+    /// local commits which reached the primary but not the secondary
+    diverged: AtomicU64,
+}
+
+impl MirrorStorage {
+    pub fn new(primary: Arc<FjallStorage>, secondary: Arc<FjallStorage>, mode: MirrorMode) -> Self {
+        Self {
+            primary,
+            secondary,
+            mode,
+            diverged: AtomicU64::new(0),
+        }
+    }
+
+    pub fn primary(&self) -> &Arc<FjallStorage> {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &Arc<FjallStorage> {
+        &self.secondary
+    }
+
+    pub fn mode(&self) -> MirrorMode {
+        self.mode
+    }
+
+    /// Returns the number of local commits which reached the primary but
+    /// failed to reach the secondary.
+    pub fn diverged_commits(&self) -> u64 {
+        self.diverged.load(Ordering::Relaxed)
+    }
+
+    /// Executes a local commit against the primary and then mirrors the
+    /// resulting commit, its pages, and the Volume to the secondary,
+    /// attaching the optional note to the commit.
+    ///
+    /// In `MirrorMode::Durable` both stores are persisted before returning,
+    /// and a failure to mirror the commit is returned as an error even
+    /// though the primary already contains it.
+    ///
+    /// Returns the resulting `Snapshot` from the primary on success.
+    pub fn commit_with_note(
+        &self,
+        vid: &VolumeId,
+        snapshot: Snapshot,
+        page_count: PageCount,
+        pages: BTreeMap<PageIdx, Page>,
+        note: Option<ByteString>,
+    ) -> Result<Snapshot, FjallStorageErr> {
+        let mirrored = pages.clone();
+        let snapshot = self
+            .primary
+            .read_write()
+            .commit_with_note(vid, snapshot, page_count, pages, note)?;
+        self.mirror_commits([(vid, &snapshot, &mirrored)])?;
+        Ok(snapshot)
+    }
+
+    /// Atomically executes several local commits against the primary, each
+    /// to a different Volume, and then mirrors every resulting commit to the
+    /// secondary in a single batch.
+    ///
+    /// Mirroring failures are handled like `commit_with_note`, with every
+    /// commit in the batch counted as diverged.
+    ///
+    /// Returns the resulting `Snapshot` from the primary for each commit, in
+    /// order.
+    pub fn multi_commit(
+        &self,
+        commits: Vec<(VolumeId, Snapshot, PageCount, BTreeMap<PageIdx, Page>)>,
+    ) -> Result<Vec<Snapshot>, FjallStorageErr> {
+        let mirrored: Vec<_> = commits
+            .iter()
+            .map(|(vid, _, _, pages)| (vid.clone(), pages.clone()))
+            .collect();
+        let snapshots = self.primary.read_write().multi_commit(commits)?;
+        self.mirror_commits(
+            mirrored
+                .iter()
+                .zip(&snapshots)
+                .map(|((vid, pages), snapshot)| (vid, snapshot, pages)),
+        )?;
+        Ok(snapshots)
+    }
+
+    /// Mirrors commits which already reached the primary, counting them as
+    /// diverged if they fail to reach the secondary. In `MirrorMode::Durable`
+    /// the failure is returned and both stores are persisted.
+    fn mirror_commits<'a>(
+        &self,
+        commits: impl IntoIterator<Item = (&'a VolumeId, &'a Snapshot, &'a BTreeMap<PageIdx, Page>)>,
+    ) -> Result<(), FjallStorageErr> {
+        let commits: Vec<_> = commits.into_iter().collect();
+        if let Err(err) = self.mirror(&commits) {
+            let count = commits.len() as u64;
+            let diverged = self.diverged.fetch_add(count, Ordering::Relaxed) + count;
+            let vids: Vec<_> = commits.iter().map(|(vid, ..)| *vid).collect();
+            tracing::warn!(
+                ?vids,
+                diverged,
+                "failed to mirror commit to secondary storage: {err}"
+            );
+            if self.mode == MirrorMode::Durable {
+                return Err(err);
+            }
+        }
+
+        if self.mode == MirrorMode::Durable {
+            self.primary.persist()?;
+        }
+        Ok(())
+    }
+
+    fn mirror(
+        &self,
+        commits: &[(&VolumeId, &Snapshot, &BTreeMap<PageIdx, Page>)],
+    ) -> Result<(), FjallStorageErr> {
+        let reader = self.primary.read();
+        let mut batch = self.secondary.batch();
+        for (vid, snapshot, pages) in commits {
+            let (log, lsn) = snapshot
+                .head()
+                .expect("local commit snapshot is never empty");
+            let volume = reader.volume(vid)?;
+            let commit = reader
+                .get_commit(log, lsn)?
+                .expect("primary must contain the commit we just wrote");
+
+            if let Some(sid) = commit.segment_id() {
+                for (pageidx, page) in pages {
+                    batch.write_page(sid.clone(), *pageidx, page.clone());
+                }
+            }
+            batch.write_commit(commit);
+            batch.write_volume(volume);
+        }
+        batch.commit()?;
+
+        if self.mode == MirrorMode::Durable {
+            self.secondary.persist()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use test_log::test;
+
+    use crate::{
+        core::{PageCount, PageIdx, page::Page},
+        local::fjall_storage::FjallStorage,
+        volume::OpenPolicy,
+    };
+
+    use super::{MirrorMode, MirrorStorage};
+
+    #[test]
+    fn mirror_commit() {
+        let mirror = MirrorStorage::new(
+            Arc::new(FjallStorage::open_temporary().unwrap()),
+            Arc::new(FjallStorage::open_temporary().unwrap()),
+            MirrorMode::Durable,
+        );
+        let vid = mirror
+            .primary()
+            .read_write()
+            .volume_open(None, None, None, OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+
+        let mut snapshot = mirror.primary().read().snapshot(&vid).unwrap();
+        for i in 1..=3u32 {
+            let pages = BTreeMap::from([(PageIdx::must_new(i), Page::test_filled(i as u8))]);
+            snapshot = mirror
+                .commit_with_note(&vid, snapshot, PageCount::new(i), pages, None)
+                .unwrap();
+        }
+
+        let primary = mirror.primary().read();
+        let secondary = mirror.secondary().read();
+        let volume = primary.volume(&vid).unwrap();
+        assert_eq!(secondary.volume(&vid).unwrap(), volume);
+        assert_eq!(
+            secondary.latest_commit(&volume.local).unwrap(),
+            primary.latest_commit(&volume.local).unwrap()
+        );
+        assert!(secondary.snapshot(&vid).unwrap().iter().eq(snapshot.iter()));
+        assert_eq!(mirror.diverged_commits(), 0);
+
+        for pageidx in snapshot.page_count.iter() {
+            let commit = primary
+                .search_page(&snapshot, pageidx)
+                .unwrap()
+                .expect("page must exist");
+            let sid = commit.segment_id().unwrap().clone();
+            assert_eq!(
+                secondary.read_page(sid.clone(), pageidx).unwrap(),
+                primary.read_page(sid, pageidx).unwrap()
+            );
+        }
+    }
+}
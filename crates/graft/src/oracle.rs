@@ -37,7 +37,11 @@ impl Oracle for NoopOracle {
 /// USENIX Association, USA.
 ///
 /// [1]: https://www.usenix.org/system/files/atc20-maruf.pdf
-#[derive(Debug, Default, Clone)]
+///
+/// To avoid over-fetching on pathological access patterns, predictions never
+/// reach further than `max_leap` pages from the requested page, and
+/// prefetching decays quickly once consecutive predictions go unused.
+#[derive(Debug, Clone)]
 pub struct LeapOracle {
     /// the last observed read
     last_read: PageIdx,
@@ -47,9 +51,52 @@ pub struct LeapOracle {
     prediction: Vec<PageIdx>,
     /// cache hits since the last prediction
     prediction_hits: usize,
+    /// the number of consecutive predictions which went entirely unused
+    unused_predictions: u8,
+    /// the maximum number of pages to predict at once
+    max_lookahead: usize,
+    /// the maximum distance in pages between a requested page and a prediction
+    max_leap: u32,
+}
+
+/// The default maximum number of pages to predict at once
+const DEFAULT_MAX_LOOKAHEAD: usize = 8;
+
+/// The default maximum distance in pages between a requested page and a
+/// prediction
+const DEFAULT_MAX_LEAP: u32 = 64;
+
+/// After this many consecutive predictions go entirely unused, the lookahead
+/// stops shrinking smoothly and drops immediately
+const DECAY_AFTER_UNUSED: u8 = 2;
+
+impl Default for LeapOracle {
+    fn default() -> Self {
+        Self {
+            last_read: Default::default(),
+            history: Default::default(),
+            prediction: Default::default(),
+            prediction_hits: 0,
+            unused_predictions: 0,
+            max_lookahead: DEFAULT_MAX_LOOKAHEAD,
+            max_leap: DEFAULT_MAX_LEAP,
+        }
+    }
 }
 
 impl LeapOracle {
+    /// Sets the maximum number of pages to predict at once.
+    pub fn with_max_lookahead(self, max_lookahead: usize) -> Self {
+        Self { max_lookahead, ..self }
+    }
+
+    /// Sets the maximum distance in pages between a requested page and a
+    /// predicted page. Predictions along a trend stop at this distance, so a
+    /// large stride can't cause the oracle to prefetch far ahead.
+    pub fn with_max_leap(self, max_leap: u32) -> Self {
+        Self { max_leap, ..self }
+    }
+
     /// Tries to find a trend in the data by searching for strict majorities in
     /// the access history. Returns None if no trend can be found.
     fn find_trend(&self) -> Option<isize> {
@@ -91,13 +138,20 @@ impl Oracle for LeapOracle {
     }
 
     fn predict_next(&mut self, pageidx: PageIdx) -> impl Iterator<Item = PageIdx> {
-        const MAX_LOOKAHEAD: usize = 8;
-
         // calculate the trend
         let trend = self.find_trend();
 
+        // track consecutive predictions which went entirely unused
+        if !self.prediction.is_empty() {
+            self.unused_predictions = if self.prediction_hits == 0 {
+                self.unused_predictions.saturating_add(1)
+            } else {
+                0
+            };
+        }
+
         // calculate the number of predictions to make
-        let lookahead = if self.prediction_hits == 0 {
+        let mut lookahead = if self.prediction_hits == 0 {
             // the last prediction wasn't great
             // check to see if reads are starting to follow a trend
             if TrendIter::once(self.last_read, trend.unwrap_or(1)) == Some(pageidx) {
@@ -109,26 +163,33 @@ impl Oracle for LeapOracle {
             // the last prediction had hits
             (self.prediction_hits + 1)
                 .checked_next_power_of_two()
-                .unwrap_or(MAX_LOOKAHEAD)
+                .unwrap_or(self.max_lookahead)
         }
         // ensure lookhead doesn't grow too large
-        .min(MAX_LOOKAHEAD)
-        // shrink lookahead smoothly
-        .max(self.prediction.len() / 2);
+        .min(self.max_lookahead);
+
+        if self.unused_predictions < DECAY_AFTER_UNUSED {
+            // shrink lookahead smoothly
+            lookahead = lookahead.max(self.prediction.len() / 2);
+        }
 
         // clear previous prediction state
         self.prediction_hits = 0;
         self.prediction.clear();
 
         // construct next prediction
+        let max_leap = self.max_leap;
         if lookahead != 0 {
             if let Some(trend) = trend {
                 // trend found, prefetch along the trend
-                self.prediction
-                    .extend(TrendIter::new(pageidx, trend).take(lookahead));
+                self.prediction.extend(
+                    TrendIter::new(pageidx, trend)
+                        .take(lookahead)
+                        .take_while(|p| p.to_u32().abs_diff(pageidx.to_u32()) <= max_leap),
+                );
             } else {
                 // no trend found, prefetch around the current page index
-                for i in 1..=(lookahead / 2) {
+                for i in 1..=(lookahead / 2).min(max_leap as usize) {
                     self.prediction.push(pageidx.saturating_add(i as u32));
                     self.prediction.push(pageidx.saturating_sub(i as u32));
                 }
@@ -335,4 +396,63 @@ mod tests {
             run_test(&mut State::default(), case);
         }
     }
+
+    /// Simulates reads through a cache, returning the number of pages
+    /// predicted on each cache miss
+    fn predictions(oracle: &mut LeapOracle, reads: impl IntoIterator<Item = u32>) -> Vec<usize> {
+        let mut cache = HashSet::new();
+        let mut predictions = vec![];
+        for pageidx in reads {
+            let pageidx = PageIdx::must_new(pageidx);
+            if cache.contains(&pageidx) {
+                oracle.observe_cache_hit(pageidx);
+            } else {
+                cache.insert(pageidx);
+                let prediction: Vec<_> = oracle.predict_next(pageidx).collect();
+                predictions.push(prediction.len());
+                cache.extend(prediction);
+            }
+        }
+        predictions
+    }
+
+    #[test]
+    fn test_leap_oracle_decay() {
+        let mut oracle = LeapOracle::default();
+
+        // a sequential scan uses every prediction, which keeps lookahead at
+        // its maximum
+        let scan = predictions(&mut oracle, 1..=40);
+        assert_eq!(scan, [0, 0, 1, 2, 4, 8, 8, 8, 8]);
+
+        // once consecutive predictions go unused, prefetching stops rather
+        // than shrinking gradually
+        let far = predictions(&mut oracle, [10000, 20000, 30000, 40000]);
+        assert_eq!(far, [4, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_leap_oracle_max_leap() {
+        let mut oracle = LeapOracle::default().with_max_leap(16);
+        let mut cache = HashSet::new();
+        for pageidx in (1..=400).step_by(4) {
+            let pageidx = PageIdx::must_new(pageidx);
+            if cache.contains(&pageidx) {
+                oracle.observe_cache_hit(pageidx);
+            } else {
+                cache.insert(pageidx);
+                for predicted in oracle.predict_next(pageidx) {
+                    assert!(predicted.to_u32().abs_diff(pageidx.to_u32()) <= 16);
+                    cache.insert(predicted);
+                }
+            }
+        }
+
+        // the stride-4 scan is still prefetched, but only within the limit
+        let prediction: Vec<_> = oracle
+            .predict_next(PageIdx::must_new(401))
+            .map(|p| p.to_u32())
+            .collect();
+        assert_eq!(prediction, [405, 409, 413, 417]);
+    }
 }
@@ -1,4 +1,4 @@
-use std::{future, ops::Range, time::Duration};
+use std::{fmt::Display, future, num::NonZero, ops::Range, time::Duration};
 
 use crate::core::{LogId, SegmentId, cbe::CBE64, commit::Commit, lsn::LSN};
 use bilrost::{Message, OwnedMessage};
@@ -19,6 +19,8 @@ use thiserror::Error;
 
 pub mod segment;
 
+use segment::MAX_FRAME_ALIGNMENT;
+
 const REMOTE_CONCURRENCY: usize = 5;
 
 enum RemotePath<'a> {
@@ -52,6 +54,12 @@ pub enum RemoteErr {
 
     #[error("Failed to decode file: {0}")]
     Decode(#[from] bilrost::DecodeError),
+
+    #[error("Remote request timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("Frame alignment of {0} bytes exceeds the maximum of {max}", max = MAX_FRAME_ALIGNMENT)]
+    FrameAlignmentTooLarge(NonZero<u64>),
 }
 
 impl RemoteErr {
@@ -70,6 +78,10 @@ impl RemoteErr {
         )
     }
 
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, RemoteErr::Timeout(_))
+    }
+
     pub fn is_not_found(&self) -> bool {
         matches!(
             self.objectstore_err_kind(),
@@ -104,20 +116,50 @@ impl RemoteConfig {
     }
 }
 
+impl Display for RemoteConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RemoteConfig::Memory => write!(f, "memory"),
+            RemoteConfig::Fs { root } => write!(f, "fs {root}"),
+            RemoteConfig::S3Compatible { bucket, prefix: Some(prefix) } => {
+                write!(f, "s3_compatible {bucket}/{prefix}")
+            }
+            RemoteConfig::S3Compatible { bucket, prefix: None } => {
+                write!(f, "s3_compatible {bucket}")
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Remote {
     store: Operator,
+
+    /// the config used to build this Remote
+    config: RemoteConfig,
+
+    /// if set, Segment frames pushed to this Remote are padded to a multiple
+    /// of this many bytes
+    frame_alignment: Option<NonZero<u64>>,
+
+    /// TESTONLY: artificial latency added to every segment read
+    #[cfg(test)]
+    latency: Option<Duration>,
+
+    /// TESTONLY: artificial latency added to every commit read
+    #[cfg(test)]
+    commit_latency: Option<Duration>,
 }
 
 impl Remote {
     pub fn with_config(config: RemoteConfig) -> Result<Self> {
-        let store = match config {
+        let store = match &config {
             RemoteConfig::Memory => Operator::new(Memory::default())?.finish(),
-            RemoteConfig::Fs { root } => Operator::new(Fs::default().root(&root))?.finish(),
+            RemoteConfig::Fs { root } => Operator::new(Fs::default().root(root))?.finish(),
             RemoteConfig::S3Compatible { bucket, prefix } => {
-                let mut builder = S3::default().bucket(&bucket);
+                let mut builder = S3::default().bucket(bucket);
                 if let Some(prefix) = prefix {
-                    builder = builder.root(&prefix);
+                    builder = builder.root(prefix);
                 }
                 if let Ok(endpoint) = std::env::var("AWS_ENDPOINT_URL") {
                     builder = builder.endpoint(&endpoint);
@@ -139,7 +181,50 @@ impl Remote {
             }
         };
 
-        Ok(Self { store })
+        Ok(Self {
+            store,
+            config,
+            frame_alignment: None,
+            #[cfg(test)]
+            latency: None,
+            #[cfg(test)]
+            commit_latency: None,
+        })
+    }
+
+    pub fn config(&self) -> &RemoteConfig {
+        &self.config
+    }
+
+    /// Aligns the frames of Segments pushed to this Remote to a multiple of
+    /// `alignment` bytes. Aligned frames are fetched with aligned range
+    /// reads, which some object stores serve more efficiently at the cost of
+    /// padding each frame. `None` disables alignment. Fails if `alignment`
+    /// exceeds `MAX_FRAME_ALIGNMENT`.
+    pub fn with_frame_alignment(self, alignment: Option<NonZero<u64>>) -> Result<Self> {
+        if let Some(alignment) = alignment
+            && alignment.get() > MAX_FRAME_ALIGNMENT.as_u64()
+        {
+            return Err(RemoteErr::FrameAlignmentTooLarge(alignment));
+        }
+        Ok(Self { frame_alignment: alignment, ..self })
+    }
+
+    pub fn frame_alignment(&self) -> Option<NonZero<u64>> {
+        self.frame_alignment
+    }
+
+    /// TESTONLY: delay every segment read by `latency`
+    #[cfg(test)]
+    pub fn testonly_with_latency(self, latency: Duration) -> Self {
+        Self { latency: Some(latency), ..self }
+    }
+
+    /// TESTONLY: delay every commit read by `latency`, which slows down
+    /// fetching, pulling, and recovering pushes
+    #[cfg(test)]
+    pub fn testonly_with_commit_latency(self, latency: Duration) -> Self {
+        Self { commit_latency: Some(latency), ..self }
     }
 
     /// Streams commits by LSN in the same order as the input iterator.
@@ -174,6 +259,11 @@ impl Remote {
     /// Fetches a single commit, returning None if the commit is not found.
     #[tracing::instrument(level = "trace", err(level = "debug"), skip(self))]
     pub async fn get_commit(&self, log: &LogId, lsn: LSN) -> Result<Option<Commit>> {
+        #[cfg(test)]
+        if let Some(latency) = self.commit_latency {
+            tokio::time::sleep(latency).await;
+        }
+
         let path = RemotePath::Commit(log, lsn).build();
         match self.store.read(&path).await {
             Ok(res) => Ok(Some(Commit::decode(res)?)),
@@ -236,6 +326,11 @@ impl Remote {
     /// Reads a byte range of a segment
     #[tracing::instrument(level = "debug", err(level = "debug"), skip(self))]
     pub async fn get_segment_range(&self, sid: &SegmentId, bytes: Range<u64>) -> Result<Bytes> {
+        #[cfg(test)]
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+
         let path = RemotePath::Segment(sid).build();
         let buffer = self
             .store
@@ -317,3 +412,22 @@ impl Remote {
             .to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_alignment_limit() {
+        let remote = RemoteConfig::Memory.build().unwrap();
+        let max = NonZero::new(MAX_FRAME_ALIGNMENT.as_u64()).unwrap();
+        let remote = remote.with_frame_alignment(Some(max)).unwrap();
+        assert_eq!(remote.frame_alignment(), Some(max));
+
+        let too_large = max.checked_add(1).unwrap();
+        assert!(matches!(
+            remote.with_frame_alignment(Some(too_large)),
+            Err(RemoteErr::FrameAlignmentTooLarge(alignment)) if alignment == too_large
+        ));
+    }
+}
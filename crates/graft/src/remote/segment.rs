@@ -1,10 +1,18 @@
 /*
 * Segments are sequences of compressed ZStd frames. All pages in a Segment is stored
 * in order by `PageIdx`.
+*
+* When a frame alignment is configured, each frame is followed by a ZStd
+* skippable frame which pads it to the next multiple of the alignment. The
+* padding is counted in the frame's size, so every frame starts on an aligned
+* byte offset and decoders skip the padding transparently.
 */
 
+use std::num::NonZero;
+
 use crate::core::{
     PageCount, PageIdx,
+    byte_unit::ByteUnit,
     commit::SegmentFrameIdx,
     page::{PAGESIZE, Page},
 };
@@ -19,6 +27,16 @@ const FRAME_MAX_PAGES: PageCount = PageCount::new(64);
 /// The ZSTD compression level
 const ZSTD_COMPRESSION_LEVEL: i32 = 3;
 
+/// The magic number of a ZStd skippable frame
+const ZSTD_SKIPPABLE_MAGIC: u32 = 0x184D2A50;
+
+/// The size of a ZStd skippable frame header: magic number and content size
+const ZSTD_SKIPPABLE_HEADER: u64 = 8;
+
+/// The largest supported frame alignment. Each frame is padded by less than
+/// two alignment units, so this bounds the size of the padding.
+pub const MAX_FRAME_ALIGNMENT: ByteUnit = ByteUnit::from_mb(1);
+
 pub struct SegmentBuilder {
     /// index of compressed frames
     frames: ThinVec<SegmentFrameIdx>,
@@ -42,6 +60,9 @@ pub struct SegmentBuilder {
 
     /// the active chunk
     chunk: Vec<u8>,
+
+    /// if set, pad each frame to a multiple of this many bytes
+    frame_alignment: Option<NonZero<u64>>,
 }
 
 impl Default for SegmentBuilder {
@@ -68,9 +89,18 @@ impl SegmentBuilder {
             current_frame_pages: PageCount::ZERO,
             current_frame_bytes: 0,
             chunk: Vec::with_capacity(CCtx::out_size()),
+            frame_alignment: None,
         }
     }
 
+    /// Pads each frame to a multiple of `alignment` bytes, such that every
+    /// frame starts on an aligned byte offset within the Segment. `None`
+    /// disables padding. `alignment` must not exceed `MAX_FRAME_ALIGNMENT`,
+    /// which `Remote::with_frame_alignment` validates.
+    pub fn with_frame_alignment(self, alignment: Option<NonZero<u64>>) -> Self {
+        Self { frame_alignment: alignment, ..self }
+    }
+
     fn flush_chunk(&mut self) {
         let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(CCtx::out_size()));
         self.chunks.push(chunk.into());
@@ -132,6 +162,8 @@ impl SegmentBuilder {
             }
         }
 
+        self.pad_frame();
+
         // record the frame
         self.frames.push(SegmentFrameIdx::new(
             self.current_frame_bytes,
@@ -146,6 +178,36 @@ impl SegmentBuilder {
             .expect("BUG: failed to reset context");
     }
 
+    /// Appends a skippable frame which pads the current frame to the next
+    /// multiple of the frame alignment.
+    fn pad_frame(&mut self) {
+        let Some(alignment) = self.frame_alignment else {
+            return;
+        };
+        let remainder = self.current_frame_bytes % alignment.get();
+        if remainder == 0 {
+            return;
+        }
+        let mut padding = alignment.get() - remainder;
+        // the padding must have room for the skippable frame header
+        while padding < ZSTD_SKIPPABLE_HEADER {
+            padding += alignment.get();
+        }
+
+        let content_size = u32::try_from(padding - ZSTD_SKIPPABLE_HEADER)
+            .expect("BUG: frame alignment exceeds MAX_FRAME_ALIGNMENT");
+        self.chunk
+            .extend_from_slice(&ZSTD_SKIPPABLE_MAGIC.to_le_bytes());
+        self.chunk.extend_from_slice(&content_size.to_le_bytes());
+        self.chunk
+            .resize(self.chunk.len() + content_size as usize, 0);
+        self.current_frame_bytes += padding;
+
+        if self.chunk.len() >= CCtx::out_size() {
+            self.flush_chunk();
+        }
+    }
+
     pub fn finish(mut self) -> (ThinVec<SegmentFrameIdx>, Vec<Bytes>) {
         // flush the last frame if needed
         if self.current_frame_pages > 0 {
@@ -238,6 +300,46 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_segment_frame_alignment() {
+        // an alignment smaller than the skippable frame header forces padding
+        // to span more than one alignment unit
+        for alignment in [1, 5, 512, 4096] {
+            let mut segment = SegmentBuilder::new().with_frame_alignment(NonZero::new(alignment));
+            for i in 1..=96 {
+                segment.write(PageIdx::must_new(i), &Page::test_filled(i as u8));
+            }
+            let (frames, chunks) = segment.finish();
+            assert_eq!(frames.len(), 2);
+
+            let data: Vec<u8> = chunks.iter().flat_map(|c| c.iter().copied()).collect();
+            assert_eq!(
+                data.len() as u64,
+                frames[0].frame_size() + frames[1].frame_size()
+            );
+
+            // every frame starts and ends on an aligned offset
+            for frame in &frames {
+                assert_eq!(frame.frame_size() % alignment, 0);
+            }
+
+            // each frame decodes independently, skipping its padding
+            let frame1 = &data[..frames[0].frame_size() as usize];
+            for (i, page) in segment_frame_iter(frame1).take(64).enumerate() {
+                assert_eq!(page, Page::test_filled((i + 1) as u8));
+            }
+            let frame2 = &data[frames[0].frame_size() as usize..];
+            for (i, page) in segment_frame_iter(frame2).take(32).enumerate() {
+                assert_eq!(page, Page::test_filled((i + 65) as u8));
+            }
+
+            // both frames decode as a single range
+            for (i, page) in segment_frame_iter(&data).take(96).enumerate() {
+                assert_eq!(page, Page::test_filled((i + 1) as u8));
+            }
+        }
+    }
+
     #[test]
     fn test_segment_with_empty_pages() {
         let mut segment = SegmentBuilder::new();
@@ -1,4 +1,4 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{collections::HashSet, num::NonZero, sync::Arc};
 
 use crate::core::{
     LogId,
@@ -18,53 +18,163 @@ use crate::{
 pub struct FetchLog {
     pub log: LogId,
     pub max_lsn: Option<LSN>,
+
+    /// if set, the log is fetched in batches of at most this many commits,
+    /// each written to storage before the next batch is requested
+    pub max_commits: Option<NonZero<usize>>,
 }
 
 impl Action for FetchLog {
     async fn run(self, storage: Arc<FjallStorage>, remote: Arc<Remote>) -> Result<()> {
-        let reader = storage.read();
-        let mut batch = storage.batch();
-
-        // calculate the lsn range to retrieve
-        let start = reader
-            .latest_lsn(&self.log)?
-            .map_or(LSN::FIRST, |lsn| lsn.next());
+        let limit = self.max_commits.map_or(usize::MAX, NonZero::get);
         let end = self.max_lsn.unwrap_or(LSN::LAST);
-        let lsns = start..=end;
+        let mut seen_lsns = HashSet::new();
+
+        loop {
+            // calculate the lsn range to retrieve
+            let start = storage
+                .read()
+                .latest_lsn(&self.log)?
+                .map_or(LSN::FIRST, |lsn| lsn.next());
+            if start > end {
+                return Ok(());
+            }
+            let lsns = start..=end;
 
-        tracing::debug!(log = ?self.log, lsns = %lsns.to_string(), "fetching log");
+            tracing::debug!(log = ?self.log, lsns = %lsns.to_string(), "fetching log");
 
-        // figure out which lsns we are missing
-        let existing_lsns = storage.read().lsns(&self.log, &lsns)?;
-        let missing_lsns =
-            (RangeOnce::new(lsns) - existing_lsns.into_ranges()).flat_map(|r| r.iter());
+            // figure out which lsns we are missing
+            let existing_lsns = storage.read().lsns(&self.log, &lsns)?;
+            let missing_lsns = (RangeOnce::new(lsns) - existing_lsns.into_ranges())
+                .flat_map(|r| r.iter())
+                .take(limit);
 
-        let mut seen_lsns = HashSet::new();
-        let mut checkpoints = HashSet::new();
-
-        // fetch missing lsns
-        let mut commits = remote.stream_commits_ordered(&self.log, missing_lsns);
-        while let Some(commit) = commits.try_next().await? {
-            seen_lsns.insert(commit.lsn);
-            // keep track of checkpoints that we need to re-fetch
-            checkpoints.extend(
-                commit
-                    .checkpoints
-                    .iter()
-                    .copied()
-                    .filter(|lsn| !seen_lsns.contains(lsn)),
-            );
-            batch.write_commit(commit);
-        }
+            let mut batch = storage.batch();
+            let mut fetched = 0;
+            let mut checkpoints = HashSet::new();
 
-        // fetch missing checkpoints
-        if !checkpoints.is_empty() {
-            let mut commits = remote.stream_commits_ordered(&self.log, checkpoints);
+            // fetch missing lsns
+            let mut commits = remote.stream_commits_ordered(&self.log, missing_lsns);
             while let Some(commit) = commits.try_next().await? {
-                batch.write_commit(commit);
+                fetched += 1;
+                seen_lsns.insert(commit.lsn);
+                // keep track of checkpoints that we need to re-fetch
+                checkpoints.extend(
+                    commit
+                        .checkpoints
+                        .iter()
+                        .copied()
+                        .filter(|lsn| !seen_lsns.contains(lsn)),
+                );
+                batch.write_remote_commit(commit)?;
+            }
+
+            // fetch missing checkpoints
+            if !checkpoints.is_empty() {
+                let mut commits = remote.stream_commits_ordered(&self.log, checkpoints);
+                while let Some(commit) = commits.try_next().await? {
+                    seen_lsns.insert(commit.lsn);
+                    batch.write_remote_commit(commit)?;
+                }
+            }
+
+            batch.commit()?;
+
+            // a short batch means we reached the end of the remote log
+            if fetched < limit {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZero, sync::Arc};
+
+    use test_log::test;
+
+    use crate::{
+        GraftErr,
+        core::{
+            LogId, PageCount, SegmentId,
+            commit::{Commit, SegmentIdx},
+            lsn::LSNRangeExt,
+            pageset::PageSet,
+        },
+        local::fjall_storage::{FjallStorage, FjallStorageErr},
+        lsn, pageidx,
+        remote::RemoteConfig,
+        rt::action::Action,
+    };
+
+    use super::FetchLog;
+
+    #[test(tokio::test)]
+    async fn test_fetch_log_in_batches() {
+        let remote = Arc::new(RemoteConfig::Memory.build().unwrap());
+        let log = LogId::random();
+        for lsn in (lsn!(1)..=lsn!(10)).iter() {
+            let commit = Commit::new(log.clone(), lsn, PageCount::ZERO);
+            remote.put_commit(&commit).await.unwrap();
+        }
+
+        // 3 doesn't divide the log evenly while 5 does
+        for max_commits in [1, 3, 5, 100] {
+            let storage = Arc::new(FjallStorage::open_temporary().unwrap());
+            FetchLog {
+                log: log.clone(),
+                max_lsn: None,
+                max_commits: NonZero::new(max_commits),
             }
+            .run(storage.clone(), remote.clone())
+            .await
+            .unwrap();
+            assert_eq!(
+                storage.read().latest_lsn(&log).unwrap(),
+                Some(lsn!(10)),
+                "max_commits = {max_commits}"
+            );
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn test_fetch_log_rejects_pages_beyond_page_count() {
+        let remote = Arc::new(RemoteConfig::Memory.build().unwrap());
+        let log = LogId::random();
+        remote
+            .put_commit(&Commit::new(log.clone(), lsn!(1), PageCount::new(1)))
+            .await
+            .unwrap();
+
+        // the second commit declares two pages but references page 5
+        let segment = SegmentIdx::new(
+            SegmentId::random(),
+            PageSet::from_range(pageidx!(1)..=pageidx!(5)),
+        );
+        let commit =
+            Commit::new(log.clone(), lsn!(2), PageCount::new(2)).with_segment_idx(Some(segment));
+        remote.put_commit(&commit).await.unwrap();
+
+        let storage = Arc::new(FjallStorage::open_temporary().unwrap());
+        let result = FetchLog {
+            log: log.clone(),
+            max_lsn: None,
+            max_commits: None,
         }
+        .run(storage.clone(), remote)
+        .await;
+        assert!(
+            matches!(
+                result,
+                Err(GraftErr::Storage(FjallStorageErr::CommitPageOutOfBounds {
+                    lsn, pageidx, page_count, ..
+                })) if lsn == lsn!(2) && pageidx == pageidx!(5) && page_count == PageCount::new(2)
+            ),
+            "{result:?}"
+        );
 
-        Ok(batch.commit()?)
+        // none of the fetched commits are stored
+        assert_eq!(storage.read().latest_lsn(&log).unwrap(), None);
     }
 }
@@ -1,11 +1,12 @@
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::core::commit::SegmentRangeRef;
 
 use crate::{
     local::fjall_storage::FjallStorage,
-    remote::{Remote, segment::segment_frame_iter},
+    remote::{Remote, RemoteErr, segment::segment_frame_iter},
     rt::action::{Action, Result},
 };
 
@@ -13,13 +14,32 @@ use crate::{
 #[derive(Debug)]
 pub struct FetchSegment {
     pub range: SegmentRangeRef,
+
+    /// if set, fail with `RemoteErr::Timeout` if the fetch takes longer than
+    /// this duration
+    pub timeout: Option<Duration>,
 }
 
 impl Action for FetchSegment {
     async fn run(self, storage: Arc<FjallStorage>, remote: Arc<Remote>) -> Result<()> {
-        let bytes = remote
-            .get_segment_range(&self.range.sid, self.range.bytes)
-            .await?;
+        #[cfg(feature = "precept")]
+        precept::sometimes_fault!(
+            "FetchSegment: network loss",
+            return Err(RemoteErr::from(opendal::Error::new(
+                opendal::ErrorKind::Unexpected,
+                "simulated network loss",
+            ))
+            .into()),
+            { "sid": self.range.sid }
+        );
+
+        let fetch = remote.get_segment_range(&self.range.sid, self.range.bytes);
+        let bytes = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, fetch)
+                .await
+                .map_err(|_| RemoteErr::Timeout(timeout))??,
+            None => fetch.await?,
+        };
         let pageidxs = self.range.pageset.iter();
         let pages = segment_frame_iter(&bytes);
         let mut batch = storage.batch();
@@ -30,3 +50,41 @@ impl Action for FetchSegment {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use assert_matches::assert_matches;
+    use test_log::test;
+
+    use crate::{
+        GraftErr,
+        core::{SegmentId, commit::SegmentRangeRef, pageset::PageSet},
+        local::fjall_storage::FjallStorage,
+        remote::{RemoteConfig, RemoteErr},
+        rt::action::Action,
+    };
+
+    use super::FetchSegment;
+
+    #[test(tokio::test(start_paused = true))]
+    async fn test_fetch_timeout() {
+        let storage = Arc::new(FjallStorage::open_temporary().unwrap());
+        let remote = RemoteConfig::Memory
+            .build()
+            .unwrap()
+            .testonly_with_latency(Duration::from_secs(3600));
+
+        let action = FetchSegment {
+            range: SegmentRangeRef {
+                sid: SegmentId::random(),
+                bytes: 0..1024,
+                pageset: PageSet::default(),
+            },
+            timeout: Some(Duration::from_secs(1)),
+        };
+        let err = action.run(storage, Arc::new(remote)).await.unwrap_err();
+        assert_matches!(err, GraftErr::Remote(RemoteErr::Timeout(timeout)) if timeout == Duration::from_secs(1));
+    }
+}
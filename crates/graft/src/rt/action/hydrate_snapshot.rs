@@ -1,10 +1,11 @@
-use std::sync::Arc;
+use std::{ops::RangeInclusive, sync::Arc, time::Duration};
 
-use futures::{StreamExt, TryStreamExt};
+use futures::StreamExt;
 use itertools::Itertools;
 
 use crate::{
     GraftErr,
+    core::PageIdx,
     local::fjall_storage::FjallStorage,
     remote::Remote,
     rt::action::{Action, fetch_segment::FetchSegment},
@@ -13,25 +14,85 @@ use crate::{
 
 const HYDRATE_CONCURRENCY: usize = 5;
 
-/// Downloads all missing pages for a Snapshot.
+/// How long to wait before the first retry; doubles after each retry
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// The longest delay between retries
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Downloads all missing pages for a Snapshot within the specified range of
+/// pages.
+///
+/// A failed fetch doesn't stop the remaining fetches, and fetched pages are
+/// kept when hydration fails. Running the action again (or retrying) only
+/// fetches the frames which are still missing.
 #[derive(Debug)]
 pub struct HydrateSnapshot {
     pub snapshot: Snapshot,
+    pub range: RangeInclusive<PageIdx>,
+
+    /// how many times to retry fetching the remaining missing frames after a
+    /// fetch fails
+    pub retries: u32,
 }
 
 impl Action for HydrateSnapshot {
     async fn run(self, storage: Arc<FjallStorage>, remote: Arc<Remote>) -> Result<(), GraftErr> {
-        let missing_frames = storage.read().find_missing_frames(&self.snapshot)?;
-        futures::stream::iter(
-            missing_frames
-                .into_iter()
-                // coalesce adjacent frames to minimize requests
-                .coalesce(|a, b| a.coalesce(b)),
-        )
-        .map(Ok)
-        .try_for_each_concurrent(HYDRATE_CONCURRENCY, |range| {
-            FetchSegment { range }.run(storage.clone(), remote.clone())
-        })
-        .await
+        let mut retries = self.retries;
+        let mut delay = RETRY_DELAY;
+        loop {
+            let missing_frames = storage
+                .read()
+                .find_missing_frames_in(&self.snapshot, self.range.clone())?;
+            let results: Vec<_> = futures::stream::iter(
+                missing_frames
+                    .into_iter()
+                    // coalesce adjacent frames to minimize requests
+                    .coalesce(|a, b| a.coalesce(b)),
+            )
+            .map(|range| FetchSegment { range, timeout: None }.run(storage.clone(), remote.clone()))
+            .buffer_unordered(HYDRATE_CONCURRENCY)
+            .collect()
+            .await;
+
+            let failed = results.iter().filter(|r| r.is_err()).count();
+            let Some(err) = results.into_iter().find_map(Result::err) else {
+                return Ok(());
+            };
+            if retries == 0 {
+                return Err(err);
+            }
+            tracing::warn!(
+                failed,
+                retries,
+                "hydrate interrupted, retrying missing frames in {delay:?}: {err}"
+            );
+            tokio::time::sleep(delay).await;
+            retries -= 1;
+            delay = next_retry_delay(delay);
+        }
+    }
+}
+
+/// Doubles the retry delay, up to `MAX_RETRY_DELAY`
+fn next_retry_delay(delay: Duration) -> Duration {
+    delay.saturating_mul(2).min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_log::test;
+
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_is_capped() {
+        let mut delay = RETRY_DELAY;
+        for _ in 0..100 {
+            delay = next_retry_delay(delay);
+            assert!(delay <= MAX_RETRY_DELAY);
+        }
+        assert_eq!(delay, MAX_RETRY_DELAY);
+        assert_eq!(next_retry_delay(Duration::MAX), MAX_RETRY_DELAY);
     }
 }
@@ -1,4 +1,4 @@
-use std::{collections::BTreeMap, ops::RangeInclusive, sync::Arc};
+use std::{collections::BTreeMap, num::NonZero, ops::RangeInclusive, sync::Arc};
 
 use crate::core::{
     CommitHashBuilder, LogId, PageCount, PageIdx, SegmentId, VolumeId,
@@ -6,18 +6,23 @@ use crate::core::{
     commit_hash::CommitHash,
     logref::LogRef,
     lsn::LSN,
+    page::Page,
 };
 use bytes::Bytes;
-use splinter_rs::{Optimizable, PartitionRead, Splinter};
+use bytestring::ByteString;
+use splinter_rs::{Optimizable, PartitionRead, PartitionWrite, Splinter};
 use thin_vec::thin_vec;
 use tokio::task::spawn_blocking;
 use tryiter::TryIteratorExt;
 
 use crate::{
     GraftErr, LogicalErr,
-    local::fjall_storage::FjallStorage,
+    local::fjall_storage::{FjallStorage, ReadGuard},
     remote::{Remote, segment::SegmentBuilder},
-    rt::action::{Action, FetchLog},
+    rt::{
+        action::{Action, FetchLog},
+        observer::EventSink,
+    },
     snapshot::Snapshot,
     volume::PendingCommit,
 };
@@ -26,30 +31,110 @@ use crate::{
 #[derive(Debug)]
 pub struct RemoteCommit {
     pub vid: VolumeId,
+
+    /// When set, pages which are identical to the page visible in the
+    /// remote snapshot we are committing on top of are omitted from the
+    /// segment, resulting in a commit containing only net-changed pages.
+    pub squash: bool,
+
+    /// When set, the pushed commit is fetched back from the remote and its
+    /// hash compared to the locally computed hash before the local commits
+    /// are cleared.
+    pub verify: bool,
+
+    /// When set, a push rejected because the remote has newer commits is
+    /// retried once after rebasing the local changes onto the remote. The
+    /// push still fails if the local and remote changes overlap.
+    pub rebase: bool,
+
+    /// if set, the remote log is fetched in batches of at most this many
+    /// commits while recovering a pending commit or rebasing
+    pub max_commits: Option<NonZero<usize>>,
+
+    /// records the local commit made by a rebase in the event log
+    pub events: EventSink,
 }
 
 impl Action for RemoteCommit {
     async fn run(self, storage: Arc<FjallStorage>, remote: Arc<Remote>) -> Result<(), GraftErr> {
+        let result = self.push(&storage, &remote).await;
+        if !self.rebase
+            || !matches!(
+                result,
+                Err(GraftErr::Logical(LogicalErr::VolumeDiverged(_)))
+            )
+        {
+            return result;
+        }
+
+        // make sure we rebase onto the latest remote commit
+        let log = storage.read().volume(&self.vid)?.remote;
+        FetchLog {
+            log,
+            max_lsn: None,
+            max_commits: self.max_commits,
+        }
+        .run(storage.clone(), remote.clone())
+        .await?;
+        if storage.read_write().rebase_onto_remote(&self.vid)? {
+            self.events.log_local(&storage, &self.vid);
+        }
+        tracing::warn!(
+            vid = ?self.vid,
+            "push conflicted with newer remote commits; rebased local changes and retrying"
+        );
+        self.push(&storage, &remote).await
+    }
+}
+
+impl RemoteCommit {
+    async fn push(
+        &self,
+        storage: &Arc<FjallStorage>,
+        remote: &Arc<Remote>,
+    ) -> Result<(), GraftErr> {
         // first, check if we need to recover from a pending commit
         // we do this *before* plan since this may modify storage
-        attempt_recovery(&storage, &remote, &self.vid).await?;
+        attempt_recovery(storage, remote, &self.vid, self.max_commits).await?;
 
-        let Some(plan) = plan_commit(&storage, &self.vid)? else {
+        let Some(plan) = plan_commit(storage, &self.vid, self.squash)? else {
             // nothing to commit
             return Ok(());
         };
 
         tracing::debug!(?plan, "RemoteCommit plan");
 
+        // the segment's pages are cached before any commit references them,
+        // so keep them safe from gc until the push completes
+        let sid = SegmentId::random();
+        let _pin = storage.pin_segment(sid.clone());
+
         // build & upload segment
         let (commit_hash, segment_idx, segment_chunks) = {
             let plan = plan.clone();
             let storage = storage.clone();
-            spawn_blocking(move || build_segment(storage, plan))
+            let frame_alignment = remote.frame_alignment();
+            spawn_blocking(move || build_segment(storage, plan, sid, frame_alignment))
                 .await
                 .expect("build_segment task failed")?
         };
 
+        // simulate the upload failing partway through with a transient
+        // error, before any commit state has been written
+        #[cfg(feature = "precept")]
+        precept::sometimes_fault!(
+            "RemoteCommit: segment upload failed",
+            return Err(crate::remote::RemoteErr::from(
+                opendal::Error::new(
+                    opendal::ErrorKind::Unexpected,
+                    "simulated segment upload failure",
+                )
+                .set_temporary()
+            )
+            .into()),
+            { "vid": self.vid, "sid": segment_idx.sid() }
+        );
+
         remote
             .put_segment(segment_idx.sid(), segment_chunks)
             .await?;
@@ -76,6 +161,7 @@ impl Action for RemoteCommit {
                 local: *plan.lsns.end(),
                 commit: plan.commit_ref.lsn,
                 commit_hash: commit_hash.clone(),
+                sid: Some(segment_idx.sid().clone()),
             },
         )?;
 
@@ -93,7 +179,8 @@ impl Action for RemoteCommit {
         )
         .with_commit_hash(Some(commit_hash.clone()))
         .with_segment_idx(Some(segment_idx))
-        .with_checkpoints(maybe_checkpoint);
+        .with_checkpoints(maybe_checkpoint)
+        .with_note(latest_note(&storage.read(), &plan)?);
 
         #[cfg(feature = "precept")]
         precept::sometimes_fault!(
@@ -122,6 +209,10 @@ impl Action for RemoteCommit {
                     "successfully pushed commit to the remote"
                 );
 
+                if self.verify {
+                    verify_pushed_commit(remote, &self.vid, &commit).await?;
+                }
+
                 storage
                     .read_write()
                     .remote_commit_success(&self.vid, commit)?;
@@ -135,7 +226,7 @@ impl Action for RemoteCommit {
                 // The commit already exists on the remote. This could be because:
                 // 1. Someone (including us) pushed the same commit (idempotency)
                 // 2. Someone (including us) pushed a DIFFERENT commit (divergence)
-                attempt_recovery(&storage, &remote, &self.vid).await
+                attempt_recovery(storage, remote, &self.vid, self.max_commits).await
             }
             Err(err) => {
                 // if any other error occurs, we leave the pending_commit in place and fail the job.
@@ -154,9 +245,16 @@ struct CommitPlan {
     lsns: RangeInclusive<LSN>,
     commit_ref: LogRef,
     page_count: PageCount,
+
+    /// The remote snapshot this commit is based on. Only set when squashing.
+    base: Option<Snapshot>,
 }
 
-fn plan_commit(storage: &FjallStorage, vid: &VolumeId) -> Result<Option<CommitPlan>, GraftErr> {
+fn plan_commit(
+    storage: &FjallStorage,
+    vid: &VolumeId,
+    squash: bool,
+) -> Result<Option<CommitPlan>, GraftErr> {
     let reader = storage.read();
     let volume = reader.volume(vid)?;
 
@@ -186,6 +284,7 @@ fn plan_commit(storage: &FjallStorage, vid: &VolumeId) -> Result<Option<CommitPl
             lsns: LSN::FIRST..=latest_local,
             commit_ref: LogRef::new(volume.remote, LSN::FIRST),
             page_count,
+            base: None,
         }));
     };
 
@@ -206,17 +305,47 @@ fn plan_commit(storage: &FjallStorage, vid: &VolumeId) -> Result<Option<CommitPl
     // calculate the commit lsn
     let commit_lsn = sync.remote.next();
 
+    let base = if squash {
+        let base_pages = reader
+            .page_count(&volume.remote, sync.remote)?
+            .expect("BUG: no page count for remote commit");
+        Some(Snapshot::new(
+            volume.remote.clone(),
+            LSN::FIRST..=sync.remote,
+            base_pages,
+        ))
+    } else {
+        None
+    };
+
     Ok(Some(CommitPlan {
         local: volume.local.clone(),
         lsns: local_lsns,
         commit_ref: LogRef::new(volume.remote.clone(), commit_lsn),
         page_count,
+        base,
     }))
 }
 
+/// Returns the note of the most recent local commit in the plan which has one.
+/// The remote commit squashes the planned local commits together, so it
+/// carries this note.
+fn latest_note(reader: &ReadGuard<'_>, plan: &CommitPlan) -> Result<Option<ByteString>, GraftErr> {
+    let snapshot = Snapshot::new(plan.local.clone(), plan.lsns.clone(), plan.page_count);
+    let mut commits = reader.commits(&snapshot);
+    while let Some(commit) = commits.try_next()? {
+        if commit.note.is_some() {
+            return Ok(commit.note);
+        }
+    }
+    Ok(None)
+}
+
 fn build_segment(
     storage: Arc<FjallStorage>,
     plan: CommitPlan,
+    sid: SegmentId,
+    frame_alignment: Option<NonZero<u64>>,
 ) -> Result<(CommitHash, SegmentIdx, Vec<Bytes>), GraftErr> {
     let reader = storage.read();
 
@@ -229,6 +358,8 @@ fn build_segment(
     let page_count = plan.page_count;
     let mut pages = BTreeMap::new();
     let mut pageset = Splinter::default();
+    // pages which we skipped because they didn't change relative to plan.base
+    let mut squashed = Splinter::default();
     let mut commits = reader.commits(&snapshot);
     while let Some(commit) = commits.try_next()? {
         if let Some(idx) = commit.segment_idx {
@@ -249,8 +380,17 @@ fn build_segment(
                     plan.page_count.contains(pageidx),
                     "BUG: page is out of range for volume"
                 );
-                let page = reader.read_page(idx.sid.clone(), pageidx)?;
-                pages.insert(pageidx, page.expect("BUG: missing page"));
+                let page = reader
+                    .read_page(idx.sid.clone(), pageidx)?
+                    .expect("BUG: missing page");
+                if let Some(base) = &plan.base
+                    && matches_base(&reader, base, pageidx, &page)?
+                {
+                    // the page changed and then changed back
+                    squashed.insert(pageidx.to_u32());
+                    continue;
+                }
+                pages.insert(pageidx, page);
             }
             // update the pageset accordingly
             pageset |= outstanding;
@@ -263,7 +403,10 @@ fn build_segment(
         }
     }
 
-    // optimize the pageset
+    // remove squashed pages and optimize the pageset
+    for pageidx in squashed.iter() {
+        pageset.remove(pageidx);
+    }
     pageset.optimize();
 
     assert_eq!(
@@ -272,7 +415,7 @@ fn build_segment(
         "BUG: pageset cardinality doesn't match number of pages in segment"
     );
 
-    let mut segment_builder = SegmentBuilder::new();
+    let mut segment_builder = SegmentBuilder::new().with_frame_alignment(frame_alignment);
     let mut commithash_builder = CommitHashBuilder::new(
         plan.commit_ref.log().clone(),
         plan.commit_ref.lsn(),
@@ -280,8 +423,6 @@ fn build_segment(
         PageCount::new(pages.len() as u32),
     );
 
-    let sid = SegmentId::random();
-
     let mut batch = storage.batch();
     for (pageidx, page) in pages {
         commithash_builder.write_page(pageidx, &page);
@@ -309,12 +450,74 @@ fn build_segment(
     Ok((commit_hash, idx, chunks))
 }
 
+/// Returns true if `page` is identical to the page visible at `pageidx` in
+/// the `base` snapshot. Pages which aren't cached locally are assumed to have
+/// changed.
+fn matches_base(
+    reader: &ReadGuard<'_>,
+    base: &Snapshot,
+    pageidx: PageIdx,
+    page: &Page,
+) -> Result<bool, GraftErr> {
+    // pages outside of the base snapshot may resurface from older commits due
+    // to soft truncation, so we must always include them
+    if !base.page_count.contains(pageidx) {
+        return Ok(false);
+    }
+    let Some(commit) = reader.search_page(base, pageidx)? else {
+        return Ok(page.is_empty());
+    };
+    let idx = commit
+        .segment_idx()
+        .expect("BUG: commit claims to contain pageidx");
+    Ok(reader
+        .read_page(idx.sid().clone(), pageidx)?
+        .is_some_and(|base_page| &base_page == page))
+}
+
+/// Fetches a pushed commit back from the remote and checks that its hash
+/// matches the commit we pushed. On mismatch the pending commit is left in
+/// place, so the local commits are not cleared. A later push will attempt
+/// recovery by fetching the remote log again.
+async fn verify_pushed_commit(
+    remote: &Remote,
+    vid: &VolumeId,
+    commit: &Commit,
+) -> Result<(), GraftErr> {
+    let remote_commit = remote.get_commit(commit.log(), commit.lsn()).await?;
+    #[allow(unused_mut, reason = "modified by precept faults")]
+    let mut remote_hash = remote_commit.as_ref().and_then(Commit::commit_hash);
+
+    #[cfg(feature = "precept")]
+    precept::sometimes_fault!(
+        "RemoteCommit: verify push mismatch",
+        remote_hash = None,
+        { "vid": vid }
+    );
+
+    if remote_hash != commit.commit_hash() {
+        tracing::warn!(
+            ?vid,
+            log = %commit.log,
+            lsn = %commit.lsn,
+            expected = ?commit.commit_hash,
+            actual = ?remote_hash,
+            "pushed commit does not match the remote; refusing to clear local commits"
+        );
+        return Err(
+            LogicalErr::PushVerificationFailed { vid: vid.clone(), lsn: commit.lsn }.into(),
+        );
+    }
+    Ok(())
+}
+
 /// Attempts to recover from a remote commit conflict by checking the remote
 /// for the commit we tried to push.
 async fn attempt_recovery(
     storage: &Arc<FjallStorage>,
     remote: &Arc<Remote>,
     vid: &VolumeId,
+    max_commits: Option<NonZero<usize>>,
 ) -> Result<(), GraftErr> {
     let reader = storage.read();
     let volume = reader.volume(vid)?;
@@ -332,6 +535,7 @@ async fn attempt_recovery(
         FetchLog {
             log: volume.remote.clone(),
             max_lsn: None,
+            max_commits,
         }
         .run(storage.clone(), remote.clone())
         .await?;
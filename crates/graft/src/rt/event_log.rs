@@ -0,0 +1,290 @@
+use std::{
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    core::{PageCount, VolumeId, commit_hash::CommitHash, lsn::LSN},
+    local::fjall_storage::{FjallStorage, FjallStorageErr},
+};
+
+/// Controls when records appended to the event log reach the disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventLogDurability {
+    /// Records are written without waiting for them to reach the disk, so the
+    /// most recent records may be lost if the machine crashes.
+    #[default]
+    Relaxed,
+
+    /// The event log is fsynced after every record.
+    Strict,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// A local commit to the Volume
+    Local,
+
+    /// A commit pushed to the Volume's remote Log
+    Push,
+}
+
+/// A single commit recorded in the event log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventRecord {
+    /// milliseconds since the unix epoch
+    pub timestamp: u64,
+    pub kind: EventKind,
+    pub vid: VolumeId,
+    pub lsn: LSN,
+    /// the Volume's page count as of this commit
+    pub page_count: PageCount,
+    /// only set on pushed commits, as local commits aren't hashed
+    pub hash: Option<CommitHash>,
+}
+
+#[derive(Debug, Error)]
+pub enum EventLogErr {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid event log record on line {line}: {msg}")]
+    InvalidRecord { line: usize, msg: String },
+}
+
+/// An append-only file recording every commit made by the runtime, separate
+/// from the main store. Each record is a single tab separated line.
+#[derive(Debug)]
+pub struct EventLog {
+    file: File,
+    durability: EventLogDurability,
+}
+
+impl EventLog {
+    /// Opens or creates the event log at `path`. A partial record left behind
+    /// by a crash while appending is truncated away.
+    pub fn open(
+        path: impl AsRef<Path>,
+        durability: EventLogDurability,
+    ) -> Result<Self, EventLogErr> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let mut contents = vec![];
+        file.read_to_end(&mut contents)?;
+        let valid_len = contents
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |pos| pos + 1);
+        if valid_len < contents.len() {
+            tracing::warn!(
+                truncated = contents.len() - valid_len,
+                "truncating partial record at the end of the event log"
+            );
+            file.set_len(valid_len as u64)?;
+            file.sync_data()?;
+        }
+        file.seek(SeekFrom::End(0))?;
+
+        Ok(Self { file, durability })
+    }
+
+    /// Appends the record to the log, syncing it to disk if durability is
+    /// strict.
+    pub fn append(&mut self, record: &EventRecord) -> Result<(), EventLogErr> {
+        // write the record in a single call so a crash can only tear the
+        // last line
+        self.file.write_all(format!("{record}\n").as_bytes())?;
+        if self.durability == EventLogDurability::Strict {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads every record from the event log at `path`, in the order they were
+/// appended. A partial record at the end of the log is ignored.
+pub fn read_event_log(path: impl AsRef<Path>) -> Result<Vec<EventRecord>, EventLogErr> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut lines: Vec<_> = contents.split('\n').collect();
+    // the last element is either empty or a partial record
+    lines.pop();
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse()
+                .map_err(|msg| EventLogErr::InvalidRecord { line: i + 1, msg })
+        })
+        .collect()
+}
+
+impl EventRecord {
+    pub fn new(
+        kind: EventKind,
+        vid: VolumeId,
+        lsn: LSN,
+        page_count: PageCount,
+        hash: Option<CommitHash>,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        Self {
+            timestamp,
+            kind,
+            vid,
+            lsn,
+            page_count,
+            hash,
+        }
+    }
+
+    /// Builds a record of the latest commit in the Volume's local Log, for
+    /// local commits which aren't made by a `VolumeWriter`.
+    pub(crate) fn latest_local(
+        storage: &FjallStorage,
+        vid: &VolumeId,
+    ) -> Result<Option<Self>, FjallStorageErr> {
+        let reader = storage.read();
+        let volume = reader.volume(vid)?;
+        Ok(reader.latest_commit(&volume.local)?.map(|commit| {
+            Self::new(
+                EventKind::Local,
+                vid.clone(),
+                commit.lsn,
+                commit.page_count,
+                None,
+            )
+        }))
+    }
+
+    /// Builds a record of the latest commit in the Volume's remote Log, which
+    /// is the commit made by the most recent successful push.
+    pub(crate) fn latest_push(
+        storage: &FjallStorage,
+        vid: &VolumeId,
+    ) -> Result<Option<Self>, FjallStorageErr> {
+        let reader = storage.read();
+        let volume = reader.volume(vid)?;
+        Ok(reader.latest_commit(&volume.remote)?.map(|commit| {
+            Self::new(
+                EventKind::Push,
+                vid.clone(),
+                commit.lsn,
+                commit.page_count,
+                commit.commit_hash,
+            )
+        }))
+    }
+}
+
+impl Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventKind::Local => write!(f, "local"),
+            EventKind::Push => write!(f, "push"),
+        }
+    }
+}
+
+impl Display for EventRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t",
+            self.timestamp, self.kind, self.vid, self.lsn, self.page_count
+        )?;
+        match &self.hash {
+            Some(hash) => write!(f, "{hash}"),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+impl FromStr for EventRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = s.split('\t').collect();
+        let [timestamp, kind, vid, lsn, page_count, hash] = fields[..] else {
+            return Err(format!("expected 6 fields, found {}", fields.len()));
+        };
+        Ok(Self {
+            timestamp: timestamp
+                .parse()
+                .map_err(|err| format!("timestamp: {err}"))?,
+            kind: match kind {
+                "local" => EventKind::Local,
+                "push" => EventKind::Push,
+                _ => return Err(format!("unknown kind `{kind}`")),
+            },
+            vid: vid.parse().map_err(|err| format!("vid: {err}"))?,
+            lsn: lsn.parse().map_err(|err| format!("lsn: {err}"))?,
+            page_count: page_count
+                .parse::<u32>()
+                .map_err(|err| format!("page count: {err}"))?
+                .into(),
+            hash: match hash {
+                "-" => None,
+                hash => Some(hash.parse().map_err(|err| format!("hash: {err}"))?),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_log_survives_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.log");
+        let vid = VolumeId::random();
+        let records = [
+            EventRecord::new(EventKind::Local, vid.clone(), LSN::FIRST, 1.into(), None),
+            EventRecord::new(
+                EventKind::Push,
+                vid.clone(),
+                LSN::FIRST,
+                1.into(),
+                Some(CommitHash::testonly_random()),
+            ),
+        ];
+
+        let mut log = EventLog::open(&path, EventLogDurability::Strict).unwrap();
+        for record in &records {
+            log.append(record).unwrap();
+        }
+        drop(log);
+        assert_eq!(read_event_log(&path).unwrap(), records);
+
+        // simulate a crash partway through appending a record
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"1234\tlocal\tGonv").unwrap();
+        drop(file);
+        assert_eq!(read_event_log(&path).unwrap(), records);
+
+        // reopening discards the partial record and appends after the
+        // complete ones
+        let mut log = EventLog::open(&path, EventLogDurability::Relaxed).unwrap();
+        let next = EventRecord::new(EventKind::Local, vid, LSN::new(2), 2.into(), None);
+        log.append(&next).unwrap();
+        drop(log);
+        let mut expected = records.to_vec();
+        expected.push(next);
+        assert_eq!(read_event_log(&path).unwrap(), expected);
+    }
+}
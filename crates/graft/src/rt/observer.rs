@@ -0,0 +1,148 @@
+use std::{fmt::Debug, sync::Arc};
+
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::Notify;
+
+use crate::{
+    GraftErr, LogicalErr,
+    core::{VolumeId, lsn::LSN},
+    local::fjall_storage::FjallStorage,
+    rt::event_log::{EventLog, EventRecord},
+};
+
+/// A significant state transition within the Runtime, delivered to the
+/// observer installed with `Runtime::set_observer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeEvent {
+    /// A Volume was opened or created
+    VolumeOpened { vid: VolumeId },
+
+    /// A local commit was made to a Volume
+    LocalCommit {
+        vid: VolumeId,
+        lsn: LSN,
+        pages: usize,
+    },
+
+    /// Pulling a Volume attached it to a newer commit in its remote Log
+    RemoteCommitReceived { vid: VolumeId, lsn: LSN },
+
+    /// A push of the Volume's local changes started, including pushes made
+    /// by autosync
+    PushStarted { vid: VolumeId },
+
+    /// A push of the Volume's local changes completed successfully
+    PushCompleted { vid: VolumeId },
+
+    /// A commit, push, or pull failed because the Volume conflicts with a
+    /// concurrent change
+    ConflictDetected { vid: VolumeId },
+}
+
+type ObserverFn = dyn Fn(&RuntimeEvent) + Send + Sync;
+
+/// Delivers `RuntimeEvent`s to the installed observer, if any, and appends
+/// commits to the event log, if one is configured. Clones share the same
+/// observer and event log.
+#[derive(Clone, Default)]
+pub(crate) struct EventSink {
+    observer: Arc<RwLock<Option<Arc<ObserverFn>>>>,
+    log: Arc<Mutex<Option<EventLog>>>,
+    /// wakes tasks waiting for any Volume to receive a remote commit
+    remote_commits: Arc<Notify>,
+}
+
+impl EventSink {
+    pub fn set(&self, observer: Option<Arc<ObserverFn>>) {
+        *self.observer.write() = observer;
+    }
+
+    /// Delivers the event built by `event`, which is only called if an
+    /// observer is installed.
+    pub fn emit(&self, event: impl FnOnce() -> RuntimeEvent) {
+        // release the lock before calling the observer, which may replace
+        // itself
+        let observer = self.observer.read().clone();
+        if let Some(observer) = observer {
+            observer(&event());
+        }
+    }
+
+    /// Emits `RuntimeEvent::RemoteCommitReceived` and wakes every task
+    /// waiting for a remote commit.
+    pub fn remote_commit_received(&self, vid: &VolumeId, lsn: LSN) {
+        self.emit(|| RuntimeEvent::RemoteCommitReceived { vid: vid.clone(), lsn });
+        self.remote_commits.notify_waiters();
+    }
+
+    /// Returns the signal notified whenever a Volume receives a remote
+    /// commit. Waiters must check whether their Volume changed, as the signal
+    /// is shared by every Volume.
+    pub fn remote_commits(&self) -> Arc<Notify> {
+        self.remote_commits.clone()
+    }
+
+    pub fn set_log(&self, log: Option<EventLog>) {
+        *self.log.lock() = log;
+    }
+
+    /// Appends the record built by `record` to the event log, which is only
+    /// called if an event log is configured. Failing to write the event log
+    /// doesn't fail the commit, which has already been made.
+    pub fn log(&self, record: impl FnOnce() -> Option<EventRecord>) {
+        let mut log = self.log.lock();
+        if let Some(log) = log.as_mut()
+            && let Some(record) = record()
+            && let Err(err) = log.append(&record)
+        {
+            tracing::warn!(vid = ?record.vid, "failed to append to the event log: {err}");
+        }
+    }
+
+    /// Appends the commit made by the Volume's most recent successful push to
+    /// the event log.
+    pub fn log_push(&self, storage: &FjallStorage, vid: &VolumeId) {
+        self.log(|| match EventRecord::latest_push(storage, vid) {
+            Ok(record) => record,
+            Err(err) => {
+                tracing::warn!(
+                    ?vid,
+                    "failed to load pushed commit for the event log: {err}"
+                );
+                None
+            }
+        })
+    }
+
+    /// Appends the Volume's latest local commit to the event log, for local
+    /// commits made outside of a `VolumeWriter` such as rebases and forks.
+    pub fn log_local(&self, storage: &FjallStorage, vid: &VolumeId) {
+        self.log(|| match EventRecord::latest_local(storage, vid) {
+            Ok(record) => record,
+            Err(err) => {
+                tracing::warn!(?vid, "failed to load local commit for the event log: {err}");
+                None
+            }
+        })
+    }
+
+    /// Emits `RuntimeEvent::ConflictDetected` if `result` failed due to a
+    /// conflict on the Volume.
+    pub fn emit_conflict<T>(&self, vid: &VolumeId, result: &Result<T, GraftErr>) {
+        if let Err(GraftErr::Logical(
+            LogicalErr::VolumeDiverged(_) | LogicalErr::VolumeConcurrentWrite(_),
+        )) = result
+        {
+            self.emit(|| RuntimeEvent::ConflictDetected { vid: vid.clone() });
+        }
+    }
+}
+
+impl Debug for EventSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventSink")
+            .field("observer", &self.observer.read().is_some())
+            .field("log", &self.log.lock().is_some())
+            .finish()
+    }
+}
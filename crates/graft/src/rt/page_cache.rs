@@ -0,0 +1,143 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    num::NonZero,
+};
+
+use crate::core::{PageIdx, SegmentId, VolumeId, page::Page};
+
+/// A bounded cache of decoded pages, shared by every connection reading
+/// through the runtime.
+///
+/// Each entry remembers the Segment the page was read from. A lookup only
+/// hits if the reader's snapshot resolves the page to the same Segment, so a
+/// reader never observes a page from a different snapshot. Entries are
+/// evicted in insertion order once the cache is full.
+#[derive(Debug)]
+pub struct PageCache {
+    capacity: NonZero<usize>,
+    entries: HashMap<(VolumeId, PageIdx), (SegmentId, Page)>,
+    /// keys in insertion order; may contain keys which were since
+    /// invalidated
+    order: VecDeque<(VolumeId, PageIdx)>,
+    hits: u64,
+    misses: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageCacheStats {
+    /// the maximum number of cached pages
+    pub capacity: usize,
+    /// the number of cached pages
+    pub pages: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl PageCache {
+    pub fn new(capacity: NonZero<usize>) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the cached page if it was read from the Segment `sid`.
+    pub fn get(&mut self, vid: &VolumeId, pageidx: PageIdx, sid: &SegmentId) -> Option<Page> {
+        match self.entries.get(&(vid.clone(), pageidx)) {
+            Some((cached_sid, page)) if cached_sid == sid => {
+                self.hits += 1;
+                Some(page.clone())
+            }
+            _ => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, vid: &VolumeId, pageidx: PageIdx, sid: SegmentId, page: Page) {
+        let key = (vid.clone(), pageidx);
+        if self.entries.insert(key.clone(), (sid, page)).is_none() {
+            self.order.push_back(key);
+        }
+        while self.entries.len() > self.capacity.get() {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    /// Removes the cached pages which were changed by a commit to the Volume.
+    pub fn invalidate(&mut self, vid: &VolumeId, pages: impl IntoIterator<Item = PageIdx>) {
+        for pageidx in pages {
+            self.entries.remove(&(vid.clone(), pageidx));
+        }
+        // drop invalidated keys once they dominate the queue
+        if self.order.len() > self.entries.len() * 2 {
+            self.order.retain(|key| self.entries.contains_key(key));
+        }
+    }
+
+    /// Removes every cached page of the Volume.
+    pub fn invalidate_volume(&mut self, vid: &VolumeId) {
+        self.entries.retain(|(cached_vid, _), _| cached_vid != vid);
+        self.order.retain(|(cached_vid, _)| cached_vid != vid);
+    }
+
+    pub fn stats(&self) -> PageCacheStats {
+        PageCacheStats {
+            capacity: self.capacity.get(),
+            pages: self.entries.len(),
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pageidx;
+
+    use super::*;
+
+    #[test]
+    fn test_page_cache() {
+        let mut cache = PageCache::new(NonZero::new(2).unwrap());
+        let vid = VolumeId::random();
+        let sid = SegmentId::random();
+
+        cache.insert(&vid, pageidx!(1), sid.clone(), Page::test_filled(1));
+        assert_eq!(
+            cache.get(&vid, pageidx!(1), &sid),
+            Some(Page::test_filled(1))
+        );
+        // a page from another segment is a different version of the page
+        assert_eq!(cache.get(&vid, pageidx!(1), &SegmentId::random()), None);
+
+        // the oldest page is evicted once the cache is full
+        cache.insert(&vid, pageidx!(2), sid.clone(), Page::test_filled(2));
+        cache.insert(&vid, pageidx!(3), sid.clone(), Page::test_filled(3));
+        assert_eq!(cache.get(&vid, pageidx!(1), &sid), None);
+        assert_eq!(cache.stats().pages, 2);
+
+        cache.invalidate(&vid, [pageidx!(2)]);
+        assert_eq!(cache.get(&vid, pageidx!(2), &sid), None);
+        assert_eq!(
+            cache.get(&vid, pageidx!(3), &sid),
+            Some(Page::test_filled(3))
+        );
+        assert_eq!(
+            cache.stats(),
+            PageCacheStats {
+                capacity: 2,
+                pages: 1,
+                hits: 2,
+                misses: 3
+            }
+        );
+    }
+}
@@ -1,30 +1,78 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    num::NonZero,
+    ops::RangeInclusive,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU8, Ordering},
+    },
+    time::Duration,
+};
 
 use crate::core::{
-    LogId, PageCount, PageIdx, VolumeId, checksum::Checksum, commit::Commit, logref::LogRef,
-    lsn::LSN, page::Page, pageset::PageSet,
+    LogId, PageCount, PageIdx, SegmentId, VolumeId,
+    checksum::Checksum,
+    commit::Commit,
+    logref::LogRef,
+    lsn::{LSN, LSNRangeExt},
+    page::{PAGESIZE, Page},
+    pageidx::PageIdxRangeExt,
+    pageset::PageSet,
 };
 use bytestring::ByteString;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
 use tracing::Instrument;
 use tryiter::TryIteratorExt;
 
 use crate::{
-    GraftErr,
-    remote::Remote,
+    GraftErr, LogicalErr,
+    remote::{Remote, RemoteConfig, RemoteErr},
     rt::{
         action::{Action, FetchLog, FetchSegment, HydrateSnapshot, RemoteCommit},
+        event_log::{EventKind, EventLog, EventRecord},
+        observer::{EventSink, RuntimeEvent},
+        page_cache::{PageCache, PageCacheStats},
+        sync_health::{SyncHealthTracker, VolumeSyncHealth},
         task::{autosync::AutosyncTask, supervise},
+        volume_metrics::{VolumeMetrics, VolumeMetricsRegistry},
+        write_amp::{WriteAmpStats, WriteAmpTracker},
     },
     snapshot::Snapshot,
-    volume::{Volume, VolumeStatus},
-    volume_reader::VolumeReader,
-    volume_writer::VolumeWriter,
+    volume::{
+        AuditReport, ConflictPolicy, ConflictReport, OpenPolicy, PageCountCheck, Volume,
+        VolumeConfigDump, VolumeDiff, VolumeStatus,
+    },
+    volume_reader::{VolumeRead, VolumeReader},
+    volume_writer::{VolumeWrite, VolumeWriter},
 };
 
-use crate::local::fjall_storage::FjallStorage;
+use crate::local::{
+    fjall_storage::{
+        FjallStorage, FjallStorageErr, GcReport, LockContention, ReadGuard, StorageStats,
+    },
+    mirror_storage::{MirrorMode, MirrorStorage},
+};
 
 type Result<T> = std::result::Result<T, GraftErr>;
 
+/// How often `Runtime::wait_for_remote_lsn` pulls from the remote if no other
+/// pull receives a remote commit
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often `Runtime::wait_for_sync_capacity` checks whether autosync has
+/// pushed enough pending commits
+const SYNC_CAPACITY_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The longest `Runtime::wait_for_sync_capacity` waits, whatever the timeout
+const MAX_SYNC_CAPACITY_WAIT: Duration = Duration::from_secs(60);
+
+/// The number of recent stalled syncs after which
+/// `Runtime::wait_for_sync_capacity` stops waiting for autosync
+const SYNC_CAPACITY_MAX_STALLS: usize = 2;
+
 #[derive(Clone, Debug)]
 pub struct Runtime {
     inner: Arc<RuntimeInner>,
@@ -35,6 +83,70 @@ struct RuntimeInner {
     tokio: tokio::runtime::Handle,
     storage: Arc<FjallStorage>,
     remote: Arc<Remote>,
+    /// shared with the autosync task; the autosync interval, if autosync is
+    /// enabled
+    autosync: Arc<Mutex<Option<Duration>>>,
+    /// whether the autosync task has been spawned
+    autosync_running: AtomicBool,
+    write_amp: Mutex<WriteAmpTracker>,
+    /// wakes the autosync task, if it's running
+    autosync_signal: Arc<Notify>,
+    /// shared with the autosync task to detect and pause sync loops
+    sync_health: Arc<Mutex<SyncHealthTracker>>,
+    /// shared with the autosync task to record sync failures
+    metrics: Arc<Mutex<VolumeMetricsRegistry>>,
+    /// shared with the autosync task; delivers events to the observer
+    events: EventSink,
+    exists: Mutex<ExistsCache>,
+    /// bounds how long a read may wait on fetching a page from the remote
+    fetch_timeout: Mutex<Option<Duration>>,
+    /// shared with the autosync task; bounds the number of commits fetched
+    /// from the remote in one batch
+    max_commits_per_fetch: Arc<Mutex<Option<NonZero<usize>>>>,
+    /// how many times hydrating a snapshot retries after a failed fetch
+    hydrate_retries: Mutex<u32>,
+    corruption_policy: Mutex<CorruptionPolicy>,
+    /// checkpoint a Volume once it accumulates more than this many commits
+    /// since its last checkpoint
+    compact_after_commits: Mutex<Option<NonZero<u64>>>,
+    /// for each Volume whose automatic checkpoint was skipped, the number of
+    /// commits since its last checkpoint at the time
+    skipped_checkpoints: Mutex<HashMap<VolumeId, u64>>,
+    /// shared with the autosync task; verify pushed commits against the
+    /// remote before clearing local commits
+    verify_push: Arc<AtomicBool>,
+    /// the byte which fills never written pages of Volumes without their own
+    /// fill byte
+    default_fill_byte: AtomicU8,
+    /// decoded pages shared by every reader, if enabled
+    page_cache: Mutex<Option<PageCache>>,
+    /// the maximum page count of Volumes without their own limit
+    default_max_pages: Mutex<Option<PageCount>>,
+    /// mirrors local commits to a secondary store, if enabled
+    mirror: Mutex<Option<Arc<MirrorStorage>>>,
+}
+
+/// Controls how reading a page handles a corrupt copy of the page in the
+/// local cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorruptionPolicy {
+    /// Fail the read.
+    #[default]
+    Fail,
+
+    /// Remove the corrupt page from the local cache and fetch it again from
+    /// the remote. The read fails if the remote can't provide the page.
+    Refetch,
+}
+
+/// Remembers Volumes and tags which are known to exist. Once created, a
+/// Volume or tag only stops existing when it's explicitly deleted, which
+/// invalidates the cached entry. Only positive results are cached.
+#[derive(Debug, Default)]
+struct ExistsCache {
+    volumes: HashSet<VolumeId>,
+    tags: HashSet<ByteString>,
 }
 
 impl Runtime {
@@ -45,35 +157,608 @@ impl Runtime {
         storage: Arc<FjallStorage>,
         autosync: Option<Duration>,
     ) -> Runtime {
+        assert!(
+            autosync != Some(Duration::ZERO),
+            "autosync interval must be non-zero"
+        );
+        let runtime = Runtime {
+            inner: Arc::new(RuntimeInner {
+                tokio: tokio_rt,
+                storage,
+                remote,
+                autosync: Arc::new(Mutex::new(autosync)),
+                autosync_running: AtomicBool::new(false),
+                write_amp: Default::default(),
+                autosync_signal: Arc::new(Notify::new()),
+                sync_health: Default::default(),
+                metrics: Default::default(),
+                events: EventSink::default(),
+                exists: Default::default(),
+                fetch_timeout: Default::default(),
+                max_commits_per_fetch: Default::default(),
+                hydrate_retries: Default::default(),
+                corruption_policy: Default::default(),
+                compact_after_commits: Default::default(),
+                skipped_checkpoints: Default::default(),
+                verify_push: Default::default(),
+                default_fill_byte: AtomicU8::new(0),
+                page_cache: Default::default(),
+                default_max_pages: Default::default(),
+                mirror: Default::default(),
+            }),
+        };
+
         // spin up background tasks as needed
-        if let Some(interval) = autosync {
-            let _guard = tokio_rt.enter();
-            let mut ticker = tokio::time::interval(interval);
-            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
-            tokio_rt.spawn(supervise(
-                storage.clone(),
-                remote.clone(),
-                AutosyncTask::new(ticker),
-            ));
+        if autosync.is_some() {
+            runtime.spawn_autosync();
         }
-        Runtime {
-            inner: Arc::new(RuntimeInner { tokio: tokio_rt, storage, remote }),
+        runtime
+    }
+
+    /// spawns the autosync task, unless it's already running. the task
+    /// keeps running if autosync is later disabled, idling until it's
+    /// enabled again.
+    fn spawn_autosync(&self) {
+        if self.inner.autosync_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let inner = &self.inner;
+        inner.tokio.spawn(supervise(
+            inner.storage.clone(),
+            inner.remote.clone(),
+            AutosyncTask::new(
+                inner.autosync.clone(),
+                inner.autosync_signal.clone(),
+                inner.sync_health.clone(),
+                inner.verify_push.clone(),
+                inner.max_commits_per_fetch.clone(),
+                inner.metrics.clone(),
+                inner.events.clone(),
+            ),
+        ));
+    }
+
+    /// Signals that the network has become available. If autosync is
+    /// enabled, this triggers a sync immediately and restarts the autosync
+    /// interval.
+    ///
+    /// Together with `notify_idle`, this supports opportunistic sync on
+    /// devices where waking the radio is costly: configure a long autosync
+    /// interval as a fallback, and signal when syncing is cheap.
+    pub fn notify_network_available(&self) {
+        tracing::trace!("network available");
+        self.inner.autosync_signal.notify_one();
+    }
+
+    /// Signals that the application is idle. If autosync is enabled, this
+    /// triggers a sync immediately and restarts the autosync interval.
+    pub fn notify_idle(&self) {
+        tracing::trace!("application idle");
+        self.inner.autosync_signal.notify_one();
+    }
+
+    /// reports whether autosync is making progress syncing the Volume, and
+    /// whether it has been paused due to syncing repeatedly without progress
+    pub fn sync_health(&self, vid: &VolumeId) -> VolumeSyncHealth {
+        let now = tokio::time::Instant::now();
+        self.inner.sync_health.lock().health(vid, now)
+    }
+
+    /// resumes autosync for a Volume which was paused due to syncing
+    /// repeatedly without progress
+    pub fn resume_autosync(&self, vid: &VolumeId) {
+        self.inner.sync_health.lock().resume(vid);
+        self.inner.autosync_signal.notify_one();
+    }
+
+    /// returns the directory containing local storage
+    pub fn data_dir(&self) -> &Path {
+        self.storage().path()
+    }
+
+    /// returns the configuration of the remote this runtime syncs with
+    pub fn remote_config(&self) -> &RemoteConfig {
+        self.inner.remote.config()
+    }
+
+    /// returns the autosync interval, or `None` if autosync is disabled
+    pub fn autosync_interval(&self) -> Option<Duration> {
+        *self.inner.autosync.lock()
+    }
+
+    /// changes the autosync interval, or disables autosync with `None`,
+    /// without restarting the runtime. enabling autosync or changing the
+    /// interval triggers a sync and restarts the interval from now.
+    ///
+    /// Panics if the interval is zero.
+    pub fn set_autosync_interval(&self, interval: Option<Duration>) {
+        assert!(
+            interval != Some(Duration::ZERO),
+            "autosync interval must be non-zero"
+        );
+        *self.inner.autosync.lock() = interval;
+        if interval.is_some() {
+            self.spawn_autosync();
         }
+        self.inner.autosync_signal.notify_one();
+    }
+
+    /// Corrupts the locally cached copy of a page, see `CorruptionPolicy`
+    #[cfg(any(test, feature = "testutil"))]
+    pub fn testonly_corrupt_page(&self, sid: SegmentId, pageidx: PageIdx) -> Result<()> {
+        Ok(self.storage().testonly_corrupt_page(sid, pageidx)?)
+    }
+
+    /// Pushes an empty commit to the Volume's remote Log as if another client
+    /// had committed, and then fetches the remote Log. The Volume is then
+    /// behind the remote, or diverged if it has local changes. Returns the
+    /// LSN of the simulated commit.
+    #[cfg(any(test, feature = "testutil"))]
+    pub fn testonly_simulate_remote_commit(&self, vid: &VolumeId) -> Result<LSN> {
+        let remote = self.storage().read().volume(vid)?.remote;
+        self.fetch_log(remote.clone(), None)?;
+
+        // commit on top of the latest remote commit, without changing pages
+        let latest = self.storage().read().latest_commit(&remote)?;
+        let (lsn, page_count) = match latest {
+            Some(latest) => (latest.lsn.next(), latest.page_count),
+            None => (LSN::FIRST, PageCount::ZERO),
+        };
+        let commit = Commit::new(remote.clone(), lsn, page_count);
+        self.inner
+            .tokio
+            .block_on(self.inner.remote.put_commit(&commit))?;
+        tracing::debug!(?vid, log = %remote, %lsn, "simulated remote commit");
+
+        self.fetch_log(remote, None)?;
+        Ok(lsn)
     }
 
     pub(crate) fn storage(&self) -> &FjallStorage {
         &self.inner.storage
     }
 
-    pub(crate) fn read_page(&self, snapshot: &Snapshot, pageidx: PageIdx) -> Result<Page> {
-        let reader = self.storage().read();
+    /// returns the indexes of the pages about to be committed, which
+    /// `record_commit` evicts from the page cache. returns `None` without
+    /// collecting them if the page cache is disabled.
+    pub(crate) fn pages_to_evict(&self, pages: &BTreeMap<PageIdx, Page>) -> Option<Vec<PageIdx>> {
+        self.inner
+            .page_cache
+            .lock()
+            .is_some()
+            .then(|| pages.keys().copied().collect())
+    }
+
+    /// records a local commit of `pages` pages for write amplification
+    /// tracking and the Volume's metrics, evicts the pages returned by
+    /// `pages_to_evict` from the page cache, notifies the observer, and
+    /// appends the commit to the event log
+    pub(crate) fn record_commit(
+        &self,
+        vid: &VolumeId,
+        snapshot: &Snapshot,
+        pages: usize,
+        evict: Option<Vec<PageIdx>>,
+    ) {
+        if let Some(evict) = evict
+            && let Some(cache) = self.inner.page_cache.lock().as_mut()
+        {
+            cache.invalidate(vid, evict);
+        }
+        self.inner.write_amp.lock().record_commit(vid, pages);
+        self.inner.metrics.lock().record_commit(vid, pages);
+        if let Some((_, lsn)) = snapshot.head() {
+            self.inner
+                .events
+                .emit(|| RuntimeEvent::LocalCommit { vid: vid.clone(), lsn, pages });
+            self.inner.events.log(|| {
+                Some(EventRecord::new(
+                    EventKind::Local,
+                    vid.clone(),
+                    lsn,
+                    snapshot.page_count,
+                    None,
+                ))
+            });
+        }
+    }
+
+    /// notifies the observer if a commit failed due to a conflict
+    pub(crate) fn record_commit_conflict<T>(&self, vid: &VolumeId, result: &Result<T>) {
+        self.inner.events.emit_conflict(vid, result);
+    }
+
+    /// installs a callback which is invoked on every significant state
+    /// transition in the runtime, replacing any previous observer. The
+    /// observer is called synchronously on the thread which caused the
+    /// transition, so it should return quickly.
+    pub fn set_observer(&self, observer: impl Fn(&RuntimeEvent) + Send + Sync + 'static) {
+        self.inner.events.set(Some(Arc::new(observer)));
+    }
+
+    /// removes the observer installed by `set_observer`
+    pub fn clear_observer(&self) {
+        self.inner.events.set(None);
+    }
+
+    /// configures the event log which records every local commit and every
+    /// commit pushed to the remote, including pushes made by autosync.
+    /// `None` disables the event log.
+    pub fn set_event_log(&self, log: Option<EventLog>) {
+        self.inner.events.set_log(log);
+    }
+
+    /// configures the average pages per commit below which the runtime
+    /// warns about write amplification. `None` disables the warning.
+    pub fn set_write_amp_threshold(&self, threshold: Option<f64>) {
+        self.inner.write_amp.lock().set_threshold(threshold);
+    }
+
+    /// configures a secondary store which receives a copy of every local
+    /// commit, typically located on a different disk. `None` disables
+    /// mirroring, which is the default.
+    pub fn set_mirror(&self, secondary: Option<Arc<FjallStorage>>, mode: MirrorMode) {
+        *self.inner.mirror.lock() = secondary.map(|secondary| {
+            Arc::new(MirrorStorage::new(
+                self.inner.storage.clone(),
+                secondary,
+                mode,
+            ))
+        });
+    }
+
+    pub(crate) fn mirror(&self) -> Option<Arc<MirrorStorage>> {
+        self.inner.mirror.lock().clone()
+    }
+
+    /// returns the number of local commits which failed to reach the
+    /// secondary store, or `None` if mirroring is disabled
+    pub fn mirror_diverged_commits(&self) -> Option<u64> {
+        self.mirror().map(|mirror| mirror.diverged_commits())
+    }
+
+    /// configures the byte which fills pages that are within a Volume's page
+    /// count but were never written, such as the pages skipped when a Volume
+    /// is extended. Applies to Volumes without their own fill byte, see
+    /// `set_fill_byte`. Defaults to 0.
+    pub fn set_default_fill_byte(&self, fill: u8) {
+        self.inner.default_fill_byte.store(fill, Ordering::Relaxed);
+    }
+
+    /// configures the Volume's fill byte, overriding the default set by
+    /// `set_default_fill_byte`. `None` removes the override. The fill byte is
+    /// persisted with the Volume.
+    pub fn set_fill_byte(&self, vid: &VolumeId, fill: Option<u8>) -> Result<()> {
+        self.storage()
+            .read_write()
+            .update_volume_settings(vid, |settings| settings.fill_byte = fill)?;
+        Ok(())
+    }
+
+    /// returns the byte which fills the Volume's never written pages
+    pub fn fill_byte(&self, vid: &VolumeId) -> Result<u8> {
+        let volume = self.storage().read().volume(vid)?;
+        Ok(self.fill_byte_for(&volume))
+    }
+
+    fn fill_byte_for(&self, volume: &Volume) -> u8 {
+        volume
+            .settings()
+            .fill_byte
+            .unwrap_or_else(|| self.inner.default_fill_byte.load(Ordering::Relaxed))
+    }
+
+    /// returns the page which the Volume's never written pages read as
+    pub(crate) fn fill_page(&self, vid: &VolumeId) -> Result<Page> {
+        let volume = self.storage().read().volume(vid)?;
+        Ok(self.fill_page_for(&volume))
+    }
+
+    fn fill_page_for(&self, volume: &Volume) -> Page {
+        match self.fill_byte_for(volume) {
+            0 => Page::EMPTY,
+            fill => Page::from(&[fill; PAGESIZE.as_usize()]),
+        }
+    }
+
+    /// configures how many times `snapshot_hydrate` retries fetching the
+    /// remaining missing pages after a fetch fails, for example due to a
+    /// network interruption. The delay before each retry doubles, up to 10
+    /// seconds. Pages fetched before the failure are kept either way.
+    /// Defaults to 0.
+    pub fn set_hydrate_retries(&self, retries: u32) {
+        *self.inner.hydrate_retries.lock() = retries;
+    }
+
+    /// configures the page cache, which keeps up to `capacity` decoded pages
+    /// in memory and shares them between every reader of this runtime.
+    /// Commits evict the pages they write. Changing the capacity drops the
+    /// cached pages and resets the statistics. `None` disables the cache,
+    /// which is the default.
+    pub fn set_page_cache_capacity(&self, capacity: Option<NonZero<usize>>) {
+        *self.inner.page_cache.lock() = capacity.map(PageCache::new);
+    }
+
+    /// returns the page cache's hit and miss counts, or `None` if the page
+    /// cache is disabled
+    pub fn page_cache_stats(&self) -> Option<PageCacheStats> {
+        self.inner.page_cache.lock().as_ref().map(PageCache::stats)
+    }
+
+    /// configures how long reading a page may wait on the remote before
+    /// failing with `RemoteErr::Timeout`. `None` waits indefinitely.
+    pub fn set_fetch_timeout(&self, timeout: Option<Duration>) {
+        *self.inner.fetch_timeout.lock() = timeout;
+    }
+
+    /// configures the maximum number of commits fetched from the remote in a
+    /// single batch. Each batch is written to storage before the next is
+    /// requested, which bounds memory when catching up a Volume that is far
+    /// behind. Applies to every fetch, including those made by autosync and
+    /// while pushing. `None` fetches all available commits in a single batch.
+    pub fn set_max_commits_per_fetch(&self, max_commits: Option<NonZero<usize>>) {
+        *self.inner.max_commits_per_fetch.lock() = max_commits;
+    }
+
+    /// configures how reads handle corrupt pages in the local cache
+    pub fn set_corruption_policy(&self, policy: CorruptionPolicy) {
+        *self.inner.corruption_policy.lock() = policy;
+    }
+
+    /// configures whether pushes fetch each pushed commit back from the
+    /// remote and compare its hash before clearing the local commits. On
+    /// mismatch the push fails with `LogicalErr::PushVerificationFailed` and
+    /// the local commits are retained.
+    pub fn set_verify_push(&self, verify: bool) {
+        self.inner.verify_push.store(verify, Ordering::Relaxed);
+    }
+
+    /// configures how pushing the Volume, including by autosync, handles a
+    /// remote Log with newer commits. Volumes default to
+    /// `ConflictPolicy::Strict`. The policy is persisted with the Volume.
+    pub fn set_conflict_policy(&self, vid: &VolumeId, policy: ConflictPolicy) -> Result<()> {
+        self.storage()
+            .read_write()
+            .update_volume_settings(vid, |settings| settings.conflict_policy = policy)?;
+        Ok(())
+    }
+
+    pub fn conflict_policy(&self, vid: &VolumeId) -> Result<ConflictPolicy> {
+        Ok(self.volume_get(vid)?.settings.conflict_policy)
+    }
+
+    /// configures the maximum page count of Volumes without their own limit.
+    /// Writes which would grow a Volume beyond its limit fail with
+    /// `LogicalErr::VolumeTooLarge`. `None` only limits Volumes to
+    /// `PageCount::MAX`, which is the default.
+    pub fn set_default_max_pages(&self, max_pages: Option<PageCount>) {
+        *self.inner.default_max_pages.lock() = max_pages;
+    }
+
+    /// configures the maximum page count of the Volume, overriding the
+    /// default set by `set_default_max_pages`. `None` removes the override.
+    /// The limit is persisted with the Volume, and applies to writers created
+    /// after it's set.
+    pub fn set_max_pages(&self, vid: &VolumeId, max_pages: Option<PageCount>) -> Result<()> {
+        self.storage()
+            .read_write()
+            .update_volume_settings(vid, |settings| settings.max_pages = max_pages)?;
+        Ok(())
+    }
+
+    /// returns the maximum page count of the Volume, if it's limited
+    pub fn max_pages(&self, vid: &VolumeId) -> Result<Option<PageCount>> {
+        let volume = self.volume_get(vid)?;
+        Ok(self.max_pages_for(&volume))
+    }
+
+    fn max_pages_for(&self, volume: &Volume) -> Option<PageCount> {
+        volume
+            .settings()
+            .max_pages
+            .or(*self.inner.default_max_pages.lock())
+    }
+
+    /// configures the number of commits a Volume may accumulate since its
+    /// last checkpoint. The next local commit after that rewrites every page
+    /// of the Volume, making it a checkpoint, as long as every page is
+    /// available locally. Each checkpoint contains a full copy of the Volume,
+    /// which is uploaded when it's pushed. `None` disables automatic
+    /// checkpoints, which is the default.
+    pub fn set_compact_after_commits(&self, commits: Option<NonZero<u64>>) {
+        *self.inner.compact_after_commits.lock() = commits;
+    }
+
+    pub(crate) fn compact_after_commits(&self) -> Option<NonZero<u64>> {
+        *self.inner.compact_after_commits.lock()
+    }
+
+    /// returns whether a Volume with `commits` since its last checkpoint is
+    /// due for an automatic checkpoint. once a checkpoint is skipped, it's
+    /// only retried after another `threshold` commits.
+    pub(crate) fn checkpoint_due(
+        &self,
+        vid: &VolumeId,
+        commits: u64,
+        threshold: NonZero<u64>,
+    ) -> bool {
+        let mut skipped = self.inner.skipped_checkpoints.lock();
+        let since = match skipped.get(vid) {
+            Some(&at) if at <= commits => commits - at,
+            // the Volume was checkpointed since the skip
+            Some(_) => {
+                skipped.remove(vid);
+                commits
+            }
+            None => commits,
+        };
+        since >= threshold.get()
+    }
+
+    /// records that the Volume's automatic checkpoint was skipped with
+    /// `commits` since its last checkpoint, or clears the record if `None`
+    pub(crate) fn record_checkpoint_skipped(&self, vid: &VolumeId, commits: Option<u64>) {
+        let mut skipped = self.inner.skipped_checkpoints.lock();
+        match commits {
+            Some(commits) => skipped.insert(vid.clone(), commits),
+            None => skipped.remove(vid),
+        };
+    }
+
+    /// returns statistics about the size of recent local commits to the
+    /// Volume
+    pub fn write_amp_stats(&self, vid: &VolumeId) -> WriteAmpStats {
+        self.inner.write_amp.lock().stats(vid)
+    }
+
+    /// returns the Volume's metrics since the runtime started or the metrics
+    /// were last reset
+    pub fn volume_metrics(&self, vid: &VolumeId) -> VolumeMetrics {
+        self.inner.metrics.lock().get(vid)
+    }
+
+    /// zeroes the Volume's metrics
+    pub fn reset_volume_metrics(&self, vid: &VolumeId) {
+        self.inner.metrics.lock().reset(vid);
+    }
+
+    /// gathers statistics about local storage
+    pub fn storage_stats(&self) -> Result<StorageStats> {
+        Ok(self.storage().stats()?)
+    }
+
+    /// lists the segments with pages in local storage which aren't
+    /// referenced by any commit in a volume's local or remote log, such as
+    /// segments left behind by deleted volumes or interrupted imports.
+    /// segments being pushed, including those of a pending commit, are
+    /// never orphaned.
+    pub fn orphan_segments(&self) -> Result<Vec<SegmentId>> {
+        Ok(self.storage().read().orphan_segments()?)
+    }
+
+    /// removes the pages of every orphaned segment from local storage,
+    /// reporting the removed segments and how many pages and bytes were
+    /// reclaimed.
+    pub fn gc_orphan_segments(&self) -> Result<GcReport> {
+        Ok(self.storage().read_write().gc_orphan_segments()?)
+    }
+
+    /// returns contention on the storage lock which serializes commits and
+    /// other read + write transactions
+    pub fn lock_contention(&self) -> LockContention {
+        self.storage().lock_contention()
+    }
+
+    /// zeroes the storage lock contention counters
+    pub fn reset_lock_contention(&self) {
+        self.storage().reset_lock_contention();
+    }
+
+    pub(crate) fn read_page(
+        &self,
+        vid: &VolumeId,
+        snapshot: &Snapshot,
+        pageidx: PageIdx,
+    ) -> Result<Page> {
+        self.inner.metrics.lock().record_storage_read(vid);
+        let mut reader = self.storage().read();
+        Ok(self
+            .read_page_from(&mut reader, vid, snapshot, pageidx, true)?
+            .expect("BUG: requested page must be read"))
+    }
+
+    /// Reads the first page in `range` followed by as many of the remaining
+    /// pages as are available locally, using a single storage reader. Only
+    /// the first page may be fetched from the remote or added to the page
+    /// cache, so the extra pages never cause additional work.
+    pub(crate) fn read_pages(
+        &self,
+        vid: &VolumeId,
+        snapshot: &Snapshot,
+        range: RangeInclusive<PageIdx>,
+    ) -> Result<Vec<Page>> {
+        self.inner.metrics.lock().record_storage_read(vid);
+        let mut reader = self.storage().read();
+        let mut pages = vec![];
+        for pageidx in range.iter() {
+            let requested = pages.is_empty();
+            match self.read_page_from(&mut reader, vid, snapshot, pageidx, requested)? {
+                Some(page) => pages.push(page),
+                None => break,
+            }
+        }
+        Ok(pages)
+    }
+
+    /// Reads the given pages using a single storage reader without fetching
+    /// or caching any of them. Returns `None` if any page isn't available
+    /// locally.
+    pub(crate) fn read_local_pages(
+        &self,
+        vid: &VolumeId,
+        snapshot: &Snapshot,
+        pageidxs: impl IntoIterator<Item = PageIdx>,
+    ) -> Result<Option<Vec<(PageIdx, Page)>>> {
+        self.inner.metrics.lock().record_storage_read(vid);
+        let mut reader = self.storage().read();
+        let mut pages = vec![];
+        for pageidx in pageidxs {
+            match self.read_page_from(&mut reader, vid, snapshot, pageidx, false)? {
+                Some(page) => pages.push((pageidx, page)),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(pages))
+    }
+
+    /// Reads a page using `reader`, which is replaced with a fresh reader if
+    /// the page had to be fetched from the remote. Pages which weren't
+    /// `requested` are neither fetched nor cached, and read as `None` if
+    /// they aren't available locally.
+    fn read_page_from<'a>(
+        &'a self,
+        reader: &mut ReadGuard<'a>,
+        vid: &VolumeId,
+        snapshot: &Snapshot,
+        pageidx: PageIdx,
+        requested: bool,
+    ) -> Result<Option<Page>> {
         if let Some(commit) = reader.search_page(snapshot, pageidx)? {
             let idx = commit
                 .segment_idx()
                 .expect("BUG: commit claims to contain pageidx");
 
-            if let Some(page) = reader.read_page(idx.sid().clone(), pageidx)? {
-                return Ok(page);
+            // a cached page is only valid if it came from the same segment
+            if let Some(cache) = self.inner.page_cache.lock().as_mut()
+                && let Some(page) = cache.get(vid, pageidx, idx.sid())
+            {
+                return Ok(Some(page));
+            }
+            let cache_page = |page: Page| {
+                if requested && let Some(cache) = self.inner.page_cache.lock().as_mut() {
+                    cache.insert(vid, pageidx, idx.sid().clone(), page.clone());
+                }
+                Some(page)
+            };
+
+            match reader.read_page(idx.sid().clone(), pageidx) {
+                Ok(Some(page)) => return Ok(cache_page(page)),
+                // leave missing and corrupt pages to a regular read
+                Ok(None) | Err(FjallStorageErr::DecodeErr(_)) if !requested => return Ok(None),
+                Ok(None) => {}
+                // pages which haven't been pushed can't be refetched
+                Err(FjallStorageErr::DecodeErr(err))
+                    if *self.inner.corruption_policy.lock() == CorruptionPolicy::Refetch
+                        && idx.frame_for_pageidx(pageidx).is_some() =>
+                {
+                    tracing::warn!(
+                        sid = ?idx.sid(),
+                        ?pageidx,
+                        "corrupt page in local cache, refetching from the remote: {err}"
+                    );
+                    self.storage().remove_page(idx.sid().clone(), pageidx)?;
+                }
+                Err(err) => return Err(err.into()),
             }
 
             // fallthrough to loading the page from the remote
@@ -82,28 +767,44 @@ impl Runtime {
                 .expect("BUG: no frame for pageidx");
 
             // fetch the segment frame containing the page
-            self.run_action(FetchSegment { range })?;
+            let timeout = *self.inner.fetch_timeout.lock();
+            let pages = u64::from(range.pageset.cardinality().to_u32());
+            self.run_action(FetchSegment { range, timeout })?;
+            self.inner.metrics.lock().record_fetch(vid, pages);
 
             // now that we've fetched the segment, read the page again using a
             // fresh storage reader
-            Ok(self
-                .storage()
-                .read()
-                .read_page(idx.sid.clone(), pageidx)?
-                .expect("BUG: page not found after fetching"))
+            *reader = self.storage().read();
+            Ok(cache_page(
+                reader
+                    .read_page(idx.sid.clone(), pageidx)?
+                    .expect("BUG: page not found after fetching"),
+            ))
         } else {
-            Ok(Page::EMPTY)
+            Ok(Some(self.fill_page_for(&reader.volume(vid)?)))
         }
     }
 
     fn run_action<A: Action>(&self, action: A) -> Result<()> {
-        let span = tracing::debug_span!("Action::run", ?action);
+        self.run_action_timeout(action, None)
+    }
+
+    /// runs the action, cancelling it and failing with `RemoteErr::Timeout`
+    /// if it takes longer than `timeout`
+    fn run_action_timeout<A: Action>(&self, action: A, timeout: Option<Duration>) -> Result<()> {
+        let span = tracing::debug_span!("Action::run", ?action, ?timeout);
 
-        self.inner.tokio.block_on(
-            action
-                .run(self.inner.storage.clone(), self.inner.remote.clone())
-                .instrument(span),
-        )
+        let run = action
+            .run(self.inner.storage.clone(), self.inner.remote.clone())
+            .instrument(span);
+        match timeout {
+            Some(timeout) => self
+                .inner
+                .tokio
+                .block_on(tokio::time::timeout(timeout, run))
+                .map_err(|_| RemoteErr::Timeout(timeout))?,
+            None => self.inner.tokio.block_on(run),
+        }
     }
 }
 
@@ -114,7 +815,16 @@ impl Runtime {
     }
 
     pub fn tag_exists(&self, name: &str) -> Result<bool> {
-        Ok(self.storage().read().tag_exists(name)?)
+        // hold the lock while checking storage to avoid racing with tag_delete
+        let mut exists = self.inner.exists.lock();
+        if exists.tags.contains(name) {
+            return Ok(true);
+        }
+        let found = self.storage().read().tag_exists(name)?;
+        if found {
+            exists.tags.insert(name.into());
+        }
+        Ok(found)
     }
 
     pub fn tag_get(&self, tag: &str) -> Result<Option<VolumeId>> {
@@ -126,9 +836,29 @@ impl Runtime {
         Ok(self.storage().read_write().tag_replace(tag, vid)?)
     }
 
+    /// atomically points each tag at the `VolumeId` the other tag points at.
+    /// fails with `LogicalErr::TagNotFound` if either tag doesn't exist.
+    pub fn swap_tags(&self, a: &str, b: &str) -> Result<()> {
+        Ok(self.storage().read_write().tag_swap(a, b)?)
+    }
+
     pub fn tag_delete(&self, tag: &str) -> Result<()> {
+        let mut exists = self.inner.exists.lock();
+        exists.tags.remove(tag);
         Ok(self.storage().tag_delete(tag)?)
     }
+
+    /// returns every tag which points at the volume, in sorted order
+    pub fn tags_for_volume(&self, vid: &VolumeId) -> Result<Vec<ByteString>> {
+        let mut tags = vec![];
+        let mut iter = self.tag_iter();
+        while let Some((tag, tag_vid)) = iter.try_next()? {
+            if &tag_vid == vid {
+                tags.push(tag);
+            }
+        }
+        Ok(tags)
+    }
 }
 
 // volume methods
@@ -138,7 +868,16 @@ impl Runtime {
     }
 
     pub fn volume_exists(&self, vid: &VolumeId) -> Result<bool> {
-        Ok(self.storage().read().volume_exists(vid)?)
+        // hold the lock while checking storage to avoid racing with volume_delete
+        let mut exists = self.inner.exists.lock();
+        if exists.volumes.contains(vid) {
+            return Ok(true);
+        }
+        let found = self.storage().read().volume_exists(vid)?;
+        if found {
+            exists.volumes.insert(vid.clone());
+        }
+        Ok(found)
     }
 
     /// opens a volume. if any id is missing, it will be randomly
@@ -150,20 +889,42 @@ impl Runtime {
         local: Option<LogId>,
         remote: Option<LogId>,
     ) -> Result<Volume> {
-        Ok(self
+        self.volume_open_with_policy(vid, local, remote, OpenPolicy::Strict)
+    }
+
+    /// opens a volume, using the provided `OpenPolicy` to handle the case
+    /// where the volume already exists with a different remote Log.
+    pub fn volume_open_with_policy(
+        &self,
+        vid: Option<VolumeId>,
+        local: Option<LogId>,
+        remote: Option<LogId>,
+        policy: OpenPolicy,
+    ) -> Result<Volume> {
+        let volume = self
             .storage()
             .read_write()
-            .volume_open(vid, local, remote)?)
+            .volume_open(vid, local, remote, policy)?;
+        self.inner
+            .events
+            .emit(|| RuntimeEvent::VolumeOpened { vid: volume.vid.clone() });
+        Ok(volume)
     }
 
     /// creates a new volume by forking an existing logref
     pub fn volume_from_logref(&self, logref: LogRef) -> Result<Option<Volume>> {
-        Ok(self.storage().volume_from_logref(logref)?)
+        let volume = self.storage().volume_from_logref(logref)?;
+        if let Some(volume) = &volume {
+            self.inner.events.log_local(self.storage(), &volume.vid);
+        }
+        Ok(volume)
     }
 
     /// creates a new volume by forking an existing snapshot
     pub fn volume_from_snapshot(&self, snapshot: &Snapshot) -> Result<Volume> {
-        Ok(self.storage().volume_from_snapshot(snapshot)?)
+        let volume = self.storage().volume_from_snapshot(snapshot)?;
+        self.inner.events.log_local(self.storage(), &volume.vid);
+        Ok(volume)
     }
 
     /// retrieves an existing volume. returns `LogicalErr::VolumeNotFound` if missing
@@ -173,122 +934,725 @@ impl Runtime {
 
     /// removes a volume but leaves the underlying logs in place
     pub fn volume_delete(&self, vid: &VolumeId) -> Result<()> {
+        let mut exists = self.inner.exists.lock();
+        exists.volumes.remove(vid);
         Ok(self.storage().volume_delete(vid)?)
     }
 
-    /// fetches the latest changes to the remote and then pulls them into the volume
-    pub fn volume_pull(&self, vid: VolumeId) -> Result<()> {
-        let volume = self.inner.storage.read().volume(&vid)?;
-        self.fetch_log(volume.remote, None)?;
-        if volume.pending_commit.is_some() {
-            self.storage().read_write().recover_pending_commit(&vid)?;
+    /// exports the volume's configuration: its remote Log and the tags which
+    /// reference it. data and sync state are not included.
+    pub fn export_config(&self, vid: &VolumeId) -> Result<VolumeConfigDump> {
+        let volume = self.volume_get(vid)?;
+        let tags = self.tags_for_volume(vid)?;
+        Ok(VolumeConfigDump {
+            vid: volume.vid,
+            remote: volume.remote,
+            tags: tags.iter().map(ToString::to_string).collect(),
+            fill_byte: volume.settings.fill_byte,
+            conflict_policy: volume.settings.conflict_policy,
+            max_pages: volume.settings.max_pages,
+        })
+    }
+
+    /// applies a configuration produced by `export_config`, creating the
+    /// volume if it doesn't exist, applying its settings, and pointing each
+    /// tag at it. fails if the
+    /// volume exists with a different remote Log.
+    pub fn import_config(&self, config: &VolumeConfigDump) -> Result<Volume> {
+        self.volume_open(Some(config.vid.clone()), None, Some(config.remote.clone()))?;
+        let volume =
+            self.storage()
+                .read_write()
+                .update_volume_settings(&config.vid, |settings| {
+                    settings.fill_byte = config.fill_byte;
+                    settings.conflict_policy = config.conflict_policy;
+                    settings.max_pages = config.max_pages;
+                })?;
+        for tag in &config.tags {
+            self.tag_replace(tag, volume.vid.clone())?;
         }
-        Ok(self
-            .storage()
-            .read_write()
-            .sync_remote_to_local(volume.vid)?)
+        Ok(volume)
     }
 
-    pub fn volume_push(&self, vid: VolumeId) -> Result<()> {
-        self.run_action(RemoteCommit { vid })
+    /// regenerates the page version index for the volume's logs from the
+    /// commits in storage, returning the number of index entries written
+    pub fn rebuild_page_versions(&self, vid: &VolumeId) -> Result<usize> {
+        Ok(self.storage().read_write().rebuild_page_versions(vid)?)
     }
 
-    pub fn volume_status(&self, vid: &VolumeId) -> Result<VolumeStatus> {
+    /// discards every locally cached page of the volume and downloads them
+    /// again from the remote, for example when the local copy is suspected to
+    /// be corrupt. the volume keeps its identity and commit history. fails
+    /// with `LogicalErr::VolumeHasLocalChanges` if the volume has commits
+    /// which haven't been pushed, as those pages can't be downloaded again.
+    pub fn resync_from_remote(&self, vid: &VolumeId) -> Result<()> {
+        // fail before pulling if there are local changes; the check is
+        // repeated under the write lock before any pages are removed
         let reader = self.storage().read();
         let volume = reader.volume(vid)?;
-        let latest_local = reader.latest_lsn(&volume.local)?;
-        let latest_remote = reader.latest_lsn(&volume.remote)?;
-        Ok(volume.status(latest_local, latest_remote))
-    }
+        if volume.pending_commit.is_some()
+            || volume
+                .local_changes(reader.latest_lsn(&volume.local)?)
+                .is_some()
+        {
+            return Err(LogicalErr::VolumeHasLocalChanges(vid.clone()).into());
+        }
+        drop(reader);
 
-    pub fn volume_snapshot(&self, vid: &VolumeId) -> Result<Snapshot> {
-        Ok(self.storage().read().snapshot(vid)?)
-    }
+        self.volume_pull(vid.clone())?;
+        let snapshot = self.storage().read_write().remove_snapshot_pages(vid)?;
+        if let Some(cache) = self.inner.page_cache.lock().as_mut() {
+            cache.invalidate_volume(vid);
+        }
+        tracing::info!(?vid, "discarded local pages, resyncing from the remote");
 
-    pub fn volume_reader(&self, vid: VolumeId) -> Result<VolumeReader> {
-        let snapshot = self.volume_snapshot(&vid)?;
-        Ok(VolumeReader::new(self.clone(), vid, snapshot))
+        self.snapshot_hydrate(snapshot)
     }
 
-    pub fn volume_writer(&self, vid: VolumeId) -> Result<VolumeWriter> {
-        let snapshot = self.volume_snapshot(&vid)?;
-        Ok(VolumeWriter::new(self.clone(), vid, snapshot))
+    /// discards the volume's local commits which haven't been pushed and
+    /// resets it to the latest remote commit in local storage, returning the
+    /// discarded local lsns, if any. fetch the remote log first to reset to
+    /// the remote's current head. fails with `LogicalErr::VolumeNeverSynced`
+    /// if no commit in the volume's remote log is known.
+    pub fn volume_reset_to_remote(&self, vid: &VolumeId) -> Result<Option<RangeInclusive<LSN>>> {
+        let discarded = self.storage().read_write().reset_volume_to_remote(vid)?;
+        if let Some(cache) = self.inner.page_cache.lock().as_mut() {
+            cache.invalidate_volume(vid);
+        }
+        Ok(discarded)
     }
-}
 
-// log methods
-impl Runtime {
-    pub fn fetch_log(&self, log: LogId, max_lsn: Option<LSN>) -> Result<()> {
-        self.run_action(FetchLog { log, max_lsn })
+    /// fetches the latest changes to the remote and then pulls them into the volume
+    pub fn volume_pull(&self, vid: VolumeId) -> Result<()> {
+        self.volume_pull_timeout(vid, None)
     }
 
-    pub fn get_commit(&self, log: &LogId, lsn: LSN) -> Result<Option<Commit>> {
-        Ok(self.storage().read().get_commit(log, lsn)?)
+    /// like `volume_pull`, but fails with `RemoteErr::Timeout` if fetching
+    /// from the remote takes longer than `timeout`. `None` waits
+    /// indefinitely.
+    pub fn volume_pull_timeout(&self, vid: VolumeId, timeout: Option<Duration>) -> Result<()> {
+        let before = self.volume_get(&vid).ok().and_then(|v| v.sync);
+        let result = self.volume_pull_inner(&vid, timeout);
+        if result.is_err() {
+            self.inner.metrics.lock().record_pull_failure(&vid);
+        }
+        self.inner.events.emit_conflict(&vid, &result);
+        if result.is_ok()
+            && let Some(after) = self.volume_get(&vid)?.sync
+            && before.is_none_or(|before| before.remote < after.remote)
+        {
+            self.inner.events.remote_commit_received(&vid, after.remote);
+        }
+        result
     }
-}
 
-// snapshot methods
-impl Runtime {
-    /// returns the total number of pages in the snapshot
-    pub fn snapshot_pages(&self, snapshot: &Snapshot) -> Result<PageCount> {
-        if let Some((log, lsn)) = snapshot.head() {
-            Ok(self
-                .storage()
-                .read()
-                .page_count(log, lsn)?
-                .expect("BUG: missing head commit for snapshot"))
-        } else {
-            Ok(PageCount::ZERO)
+    fn volume_pull_inner(&self, vid: &VolumeId, timeout: Option<Duration>) -> Result<()> {
+        let volume = self.inner.storage.read().volume(vid)?;
+        self.fetch_log_timeout(volume.remote, None, timeout)?;
+        if volume.pending_commit.is_some() {
+            self.storage().read_write().recover_pending_commit(vid)?;
         }
+        Ok(self
+            .storage()
+            .read_write()
+            .sync_remote_to_local(volume.vid)?)
     }
 
-    pub fn snapshot_is_latest(&self, vid: &VolumeId, snapshot: &Snapshot) -> Result<bool> {
-        Ok(self.storage().read().is_latest_snapshot(vid, snapshot)?)
+    /// compares the page count of the remote commit the volume is synced to,
+    /// as recorded in local storage, against the same commit fetched from
+    /// the remote. returns `None` if the volume has never synced. while the
+    /// volume has no unpushed local commits, the local copy is also the
+    /// source of the volume's page count, so a mismatch indicates a bug or
+    /// corruption.
+    pub fn check_page_count(&self, vid: &VolumeId) -> Result<Option<PageCountCheck>> {
+        let volume = self.volume_get(vid)?;
+        let Some(sync) = volume.sync() else {
+            return Ok(None);
+        };
+        let local = self
+            .storage()
+            .read()
+            .page_count(&volume.remote, sync.remote)?
+            .expect("BUG: missing commit for the volume's sync point");
+        let remote = self
+            .inner
+            .tokio
+            .block_on(self.inner.remote.get_commit(&volume.remote, sync.remote))?
+            .map(|commit| commit.page_count());
+        Ok(Some(PageCountCheck {
+            remote_lsn: sync.remote,
+            local,
+            remote,
+        }))
     }
 
-    /// returns the checksum of the snapshot
-    pub fn snapshot_checksum(&self, snapshot: &Snapshot) -> Result<Checksum> {
-        Ok(self.storage().read().checksum(snapshot)?)
+    /// blocks until the volume has pulled the remote Log up to at least
+    /// `lsn`. Between pulls, the wait is woken as soon as another pull,
+    /// including one made by autosync, receives a remote commit, and
+    /// otherwise pulls again every `WAIT_POLL_INTERVAL`. Returns false if the
+    /// timeout elapses before the target LSN is reached.
+    pub fn wait_for_remote_lsn(&self, vid: &VolumeId, lsn: LSN, timeout: Duration) -> Result<bool> {
+        // enter the runtime so that we observe the runtime's clock
+        let _guard = self.inner.tokio.enter();
+        let deadline = tokio::time::Instant::now() + timeout;
+        let signal = self.inner.events.remote_commits();
+        loop {
+            // register for the signal before pulling so a commit received by
+            // another pull in between isn't missed
+            let notified = signal.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            self.volume_pull(vid.clone())?;
+            if self.volume_get(vid)?.remote_commit() >= Some(lsn) {
+                return Ok(true);
+            }
+
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Ok(false);
+            }
+            let wake = deadline.min(now + WAIT_POLL_INTERVAL);
+            let _ = self
+                .inner
+                .tokio
+                .block_on(tokio::time::timeout_at(wake, notified));
+        }
     }
 
-    pub fn snapshot_missing_pages(&self, snapshot: &Snapshot) -> Result<PageSet> {
-        let missing_frames = self.storage().read().find_missing_frames(snapshot)?;
-        // merge missing_frames into a single PageSet
-        Ok(missing_frames
-            .into_iter()
-            .fold(PageSet::EMPTY, |mut pageset, frame| {
-                pageset |= frame.pageset;
-                pageset
-            }))
+    /// returns a future which resolves to the volume's latest snapshot once
+    /// a pull, including one made by autosync, attaches the volume to a
+    /// newer remote commit than it had when this method was called. if a
+    /// newer remote commit already arrived by the time the future is polled,
+    /// it resolves immediately.
+    pub fn next_remote_commit(
+        &self,
+        vid: &VolumeId,
+    ) -> impl Future<Output = Result<Snapshot>> + Send + 'static {
+        let runtime = self.clone();
+        let vid = vid.clone();
+        let since = self.volume_get(&vid).map(|volume| volume.remote_commit());
+        let signal = self.inner.events.remote_commits();
+        async move {
+            let since = since?;
+            loop {
+                // register for the signal before checking the volume so a
+                // commit received in between isn't missed
+                let notified = signal.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+                if runtime.volume_get(&vid)?.remote_commit() > since {
+                    return runtime.volume_snapshot(&vid);
+                }
+                notified.await;
+            }
+        }
     }
 
-    pub fn snapshot_hydrate(&self, snapshot: Snapshot) -> Result<()> {
-        self.run_action(HydrateSnapshot { snapshot })
+    /// returns the number of local commits which haven't been pushed to the
+    /// remote
+    pub fn pending_commits(&self, vid: &VolumeId) -> Result<u64> {
+        let status = self.volume_status(vid)?;
+        Ok(status.local_status.changes().map_or(0, |lsns| lsns.len()))
     }
-}
 
-#[cfg(test)]
+    /// blocks until the volume has at most `max_pending` pending local
+    /// commits, waking autosync to push them. returns early if autosync is
+    /// disabled or paused for the volume, if the volume has diverged or needs
+    /// recovery, or after repeated syncs fail to make progress, as autosync
+    /// can't push the pending commits in any of those cases. otherwise waits
+    /// for at most `timeout`, capped at `MAX_SYNC_CAPACITY_WAIT`, which is
+    /// also the wait when `timeout` is `None`.
+    pub fn wait_for_sync_capacity(
+        &self,
+        vid: &VolumeId,
+        max_pending: u64,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        if self.autosync_interval().is_none() {
+            return Ok(());
+        }
+        // enter the runtime so that we observe the runtime's clock
+        let _guard = self.inner.tokio.enter();
+        let timeout = timeout.map_or(MAX_SYNC_CAPACITY_WAIT, |t| t.min(MAX_SYNC_CAPACITY_WAIT));
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let status = self.volume_status(vid)?;
+            if status.local_status.changes().map_or(0, |lsns| lsns.len()) <= max_pending {
+                return Ok(());
+            }
+            let health = self.sync_health(vid);
+            if status.has_diverged()
+                || self.volume_get(vid)?.pending_commit().is_some()
+                || health.paused_for.is_some()
+                || health.stalled_syncs >= SYNC_CAPACITY_MAX_STALLS
+            {
+                tracing::debug!(
+                    ?vid,
+                    %status,
+                    stalled_syncs = health.stalled_syncs,
+                    "autosync can't push pending commits; not waiting for sync capacity"
+                );
+                return Ok(());
+            }
+            self.inner.autosync_signal.notify_one();
+
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                tracing::debug!(?vid, "timed out waiting for sync capacity");
+                return Ok(());
+            }
+            let delay = SYNC_CAPACITY_POLL_INTERVAL.min(deadline - now);
+            self.inner.tokio.block_on(tokio::time::sleep(delay));
+        }
+    }
+
+    pub fn volume_push(&self, vid: VolumeId) -> Result<()> {
+        self.volume_push_inner(vid, false, None)
+    }
+
+    /// like `volume_push`, but fails with `RemoteErr::Timeout` if the push
+    /// takes longer than `timeout`. `None` waits indefinitely. a push which
+    /// times out after writing to the remote is recovered by the next push
+    /// or pull.
+    pub fn volume_push_timeout(&self, vid: VolumeId, timeout: Option<Duration>) -> Result<()> {
+        self.volume_push_inner(vid, false, timeout)
+    }
+
+    /// pushes the volume's pending local changes to the remote as a single
+    /// commit, omitting any pages which are unchanged relative to the remote
+    pub fn volume_squash_push(&self, vid: VolumeId) -> Result<()> {
+        self.volume_push_inner(vid, true, None)
+    }
+
+    /// like `volume_squash_push`, with a timeout as in `volume_push_timeout`
+    pub fn volume_squash_push_timeout(
+        &self,
+        vid: VolumeId,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        self.volume_push_inner(vid, true, timeout)
+    }
+
+    fn volume_push_inner(
+        &self,
+        vid: VolumeId,
+        squash: bool,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        let verify = self.inner.verify_push.load(Ordering::Relaxed);
+        let rebase = self.conflict_policy(&vid)? == ConflictPolicy::LastWriterWins;
+        self.inner
+            .events
+            .emit(|| RuntimeEvent::PushStarted { vid: vid.clone() });
+        let result = self.run_action_timeout(
+            RemoteCommit {
+                vid: vid.clone(),
+                squash,
+                verify,
+                rebase,
+                max_commits: *self.inner.max_commits_per_fetch.lock(),
+                events: self.inner.events.clone(),
+            },
+            timeout,
+        );
+        if result.is_err() {
+            self.inner.metrics.lock().record_push_failure(&vid);
+        } else {
+            self.inner
+                .events
+                .emit(|| RuntimeEvent::PushCompleted { vid: vid.clone() });
+            self.inner.events.log_push(self.storage(), &vid);
+        }
+        self.inner.events.emit_conflict(&vid, &result);
+        result
+    }
+
+    /// returns the union of all pages changed by the volume's pending local
+    /// commits, truncated to the latest local page count
+    pub fn coalesce_pending_changes(&self, vid: &VolumeId) -> Result<PageSet> {
+        let reader = self.storage().read();
+        let volume = reader.volume(vid)?;
+        let latest_local = reader.latest_lsn(&volume.local)?;
+        let Some(lsns) = volume.local_changes(latest_local) else {
+            return Ok(PageSet::EMPTY);
+        };
+        let page_count = reader
+            .page_count(&volume.local, *lsns.end())?
+            .expect("BUG: no page count for commit");
+
+        let snapshot = Snapshot::new(volume.local, lsns, page_count);
+        let mut pageset = PageSet::EMPTY;
+        let mut commits = reader.commits(&snapshot);
+        while let Some(commit) = commits.try_next()? {
+            if let Some(idx) = commit.segment_idx {
+                pageset |= idx.pageset;
+            }
+        }
+        pageset.truncate(page_count);
+        Ok(pageset)
+    }
+
+    /// counts how many of the volume's pending local commits modified each
+    /// page, ordered from the most to the least frequently modified page.
+    /// pages beyond the latest local page count are omitted.
+    pub fn pending_change_heatmap(&self, vid: &VolumeId) -> Result<Vec<(PageIdx, u32)>> {
+        let reader = self.storage().read();
+        let volume = reader.volume(vid)?;
+        let latest_local = reader.latest_lsn(&volume.local)?;
+        let Some(lsns) = volume.local_changes(latest_local) else {
+            return Ok(vec![]);
+        };
+        let page_count = reader
+            .page_count(&volume.local, *lsns.end())?
+            .expect("BUG: no page count for commit");
+
+        let snapshot = Snapshot::new(volume.local, lsns, page_count);
+        let mut counts: BTreeMap<PageIdx, u32> = BTreeMap::new();
+        let mut commits = reader.commits(&snapshot);
+        while let Some(commit) = commits.try_next()? {
+            if let Some(idx) = commit.segment_idx {
+                for pageidx in idx.pageset.iter().filter(|&p| page_count.contains(p)) {
+                    *counts.entry(pageidx).or_default() += 1;
+                }
+            }
+        }
+        let mut heatmap: Vec<_> = counts.into_iter().collect();
+        // stable sort keeps pages with equal counts in ascending order
+        heatmap.sort_by(|(_, a), (_, b)| b.cmp(a));
+        Ok(heatmap)
+    }
+
+    /// describes how the volume's unpushed local commits conflict with the
+    /// remote commits it hasn't pulled, for example after a pull fails with
+    /// `LogicalErr::VolumeDiverged`. reflects the remote log as of the last
+    /// fetch. returns `None` if the volume hasn't diverged.
+    pub fn conflict_report(&self, vid: &VolumeId) -> Result<Option<ConflictReport>> {
+        Ok(self.storage().read().conflict_report(vid)?)
+    }
+
+    pub fn volume_status(&self, vid: &VolumeId) -> Result<VolumeStatus> {
+        let reader = self.storage().read();
+        let volume = reader.volume(vid)?;
+        let latest_local = reader.latest_lsn(&volume.local)?;
+        let latest_remote = reader.latest_lsn(&volume.remote)?;
+        Ok(volume.status(latest_local, latest_remote))
+    }
+
+    pub fn volume_snapshot(&self, vid: &VolumeId) -> Result<Snapshot> {
+        Ok(self.storage().read().snapshot(vid)?)
+    }
+
+    pub fn volume_reader(&self, vid: VolumeId) -> Result<VolumeReader> {
+        let snapshot = self.volume_snapshot(&vid)?;
+        Ok(VolumeReader::new(self.clone(), vid, snapshot))
+    }
+
+    /// writes every page in the snapshot to `w` in order, returning the
+    /// number of bytes written. pages missing locally are fetched from the
+    /// remote.
+    pub fn export_to_writer(
+        &self,
+        vid: &VolumeId,
+        snapshot: &Snapshot,
+        w: impl std::io::Write,
+    ) -> Result<usize> {
+        VolumeReader::new(self.clone(), vid.clone(), snapshot.clone()).export_to_writer(w)
+    }
+
+    /// resumes an interrupted `export_to_writer` of the snapshot into `f`,
+    /// verifying the pages already written before appending the rest.
+    /// returns the number of bytes written.
+    pub fn resume_export(
+        &self,
+        vid: &VolumeId,
+        snapshot: &Snapshot,
+        f: impl std::io::Read + std::io::Write + std::io::Seek,
+    ) -> Result<usize> {
+        VolumeReader::new(self.clone(), vid.clone(), snapshot.clone()).resume_export(f)
+    }
+
+    pub fn volume_writer(&self, vid: VolumeId) -> Result<VolumeWriter> {
+        let reader = self.storage().read();
+        let snapshot = reader.snapshot(&vid)?;
+        let max_pages = self
+            .max_pages_for(&reader.volume(&vid)?)
+            .unwrap_or(PageCount::MAX);
+        Ok(VolumeWriter::new(self.clone(), vid, snapshot, max_pages))
+    }
+
+    /// Atomically commits several writers, each to a different Volume. Either
+    /// every Volume advances or none of them do.
+    ///
+    /// Fails with `LogicalErr::VolumeConcurrentWrite` if any writer is not
+    /// based on the latest snapshot of its Volume, or if two writers target
+    /// the same Volume.
+    ///
+    /// Returns the resulting `Snapshot` for each writer, in order.
+    pub fn multi_commit(&self, writers: Vec<VolumeWriter>) -> Result<Vec<Snapshot>> {
+        let commits: Vec<_> = writers
+            .into_iter()
+            .map(|mut writer| {
+                writer.maybe_checkpoint();
+                writer.into_parts()
+            })
+            .collect();
+        let stats: Vec<_> = commits
+            .iter()
+            .map(|(vid, _, _, pages)| (vid.clone(), pages.len(), self.pages_to_evict(pages)))
+            .collect();
+        let snapshots = match self.mirror() {
+            Some(mirror) => mirror.multi_commit(commits),
+            None => self.storage().read_write().multi_commit(commits),
+        }
+        .map_err(GraftErr::from);
+        if let Err(GraftErr::Logical(LogicalErr::VolumeConcurrentWrite(vid))) = &snapshots {
+            self.inner
+                .events
+                .emit(|| RuntimeEvent::ConflictDetected { vid: vid.clone() });
+        }
+        let snapshots = snapshots?;
+        for ((vid, pages, evict), snapshot) in stats.into_iter().zip(&snapshots) {
+            self.record_commit(&vid, snapshot, pages, evict);
+        }
+        Ok(snapshots)
+    }
+
+    /// Rewrites every page of the Volume into a single local commit, which
+    /// makes the commit a checkpoint. Loading pages from a snapshot stops at
+    /// the most recent checkpoint, so this bounds the cost of reads on a
+    /// Volume with a long history. Pages missing locally are fetched from the
+    /// remote.
+    pub fn volume_checkpoint(&self, vid: &VolumeId) -> Result<VolumeReader> {
+        let mut writer = self.volume_writer(vid.clone())?;
+        for pageidx in writer.page_count().iter() {
+            let page = writer.read_page(pageidx)?;
+            writer.write_page(pageidx, page)?;
+        }
+        let reader = writer.commit()?;
+        tracing::debug!(?vid, page_count = %reader.page_count(), "checkpointed volume");
+        Ok(reader)
+    }
+
+    /// returns the number of commits in the Volume's latest snapshot which
+    /// are newer than its most recent checkpoint
+    pub fn commits_since_checkpoint(&self, vid: &VolumeId) -> Result<u64> {
+        let reader = self.storage().read();
+        let snapshot = reader.snapshot(vid)?;
+        Ok(reader.commits_since_checkpoint(&snapshot)?)
+    }
+}
+
+// log methods
+impl Runtime {
+    pub fn fetch_log(&self, log: LogId, max_lsn: Option<LSN>) -> Result<()> {
+        self.fetch_log_timeout(log, max_lsn, None)
+    }
+
+    /// like `fetch_log`, but fails with `RemoteErr::Timeout` if fetching takes
+    /// longer than `timeout`
+    pub fn fetch_log_timeout(
+        &self,
+        log: LogId,
+        max_lsn: Option<LSN>,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        let max_commits = *self.inner.max_commits_per_fetch.lock();
+        self.run_action_timeout(FetchLog { log, max_lsn, max_commits }, timeout)
+    }
+
+    pub fn get_commit(&self, log: &LogId, lsn: LSN) -> Result<Option<Commit>> {
+        Ok(self.storage().read().get_commit(log, lsn)?)
+    }
+
+    /// returns the LSN of the nth most recent checkpoint known for the log,
+    /// where `n == 0` is the latest checkpoint
+    pub fn nth_checkpoint(&self, log: &LogId, n: usize) -> Result<Option<LSN>> {
+        Ok(self.storage().read().nth_checkpoint(log, n)?)
+    }
+}
+
+// snapshot methods
+impl Runtime {
+    /// returns the total number of pages in the snapshot
+    pub fn snapshot_pages(&self, snapshot: &Snapshot) -> Result<PageCount> {
+        if let Some((log, lsn)) = snapshot.head() {
+            Ok(self
+                .storage()
+                .read()
+                .page_count(log, lsn)?
+                .expect("BUG: missing head commit for snapshot"))
+        } else {
+            Ok(PageCount::ZERO)
+        }
+    }
+
+    /// returns every commit reachable by the snapshot, from newest to oldest
+    pub fn snapshot_commits(&self, snapshot: &Snapshot) -> Result<Vec<Commit>> {
+        Ok(self
+            .storage()
+            .read()
+            .commits(snapshot)
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    pub fn snapshot_is_latest(&self, vid: &VolumeId, snapshot: &Snapshot) -> Result<bool> {
+        Ok(self.storage().read().is_latest_snapshot(vid, snapshot)?)
+    }
+
+    /// returns the checksum of the snapshot
+    pub fn snapshot_checksum(&self, snapshot: &Snapshot) -> Result<Checksum> {
+        Ok(self.storage().read().checksum(snapshot)?)
+    }
+
+    /// returns the checksum of the snapshot's visible pages within the range.
+    /// like `snapshot_checksum`, only pages available locally are included.
+    pub fn snapshot_checksum_range(
+        &self,
+        snapshot: &Snapshot,
+        pages: RangeInclusive<PageIdx>,
+    ) -> Result<Checksum> {
+        Ok(self.storage().read().checksum_range(snapshot, pages)?)
+    }
+
+    pub fn snapshot_missing_pages(&self, snapshot: &Snapshot) -> Result<PageSet> {
+        let missing_frames = self.storage().read().find_missing_frames(snapshot)?;
+        // merge missing_frames into a single PageSet
+        Ok(missing_frames
+            .into_iter()
+            .fold(PageSet::EMPTY, |mut pageset, frame| {
+                pageset |= frame.pageset;
+                pageset
+            }))
+    }
+
+    /// reports which of the snapshot's pages are available locally. the
+    /// checksum is only computed once every page is available.
+    pub fn audit_report(&self, snapshot: &Snapshot) -> Result<AuditReport> {
+        let mut missing = self.snapshot_missing_pages(snapshot)?;
+        // missing frames may extend past the end of the snapshot
+        missing.truncate(snapshot.page_count);
+        let total_pages = snapshot.page_count;
+        let cached_pages = PageCount::new(total_pages.to_u32() - missing.cardinality().to_u32());
+        let pct_cached = if total_pages.is_empty() {
+            100.0
+        } else {
+            cached_pages.to_u32() as f64 / total_pages.to_u32() as f64 * 100.0
+        };
+        let checksum = if missing.is_empty() {
+            Some(self.snapshot_checksum(snapshot)?)
+        } else {
+            None
+        };
+        Ok(AuditReport {
+            total_pages,
+            cached_pages,
+            missing,
+            pct_cached,
+            checksum,
+        })
+    }
+
+    /// returns each Volume whose latest snapshot has pages which are not
+    /// available locally, along with the number of missing pages. pages are
+    /// fetched in frames, so the count includes every page in each missing
+    /// frame.
+    pub fn incomplete_volumes(&self) -> Result<Vec<(VolumeId, u64)>> {
+        let mut incomplete = vec![];
+        let mut volumes = self.volume_iter();
+        while let Some(volume) = volumes.try_next()? {
+            let snapshot = self.volume_snapshot(&volume.vid)?;
+            let missing = self.snapshot_missing_pages(&snapshot)?.cardinality();
+            if !missing.is_empty() {
+                incomplete.push((volume.vid, u64::from(missing.to_u32())));
+            }
+        }
+        Ok(incomplete)
+    }
+
+    pub fn snapshot_hydrate(&self, snapshot: Snapshot) -> Result<()> {
+        let retries = *self.inner.hydrate_retries.lock();
+        self.run_action(HydrateSnapshot {
+            snapshot,
+            range: PageIdx::FIRST..=PageIdx::LAST,
+            retries,
+        })
+    }
+
+    /// Compares the latest snapshots of two Volumes page by page. Pages which
+    /// are only contained by the larger Volume are reported as a single
+    /// region rather than compared.
+    pub fn compare_volumes(&self, left: &VolumeId, right: &VolumeId) -> Result<VolumeDiff> {
+        let left_snapshot = self.volume_snapshot(left)?;
+        let right_snapshot = self.volume_snapshot(right)?;
+        let left_pages = left_snapshot.page_count;
+        let right_pages = right_snapshot.page_count;
+
+        let mut changed = PageSet::EMPTY;
+        for pageidx in left_pages.min(right_pages).iter() {
+            let left_page = self.read_page(left, &left_snapshot, pageidx)?;
+            let right_page = self.read_page(right, &right_snapshot, pageidx)?;
+            if left_page != right_page {
+                changed.insert(pageidx);
+            }
+        }
+
+        Ok(VolumeDiff {
+            left: left_pages,
+            right: right_pages,
+            changed,
+        })
+    }
+
+    /// Downloads any missing pages in the specified range of the snapshot.
+    /// Used to read ahead of sequential scans.
+    pub fn snapshot_prefetch(
+        &self,
+        snapshot: Snapshot,
+        range: RangeInclusive<PageIdx>,
+    ) -> Result<()> {
+        self.run_action(HydrateSnapshot { snapshot, range, retries: 0 })
+    }
+}
+
+#[cfg(test)]
 mod tests {
-    use std::{sync::Arc, time::Duration};
+    use std::{num::NonZero, sync::Arc, time::Duration};
 
-    use crate::core::{LogId, PageIdx, page::Page};
+    use crate::core::{
+        LogId, PageCount, PageIdx, SegmentId, VolumeId,
+        commit::Commit,
+        page::{PAGESIZE, Page},
+        pageset::PageSet,
+    };
+    use crate::pageidx;
+    use futures::FutureExt;
     use test_log::test;
     use tokio::time::sleep;
 
     use crate::{
-        local::fjall_storage::FjallStorage, remote::RemoteConfig, rt::runtime::Runtime,
-        volume_reader::VolumeRead, volume_writer::VolumeWrite,
+        GraftErr, LogicalErr,
+        core::lsn::LSN,
+        local::{fjall_storage::FjallStorage, mirror_storage::MirrorMode},
+        remote::{RemoteConfig, RemoteErr},
+        rt::{
+            event_log::{EventKind, EventLog, EventLogDurability, read_event_log},
+            observer::RuntimeEvent,
+            page_cache::PageCacheStats,
+            runtime::Runtime,
+        },
+        testutil::{memory_remote, test_runtime, test_tokio_rt},
+        volume::{ConflictPolicy, OpenPolicy},
+        volume_reader::VolumeRead,
+        volume_writer::VolumeWrite,
     };
+    use parking_lot::Mutex;
 
     #[test]
     fn runtime_sanity() {
-        let tokio_rt = tokio::runtime::Builder::new_current_thread()
-            .start_paused(true)
-            .enable_all()
-            .build()
-            .unwrap();
+        let tokio_rt = test_tokio_rt();
 
-        let remote = Arc::new(RemoteConfig::Memory.build().unwrap());
+        let remote = memory_remote();
         let storage = Arc::new(FjallStorage::open_temporary().unwrap());
         let runtime = Runtime::new(
             tokio_rt.handle().clone(),
@@ -402,4 +1766,1539 @@ mod tests {
         });
         tokio_rt.block_on(task).unwrap();
     }
+
+    #[test]
+    fn open_policy_adopt_remote() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        let volume = runtime.volume_open(None, None, None).unwrap();
+        let vid = volume.vid.clone();
+        let other_remote = LogId::random();
+
+        // strict is the default
+        let err = runtime
+            .volume_open(Some(vid.clone()), None, Some(other_remote.clone()))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GraftErr::Logical(LogicalErr::VolumeRemoteMismatch { .. })
+        ));
+
+        // adopt remote succeeds on a clean volume
+        let adopted = runtime
+            .volume_open_with_policy(
+                Some(vid.clone()),
+                None,
+                Some(other_remote.clone()),
+                OpenPolicy::AdoptRemote,
+            )
+            .unwrap();
+        assert_eq!(adopted.vid, vid);
+        assert_eq!(adopted.remote, other_remote);
+        assert_eq!(runtime.volume_get(&vid).unwrap().remote, other_remote);
+
+        // adopt remote is refused once local commits are pending
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(1), Page::test_filled(1))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let err = runtime
+            .volume_open_with_policy(
+                Some(vid.clone()),
+                None,
+                Some(volume.remote),
+                OpenPolicy::AdoptRemote,
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GraftErr::Logical(LogicalErr::VolumeRemoteMismatch { .. })
+        ));
+        assert_eq!(runtime.volume_get(&vid).unwrap().remote, other_remote);
+    }
+
+    #[test]
+    fn mirror_local_commits() {
+        let tokio_rt = test_tokio_rt();
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+        assert_eq!(runtime.mirror_diverged_commits(), None);
+
+        let secondary = Arc::new(FjallStorage::open_temporary().unwrap());
+        runtime.set_mirror(Some(secondary.clone()), MirrorMode::Durable);
+
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(1), Page::test_filled(1))
+            .unwrap();
+        let reader = writer.commit().unwrap();
+
+        // the secondary contains the commit and its pages
+        let mirrored = secondary.read();
+        assert_eq!(
+            mirrored.volume(&vid).unwrap(),
+            runtime.volume_get(&vid).unwrap()
+        );
+        let commit = mirrored
+            .search_page(reader.snapshot(), pageidx!(1))
+            .unwrap()
+            .expect("page must be mirrored");
+        assert_eq!(
+            mirrored
+                .read_page(commit.segment_id().unwrap().clone(), pageidx!(1))
+                .unwrap(),
+            Some(Page::test_filled(1))
+        );
+        assert_eq!(runtime.mirror_diverged_commits(), Some(0));
+
+        // disabling the mirror stops mirroring new commits
+        runtime.set_mirror(None, MirrorMode::Durable);
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(2), Page::test_filled(2))
+            .unwrap();
+        let reader = writer.commit().unwrap();
+        let (log, lsn) = reader.snapshot().head().unwrap();
+        assert!(secondary.read().get_commit(log, lsn).unwrap().is_none());
+    }
+
+    #[test]
+    fn mirror_multi_commit() {
+        let tokio_rt = test_tokio_rt();
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+        let secondary = Arc::new(FjallStorage::open_temporary().unwrap());
+        runtime.set_mirror(Some(secondary.clone()), MirrorMode::Durable);
+
+        let a = runtime.volume_open(None, None, None).unwrap().vid;
+        let b = runtime.volume_open(None, None, None).unwrap().vid;
+        let mut wa = runtime.volume_writer(a.clone()).unwrap();
+        wa.write_page(pageidx!(1), Page::test_filled(1)).unwrap();
+        let mut wb = runtime.volume_writer(b.clone()).unwrap();
+        wb.write_page(pageidx!(1), Page::test_filled(2)).unwrap();
+        let snapshots = runtime.multi_commit(vec![wa, wb]).unwrap();
+
+        // the secondary contains both commits and their pages
+        let mirrored = secondary.read();
+        for (vid, snapshot, fill) in [(&a, &snapshots[0], 1), (&b, &snapshots[1], 2)] {
+            assert_eq!(
+                mirrored.volume(vid).unwrap(),
+                runtime.volume_get(vid).unwrap()
+            );
+            let commit = mirrored
+                .search_page(snapshot, pageidx!(1))
+                .unwrap()
+                .expect("page must be mirrored");
+            assert_eq!(
+                mirrored
+                    .read_page(commit.segment_id().unwrap().clone(), pageidx!(1))
+                    .unwrap(),
+                Some(Page::test_filled(fill))
+            );
+        }
+        assert_eq!(runtime.mirror_diverged_commits(), Some(0));
+    }
+
+    #[test]
+    fn compare_volumes() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        for i in 1..=4u8 {
+            writer
+                .write_page(PageIdx::must_new(i as u32), Page::test_filled(i))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+
+        // an identical fork compares equal
+        let snapshot = runtime.volume_snapshot(&vid).unwrap();
+        let fork = runtime.volume_from_snapshot(&snapshot).unwrap().vid;
+        let diff = runtime.compare_volumes(&vid, &fork).unwrap();
+        assert!(diff.is_identical(), "{diff}");
+
+        // modify the fork and extend it by two pages
+        let mut writer = runtime.volume_writer(fork.clone()).unwrap();
+        writer
+            .write_page(pageidx!(2), Page::test_filled(20))
+            .unwrap();
+        writer
+            .write_page(pageidx!(6), Page::test_filled(6))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let diff = runtime.compare_volumes(&vid, &fork).unwrap();
+        assert!(!diff.is_identical());
+        assert_eq!(diff.changed.iter().collect::<Vec<_>>(), [pageidx!(2)]);
+        assert_eq!(diff.extra_pages(), Some(pageidx!(5)..=pageidx!(6)));
+    }
+
+    #[test]
+    fn exists_cache_invalidation() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        assert!(!runtime.tag_exists("main").unwrap());
+        runtime.tag_replace("main", vid.clone()).unwrap();
+
+        // repeated checks are served by the cache
+        for _ in 0..2 {
+            assert!(runtime.volume_exists(&vid).unwrap());
+            assert!(runtime.tag_exists("main").unwrap());
+        }
+
+        // deleting invalidates the cache
+        runtime.tag_delete("main").unwrap();
+        assert!(!runtime.tag_exists("main").unwrap());
+        assert!(runtime.volume_exists(&vid).unwrap());
+        runtime.volume_delete(&vid).unwrap();
+        assert!(!runtime.volume_exists(&vid).unwrap());
+
+        // recreating is observed again
+        runtime.volume_open(Some(vid.clone()), None, None).unwrap();
+        runtime.tag_replace("main", vid.clone()).unwrap();
+        assert!(runtime.volume_exists(&vid).unwrap());
+        assert!(runtime.tag_exists("main").unwrap());
+    }
+
+    #[test]
+    fn pending_change_heatmap() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        assert!(runtime.pending_change_heatmap(&vid).unwrap().is_empty());
+
+        // page 3 is rewritten by every commit
+        let commits = [&[1, 3][..], &[2, 3], &[3], &[2, 3]];
+        for pages in commits {
+            let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+            for &i in pages {
+                writer
+                    .write_page(PageIdx::must_new(i), Page::test_filled(i as u8))
+                    .unwrap();
+            }
+            writer.commit().unwrap();
+        }
+
+        let heatmap = runtime.pending_change_heatmap(&vid).unwrap();
+        assert_eq!(
+            heatmap,
+            [(pageidx!(3), 4), (pageidx!(2), 2), (pageidx!(1), 1)]
+        );
+    }
+
+    #[test]
+    fn export_to_writer() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        // leave page 2 empty
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(1), Page::test_filled(1))
+            .unwrap();
+        writer
+            .write_page(pageidx!(3), Page::test_filled(3))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let snapshot = runtime.volume_snapshot(&vid).unwrap();
+        let mut out = Vec::new();
+        let written = runtime.export_to_writer(&vid, &snapshot, &mut out).unwrap();
+        assert_eq!(written, out.len());
+        assert_eq!(
+            out.len(),
+            snapshot.page_count.to_usize() * PAGESIZE.as_usize()
+        );
+
+        let pages: Vec<_> = out.chunks(PAGESIZE.as_usize()).collect();
+        assert_eq!(pages[0], Page::test_filled(1).as_ref());
+        assert_eq!(pages[1], Page::EMPTY.as_ref());
+        assert_eq!(pages[2], Page::test_filled(3).as_ref());
+    }
+
+    #[test]
+    fn resume_export() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        for i in 1..=5u8 {
+            writer
+                .write_page(PageIdx::must_new(i as u32), Page::test_filled(i))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+
+        let snapshot = runtime.volume_snapshot(&vid).unwrap();
+        let mut full = Vec::new();
+        runtime
+            .export_to_writer(&vid, &snapshot, &mut full)
+            .unwrap();
+
+        // interrupt the export midway and resume it
+        let pagesize = PAGESIZE.as_usize();
+        let mut partial = std::io::Cursor::new(full[..2 * pagesize].to_vec());
+        let written = runtime
+            .resume_export(&vid, &snapshot, &mut partial)
+            .unwrap();
+        assert_eq!(written, 3 * pagesize);
+        assert_eq!(partial.into_inner(), full);
+
+        // resuming a complete export writes nothing
+        let mut complete = std::io::Cursor::new(full.clone());
+        assert_eq!(
+            runtime
+                .resume_export(&vid, &snapshot, &mut complete)
+                .unwrap(),
+            0
+        );
+
+        // a partial page can't be resumed
+        let mut torn = std::io::Cursor::new(full[..pagesize + 1].to_vec());
+        assert!(matches!(
+            runtime.resume_export(&vid, &snapshot, &mut torn),
+            Err(GraftErr::Io(_))
+        ));
+
+        // a prefix which doesn't match the snapshot is rejected
+        let mut mismatched = full[..2 * pagesize].to_vec();
+        mismatched[pagesize] ^= 0xff;
+        let mut mismatched = std::io::Cursor::new(mismatched);
+        assert!(matches!(
+            runtime.resume_export(&vid, &snapshot, &mut mismatched),
+            Err(GraftErr::Io(_))
+        ));
+    }
+
+    #[test]
+    fn snapshot_checksum_range() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        for i in 1..=10u8 {
+            writer
+                .write_page(PageIdx::must_new(i as u32), Page::test_filled(i))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+
+        let range = pageidx!(3)..=pageidx!(6);
+        let checksum = |runtime: &Runtime| {
+            let snapshot = runtime.volume_snapshot(&vid).unwrap();
+            runtime
+                .snapshot_checksum_range(&snapshot, range.clone())
+                .unwrap()
+        };
+        let before = checksum(&runtime);
+        assert_eq!(checksum(&runtime), before);
+
+        // the range covers a subset of the volume
+        let snapshot = runtime.volume_snapshot(&vid).unwrap();
+        assert_ne!(runtime.snapshot_checksum(&snapshot).unwrap(), before);
+
+        // changing a page outside the range doesn't affect the checksum
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(8), Page::test_filled(80))
+            .unwrap();
+        writer.commit().unwrap();
+        assert_eq!(checksum(&runtime), before);
+
+        // changing a page inside the range does
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(4), Page::test_filled(40))
+            .unwrap();
+        writer.commit().unwrap();
+        assert_ne!(checksum(&runtime), before);
+    }
+
+    #[test]
+    fn fill_byte() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+        runtime.set_default_fill_byte(0xAB);
+
+        // extend the volume by writing past its end, skipping pages 1 and 2
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(3), Page::test_filled(3))
+            .unwrap();
+        assert_eq!(
+            writer.read_page(pageidx!(1)).unwrap(),
+            Page::test_filled(0xAB)
+        );
+        writer.commit().unwrap();
+
+        let reader = runtime.volume_reader(vid.clone()).unwrap();
+        assert_eq!(
+            reader.read_page(pageidx!(1)).unwrap(),
+            Page::test_filled(0xAB)
+        );
+        assert_eq!(
+            reader.read_page(pageidx!(2)).unwrap(),
+            Page::test_filled(0xAB)
+        );
+        assert_eq!(reader.read_page(pageidx!(3)).unwrap(), Page::test_filled(3));
+        // pages past the end of the volume are not filled
+        assert_eq!(reader.read_page(pageidx!(4)).unwrap(), Page::EMPTY);
+
+        // explicitly written pages replace the fill, even when empty
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer.write_page(pageidx!(1), Page::EMPTY).unwrap();
+        writer.commit().unwrap();
+        let reader = runtime.volume_reader(vid.clone()).unwrap();
+        assert_eq!(reader.read_page(pageidx!(1)).unwrap(), Page::EMPTY);
+        assert_eq!(
+            reader.read_page(pageidx!(2)).unwrap(),
+            Page::test_filled(0xAB)
+        );
+
+        // a Volume's own fill byte overrides the default, and is persisted
+        let other = runtime.volume_open(None, None, None).unwrap().vid;
+        runtime.set_fill_byte(&other, Some(0xCD)).unwrap();
+        assert_eq!(runtime.fill_byte(&other).unwrap(), 0xCD);
+        assert_eq!(runtime.fill_byte(&vid).unwrap(), 0xAB);
+        assert_eq!(
+            runtime.volume_get(&other).unwrap().settings().fill_byte,
+            Some(0xCD)
+        );
+        let mut writer = runtime.volume_writer(other.clone()).unwrap();
+        writer
+            .write_page(pageidx!(2), Page::test_filled(2))
+            .unwrap();
+        writer.commit().unwrap();
+        let reader = runtime.volume_reader(other.clone()).unwrap();
+        assert_eq!(
+            reader.read_page(pageidx!(1)).unwrap(),
+            Page::test_filled(0xCD)
+        );
+
+        // the default doesn't change the fill of a Volume with its own
+        runtime.set_default_fill_byte(0);
+        assert_eq!(
+            reader.read_page(pageidx!(1)).unwrap(),
+            Page::test_filled(0xCD)
+        );
+        let reader = runtime.volume_reader(vid.clone()).unwrap();
+        assert_eq!(reader.read_page(pageidx!(2)).unwrap(), Page::EMPTY);
+
+        // removing the override reverts to the default
+        runtime.set_fill_byte(&other, None).unwrap();
+        assert_eq!(runtime.fill_byte(&other).unwrap(), 0);
+    }
+
+    #[test]
+    fn volume_metrics() {
+        let tokio_rt = test_tokio_rt();
+
+        let remote = memory_remote();
+        let new_runtime = || test_runtime(&tokio_rt, remote.clone());
+        let writer_rt = new_runtime();
+        let reader_rt = new_runtime();
+
+        let volume = writer_rt.volume_open(None, None, None).unwrap();
+        let vid = volume.vid.clone();
+        for i in 1..=2u8 {
+            let mut writer = writer_rt.volume_writer(vid.clone()).unwrap();
+            writer
+                .write_page(PageIdx::must_new(i as u32), Page::test_filled(i))
+                .unwrap();
+            writer.commit().unwrap();
+        }
+        writer_rt.volume_push(vid.clone()).unwrap();
+
+        let metrics = writer_rt.volume_metrics(&vid);
+        assert_eq!(metrics.commits, 2);
+        assert_eq!(metrics.pages_written, 2);
+        assert_eq!(metrics.push_failures, 0);
+
+        // reading a page which isn't available locally fetches it
+        let other = reader_rt
+            .volume_open(None, None, Some(volume.remote))
+            .unwrap()
+            .vid;
+        reader_rt.volume_pull(other.clone()).unwrap();
+        let reader = reader_rt.volume_reader(other.clone()).unwrap();
+        assert_eq!(reader.read_page(pageidx!(1)).unwrap(), Page::test_filled(1));
+        assert!(reader_rt.volume_metrics(&other).pages_fetched > 0);
+
+        // failed syncs are counted
+        let missing = VolumeId::random();
+        assert!(writer_rt.volume_push(missing.clone()).is_err());
+        assert!(writer_rt.volume_pull(missing.clone()).is_err());
+        let metrics = writer_rt.volume_metrics(&missing);
+        assert_eq!(metrics.push_failures, 1);
+        assert_eq!(metrics.pull_failures, 1);
+
+        // resetting zeroes the counters
+        writer_rt.reset_volume_metrics(&vid);
+        assert_eq!(writer_rt.volume_metrics(&vid), Default::default());
+    }
+
+    #[test]
+    fn observer_events() {
+        let tokio_rt = test_tokio_rt();
+
+        let remote = memory_remote();
+        let new_runtime = || {
+            let runtime = test_runtime(&tokio_rt, remote.clone());
+            let events = Arc::new(Mutex::new(vec![]));
+            let sink = events.clone();
+            runtime.set_observer(move |event| sink.lock().push(event.clone()));
+            (runtime, events)
+        };
+        let (writer_rt, writer_events) = new_runtime();
+        let (reader_rt, reader_events) = new_runtime();
+
+        let volume = writer_rt.volume_open(None, None, None).unwrap();
+        let vid = volume.vid.clone();
+        let mut writer = writer_rt.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(1), Page::test_filled(1))
+            .unwrap();
+        writer.commit().unwrap();
+        writer_rt.volume_push(vid.clone()).unwrap();
+
+        assert_eq!(
+            *writer_events.lock(),
+            [
+                RuntimeEvent::VolumeOpened { vid: vid.clone() },
+                RuntimeEvent::LocalCommit {
+                    vid: vid.clone(),
+                    lsn: LSN::FIRST,
+                    pages: 1
+                },
+                RuntimeEvent::PushStarted { vid: vid.clone() },
+                RuntimeEvent::PushCompleted { vid: vid.clone() },
+            ]
+        );
+
+        let other = reader_rt
+            .volume_open(None, None, Some(volume.remote))
+            .unwrap()
+            .vid;
+        reader_rt.volume_pull(other.clone()).unwrap();
+        // pulling again without new remote commits emits nothing
+        reader_rt.volume_pull(other.clone()).unwrap();
+
+        assert_eq!(
+            *reader_events.lock(),
+            [
+                RuntimeEvent::VolumeOpened { vid: other.clone() },
+                RuntimeEvent::RemoteCommitReceived { vid: other.clone(), lsn: LSN::FIRST },
+            ]
+        );
+
+        // a stale writer conflicts with the latest snapshot
+        let mut stale = writer_rt.volume_writer(vid.clone()).unwrap();
+        stale.write_page(pageidx!(2), Page::test_filled(2)).unwrap();
+        let mut writer = writer_rt.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(3), Page::test_filled(3))
+            .unwrap();
+        writer.commit().unwrap();
+        writer_events.lock().clear();
+        assert!(stale.commit().is_err());
+        assert_eq!(
+            *writer_events.lock(),
+            [RuntimeEvent::ConflictDetected { vid: vid.clone() }]
+        );
+    }
+
+    #[test]
+    fn next_remote_commit() {
+        let tokio_rt = test_tokio_rt();
+
+        let remote = memory_remote();
+        let new_runtime = || test_runtime(&tokio_rt, remote.clone());
+        let writer_rt = new_runtime();
+        let reader_rt = new_runtime();
+
+        let volume = writer_rt.volume_open(None, None, None).unwrap();
+        let vid = volume.vid.clone();
+        let other = reader_rt
+            .volume_open(None, None, Some(volume.remote.clone()))
+            .unwrap()
+            .vid;
+        let push = |n: u8| {
+            let mut writer = writer_rt.volume_writer(vid.clone()).unwrap();
+            writer
+                .write_page(pageidx!(1), Page::test_filled(n))
+                .unwrap();
+            writer.commit().unwrap();
+            writer_rt.volume_push(vid.clone()).unwrap();
+        };
+
+        // the future stays pending until a pull receives a remote commit
+        let next = reader_rt.next_remote_commit(&other);
+        tokio::pin!(next);
+        assert!(next.as_mut().now_or_never().is_none());
+        push(1);
+        assert!(next.as_mut().now_or_never().is_none());
+        reader_rt.volume_pull(other.clone()).unwrap();
+        let snapshot = tokio_rt.block_on(next).unwrap();
+        assert_eq!(snapshot.head(), Some((&volume.remote, LSN::FIRST)));
+
+        // pulling without new remote commits doesn't resolve the future
+        let next = reader_rt.next_remote_commit(&other);
+        tokio::pin!(next);
+        reader_rt.volume_pull(other.clone()).unwrap();
+        assert!(next.as_mut().now_or_never().is_none());
+
+        // a commit received while nothing is polling the future still
+        // resolves it
+        push(2);
+        reader_rt.volume_pull(other.clone()).unwrap();
+        let snapshot = next.now_or_never().unwrap().unwrap();
+        assert_eq!(snapshot.head(), Some((&volume.remote, LSN::new(2))));
+        let reader = reader_rt.volume_reader(other).unwrap();
+        assert_eq!(reader.read_page(pageidx!(1)).unwrap(), Page::test_filled(2));
+    }
+
+    #[test]
+    fn export_import_config() {
+        let tokio_rt = test_tokio_rt();
+
+        let new_runtime = || test_runtime(&tokio_rt, memory_remote());
+
+        let runtime = new_runtime();
+        let remote_log = LogId::random();
+        let vid = runtime
+            .volume_open(None, None, Some(remote_log.clone()))
+            .unwrap()
+            .vid;
+        let other = runtime.volume_open(None, None, None).unwrap().vid;
+        runtime.tag_replace("main", vid.clone()).unwrap();
+        runtime.tag_replace("backup", vid.clone()).unwrap();
+        runtime.tag_replace("other", other).unwrap();
+        runtime.set_fill_byte(&vid, Some(0xAB)).unwrap();
+        runtime
+            .set_conflict_policy(&vid, ConflictPolicy::LastWriterWins)
+            .unwrap();
+        runtime
+            .set_max_pages(&vid, Some(PageCount::new(16)))
+            .unwrap();
+
+        let config = runtime.export_config(&vid).unwrap();
+        assert_eq!(config.vid, vid);
+        assert_eq!(config.remote, remote_log);
+        assert_eq!(config.tags, ["backup", "main"]);
+        assert_eq!(config.fill_byte, Some(0xAB));
+        assert_eq!(config.conflict_policy, ConflictPolicy::LastWriterWins);
+        assert_eq!(config.max_pages, Some(PageCount::new(16)));
+
+        // restore the config into a fresh installation
+        let restored = new_runtime();
+        let volume = restored.import_config(&config).unwrap();
+        assert_eq!(volume.vid, vid);
+        assert_eq!(volume.remote, remote_log);
+        assert_eq!(restored.fill_byte(&vid).unwrap(), 0xAB);
+        assert_eq!(
+            restored.conflict_policy(&vid).unwrap(),
+            ConflictPolicy::LastWriterWins
+        );
+        assert_eq!(restored.max_pages(&vid).unwrap(), Some(PageCount::new(16)));
+        assert_eq!(restored.export_config(&vid).unwrap(), config);
+
+        // importing is idempotent
+        restored.import_config(&config).unwrap();
+        assert_eq!(restored.export_config(&vid).unwrap(), config);
+    }
+
+    #[test]
+    fn tags_for_volume() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        let aliased = runtime.volume_open(None, None, None).unwrap().vid;
+        let single = runtime.volume_open(None, None, None).unwrap().vid;
+        let untagged = runtime.volume_open(None, None, None).unwrap().vid;
+        for tag in ["main", "prod", "latest"] {
+            runtime.tag_replace(tag, aliased.clone()).unwrap();
+        }
+        runtime.tag_replace("other", single.clone()).unwrap();
+
+        assert_eq!(
+            runtime.tags_for_volume(&aliased).unwrap(),
+            ["latest", "main", "prod"]
+        );
+        assert_eq!(runtime.tags_for_volume(&single).unwrap(), ["other"]);
+        assert!(runtime.tags_for_volume(&untagged).unwrap().is_empty());
+
+        // retargeting a tag removes it from the old volume's aliases
+        runtime.tag_replace("prod", single.clone()).unwrap();
+        assert_eq!(
+            runtime.tags_for_volume(&aliased).unwrap(),
+            ["latest", "main"]
+        );
+        assert_eq!(runtime.tags_for_volume(&single).unwrap(), ["other", "prod"]);
+    }
+
+    #[test]
+    fn automatic_checkpoint() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+        runtime.set_compact_after_commits(NonZero::new(3));
+
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        let write = |i: u8| {
+            let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+            writer
+                .write_page(PageIdx::must_new(i as u32), Page::test_filled(i))
+                .unwrap();
+            writer.commit().unwrap();
+        };
+
+        // none of these commits contain every page, so none are checkpoints
+        for i in [10, 1, 2] {
+            write(i);
+        }
+        assert_eq!(runtime.commits_since_checkpoint(&vid).unwrap(), 3);
+
+        // the commit which exceeds the threshold becomes a checkpoint
+        write(3);
+        assert_eq!(runtime.commits_since_checkpoint(&vid).unwrap(), 0);
+
+        // loading the snapshot's pages stops at the checkpoint
+        let snapshot = runtime.volume_snapshot(&vid).unwrap();
+        let segments = runtime
+            .storage()
+            .read()
+            .iter_visible_pages(&snapshot)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].1.cardinality(), 10);
+
+        let reader = runtime.volume_reader(vid.clone()).unwrap();
+        for i in [1u8, 2, 3, 10] {
+            let pageidx = PageIdx::must_new(i as u32);
+            assert_eq!(reader.read_page(pageidx).unwrap(), Page::test_filled(i));
+        }
+        assert!(reader.read_page(pageidx!(4)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn automatic_checkpoint_skipped() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+        runtime.set_compact_after_commits(NonZero::new(2));
+
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        let write = |i: u8| {
+            let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+            writer
+                .write_page(PageIdx::must_new(i as u32), Page::test_filled(i))
+                .unwrap();
+            writer.commit().unwrap();
+        };
+        let storage_reads = || runtime.volume_metrics(&vid).storage_reads;
+
+        write(10);
+        write(1);
+
+        // simulate a partially hydrated volume by removing page 1 locally
+        let snapshot = runtime.volume_snapshot(&vid).unwrap();
+        let commit = runtime
+            .storage()
+            .read()
+            .search_page(&snapshot, pageidx!(1))
+            .unwrap()
+            .unwrap();
+        runtime
+            .storage()
+            .remove_page(commit.segment_id().unwrap().clone(), pageidx!(1))
+            .unwrap();
+
+        // the checkpoint is attempted once the threshold is reached, and
+        // skipped as page 1 is missing
+        write(2);
+        assert_eq!(storage_reads(), 1);
+
+        // the next commit doesn't rescan the volume's pages
+        write(3);
+        assert_eq!(storage_reads(), 1);
+
+        // the checkpoint is retried after another threshold of commits
+        write(4);
+        assert_eq!(storage_reads(), 2);
+        assert_eq!(runtime.commits_since_checkpoint(&vid).unwrap(), 5);
+    }
+
+    #[test]
+    fn multi_commit_is_atomic() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        let a = runtime.volume_open(None, None, None).unwrap().vid;
+        let b = runtime.volume_open(None, None, None).unwrap().vid;
+
+        // both volumes advance together
+        let mut wa = runtime.volume_writer(a.clone()).unwrap();
+        wa.write_page(pageidx!(1), Page::test_filled(1)).unwrap();
+        let mut wb = runtime.volume_writer(b.clone()).unwrap();
+        wb.write_page(pageidx!(1), Page::test_filled(2)).unwrap();
+        let snapshots = runtime.multi_commit(vec![wa, wb]).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0], runtime.volume_snapshot(&a).unwrap());
+        assert_eq!(snapshots[1], runtime.volume_snapshot(&b).unwrap());
+
+        // a conflict on one volume aborts the entire commit
+        let mut wa = runtime.volume_writer(a.clone()).unwrap();
+        wa.write_page(pageidx!(2), Page::test_filled(3)).unwrap();
+        let mut stale = runtime.volume_writer(b.clone()).unwrap();
+        stale.write_page(pageidx!(2), Page::test_filled(4)).unwrap();
+        let mut wb = runtime.volume_writer(b.clone()).unwrap();
+        wb.write_page(pageidx!(3), Page::test_filled(5)).unwrap();
+        wb.commit().unwrap();
+
+        let before_a = runtime.volume_snapshot(&a).unwrap();
+        let before_b = runtime.volume_snapshot(&b).unwrap();
+        let err = runtime.multi_commit(vec![wa, stale]).unwrap_err();
+        assert!(matches!(
+            err,
+            GraftErr::Logical(LogicalErr::VolumeConcurrentWrite(ref vid)) if vid == &b
+        ));
+        assert_eq!(runtime.volume_snapshot(&a).unwrap(), before_a);
+        assert_eq!(runtime.volume_snapshot(&b).unwrap(), before_b);
+        let reader = runtime.volume_reader(a.clone()).unwrap();
+        assert!(reader.read_page(pageidx!(2)).unwrap().is_empty());
+
+        // writers targeting the same volume conflict with each other
+        let w1 = runtime.volume_writer(a.clone()).unwrap();
+        let w2 = runtime.volume_writer(a.clone()).unwrap();
+        assert!(matches!(
+            runtime.multi_commit(vec![w1, w2]).unwrap_err(),
+            GraftErr::Logical(LogicalErr::VolumeConcurrentWrite(_))
+        ));
+        assert_eq!(runtime.volume_snapshot(&a).unwrap(), before_a);
+    }
+
+    #[test]
+    fn event_log() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.log");
+        runtime.set_event_log(Some(
+            EventLog::open(&path, EventLogDurability::Strict).unwrap(),
+        ));
+
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        for i in [1u8, 2] {
+            let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+            writer
+                .write_page(PageIdx::must_new(i as u32), Page::test_filled(i))
+                .unwrap();
+            writer.commit().unwrap();
+        }
+        runtime.volume_push(vid.clone()).unwrap();
+        let hash = runtime
+            .storage()
+            .read()
+            .latest_commit(&runtime.volume_get(&vid).unwrap().remote)
+            .unwrap()
+            .unwrap()
+            .commit_hash;
+        assert!(hash.is_some());
+
+        // the file is complete as soon as each commit returns
+        let records: Vec<_> = read_event_log(&path)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.kind, r.vid, r.lsn, r.page_count, r.hash))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                (
+                    EventKind::Local,
+                    vid.clone(),
+                    LSN::FIRST,
+                    PageCount::new(1),
+                    None
+                ),
+                (
+                    EventKind::Local,
+                    vid.clone(),
+                    LSN::new(2),
+                    PageCount::new(2),
+                    None
+                ),
+                (
+                    EventKind::Push,
+                    vid.clone(),
+                    LSN::FIRST,
+                    PageCount::new(2),
+                    hash
+                ),
+            ]
+        );
+
+        // checkpoints, forks, and rebases are recorded as local commits
+        runtime.volume_checkpoint(&vid).unwrap();
+        let snapshot = runtime.volume_snapshot(&vid).unwrap();
+        let fork = runtime.volume_from_snapshot(&snapshot).unwrap();
+        let fork_lsn = runtime
+            .storage()
+            .read()
+            .latest_lsn(&fork.local)
+            .unwrap()
+            .unwrap();
+        runtime.testonly_simulate_remote_commit(&vid).unwrap();
+        runtime
+            .set_conflict_policy(&vid, ConflictPolicy::LastWriterWins)
+            .unwrap();
+        runtime.volume_push(vid.clone()).unwrap();
+        let hash = runtime
+            .storage()
+            .read()
+            .latest_commit(&runtime.volume_get(&vid).unwrap().remote)
+            .unwrap()
+            .unwrap()
+            .commit_hash;
+
+        let records: Vec<_> = read_event_log(&path)
+            .unwrap()
+            .into_iter()
+            .skip(3)
+            .map(|r| (r.kind, r.vid, r.lsn, r.page_count, r.hash))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                (
+                    EventKind::Local,
+                    vid.clone(),
+                    LSN::new(3),
+                    PageCount::new(2),
+                    None
+                ),
+                (
+                    EventKind::Local,
+                    fork.vid,
+                    fork_lsn,
+                    PageCount::new(2),
+                    None
+                ),
+                (
+                    EventKind::Local,
+                    vid.clone(),
+                    LSN::FIRST,
+                    PageCount::new(2),
+                    None
+                ),
+                (
+                    EventKind::Push,
+                    vid.clone(),
+                    LSN::new(3),
+                    PageCount::new(2),
+                    hash
+                ),
+            ]
+        );
+
+        // disabling the event log stops recording commits
+        runtime.set_event_log(None);
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(3), Page::test_filled(3))
+            .unwrap();
+        writer.commit().unwrap();
+        assert_eq!(read_event_log(&path).unwrap().len(), 7);
+    }
+
+    #[test]
+    fn audit_report() {
+        let tokio_rt = test_tokio_rt();
+
+        let remote = memory_remote();
+        let new_runtime = || test_runtime(&tokio_rt, remote.clone());
+        let writer_rt = new_runtime();
+        let reader_rt = new_runtime();
+
+        // push two commits so the halves of the volume live in separate
+        // segments
+        let volume = writer_rt.volume_open(None, None, None).unwrap();
+        for pages in [1..=64, 65..=128] {
+            let mut writer = writer_rt.volume_writer(volume.vid.clone()).unwrap();
+            for i in pages {
+                writer
+                    .write_page(PageIdx::must_new(i), Page::test_filled(i as u8))
+                    .unwrap();
+            }
+            writer.commit().unwrap();
+            writer_rt.volume_push(volume.vid.clone()).unwrap();
+        }
+
+        let vid = reader_rt
+            .volume_open(None, None, Some(volume.remote))
+            .unwrap()
+            .vid;
+        reader_rt.volume_pull(vid.clone()).unwrap();
+        let snapshot = reader_rt.volume_snapshot(&vid).unwrap();
+
+        // only hydrate the first half of the volume
+        reader_rt
+            .snapshot_prefetch(snapshot.clone(), pageidx!(1)..=pageidx!(64))
+            .unwrap();
+        let report = reader_rt.audit_report(&snapshot).unwrap();
+        assert_eq!(report.total_pages, PageCount::new(128));
+        assert_eq!(report.cached_pages, PageCount::new(64));
+        assert_eq!(
+            report.missing,
+            PageSet::from_range(pageidx!(65)..=pageidx!(128))
+        );
+        assert_eq!(report.pct_cached, 50.0);
+        assert!(!report.is_complete());
+        assert!(report.checksum.is_none());
+
+        // once complete, the report includes the checksum
+        reader_rt.snapshot_hydrate(snapshot.clone()).unwrap();
+        let report = reader_rt.audit_report(&snapshot).unwrap();
+        assert!(report.is_complete());
+        assert_eq!(report.pct_cached, 100.0);
+        assert_eq!(
+            report.checksum,
+            Some(
+                writer_rt
+                    .snapshot_checksum(&writer_rt.volume_snapshot(&volume.vid).unwrap())
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn conflict_policy_last_writer_wins() {
+        let tokio_rt = test_tokio_rt();
+
+        let remote = memory_remote();
+        let new_runtime = || test_runtime(&tokio_rt, remote.clone());
+        let rt_a = new_runtime();
+        let rt_b = new_runtime();
+        let write = |runtime: &Runtime, vid: &VolumeId, pageidx: PageIdx, fill: u8| {
+            let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+            writer.write_page(pageidx, Page::test_filled(fill)).unwrap();
+            writer.commit().unwrap();
+        };
+
+        let volume = rt_a.volume_open(None, None, None).unwrap();
+        let vid_a = volume.vid;
+        write(&rt_a, &vid_a, pageidx!(1), 1);
+        rt_a.volume_push(vid_a.clone()).unwrap();
+        let vid_b = rt_b
+            .volume_open(None, None, Some(volume.remote))
+            .unwrap()
+            .vid;
+        rt_b.volume_pull(vid_b.clone()).unwrap();
+
+        // both peers write disjoint pages; by default the second push conflicts
+        write(&rt_a, &vid_a, pageidx!(2), 2);
+        rt_a.volume_push(vid_a.clone()).unwrap();
+        write(&rt_b, &vid_b, pageidx!(3), 3);
+        assert!(matches!(
+            rt_b.volume_push(vid_b.clone()).unwrap_err(),
+            GraftErr::Logical(LogicalErr::VolumeDiverged(_))
+        ));
+
+        // last-writer-wins rebases the local changes and retries the push
+        rt_b.set_conflict_policy(&vid_b, ConflictPolicy::LastWriterWins)
+            .unwrap();
+        rt_b.volume_push(vid_b.clone()).unwrap();
+        rt_a.volume_pull(vid_a.clone()).unwrap();
+        for (runtime, vid) in [(&rt_a, &vid_a), (&rt_b, &vid_b)] {
+            let reader = runtime.volume_reader(vid.clone()).unwrap();
+            for i in [1u8, 2, 3] {
+                assert_eq!(
+                    reader.read_page(PageIdx::must_new(i as u32)).unwrap(),
+                    Page::test_filled(i)
+                );
+            }
+        }
+        assert_eq!(rt_b.volume_status(&vid_b).unwrap().to_string(), "1 r3");
+        // the policy is stored with the Volume, which survives the rebase
+        assert_eq!(
+            rt_b.conflict_policy(&vid_b).unwrap(),
+            ConflictPolicy::LastWriterWins
+        );
+
+        // overlapping writes still conflict
+        write(&rt_a, &vid_a, pageidx!(2), 4);
+        rt_a.volume_push(vid_a.clone()).unwrap();
+        write(&rt_b, &vid_b, pageidx!(2), 5);
+        assert!(matches!(
+            rt_b.volume_push(vid_b.clone()).unwrap_err(),
+            GraftErr::Logical(LogicalErr::VolumeDiverged(_))
+        ));
+        let reader = rt_b.volume_reader(vid_b.clone()).unwrap();
+        assert_eq!(reader.read_page(pageidx!(2)).unwrap(), Page::test_filled(5));
+    }
+
+    #[test]
+    fn reachable_range() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        let volume = runtime.volume_open(None, None, None).unwrap();
+        let vid = volume.vid.clone();
+        assert_eq!(
+            runtime
+                .volume_reader(vid.clone())
+                .unwrap()
+                .reachable_range(),
+            None
+        );
+
+        let commit = |i: u8| {
+            let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+            writer
+                .write_page(PageIdx::must_new(i as u32), Page::test_filled(i))
+                .unwrap();
+            writer.commit().unwrap();
+        };
+        for i in 1..=3 {
+            commit(i);
+        }
+        let reader = runtime.volume_reader(vid.clone()).unwrap();
+        assert_eq!(
+            reader.reachable_range(),
+            Some((volume.local.clone(), LSN::FIRST..=LSN::new(3)))
+        );
+        assert_eq!(
+            reader.reachable_layers(),
+            [(volume.local.clone(), LSN::FIRST..=LSN::new(3))]
+        );
+
+        // once pushed, only the remote is reachable
+        runtime.volume_push(vid.clone()).unwrap();
+        let reader = runtime.volume_reader(vid.clone()).unwrap();
+        assert_eq!(
+            reader.reachable_range(),
+            Some((volume.remote.clone(), LSN::FIRST..=LSN::FIRST))
+        );
+
+        // new local commits layer on top of the remote
+        for i in 4..=5 {
+            commit(i);
+        }
+        let reader = runtime.volume_reader(vid.clone()).unwrap();
+        assert_eq!(
+            reader.reachable_range(),
+            Some((volume.local.clone(), LSN::new(4)..=LSN::new(5)))
+        );
+        assert_eq!(
+            reader.reachable_layers(),
+            [
+                (volume.local.clone(), LSN::new(4)..=LSN::new(5)),
+                (volume.remote.clone(), LSN::FIRST..=LSN::FIRST),
+            ]
+        );
+    }
+
+    #[test]
+    fn page_cache() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+        assert_eq!(runtime.page_cache_stats(), None);
+        runtime.set_page_cache_capacity(NonZero::new(16));
+
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(1), Page::test_filled(1))
+            .unwrap();
+        writer.commit().unwrap();
+
+        // a page read by one connection is served from the cache to another
+        let reader_a = runtime.volume_reader(vid.clone()).unwrap();
+        let reader_b = runtime.volume_reader(vid.clone()).unwrap();
+        assert_eq!(
+            reader_a.read_page(pageidx!(1)).unwrap(),
+            Page::test_filled(1)
+        );
+        assert_eq!(
+            reader_b.read_page(pageidx!(1)).unwrap(),
+            Page::test_filled(1)
+        );
+        let stats = runtime.page_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.pages), (1, 1, 1));
+
+        // writing the page evicts it from the cache
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(1), Page::test_filled(2))
+            .unwrap();
+        writer.commit().unwrap();
+        assert_eq!(runtime.page_cache_stats().unwrap().pages, 0);
+        let reader_c = runtime.volume_reader(vid.clone()).unwrap();
+        assert_eq!(
+            reader_c.read_page(pageidx!(1)).unwrap(),
+            Page::test_filled(2)
+        );
+
+        // readers of the older snapshot never see the newer cached page
+        assert_eq!(
+            reader_a.read_page(pageidx!(1)).unwrap(),
+            Page::test_filled(1)
+        );
+        assert_eq!(
+            runtime.page_cache_stats().unwrap(),
+            PageCacheStats {
+                capacity: 16,
+                pages: 1,
+                hits: 1,
+                misses: 3
+            }
+        );
+    }
+
+    #[test]
+    fn conflict_report() {
+        let tokio_rt = test_tokio_rt();
+
+        let remote = memory_remote();
+        let new_runtime = || test_runtime(&tokio_rt, remote.clone());
+        let rt_a = new_runtime();
+        let rt_b = new_runtime();
+        let write = |runtime: &Runtime, vid: &VolumeId, pages: &[u32]| {
+            let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+            for &i in pages {
+                writer
+                    .write_page(PageIdx::must_new(i), Page::test_filled(i as u8))
+                    .unwrap();
+            }
+            writer.commit().unwrap();
+        };
+
+        let volume = rt_a.volume_open(None, None, None).unwrap();
+        let vid_a = volume.vid;
+        write(&rt_a, &vid_a, &[1]);
+        rt_a.volume_push(vid_a.clone()).unwrap();
+        let vid_b = rt_b
+            .volume_open(None, None, Some(volume.remote))
+            .unwrap()
+            .vid;
+        rt_b.volume_pull(vid_b.clone()).unwrap();
+        assert_eq!(rt_b.conflict_report(&vid_b).unwrap(), None);
+
+        // both peers change the volume independently
+        for pages in [&[2, 3][..], &[5]] {
+            write(&rt_a, &vid_a, pages);
+            rt_a.volume_push(vid_a.clone()).unwrap();
+        }
+        write(&rt_b, &vid_b, &[3, 4]);
+        // only local changes are not a conflict
+        assert_eq!(rt_b.conflict_report(&vid_b).unwrap(), None);
+        assert!(matches!(
+            rt_b.volume_pull(vid_b.clone()).unwrap_err(),
+            GraftErr::Logical(LogicalErr::VolumeDiverged(_))
+        ));
+
+        let report = rt_b.conflict_report(&vid_b).unwrap().unwrap();
+        assert_eq!(report.vid, vid_b);
+        assert_eq!(report.fork_point, Some(LSN::FIRST));
+        let lsns = |commits: &[Commit]| commits.iter().map(|c| c.lsn).collect::<Vec<_>>();
+        assert_eq!(lsns(&report.local_commits), [LSN::FIRST]);
+        assert_eq!(lsns(&report.remote_commits), [LSN::new(2), LSN::new(3)]);
+        assert_eq!(
+            report.overlapping,
+            PageSet::from_range(pageidx!(3)..=pageidx!(3))
+        );
+    }
+
+    #[test]
+    fn volume_pull_timeout() {
+        let tokio_rt = test_tokio_rt();
+
+        // every commit read stalls for an hour
+        let remote = Arc::new(
+            RemoteConfig::Memory
+                .build()
+                .unwrap()
+                .testonly_with_commit_latency(Duration::from_secs(3600)),
+        );
+        let runtime = test_runtime(&tokio_rt, remote);
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+
+        let timeout = Duration::from_secs(1);
+        let err = runtime
+            .volume_pull_timeout(vid.clone(), Some(timeout))
+            .unwrap_err();
+        assert!(matches!(err, GraftErr::Remote(RemoteErr::Timeout(t)) if t == timeout));
+
+        // the volume is unchanged and can still be used
+        assert_eq!(runtime.volume_status(&vid).unwrap().to_string(), "_ r_");
+        let mut writer = runtime.volume_writer(vid).unwrap();
+        writer
+            .write_page(pageidx!(1), Page::test_filled(1))
+            .unwrap();
+        writer.commit().unwrap();
+    }
+
+    #[test]
+    fn check_page_count() {
+        let tokio_rt = test_tokio_rt();
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        // a volume which never synced has nothing to check
+        let volume = runtime.volume_open(None, None, None).unwrap();
+        let vid = volume.vid.clone();
+        assert_eq!(runtime.check_page_count(&vid).unwrap(), None);
+
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(3), Page::test_filled(3))
+            .unwrap();
+        writer.commit().unwrap();
+        runtime.volume_push(vid.clone()).unwrap();
+
+        let check = runtime.check_page_count(&vid).unwrap().unwrap();
+        assert_eq!(check.remote_lsn, LSN::FIRST);
+        assert_eq!(check.local, PageCount::new(3));
+        assert!(check.agrees(), "{check:?}");
+
+        // corrupt the local copy of the synced commit
+        let mut commit = runtime
+            .get_commit(&volume.remote, LSN::FIRST)
+            .unwrap()
+            .unwrap();
+        commit.page_count = PageCount::new(7);
+        let mut batch = runtime.storage().batch();
+        batch.write_commit(commit);
+        batch.commit().unwrap();
+
+        let check = runtime.check_page_count(&vid).unwrap().unwrap();
+        assert_eq!(check.local, PageCount::new(7));
+        assert_eq!(check.remote, Some(PageCount::new(3)));
+        assert!(!check.agrees());
+    }
+
+    #[test]
+    fn gc_orphan_segments() {
+        let tokio_rt = test_tokio_rt();
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+
+        let write = |vid: &VolumeId, fill: u8| {
+            let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+            writer
+                .write_page(pageidx!(1), Page::test_filled(fill))
+                .unwrap();
+            writer.commit().unwrap();
+        };
+
+        // a pushed volume references segments in both of its logs
+        let kept = runtime.volume_open(None, None, None).unwrap().vid;
+        write(&kept, 1);
+        runtime.volume_push(kept.clone()).unwrap();
+        write(&kept, 2);
+
+        // a deleted volume's segments are no longer referenced
+        let deleted = runtime.volume_open(None, None, None).unwrap().vid;
+        write(&deleted, 3);
+        runtime.volume_delete(&deleted).unwrap();
+
+        // as are pages written without a commit, e.g. by an interrupted import
+        let stray = SegmentId::random();
+        runtime
+            .storage()
+            .write_page(stray.clone(), pageidx!(1), Page::test_filled(4))
+            .unwrap();
+
+        let orphans = runtime.orphan_segments().unwrap();
+        assert_eq!(orphans.len(), 2, "{orphans:?}");
+        assert!(orphans.contains(&stray));
+
+        let report = runtime.gc_orphan_segments().unwrap();
+        assert_eq!(report.segments, orphans);
+        assert_eq!(report.pages, 2);
+        assert_eq!(report.bytes.as_u64(), 2 * PAGESIZE.as_u64());
+        assert_eq!(runtime.orphan_segments().unwrap(), []);
+        assert!(
+            !runtime
+                .storage()
+                .read()
+                .has_page(stray, pageidx!(1))
+                .unwrap()
+        );
+
+        // the referenced segments survive
+        let reader = runtime.volume_reader(kept.clone()).unwrap();
+        assert_eq!(reader.read_page(pageidx!(1)).unwrap(), Page::test_filled(2));
+        assert_eq!(runtime.volume_metrics(&kept).pages_fetched, 0);
+    }
+
+    #[test]
+    fn set_autosync_interval() {
+        // use real time, as the test waits on the autosync task
+        let tokio_rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+
+        let commit = |fill: u8| {
+            let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+            writer
+                .write_page(pageidx!(1), Page::test_filled(fill))
+                .unwrap();
+            writer.commit().unwrap();
+        };
+        let wait_for_push = || {
+            let deadline = std::time::Instant::now() + Duration::from_secs(10);
+            while runtime.pending_commits(&vid).unwrap() > 0 {
+                assert!(std::time::Instant::now() < deadline, "autosync didn't push");
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        };
+
+        // nothing syncs while autosync is disabled
+        commit(1);
+        runtime.notify_idle();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(runtime.pending_commits(&vid).unwrap(), 1);
+
+        // enabling autosync syncs immediately rather than after the interval
+        let interval = Duration::from_secs(3600);
+        runtime.set_autosync_interval(Some(interval));
+        assert_eq!(runtime.autosync_interval(), Some(interval));
+        wait_for_push();
+
+        // a shorter interval takes effect without restarting the runtime
+        runtime.set_autosync_interval(Some(Duration::from_millis(10)));
+        std::thread::sleep(Duration::from_millis(50));
+        commit(2);
+        wait_for_push();
+
+        // disabling autosync stops the task from syncing, even when signaled
+        runtime.set_autosync_interval(None);
+        std::thread::sleep(Duration::from_millis(50));
+        commit(3);
+        runtime.notify_idle();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(runtime.autosync_interval(), None);
+        assert_eq!(runtime.pending_commits(&vid).unwrap(), 1);
+    }
+
+    #[test]
+    fn commit_backpressure() {
+        // use real time, as backpressure waits on the autosync task
+        let tokio_rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        // every commit read is slow, which bounds how quickly autosync can
+        // fetch and push commits
+        let latency = Duration::from_millis(20);
+        let remote = Arc::new(
+            RemoteConfig::Memory
+                .build()
+                .unwrap()
+                .testonly_with_commit_latency(latency),
+        );
+        let storage = Arc::new(FjallStorage::open_temporary().unwrap());
+        // a long interval, so that only backpressure wakes autosync
+        let runtime = Runtime::new(
+            tokio_rt.handle().clone(),
+            remote.clone(),
+            storage,
+            Some(Duration::from_secs(3600)),
+        );
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+
+        let commit = |runtime: &Runtime, vid: &VolumeId, fill: u8| {
+            let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+            writer
+                .write_page(pageidx!(1), Page::test_filled(fill))
+                .unwrap();
+            writer.commit().unwrap();
+        };
+
+        // each commit waits for autosync to push it before the next commit
+        let start = std::time::Instant::now();
+        for i in 1..=5 {
+            commit(&runtime, &vid, i);
+            runtime.wait_for_sync_capacity(&vid, 0, None).unwrap();
+            assert_eq!(runtime.pending_commits(&vid).unwrap(), 0);
+        }
+        assert!(start.elapsed() >= latency * 5, "{:?}", start.elapsed());
+        assert_eq!(runtime.volume_status(&vid).unwrap().to_string(), "5 r5");
+
+        // commits don't wait while the backlog is within capacity
+        commit(&runtime, &vid, 6);
+        runtime.wait_for_sync_capacity(&vid, 1, None).unwrap();
+        assert_eq!(runtime.pending_commits(&vid).unwrap(), 1);
+
+        // without autosync, nothing would push the backlog, so commits
+        // don't wait
+        let runtime = test_runtime(&tokio_rt, remote);
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        for i in 1..=3 {
+            commit(&runtime, &vid, i);
+            runtime.wait_for_sync_capacity(&vid, 0, None).unwrap();
+        }
+        assert_eq!(runtime.pending_commits(&vid).unwrap(), 3);
+    }
+
+    #[graft_test::test]
+    fn commit_backpressure_diverged() {
+        let tokio_rt = test_tokio_rt();
+        let remote = memory_remote();
+        let storage = Arc::new(FjallStorage::open_temporary().unwrap());
+        let runtime = Runtime::new(
+            tokio_rt.handle().clone(),
+            remote,
+            storage,
+            Some(Duration::from_secs(3600)),
+        );
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(1), Page::test_filled(1))
+            .unwrap();
+        writer.commit().unwrap();
+        runtime.testonly_simulate_remote_commit(&vid).unwrap();
+        assert!(runtime.volume_status(&vid).unwrap().has_diverged());
+
+        // autosync can't push a diverged volume, so the commit doesn't wait
+        // for the timeout
+        let start = std::time::Instant::now();
+        runtime.wait_for_sync_capacity(&vid, 0, None).unwrap();
+        assert!(
+            start.elapsed() < Duration::from_secs(1),
+            "{:?}",
+            start.elapsed()
+        );
+        assert_eq!(runtime.pending_commits(&vid).unwrap(), 1);
+    }
 }
@@ -0,0 +1,165 @@
+use std::{collections::HashMap, fmt::Display, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::core::VolumeId;
+
+/// The number of syncs without progress within `WINDOW` which causes autosync
+/// to pause a Volume
+const MAX_STALLED_SYNCS: usize = 8;
+
+/// Stalled syncs older than this are forgotten
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Detects Volumes which autosync keeps syncing without making progress, for
+/// example two peers ping-ponging a diverged Volume. A sync makes progress
+/// when it changes the Volume's sync point.
+///
+/// Once a Volume stalls more than `MAX_STALLED_SYNCS` times within `WINDOW`,
+/// autosync is paused for that Volume until it makes progress through some
+/// other means (such as a manual push or pull) or is explicitly resumed.
+#[derive(Debug, Default)]
+pub struct SyncHealthTracker {
+    volumes: HashMap<VolumeId, VolumeSyncState>,
+}
+
+#[derive(Debug, Default)]
+struct VolumeSyncState {
+    /// when recent syncs without progress happened, oldest first
+    stalls: Vec<Instant>,
+    paused_at: Option<Instant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeSyncHealth {
+    /// the number of syncs without progress within the window
+    pub stalled_syncs: usize,
+    /// how long ago autosync was paused for this Volume, if it's paused
+    pub paused_for: Option<Duration>,
+}
+
+impl SyncHealthTracker {
+    /// Records the outcome of an autosync attempt which had work to do.
+    /// Returns true if this attempt caused the Volume to be paused.
+    pub fn record_sync(&mut self, vid: &VolumeId, progressed: bool, now: Instant) -> bool {
+        if progressed {
+            self.volumes.remove(vid);
+            return false;
+        }
+
+        let state = self.volumes.entry(vid.clone()).or_default();
+        state.stalls.retain(|t| now.duration_since(*t) < WINDOW);
+        state.stalls.push(now);
+
+        if state.paused_at.is_none() && state.stalls.len() > MAX_STALLED_SYNCS {
+            tracing::warn!(
+                ?vid,
+                stalled_syncs = state.stalls.len(),
+                window = ?WINDOW,
+                "volume is syncing repeatedly without making progress; pausing autosync"
+            );
+            state.paused_at = Some(now);
+            return true;
+        }
+        false
+    }
+
+    pub fn is_paused(&self, vid: &VolumeId) -> bool {
+        self.volumes.get(vid).is_some_and(|s| s.paused_at.is_some())
+    }
+
+    /// Forgets all recorded stalls for the Volume, resuming autosync if it
+    /// was paused.
+    pub fn resume(&mut self, vid: &VolumeId) {
+        if self
+            .volumes
+            .remove(vid)
+            .is_some_and(|s| s.paused_at.is_some())
+        {
+            tracing::info!(?vid, "resuming autosync");
+        }
+    }
+
+    pub fn health(&self, vid: &VolumeId, now: Instant) -> VolumeSyncHealth {
+        let Some(state) = self.volumes.get(vid) else {
+            return VolumeSyncHealth { stalled_syncs: 0, paused_for: None };
+        };
+        VolumeSyncHealth {
+            stalled_syncs: state
+                .stalls
+                .iter()
+                .filter(|t| now.duration_since(**t) < WINDOW)
+                .count(),
+            paused_for: state.paused_at.map(|t| now.duration_since(t)),
+        }
+    }
+}
+
+impl Display for VolumeSyncHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.paused_for {
+            Some(paused_for) => write!(
+                f,
+                "Autosync paused {}s ago after syncing repeatedly without progress",
+                paused_for.as_secs()
+            ),
+            None => write!(
+                f,
+                "Healthy: {} of {} allowed syncs without progress in the last {}s",
+                self.stalled_syncs,
+                MAX_STALLED_SYNCS,
+                WINDOW.as_secs()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalled_sync_loop_pauses() {
+        let mut tracker = SyncHealthTracker::default();
+        let vid = VolumeId::random();
+        let mut now = Instant::now();
+
+        // simulate a sync loop which never makes progress
+        for _ in 0..MAX_STALLED_SYNCS {
+            assert!(!tracker.record_sync(&vid, false, now));
+            now += Duration::from_secs(1);
+        }
+        assert!(!tracker.is_paused(&vid));
+        assert!(tracker.record_sync(&vid, false, now));
+        assert!(tracker.is_paused(&vid));
+
+        let health = tracker.health(&vid, now + Duration::from_secs(5));
+        assert_eq!(health.paused_for, Some(Duration::from_secs(5)));
+
+        // further stalls don't pause again
+        assert!(!tracker.record_sync(&vid, false, now));
+
+        // progress resumes autosync
+        tracker.record_sync(&vid, true, now);
+        assert!(!tracker.is_paused(&vid));
+        assert_eq!(tracker.health(&vid, now).stalled_syncs, 0);
+    }
+
+    #[test]
+    fn test_slow_stalls_dont_pause() {
+        let mut tracker = SyncHealthTracker::default();
+        let vid = VolumeId::random();
+        let mut now = Instant::now();
+
+        // stalls spread out beyond the window never accumulate
+        for _ in 0..MAX_STALLED_SYNCS * 4 {
+            assert!(!tracker.record_sync(&vid, false, now));
+            now += WINDOW / 4;
+        }
+        assert!(!tracker.is_paused(&vid));
+
+        // resume clears any recorded stalls
+        tracker.resume(&vid);
+        assert_eq!(tracker.health(&vid, now).stalled_syncs, 0);
+    }
+}
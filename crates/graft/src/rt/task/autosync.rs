@@ -1,8 +1,21 @@
-use std::{collections::HashSet, fmt::Debug, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    num::NonZero,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
 use crate::core::VolumeId;
 use futures::stream::FuturesUnordered;
-use tokio::time::Interval;
+use parking_lot::Mutex;
+use tokio::{
+    sync::Notify,
+    time::{Instant, Interval, MissedTickBehavior},
+};
 use tokio_stream::StreamExt;
 use tryiter::TryIteratorExt;
 
@@ -12,24 +25,108 @@ use crate::{
     remote::Remote,
     rt::{
         action::{Action, FetchLog, RemoteCommit},
+        observer::{EventSink, RuntimeEvent},
+        sync_health::SyncHealthTracker,
         task::{Result, Task},
+        volume_metrics::VolumeMetricsRegistry,
     },
+    volume::{ConflictPolicy, SyncPoint},
 };
 
 pub struct AutosyncTask {
-    ticker: Interval,
+    /// the autosync interval, or `None` while autosync is disabled, see
+    /// `Runtime::set_autosync_interval`
+    interval: Arc<Mutex<Option<Duration>>>,
+    /// ticks at the current interval; created once the task starts running
+    ticker: Option<Interval>,
+    /// wakes the task up early, see `Runtime::notify_network_available`
+    signal: Arc<Notify>,
+    /// detects Volumes which sync repeatedly without making progress
+    health: Arc<Mutex<SyncHealthTracker>>,
+    /// the sync point of each paused Volume at the time it was paused
+    paused: HashMap<VolumeId, Option<SyncPoint>>,
+    /// verify pushed commits against the remote, see `Runtime::set_verify_push`
+    verify_push: Arc<AtomicBool>,
+    /// bounds each batch of commits fetched from the remote, see
+    /// `Runtime::set_max_commits_per_fetch`
+    max_commits_per_fetch: Arc<Mutex<Option<NonZero<usize>>>>,
+    /// records failed pushes and pulls, see `Runtime::volume_metrics`
+    metrics: Arc<Mutex<VolumeMetricsRegistry>>,
+    /// delivers events to the observer, see `Runtime::set_observer`
+    events: EventSink,
 }
 
 impl AutosyncTask {
-    pub fn new(ticker: Interval) -> Self {
-        Self { ticker }
+    pub fn new(
+        interval: Arc<Mutex<Option<Duration>>>,
+        signal: Arc<Notify>,
+        health: Arc<Mutex<SyncHealthTracker>>,
+        verify_push: Arc<AtomicBool>,
+        max_commits_per_fetch: Arc<Mutex<Option<NonZero<usize>>>>,
+        metrics: Arc<Mutex<VolumeMetricsRegistry>>,
+        events: EventSink,
+    ) -> Self {
+        Self {
+            interval,
+            ticker: None,
+            signal,
+            health,
+            paused: HashMap::new(),
+            verify_push,
+            max_commits_per_fetch,
+            metrics,
+            events,
+        }
+    }
+}
+
+impl AutosyncTask {
+    /// Waits for the next tick or for the app to signal that now is a good
+    /// time to sync. Returns false if autosync is disabled, in which case
+    /// nothing should be synced.
+    async fn wait(&mut self) -> bool {
+        match self.ticker.as_mut() {
+            Some(ticker) => tokio::select! {
+                _ = ticker.tick() => {}
+                _ = self.signal.notified() => {
+                    tracing::trace!("autosync signaled");
+                    // the interval is a fallback; restart it from now
+                    ticker.reset();
+                }
+            },
+            // changing the interval signals the task
+            None => self.signal.notified().await,
+        }
+        if self.update_ticker()
+            && let Some(ticker) = self.ticker.as_mut()
+        {
+            // we are about to sync, so the next tick is a full interval away
+            ticker.reset();
+        }
+        self.ticker.is_some()
+    }
+
+    /// Replaces the ticker if the interval changed. Returns true if it
+    /// changed.
+    fn update_ticker(&mut self) -> bool {
+        let interval = *self.interval.lock();
+        if interval == self.ticker.as_ref().map(Interval::period) {
+            return false;
+        }
+        tracing::debug!(?interval, "autosync interval changed");
+        self.ticker = interval.map(|interval| {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticker
+        });
+        true
     }
 }
 
 impl Debug for AutosyncTask {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AutosyncTask")
-            .field("interval", &self.ticker.period())
+            .field("interval", &*self.interval.lock())
             .finish()
     }
 }
@@ -38,12 +135,17 @@ impl Task for AutosyncTask {
     const NAME: &'static str = "autosync";
 
     async fn run(&mut self, storage: Arc<FjallStorage>, remote: Arc<Remote>) -> Result<()> {
+        if self.ticker.is_none() {
+            self.update_ticker();
+        }
         loop {
-            // wait for the next tick
-            self.ticker.tick().await;
+            if !self.wait().await {
+                // autosync has been disabled
+                continue;
+            }
 
             enum Subtask {
-                Push { vid: VolumeId },
+                Push { vid: VolumeId, rebase: bool },
                 Pull { vid: VolumeId },
             }
 
@@ -51,24 +153,40 @@ impl Task for AutosyncTask {
             let mut fetches = HashSet::new();
             // a set of actions to execute
             let mut actions = vec![];
+            // the sync point of each Volume with pending changes, used to
+            // detect whether syncing it made progress
+            let mut pending = HashMap::new();
 
             // collect actions
             {
                 let reader = storage.read();
                 let mut volumes = reader.iter_volumes().map_err(GraftErr::from);
                 while let Some(volume) = volumes.try_next()? {
+                    if self.is_paused(&volume.vid, volume.sync()) {
+                        continue;
+                    }
+
                     let latest_local = reader.latest_lsn(&volume.local)?;
                     let latest_remote = reader.latest_lsn(&volume.remote)?;
                     let local_changes = volume.local_changes(latest_local).is_some();
                     let remote_changes = volume.remote_changes(latest_remote).is_some();
+                    if local_changes || remote_changes {
+                        pending.insert(volume.vid.clone(), volume.sync.clone());
+                    }
 
+                    // pushing may rebase onto newer remote commits
+                    let rebase = volume.settings.conflict_policy == ConflictPolicy::LastWriterWins;
                     if remote_changes && local_changes {
-                        // volume has diverged and requires user/app intervention
+                        // volume has diverged and requires user/app
+                        // intervention, unless pushing may rebase it
+                        if rebase {
+                            actions.push(Subtask::Push { vid: volume.vid, rebase })
+                        }
                     } else if remote_changes {
                         actions.push(Subtask::Pull { vid: volume.vid })
                     } else if local_changes {
                         fetches.insert(volume.remote);
-                        actions.push(Subtask::Push { vid: volume.vid })
+                        actions.push(Subtask::Push { vid: volume.vid, rebase })
                     } else {
                         fetches.insert(volume.remote);
                         actions.push(Subtask::Pull { vid: volume.vid });
@@ -77,9 +195,13 @@ impl Task for AutosyncTask {
             }
 
             // execute all scheduled fetches
+            let max_commits = *self.max_commits_per_fetch.lock();
             let mut futures: FuturesUnordered<_> = fetches
                 .into_iter()
-                .map(|log| FetchLog { log, max_lsn: None }.run(storage.clone(), remote.clone()))
+                .map(|log| {
+                    FetchLog { log, max_lsn: None, max_commits }
+                        .run(storage.clone(), remote.clone())
+                })
                 .collect();
             while let Some(result) = futures.next().await {
                 if let Err(err) = result {
@@ -88,26 +210,276 @@ impl Task for AutosyncTask {
             }
 
             // execute all scheduled actions
+            let verify = self.verify_push.load(Ordering::Relaxed);
+            let metrics = self.metrics.clone();
+            let events = self.events.clone();
             let mut futures: FuturesUnordered<_> = actions
                 .into_iter()
                 .map(|action| async {
                     match action {
-                        Subtask::Push { vid } => {
-                            RemoteCommit { vid }
-                                .run(storage.clone(), remote.clone())
-                                .await
+                        Subtask::Push { vid, rebase } => {
+                            events.emit(|| RuntimeEvent::PushStarted { vid: vid.clone() });
+                            let result = RemoteCommit {
+                                vid: vid.clone(),
+                                squash: false,
+                                verify,
+                                rebase,
+                                max_commits,
+                                events: events.clone(),
+                            }
+                            .run(storage.clone(), remote.clone())
+                            .await;
+                            if result.is_err() {
+                                metrics.lock().record_push_failure(&vid);
+                            } else {
+                                events.emit(|| RuntimeEvent::PushCompleted { vid: vid.clone() });
+                                events.log_push(&storage, &vid);
+                            }
+                            events.emit_conflict(&vid, &result);
+                            (vid, result)
                         }
                         Subtask::Pull { vid } => {
-                            Ok(storage.read_write().sync_remote_to_local(vid)?)
+                            let before = storage.read().volume(&vid).ok().and_then(|v| v.sync);
+                            let result = storage
+                                .read_write()
+                                .sync_remote_to_local(vid.clone())
+                                .map_err(GraftErr::from);
+                            if result.is_err() {
+                                metrics.lock().record_pull_failure(&vid);
+                            }
+                            events.emit_conflict(&vid, &result);
+                            if result.is_ok()
+                                && let Ok(volume) = storage.read().volume(&vid)
+                                && let Some(after) = volume.sync
+                                && before.is_none_or(|before| before.remote < after.remote)
+                            {
+                                events.remote_commit_received(&vid, after.remote);
+                            }
+                            (vid, result)
                         }
                     }
                 })
                 .collect();
-            while let Some(result) = futures.next().await {
-                if let Err(err) = result {
+            while let Some((vid, result)) = futures.next().await {
+                if let Err(err) = &result {
                     tracing::error!("Autosync action failed: {:?}", err);
                 }
+                let before = pending.remove(&vid);
+                let Ok(volume) = storage.read().volume(&vid) else {
+                    continue;
+                };
+                let progressed = match (result, before) {
+                    // a failed sync never makes progress
+                    (Err(_), _) => false,
+                    (Ok(_), Some(before)) => volume.sync != before,
+                    // there was nothing to sync
+                    (Ok(_), None) => continue,
+                };
+                if self
+                    .health
+                    .lock()
+                    .record_sync(&vid, progressed, Instant::now())
+                {
+                    self.paused.insert(vid, volume.sync);
+                }
             }
         }
     }
 }
+
+impl AutosyncTask {
+    /// Returns true if autosync is paused for the Volume. A paused Volume
+    /// resumes once its sync point changes, for example due to a manual push
+    /// or pull.
+    fn is_paused(&mut self, vid: &VolumeId, sync: Option<&SyncPoint>) -> bool {
+        let mut health = self.health.lock();
+        if !health.is_paused(vid) {
+            self.paused.remove(vid);
+            return false;
+        }
+        let paused_at = self
+            .paused
+            .entry(vid.clone())
+            .or_insert_with(|| sync.cloned());
+        if paused_at.as_ref() != sync {
+            health.resume(vid);
+            self.paused.remove(vid);
+            return false;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+    use parking_lot::Mutex;
+    use test_log::test;
+    use tokio::sync::Notify;
+
+    use crate::{
+        core::{LogId, PageCount, PageIdx, VolumeId, page::Page},
+        local::fjall_storage::FjallStorage,
+        remote::RemoteConfig,
+        rt::{
+            action::{Action, FetchLog, RemoteCommit},
+            sync_health::SyncHealthTracker,
+            task::supervise,
+            volume_metrics::VolumeMetricsRegistry,
+        },
+        volume::{ConflictPolicy, OpenPolicy},
+    };
+
+    use super::AutosyncTask;
+
+    /// commits a page filled with `fill` to the first page of the Volume
+    fn commit_page(storage: &FjallStorage, vid: &VolumeId, fill: u8) {
+        let snapshot = storage.read().snapshot(vid).unwrap();
+        let pages = BTreeMap::from([(PageIdx::FIRST, Page::test_filled(fill))]);
+        storage
+            .read_write()
+            .commit(vid, snapshot, PageCount::ONE, pages)
+            .unwrap();
+    }
+
+    fn push(vid: &VolumeId) -> RemoteCommit {
+        RemoteCommit {
+            vid: vid.clone(),
+            squash: false,
+            verify: false,
+            rebase: false,
+            max_commits: None,
+            events: Default::default(),
+        }
+    }
+
+    #[test(tokio::test(start_paused = true))]
+    async fn test_signal_triggers_sync() {
+        let storage = Arc::new(FjallStorage::open_temporary().unwrap());
+        let remote = Arc::new(RemoteConfig::Memory.build().unwrap());
+        let signal = Arc::new(Notify::new());
+
+        // the fallback interval is much longer than this test will wait
+        let interval = Duration::from_secs(3600);
+        let task = AutosyncTask::new(
+            Arc::new(Mutex::new(Some(interval))),
+            signal.clone(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+        tokio::spawn(supervise(storage.clone(), remote, task));
+
+        // let the task run its initial sync
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        // commit locally
+        let vid = storage
+            .read_write()
+            .volume_open(None, None, None, OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+        let snapshot = storage.read().snapshot(&vid).unwrap();
+        let pages = BTreeMap::from([(PageIdx::FIRST, Page::test_filled(1))]);
+        storage
+            .read_write()
+            .commit(&vid, snapshot, PageCount::ONE, pages)
+            .unwrap();
+
+        let pushed = || {
+            let volume = storage.read().volume(&vid).unwrap();
+            storage.read().latest_lsn(&volume.remote).unwrap().is_some()
+        };
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(!pushed(), "nothing should sync until signaled");
+
+        // signal that the network is available
+        signal.notify_one();
+        tokio::time::timeout(Duration::from_secs(60), async {
+            while !pushed() {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("signal should trigger a sync before the fallback interval");
+    }
+
+    #[test(tokio::test(start_paused = true))]
+    async fn test_failing_push_pauses_volume() {
+        let remote = Arc::new(RemoteConfig::Memory.build().unwrap());
+        let log = LogId::random();
+
+        // a peer pushes the first commit to the remote log
+        let peer = Arc::new(FjallStorage::open_temporary().unwrap());
+        let peer_vid = peer
+            .read_write()
+            .volume_open(None, None, Some(log.clone()), OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+        commit_page(&peer, &peer_vid, 1);
+        push(&peer_vid)
+            .run(peer.clone(), remote.clone())
+            .await
+            .unwrap();
+
+        // pull the first commit
+        let storage = Arc::new(FjallStorage::open_temporary().unwrap());
+        let vid = storage
+            .read_write()
+            .volume_open(None, None, Some(log.clone()), OpenPolicy::Strict)
+            .unwrap()
+            .vid;
+        FetchLog { log, max_lsn: None, max_commits: None }
+            .run(storage.clone(), remote.clone())
+            .await
+            .unwrap();
+        storage
+            .read_write()
+            .sync_remote_to_local(vid.clone())
+            .unwrap();
+
+        // both sides change the same page, so every push fails to rebase
+        commit_page(&peer, &peer_vid, 2);
+        push(&peer_vid)
+            .run(peer.clone(), remote.clone())
+            .await
+            .unwrap();
+        commit_page(&storage, &vid, 3);
+        storage
+            .read_write()
+            .update_volume_settings(&vid, |settings| {
+                settings.conflict_policy = ConflictPolicy::LastWriterWins
+            })
+            .unwrap();
+
+        let health: Arc<Mutex<SyncHealthTracker>> = Default::default();
+        let metrics: Arc<Mutex<VolumeMetricsRegistry>> = Default::default();
+        let task = AutosyncTask::new(
+            Arc::new(Mutex::new(Some(Duration::from_secs(1)))),
+            Default::default(),
+            health.clone(),
+            Default::default(),
+            Default::default(),
+            metrics.clone(),
+            Default::default(),
+        );
+        tokio::spawn(supervise(storage.clone(), remote, task));
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            while !health.lock().is_paused(&vid) {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("repeatedly failing pushes should pause autosync");
+
+        // a paused Volume is no longer pushed
+        let failures = metrics.lock().get(&vid).push_failures;
+        assert!(failures > 0);
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(metrics.lock().get(&vid).push_failures, failures);
+    }
+}
@@ -0,0 +1,111 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::core::VolumeId;
+
+/// Counters describing the activity of a single Volume since the runtime
+/// started or the counters were last reset.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeMetrics {
+    /// local commits made to the Volume
+    pub commits: u64,
+    /// pages written by local commits
+    pub pages_written: u64,
+    /// pages fetched from the remote while reading the Volume
+    pub pages_fetched: u64,
+    /// lookups against local storage while reading the Volume; a batched
+    /// read of several pages counts as a single lookup
+    pub storage_reads: u64,
+    /// failed attempts to push the Volume, including by autosync
+    pub push_failures: u64,
+    /// failed attempts to pull the Volume, including by autosync
+    pub pull_failures: u64,
+}
+
+/// Tracks `VolumeMetrics` for each Volume touched by the runtime. Metrics are
+/// kept in memory and don't survive a restart.
+#[derive(Debug, Default)]
+pub struct VolumeMetricsRegistry {
+    volumes: HashMap<VolumeId, VolumeMetrics>,
+}
+
+impl VolumeMetricsRegistry {
+    pub fn record_commit(&mut self, vid: &VolumeId, pages: usize) {
+        let metrics = self.volumes.entry(vid.clone()).or_default();
+        metrics.commits += 1;
+        metrics.pages_written += pages as u64;
+    }
+
+    pub fn record_fetch(&mut self, vid: &VolumeId, pages: u64) {
+        self.volumes.entry(vid.clone()).or_default().pages_fetched += pages;
+    }
+
+    pub fn record_storage_read(&mut self, vid: &VolumeId) {
+        self.volumes.entry(vid.clone()).or_default().storage_reads += 1;
+    }
+
+    pub fn record_push_failure(&mut self, vid: &VolumeId) {
+        self.volumes.entry(vid.clone()).or_default().push_failures += 1;
+    }
+
+    pub fn record_pull_failure(&mut self, vid: &VolumeId) {
+        self.volumes.entry(vid.clone()).or_default().pull_failures += 1;
+    }
+
+    pub fn get(&self, vid: &VolumeId) -> VolumeMetrics {
+        self.volumes.get(vid).cloned().unwrap_or_default()
+    }
+
+    /// Zeroes every counter for the Volume.
+    pub fn reset(&mut self, vid: &VolumeId) {
+        self.volumes.remove(vid);
+    }
+}
+
+impl Display for VolumeMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Commits: {}", self.commits)?;
+        writeln!(f, "Pages written: {}", self.pages_written)?;
+        writeln!(f, "Pages fetched: {}", self.pages_fetched)?;
+        writeln!(f, "Storage reads: {}", self.storage_reads)?;
+        writeln!(f, "Push failures: {}", self.push_failures)?;
+        writeln!(f, "Pull failures: {}", self.pull_failures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_reset() {
+        let mut registry = VolumeMetricsRegistry::default();
+        let vid = VolumeId::random();
+        let other = VolumeId::random();
+
+        registry.record_commit(&vid, 3);
+        registry.record_commit(&vid, 2);
+        registry.record_fetch(&vid, 16);
+        registry.record_storage_read(&vid);
+        registry.record_push_failure(&vid);
+        registry.record_pull_failure(&vid);
+        registry.record_pull_failure(&vid);
+        registry.record_commit(&other, 1);
+
+        assert_eq!(
+            registry.get(&vid),
+            VolumeMetrics {
+                commits: 2,
+                pages_written: 5,
+                pages_fetched: 16,
+                storage_reads: 1,
+                push_failures: 1,
+                pull_failures: 2,
+            }
+        );
+
+        // resetting one Volume leaves the others untouched
+        registry.reset(&vid);
+        assert_eq!(registry.get(&vid), VolumeMetrics::default());
+        assert_eq!(registry.get(&other).commits, 1);
+    }
+}
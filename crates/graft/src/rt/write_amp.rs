@@ -0,0 +1,200 @@
+use std::{collections::HashMap, fmt::Display};
+
+use circular_buffer::CircularBuffer;
+
+use crate::core::VolumeId;
+
+/// The number of recent local commits considered by `WriteAmpTracker`
+const WINDOW: usize = 32;
+
+/// Tracks the number of pages written by recent local commits to each Volume
+/// in order to detect applications making many tiny commits. Each commit
+/// produces a new segment, so small commits amplify the per-commit overhead.
+///
+/// Volumes are tracked independently so that a Volume receiving large
+/// batched commits doesn't mask another Volume receiving tiny ones.
+///
+/// This is purely informational and never changes commit behavior.
+#[derive(Debug, Default)]
+pub struct WriteAmpTracker {
+    /// warn when the average pages per commit drops below this threshold
+    threshold: Option<f64>,
+    volumes: HashMap<VolumeId, CommitWindow>,
+}
+
+#[derive(Debug, Default)]
+struct CommitWindow {
+    /// pages per commit, ordered from most recent to least recent
+    history: CircularBuffer<WINDOW, usize>,
+    /// true if we are currently below the threshold; used to only warn once
+    /// each time we cross the threshold
+    below_threshold: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteAmpStats {
+    /// the number of commits in the window
+    pub commits: usize,
+    /// the average number of pages per commit within the window
+    pub avg_pages_per_commit: f64,
+    pub threshold: Option<f64>,
+    pub below_threshold: bool,
+}
+
+impl WriteAmpTracker {
+    pub fn set_threshold(&mut self, threshold: Option<f64>) {
+        self.threshold = threshold;
+        for window in self.volumes.values_mut() {
+            window.below_threshold = false;
+        }
+    }
+
+    /// Records a local commit to the Volume containing the specified number
+    /// of pages. Emits a warning if this commit causes the Volume's average
+    /// pages per commit to drop below the configured threshold.
+    pub fn record_commit(&mut self, vid: &VolumeId, pages: usize) {
+        let window = self.volumes.entry(vid.clone()).or_default();
+        window.history.push_front(pages);
+
+        // wait for a full window to avoid warning on startup noise
+        let Some(threshold) = self.threshold else {
+            return;
+        };
+        if !window.history.is_full() {
+            return;
+        }
+
+        let avg = window.avg_pages_per_commit();
+        let below = avg < threshold;
+        if below && !window.below_threshold {
+            tracing::warn!(
+                ?vid,
+                avg_pages_per_commit = avg,
+                threshold,
+                window = WINDOW,
+                "recent commits are small; consider batching writes into fewer transactions"
+            );
+        }
+        window.below_threshold = below;
+    }
+
+    pub fn stats(&self, vid: &VolumeId) -> WriteAmpStats {
+        let (commits, avg_pages_per_commit, below_threshold) = match self.volumes.get(vid) {
+            Some(window) => (
+                window.history.len(),
+                window.avg_pages_per_commit(),
+                window.below_threshold,
+            ),
+            None => (0, 0.0, false),
+        };
+        WriteAmpStats {
+            commits,
+            avg_pages_per_commit,
+            threshold: self.threshold,
+            below_threshold,
+        }
+    }
+}
+
+impl CommitWindow {
+    fn avg_pages_per_commit(&self) -> f64 {
+        if self.history.is_empty() {
+            return 0.0;
+        }
+        let total: usize = self.history.iter().sum();
+        total as f64 / self.history.len() as f64
+    }
+}
+
+impl Display for WriteAmpStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.02} pages per commit over the last {} commits",
+            self.avg_pages_per_commit, self.commits
+        )?;
+        match self.threshold {
+            Some(threshold) if self.below_threshold => {
+                write!(f, " (below threshold of {threshold:.02})")
+            }
+            Some(threshold) => write!(f, " (threshold {threshold:.02})"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_amp_tracker() {
+        let vid = VolumeId::random();
+        let mut tracker = WriteAmpTracker::default();
+        tracker.set_threshold(Some(4.0));
+
+        // batched commits stay above the threshold
+        for _ in 0..WINDOW * 2 {
+            tracker.record_commit(&vid, 64);
+        }
+        let stats = tracker.stats(&vid);
+        assert_eq!(stats.commits, WINDOW);
+        assert_eq!(stats.avg_pages_per_commit, 64.0);
+        assert!(!stats.below_threshold);
+
+        // many tiny commits drop below the threshold
+        for _ in 0..WINDOW {
+            tracker.record_commit(&vid, 1);
+        }
+        let stats = tracker.stats(&vid);
+        assert_eq!(stats.avg_pages_per_commit, 1.0);
+        assert!(stats.below_threshold);
+
+        // batching again recovers
+        for _ in 0..WINDOW {
+            tracker.record_commit(&vid, 16);
+        }
+        assert!(!tracker.stats(&vid).below_threshold);
+    }
+
+    #[test]
+    fn test_write_amp_tracker_no_threshold() {
+        let vid = VolumeId::random();
+        let mut tracker = WriteAmpTracker::default();
+        for _ in 0..WINDOW {
+            tracker.record_commit(&vid, 1);
+        }
+        let stats = tracker.stats(&vid);
+        assert_eq!(stats.avg_pages_per_commit, 1.0);
+        assert!(!stats.below_threshold);
+    }
+
+    #[test]
+    fn test_write_amp_tracker_per_volume() {
+        let batched = VolumeId::random();
+        let tiny = VolumeId::random();
+        let mut tracker = WriteAmpTracker::default();
+        tracker.set_threshold(Some(4.0));
+
+        // interleave commits so a shared window would average out to 32.5
+        for _ in 0..WINDOW {
+            tracker.record_commit(&batched, 64);
+            tracker.record_commit(&tiny, 1);
+        }
+
+        let stats = tracker.stats(&batched);
+        assert_eq!(stats.commits, WINDOW);
+        assert_eq!(stats.avg_pages_per_commit, 64.0);
+        assert!(!stats.below_threshold);
+
+        let stats = tracker.stats(&tiny);
+        assert_eq!(stats.commits, WINDOW);
+        assert_eq!(stats.avg_pages_per_commit, 1.0);
+        assert!(stats.below_threshold);
+
+        // untouched volumes report an empty window
+        let stats = tracker.stats(&VolumeId::random());
+        assert_eq!(stats.commits, 0);
+        assert!(!stats.below_threshold);
+    }
+}
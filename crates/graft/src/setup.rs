@@ -4,9 +4,16 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::{
-    local::fjall_storage::{FjallStorage, FjallStorageErr},
+    core::PageCount,
+    local::{
+        fjall_storage::{FjallStorage, FjallStorageErr, StorageOptions},
+        mirror_storage::MirrorMode,
+    },
     remote::{RemoteConfig, RemoteErr},
-    rt::runtime::Runtime,
+    rt::{
+        event_log::{EventLog, EventLogDurability, EventLogErr},
+        runtime::{CorruptionPolicy, Runtime},
+    },
 };
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -20,6 +27,78 @@ pub struct GraftConfig {
     /// if set, specifies the autosync interval in seconds
     #[serde(default)]
     pub autosync: Option<NonZero<u64>>,
+
+    /// if set, warn when the average number of pages per local commit drops
+    /// below this threshold
+    #[serde(default)]
+    pub write_amp_threshold: Option<f64>,
+
+    /// if set, reads fail rather than waiting longer than this many seconds
+    /// for a page to be fetched from the remote
+    #[serde(default)]
+    pub fetch_timeout: Option<NonZero<u64>>,
+
+    /// if set, fetch the remote log in batches of at most this many commits
+    #[serde(default)]
+    pub max_commits_per_fetch: Option<NonZero<usize>>,
+
+    /// controls how reads handle corrupt pages in the local cache
+    #[serde(default)]
+    pub corruption_policy: CorruptionPolicy,
+
+    /// checkpoint a Volume once it accumulates more than this many commits
+    /// since its last checkpoint; 0 disables automatic checkpoints
+    #[serde(default)]
+    pub compact_after_commits: u64,
+
+    /// verify each pushed commit against the remote before clearing the
+    /// local commits
+    #[serde(default)]
+    pub verify_push: bool,
+
+    /// pad each Segment frame pushed to the remote to a multiple of this many
+    /// bytes; 0 disables padding
+    #[serde(default)]
+    pub frame_alignment: u64,
+
+    /// the byte which fills pages that were never written, such as the pages
+    /// skipped when a Volume is extended, unless the Volume has its own
+    #[serde(default)]
+    pub fill_byte: u8,
+
+    /// how many times hydrating a Volume retries after a failed fetch
+    #[serde(default)]
+    pub hydrate_retries: u32,
+
+    /// how many decoded pages to cache in memory and share between
+    /// connections; 0 disables the page cache
+    #[serde(default)]
+    pub page_cache_pages: usize,
+
+    /// the maximum number of pages in each Volume; 0 disables the limit
+    #[serde(default)]
+    pub max_pages: u32,
+
+    /// if set, append a record of every commit to this file
+    #[serde(default)]
+    pub event_log: Option<PathBuf>,
+
+    /// controls when records appended to the event log reach the disk
+    #[serde(default)]
+    pub event_log_durability: EventLogDurability,
+
+    /// if set, mirror every local commit to a second data directory at this
+    /// path
+    #[serde(default)]
+    pub mirror_dir: Option<PathBuf>,
+
+    /// controls whether local commits wait for the mirror to be durable
+    #[serde(default)]
+    pub mirror_mode: MirrorMode,
+
+    /// store a single copy of identical pages in the data directory
+    #[serde(default)]
+    pub dedup_pages: bool,
 }
 
 #[derive(Debug, Error)]
@@ -32,6 +111,9 @@ pub enum InitErr {
 
     #[error(transparent)]
     Remote(#[from] RemoteErr),
+
+    #[error(transparent)]
+    EventLog(#[from] EventLogErr),
 }
 
 /// An opinionated but simple setup method. Sets up a Tokio current thread
@@ -52,8 +134,38 @@ pub fn setup_graft(config: GraftConfig) -> Result<Runtime, InitErr> {
             rt.block_on(pending::<()>())
         })?;
 
-    let remote = Arc::new(config.remote.build()?);
-    let storage = Arc::new(FjallStorage::open(config.data_dir)?);
+    let remote = Arc::new(
+        config
+            .remote
+            .build()?
+            .with_frame_alignment(NonZero::new(config.frame_alignment))?,
+    );
+    let storage_opts = StorageOptions {
+        dedup_pages: config.dedup_pages,
+        ..Default::default()
+    };
+    let storage = Arc::new(FjallStorage::open_with_options(
+        config.data_dir,
+        storage_opts.clone(),
+    )?);
     let autosync = config.autosync.map(|s| Duration::from_secs(s.get()));
-    Ok(Runtime::new(tokio_handle, remote, storage, autosync))
+    let runtime = Runtime::new(tokio_handle, remote, storage, autosync);
+    runtime.set_write_amp_threshold(config.write_amp_threshold);
+    runtime.set_max_commits_per_fetch(config.max_commits_per_fetch);
+    runtime.set_corruption_policy(config.corruption_policy);
+    runtime.set_compact_after_commits(NonZero::new(config.compact_after_commits));
+    runtime.set_verify_push(config.verify_push);
+    runtime.set_default_fill_byte(config.fill_byte);
+    runtime.set_hydrate_retries(config.hydrate_retries);
+    runtime.set_page_cache_capacity(NonZero::new(config.page_cache_pages));
+    runtime.set_default_max_pages((config.max_pages > 0).then(|| PageCount::new(config.max_pages)));
+    runtime.set_fetch_timeout(config.fetch_timeout.map(|s| Duration::from_secs(s.get())));
+    if let Some(path) = config.event_log {
+        runtime.set_event_log(Some(EventLog::open(path, config.event_log_durability)?));
+    }
+    if let Some(path) = config.mirror_dir {
+        let secondary = Arc::new(FjallStorage::open_with_options(path, storage_opts)?);
+        runtime.set_mirror(Some(secondary), config.mirror_mode);
+    }
+    Ok(runtime)
 }
@@ -6,6 +6,22 @@ use crate::core::{
     lsn::{LSN, LSNRangeExt},
 };
 use thin_vec::{ThinVec, thin_vec};
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SnapshotErr {
+    #[error("a snapshot must contain at least one layer")]
+    NoLayers,
+
+    #[error("snapshot layer for Log {0} has an empty LSN range")]
+    EmptyLayer(LogId),
+
+    #[error("snapshot layers for Log {0} overlap")]
+    OverlappingLayers(LogId),
+
+    #[error("snapshot layers for Log {0} must be ordered from newest to oldest")]
+    MisorderedLayers(LogId),
+}
 
 /// A `Snapshot` represents a logical view of a Volume, possibly made
 /// up of LSN ranges from multiple Logs.
@@ -47,6 +63,34 @@ impl Snapshot {
         }
     }
 
+    /// Builds a `Snapshot` from a sequence of layers ordered from newest to
+    /// oldest. Layers referencing the same Log must not overlap and must
+    /// descend in LSN order.
+    pub fn from_layers(
+        layers: impl IntoIterator<Item = (LogId, RangeInclusive<LSN>)>,
+        page_count: PageCount,
+    ) -> Result<Self, SnapshotErr> {
+        let mut path: ThinVec<LogRangeRef> = ThinVec::new();
+        for (log, lsns) in layers {
+            if lsns.is_empty() {
+                return Err(SnapshotErr::EmptyLayer(log));
+            }
+            for newer in path.iter().filter(|layer| layer.log == log) {
+                if lsns.start() <= newer.lsns.end() && newer.lsns.start() <= lsns.end() {
+                    return Err(SnapshotErr::OverlappingLayers(log));
+                }
+                if lsns.start() > newer.lsns.end() {
+                    return Err(SnapshotErr::MisorderedLayers(log));
+                }
+            }
+            path.push(LogRangeRef { log, lsns });
+        }
+        if path.is_empty() {
+            return Err(SnapshotErr::NoLayers);
+        }
+        Ok(Self { page_count, path })
+    }
+
     pub fn empty() -> Self {
         Self {
             page_count: PageCount::ZERO,
@@ -60,6 +104,14 @@ impl Snapshot {
             .map(|entry| (&entry.log, *entry.lsns.end()))
     }
 
+    /// returns the newest layer's Log along with the range of LSNs reachable
+    /// within it
+    pub fn head_range(&self) -> Option<(&LogId, RangeInclusive<LSN>)> {
+        self.path
+            .first()
+            .map(|entry| (&entry.log, entry.lsns.clone()))
+    }
+
     pub fn is_empty(&self) -> bool {
         self.path.is_empty()
     }
@@ -88,3 +140,67 @@ impl std::fmt::Debug for Snapshot {
         f.debug_tuple("Snapshot").field(&self.path).finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{LogId, PageCount};
+    use crate::lsn;
+
+    use super::{Snapshot, SnapshotErr};
+
+    #[test]
+    fn test_from_layers() {
+        let local = LogId::random();
+        let remote = LogId::random();
+        let page_count = PageCount::new(10);
+
+        let snapshot = Snapshot::from_layers(
+            [
+                (local.clone(), lsn!(5)..=lsn!(6)),
+                (remote.clone(), lsn!(1)..=lsn!(3)),
+                (local.clone(), lsn!(1)..=lsn!(4)),
+            ],
+            page_count,
+        )
+        .unwrap();
+        assert_eq!(snapshot.head(), Some((&local, lsn!(6))));
+        assert_eq!(snapshot.page_count, page_count);
+        assert_eq!(snapshot.iter().count(), 3);
+
+        assert_eq!(
+            Snapshot::from_layers([], page_count).unwrap_err(),
+            SnapshotErr::NoLayers
+        );
+
+        let empty = lsn!(3)..=lsn!(1);
+        assert_eq!(
+            Snapshot::from_layers([(local.clone(), empty)], page_count).unwrap_err(),
+            SnapshotErr::EmptyLayer(local.clone())
+        );
+
+        assert_eq!(
+            Snapshot::from_layers(
+                [
+                    (local.clone(), lsn!(3)..=lsn!(6)),
+                    (remote.clone(), lsn!(1)..=lsn!(3)),
+                    (local.clone(), lsn!(1)..=lsn!(4)),
+                ],
+                page_count,
+            )
+            .unwrap_err(),
+            SnapshotErr::OverlappingLayers(local.clone())
+        );
+
+        assert_eq!(
+            Snapshot::from_layers(
+                [
+                    (local.clone(), lsn!(1)..=lsn!(2)),
+                    (local.clone(), lsn!(3)..=lsn!(4)),
+                ],
+                page_count,
+            )
+            .unwrap_err(),
+            SnapshotErr::MisorderedLayers(local)
+        );
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(test)]
+use std::sync::Arc;
+
 use bytes::BytesMut;
 use rand::{
     Rng, RngExt,
@@ -12,6 +15,12 @@ use crate::core::{
     page::{PAGESIZE, Page},
     pageidx::PageIdx,
 };
+#[cfg(test)]
+use crate::{
+    local::fjall_storage::FjallStorage,
+    remote::{Remote, RemoteConfig},
+    rt::runtime::Runtime,
+};
 
 impl Page {
     pub fn test_filled(value: u8) -> Self {
@@ -68,3 +77,27 @@ impl PageIdx {
         Self::try_new(n).expect("page index must be non-zero")
     }
 }
+
+/// Returns a current thread tokio runtime whose clock starts paused, for unit
+/// tests which drive a `Runtime` from the test thread.
+#[cfg(test)]
+pub fn test_tokio_rt() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .start_paused(true)
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+#[cfg(test)]
+pub fn memory_remote() -> Arc<Remote> {
+    Arc::new(RemoteConfig::Memory.build().unwrap())
+}
+
+/// Returns a `Runtime` with autosync disabled, backed by temporary storage and
+/// syncing with `remote`. Runtimes sharing a remote act as peers.
+#[cfg(test)]
+pub fn test_runtime(tokio_rt: &tokio::runtime::Runtime, remote: Arc<Remote>) -> Runtime {
+    let storage = Arc::new(FjallStorage::open_temporary().unwrap());
+    Runtime::new(tokio_rt.handle().clone(), remote, storage, None)
+}
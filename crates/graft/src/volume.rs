@@ -1,8 +1,14 @@
 use std::{fmt::Display, ops::RangeInclusive};
 
-use bilrost::Message;
+use bilrost::{Enumeration, Message};
 
-use crate::core::{LogId, commit_hash::CommitHash, gid::VolumeId, lsn::LSN};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    LogId, PageCount, PageIdx, SegmentId, checksum::Checksum, commit::Commit,
+    commit_hash::CommitHash, gid::VolumeId, lsn::LSN, pageset::PageSet,
+};
 
 #[derive(Debug, Clone, Message, PartialEq, Eq)]
 pub struct SyncPoint {
@@ -31,6 +37,12 @@ pub struct PendingCommit {
     /// while attempting to push.
     #[bilrost(3)]
     pub commit_hash: CommitHash,
+
+    /// The Segment uploaded for the pending commit. Its pages are cached
+    /// locally, but no commit references them until the push completes.
+    /// Unset on commits prepared before this was recorded.
+    #[bilrost(4)]
+    pub sid: Option<SegmentId>,
 }
 
 impl From<PendingCommit> for SyncPoint {
@@ -42,6 +54,56 @@ impl From<PendingCommit> for SyncPoint {
     }
 }
 
+/// Controls how opening an existing Volume handles a remote Log which doesn't
+/// match the requested remote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpenPolicy {
+    /// Fail with `LogicalErr::VolumeRemoteMismatch`.
+    #[default]
+    Strict,
+
+    /// Graft the Volume onto the requested remote Log. The Volume is reset to
+    /// track the latest remote LSN using a fresh local Log. Only applies to
+    /// Volumes without any unpushed local commits or a pending commit;
+    /// otherwise this behaves like `Strict`.
+    AdoptRemote,
+}
+
+/// Controls how pushing a Volume handles a remote Log which has newer commits
+/// than the Volume, for example because another runtime pushed to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Enumeration, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Fail the push with `LogicalErr::VolumeDiverged`.
+    #[default]
+    Strict = 0,
+
+    /// Rebase the Volume's local changes onto the newer remote commits and
+    /// retry the push once, logging a warning. The push still fails with
+    /// `LogicalErr::VolumeDiverged` if both sides changed the same page.
+    /// Only suitable for data which tolerates interleaved writers.
+    LastWriterWins = 1,
+}
+
+/// Settings which apply to a single Volume. They are persisted along with the
+/// Volume, and survive resetting or rebasing it.
+#[derive(Debug, Clone, Message, PartialEq, Eq, Default)]
+pub struct VolumeSettings {
+    /// The byte which fills pages within the Volume that were never written.
+    /// If unset, the Runtime's default fill byte is used.
+    #[bilrost(1)]
+    pub fill_byte: Option<u8>,
+
+    /// How pushing the Volume handles newer remote commits.
+    #[bilrost(2)]
+    pub conflict_policy: ConflictPolicy,
+
+    /// The maximum page count of the Volume. If unset, the Runtime's default
+    /// limit applies.
+    #[bilrost(3)]
+    pub max_pages: Option<PageCount>,
+}
+
 #[derive(Debug, Clone, Message, PartialEq, Eq, Default)]
 pub struct Volume {
     /// The Volume Id
@@ -67,6 +129,10 @@ pub struct Volume {
     /// abort the commit process.
     #[bilrost(5)]
     pub pending_commit: Option<PendingCommit>,
+
+    /// Settings which apply to this Volume
+    #[bilrost(6)]
+    pub settings: VolumeSettings,
 }
 
 impl Volume {
@@ -77,7 +143,14 @@ impl Volume {
         sync: Option<SyncPoint>,
         pending_commit: Option<PendingCommit>,
     ) -> Self {
-        Self { vid, local, remote, sync, pending_commit }
+        Self {
+            vid,
+            local,
+            remote,
+            sync,
+            pending_commit,
+            settings: VolumeSettings::default(),
+        }
     }
 
     pub fn new_random() -> Self {
@@ -87,6 +160,7 @@ impl Volume {
             remote: LogId::random(),
             sync: None,
             pending_commit: None,
+            settings: VolumeSettings::default(),
         }
     }
 
@@ -106,6 +180,14 @@ impl Volume {
         self.pending_commit.as_ref()
     }
 
+    pub fn with_settings(self, settings: VolumeSettings) -> Self {
+        Self { settings, ..self }
+    }
+
+    pub fn settings(&self) -> &VolumeSettings {
+        &self.settings
+    }
+
     pub fn local_watermark(&self) -> Option<LSN> {
         self.sync().and_then(|s| s.local_watermark)
     }
@@ -143,6 +225,90 @@ impl Volume {
     }
 }
 
+/// The configuration of a Volume, excluding its data and sync state. Produced
+/// by `Runtime::export_config` and reapplied by `Runtime::import_config`, for
+/// example to restore a Volume's settings after a reinstall.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VolumeConfigDump {
+    pub vid: VolumeId,
+
+    /// The remote Log backing the Volume.
+    pub remote: LogId,
+
+    /// The tags which reference the Volume, in sorted order.
+    pub tags: Vec<String>,
+
+    /// The Volume's own fill byte, if it has one.
+    #[serde(default)]
+    pub fill_byte: Option<u8>,
+
+    /// How pushing the Volume handles newer remote commits.
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+
+    /// The Volume's own maximum page count, if it has one.
+    #[serde(default)]
+    pub max_pages: Option<PageCount>,
+}
+
+/// How much of a Snapshot is available locally. Produced by
+/// `Runtime::audit_report`, for example to show hydration progress.
+#[derive(Debug, Clone, Message, PartialEq)]
+pub struct AuditReport {
+    /// The number of pages in the Snapshot
+    #[bilrost(1)]
+    pub total_pages: PageCount,
+
+    /// The number of pages which are available locally
+    #[bilrost(2)]
+    pub cached_pages: PageCount,
+
+    /// The pages which must be fetched from the remote before they can be
+    /// read
+    #[bilrost(3)]
+    pub missing: PageSet,
+
+    /// The percentage of pages which are available locally, from 0 to 100
+    #[bilrost(4)]
+    pub pct_cached: f64,
+
+    /// The checksum of the Snapshot, only computed once every page is
+    /// available locally
+    #[bilrost(5)]
+    pub checksum: Option<Checksum>,
+}
+
+impl AuditReport {
+    /// Returns true if every page in the Snapshot is available locally
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// The page count of the remote commit a Volume is synced to, as recorded in
+/// local storage and as reported by the remote. Produced by
+/// `Runtime::check_page_count`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageCountCheck {
+    /// The LSN of the remote commit the Volume is synced to
+    pub remote_lsn: LSN,
+
+    /// The page count recorded by the local copy of the commit
+    pub local: PageCount,
+
+    /// The page count reported by the remote, or `None` if the remote
+    /// doesn't have the commit
+    pub remote: Option<PageCount>,
+}
+
+impl PageCountCheck {
+    /// Returns true if the remote reports the same page count as local
+    /// storage
+    pub fn agrees(&self) -> bool {
+        self.remote == Some(self.local)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct AheadStatus {
     pub head: Option<LSN>,
@@ -214,3 +380,123 @@ impl VolumeStatus {
         self.local_status.changes().is_some() && self.remote_status.changes().is_some()
     }
 }
+
+/// Describes a Volume which has diverged from its remote Log, produced by
+/// `Runtime::conflict_report`. Applications can use this to present the
+/// conflicting changes before deciding how to resolve them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictReport {
+    pub vid: VolumeId,
+
+    /// The last remote commit included in the Volume, which both sides
+    /// changed independently from. `None` if the Volume was never synced.
+    pub fork_point: Option<LSN>,
+
+    /// Local commits which haven't been pushed, in ascending LSN order
+    pub local_commits: Vec<Commit>,
+
+    /// Remote commits which haven't been pulled, in ascending LSN order
+    pub remote_commits: Vec<Commit>,
+
+    /// Pages changed by both the local and the remote commits
+    pub overlapping: PageSet,
+}
+
+/// The result of comparing two Volumes page by page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeDiff {
+    /// The page count of the first Volume
+    pub left: PageCount,
+    /// The page count of the second Volume
+    pub right: PageCount,
+    /// Pages which differ in contents, among the pages contained by both
+    /// Volumes
+    pub changed: PageSet,
+}
+
+impl VolumeDiff {
+    /// Returns true if both Volumes have identical contents
+    pub fn is_identical(&self) -> bool {
+        self.left == self.right && self.changed.is_empty()
+    }
+
+    /// Returns the range of pages which are only contained by the larger
+    /// Volume, if the Volumes have different page counts.
+    pub fn extra_pages(&self) -> Option<RangeInclusive<PageIdx>> {
+        let smaller = self.left.min(self.right);
+        let larger = self.left.max(self.right);
+        if smaller == larger {
+            return None;
+        }
+        Some(smaller.saturating_incr().last_pageidx()?..=larger.last_pageidx()?)
+    }
+}
+
+impl Display for VolumeDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_identical() {
+            return write!(f, "Volumes are identical ({} pages)", self.left);
+        }
+        if self.changed.is_empty() {
+            write!(f, "No common pages differ")?;
+        } else {
+            write!(
+                f,
+                "{} common pages differ: {}",
+                self.changed.cardinality(),
+                self.changed.iter().format(",")
+            )?;
+        }
+        if let Some(extra) = self.extra_pages() {
+            let which = if self.left > self.right {
+                "first"
+            } else {
+                "second"
+            };
+            write!(
+                f,
+                "\nThe {which} Volume is larger ({} vs {} pages); pages {}..={} are only in the {which} Volume",
+                self.left.max(self.right),
+                self.left.min(self.right),
+                extra.start(),
+                extra.end(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bilrost::OwnedMessage;
+
+    use crate::{core::checksum::ChecksumBuilder, pageidx};
+
+    use super::*;
+
+    #[test]
+    fn test_audit_report_serialization() {
+        let mut builder = ChecksumBuilder::new();
+        builder.write(&"page");
+        let reports = [
+            AuditReport {
+                total_pages: PageCount::new(10),
+                cached_pages: PageCount::new(7),
+                missing: PageSet::from_range(pageidx!(8)..=pageidx!(10)),
+                pct_cached: 70.0,
+                checksum: None,
+            },
+            AuditReport {
+                total_pages: PageCount::new(10),
+                cached_pages: PageCount::new(10),
+                missing: PageSet::EMPTY,
+                pct_cached: 100.0,
+                checksum: Some(builder.build()),
+            },
+        ];
+        for report in reports {
+            let decoded = AuditReport::decode(report.encode_to_bytes()).unwrap();
+            assert_eq!(decoded, report);
+        }
+    }
+}
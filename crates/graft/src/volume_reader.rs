@@ -1,6 +1,15 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    io::{self, Read, Seek, SeekFrom, Write},
+    ops::RangeInclusive,
+};
 
-use crate::core::{PageCount, PageIdx, VolumeId, page::Page};
+use crate::core::{
+    LogId, PageCount, PageIdx, VolumeId,
+    lsn::LSN,
+    page::{PAGESIZE, Page},
+    pageidx::PageIdxIter,
+};
 
 use crate::{GraftErr, rt::runtime::Runtime, snapshot::Snapshot, volume_writer::VolumeWriter};
 
@@ -8,7 +17,124 @@ use crate::{GraftErr, rt::runtime::Runtime, snapshot::Snapshot, volume_writer::V
 pub trait VolumeRead {
     fn snapshot(&self) -> &Snapshot;
     fn page_count(&self) -> PageCount;
+
+    /// Reads a page from the Volume. Pages which have never been written, as
+    /// well as pages beyond the end of the Volume, read as `Page::EMPTY`.
     fn read_page(&self, pageidx: PageIdx) -> Result<Page, GraftErr>;
+
+    /// Reads the first page in `range`, along with as many of the following
+    /// pages as can be read cheaply, returning them in order. The result
+    /// always contains the first page, and never extends past the range.
+    /// Implementations may serve the whole result with a single storage
+    /// lookup, but must not fetch pages after the first from the remote.
+    fn read_pages(&self, range: RangeInclusive<PageIdx>) -> Result<Vec<Page>, GraftErr> {
+        Ok(vec![self.read_page(*range.start())?])
+    }
+
+    /// Returns the Log and range of LSNs of the snapshot's newest layer,
+    /// which bound the commits reachable from this reader. Returns `None` if
+    /// the Volume is empty.
+    fn reachable_range(&self) -> Option<(LogId, RangeInclusive<LSN>)> {
+        self.snapshot()
+            .head_range()
+            .map(|(log, lsns)| (log.clone(), lsns))
+    }
+
+    /// Returns the Log and range of LSNs of every layer in the snapshot, from
+    /// newest to oldest.
+    fn reachable_layers(&self) -> Vec<(LogId, RangeInclusive<LSN>)> {
+        self.snapshot()
+            .iter()
+            .map(|layer| (layer.log.clone(), layer.lsns.clone()))
+            .collect()
+    }
+
+    /// Writes every page in the Volume to `w` in order, returning the number
+    /// of bytes written.
+    fn export_to_writer<W: Write>(&self, mut w: W) -> Result<usize, GraftErr>
+    where
+        Self: Sized,
+    {
+        let mut written = 0;
+        for pageidx in self.page_count().iter() {
+            let page = self.read_page(pageidx)?;
+            w.write_all(page.as_ref())?;
+            written += page.as_ref().len();
+        }
+        w.flush()?;
+        Ok(written)
+    }
+
+    /// Resumes an interrupted `export_to_writer` into `f`, which holds a
+    /// partial export of this Volume. The pages already in `f` are verified
+    /// against the Volume before the remaining pages are appended. Returns
+    /// the number of bytes written.
+    ///
+    /// Fails with an `InvalidData` I/O error if `f` is not a whole number of
+    /// pages, is longer than the Volume, or doesn't match the Volume.
+    fn resume_export<F: Read + Write + Seek>(&self, mut f: F) -> Result<usize, GraftErr>
+    where
+        Self: Sized,
+    {
+        let len = f.seek(SeekFrom::End(0))?;
+        let pagesize = PAGESIZE.as_u64();
+        let page_count = self.page_count();
+        if len % pagesize != 0 || len / pagesize > u64::from(page_count.to_u32()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("partial export of {len} bytes can't be resumed"),
+            )
+            .into());
+        }
+        let exported = len / pagesize;
+
+        // verify the already exported prefix
+        f.seek(SeekFrom::Start(0))?;
+        let mut buf = vec![0; PAGESIZE.as_usize()];
+        for pageidx in page_count.iter().take(exported as usize) {
+            f.read_exact(&mut buf)?;
+            if buf != self.read_page(pageidx)?.as_ref() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("partial export does not match the Volume at page {pageidx}"),
+                )
+                .into());
+            }
+        }
+
+        let mut written = 0;
+        for pageidx in page_count.iter().skip(exported as usize) {
+            let page = self.read_page(pageidx)?;
+            f.write_all(page.as_ref())?;
+            written += page.as_ref().len();
+        }
+        f.flush()?;
+        Ok(written)
+    }
+}
+
+/// Implements `VolumeRead::read_pages` for a snapshot with a single storage
+/// lookup. Pages beyond the end of the snapshot read as `Page::EMPTY`.
+pub(crate) fn read_snapshot_pages(
+    runtime: &Runtime,
+    vid: &VolumeId,
+    snapshot: &Snapshot,
+    range: RangeInclusive<PageIdx>,
+) -> Result<Vec<Page>, GraftErr> {
+    let (start, end) = range.into_inner();
+    let Some(last) = snapshot
+        .page_count
+        .last_pageidx()
+        .filter(|&last| start <= last)
+    else {
+        return Ok(vec![Page::EMPTY; PageIdxIter::new(start..=end).len()]);
+    };
+    let mut pages = runtime.read_pages(vid, snapshot, start..=end.min(last))?;
+    if pages.len() == PageIdxIter::new(start..=end.min(last)).len() {
+        // every visible page was read, so the rest of the range is empty
+        pages.resize(PageIdxIter::new(start..=end).len(), Page::EMPTY);
+    }
+    Ok(pages)
 }
 
 #[derive(Debug, Clone)]
@@ -26,7 +152,15 @@ impl VolumeReader {
 
 impl From<VolumeReader> for VolumeWriter {
     fn from(reader: VolumeReader) -> Self {
-        Self::new(reader.runtime, reader.vid, reader.snapshot)
+        // if the limit can't be read, neither can the Volume, so committing
+        // the writer fails regardless
+        let max_pages = reader
+            .runtime
+            .max_pages(&reader.vid)
+            .ok()
+            .flatten()
+            .unwrap_or(PageCount::MAX);
+        Self::new(reader.runtime, reader.vid, reader.snapshot, max_pages)
     }
 }
 
@@ -40,7 +174,16 @@ impl VolumeRead for VolumeReader {
     }
 
     fn read_page(&self, pageidx: PageIdx) -> Result<Page, GraftErr> {
-        self.runtime.read_page(&self.snapshot, pageidx)
+        if !self.page_count().contains(pageidx) {
+            // the page may still exist in an older commit hidden by a truncate
+            Ok(Page::EMPTY)
+        } else {
+            self.runtime.read_page(&self.vid, &self.snapshot, pageidx)
+        }
+    }
+
+    fn read_pages(&self, range: RangeInclusive<PageIdx>) -> Result<Vec<Page>, GraftErr> {
+        read_snapshot_pages(&self.runtime, &self.vid, &self.snapshot, range)
     }
 }
 
@@ -70,4 +213,11 @@ impl VolumeRead for VolumeReadRef<'_> {
             VolumeReadRef::Writer(w) => w.read_page(pageidx),
         }
     }
+
+    fn read_pages(&self, range: RangeInclusive<PageIdx>) -> Result<Vec<Page>, GraftErr> {
+        match self {
+            VolumeReadRef::Reader(r) => r.read_pages(range),
+            VolumeReadRef::Writer(w) => w.read_pages(range),
+        }
+    }
 }
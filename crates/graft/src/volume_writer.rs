@@ -1,12 +1,14 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, num::NonZero, ops::RangeInclusive};
 
-use crate::core::{PageCount, PageIdx, VolumeId, page::Page};
+use bytestring::ByteString;
+
+use crate::core::{PageCount, PageIdx, VolumeId, page::Page, pageidx::PageIdxIter};
 
 use crate::{
-    GraftErr,
+    GraftErr, LogicalErr,
     rt::runtime::Runtime,
     snapshot::Snapshot,
-    volume_reader::{VolumeRead, VolumeReader},
+    volume_reader::{VolumeRead, VolumeReader, read_snapshot_pages},
 };
 
 /// A type which can write to a Volume
@@ -32,16 +34,185 @@ pub struct VolumeWriter {
     vid: VolumeId,
     snapshot: Snapshot,
     pages: BTreeMap<PageIdx, Page>,
+    /// the page count of the snapshot this writer is based on
+    base_page_count: PageCount,
+    /// writes may not grow the Volume beyond this page count
+    max_pages: PageCount,
 }
 
 impl VolumeWriter {
-    pub(crate) fn new(runtime: Runtime, vid: VolumeId, snapshot: Snapshot) -> Self {
+    pub(crate) fn new(
+        runtime: Runtime,
+        vid: VolumeId,
+        snapshot: Snapshot,
+        max_pages: PageCount,
+    ) -> Self {
+        let base_page_count = snapshot.page_count;
         Self {
             runtime,
             vid,
             snapshot,
             pages: Default::default(),
+            base_page_count,
+            max_pages,
+        }
+    }
+
+    /// Returns true if this writer hasn't written any pages or changed the
+    /// Volume's page count, in which case committing it would only record an
+    /// empty commit.
+    pub fn is_unchanged(&self) -> bool {
+        self.pages.is_empty() && self.snapshot.page_count == self.base_page_count
+    }
+
+    /// Fails with `LogicalErr::VolumeTooLarge` if growing the Volume to
+    /// `page_count` would exceed its maximum page count. Shrinking the
+    /// Volume, or leaving its size unchanged, always succeeds.
+    fn check_growth(&self, page_count: PageCount) -> Result<(), GraftErr> {
+        let current = self.page_count();
+        if page_count > current && page_count > self.max_pages {
+            return Err(LogicalErr::VolumeTooLarge {
+                vid: self.vid.clone(),
+                pages: page_count.to_u32() - current.to_u32(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Checks whether committing this writer would currently succeed, without
+    /// writing anything. Returns `LogicalErr::VolumeConcurrentWrite` if the
+    /// Volume has advanced past the snapshot this writer is based on.
+    ///
+    /// This is advisory: a concurrent commit may still cause a subsequent
+    /// `commit` to fail.
+    pub fn validate(&self) -> Result<(), GraftErr> {
+        let reader = self.runtime.storage().read();
+        if !reader.is_latest_snapshot(&self.vid, &self.snapshot)? {
+            return Err(LogicalErr::VolumeConcurrentWrite(self.vid.clone()).into());
         }
+        Ok(())
+    }
+
+    /// Reserves `n` fresh pages immediately after the Volume's current page
+    /// count, returning their contiguous indices. The page count grows to
+    /// include the reserved pages, which read as the Volume's fill page until
+    /// they are written, so repeated calls never return overlapping ranges.
+    ///
+    /// Fails with `LogicalErr::VolumeTooLarge` if the Volume can't grow by `n`
+    /// pages without exceeding its maximum page count.
+    pub fn allocate_pages(&mut self, n: NonZero<u32>) -> Result<RangeInclusive<PageIdx>, GraftErr> {
+        let count = self.page_count().to_u32();
+        let (Some(first), Some(last)) = (
+            count.checked_add(1).and_then(PageIdx::try_new),
+            count.checked_add(n.get()).and_then(PageIdx::try_new),
+        ) else {
+            return Err(
+                LogicalErr::VolumeTooLarge { vid: self.vid.clone(), pages: n.get() }.into(),
+            );
+        };
+        self.check_growth(last.pages())?;
+
+        // explicitly write the reserved pages, as pages hidden by an earlier
+        // soft truncation would otherwise become visible again
+        let fill = self.runtime.fill_page(&self.vid)?;
+        for pageidx in PageIdxIter::new(first..=last) {
+            self.pages.insert(pageidx, fill.clone());
+        }
+        self.snapshot.page_count = last.pages();
+        Ok(first..=last)
+    }
+
+    /// Commits this writer, attaching an application-supplied note to the
+    /// commit. The note is replicated along with the commit when it's pushed.
+    ///
+    /// Fails with `LogicalErr::CommitNoteTooLong` if the note is longer than
+    /// `MAX_NOTE_LEN` bytes.
+    pub fn commit_with_note(self, note: impl Into<ByteString>) -> Result<VolumeReader, GraftErr> {
+        self.commit_inner(Some(note.into()))
+    }
+
+    /// Splits the writer into its Volume, base snapshot, page count, and
+    /// pending pages.
+    pub(crate) fn into_parts(self) -> (VolumeId, Snapshot, PageCount, BTreeMap<PageIdx, Page>) {
+        let page_count = self.snapshot.page_count;
+        (self.vid, self.snapshot, page_count, self.pages)
+    }
+
+    /// Turns this commit into a checkpoint by writing every page of the
+    /// Volume, if the Volume has accumulated the configured number of commits
+    /// since its last checkpoint. See `Runtime::set_compact_after_commits`.
+    ///
+    /// This runs as part of committing, so it's serialized with every other
+    /// writer of the Volume. A Volume with pages missing from local storage
+    /// isn't checkpointed, rather than fetching the missing pages. The
+    /// checkpoint is then retried once the Volume accumulates another
+    /// threshold of commits, so commits to a partially hydrated Volume don't
+    /// each scan its pages.
+    pub(crate) fn maybe_checkpoint(&mut self) {
+        let Some(threshold) = self.runtime.compact_after_commits() else {
+            return;
+        };
+        if let Err(err) = self.fill_checkpoint(threshold) {
+            tracing::warn!(vid = ?self.vid, "automatic checkpoint failed: {err}");
+        }
+    }
+
+    fn fill_checkpoint(&mut self, threshold: NonZero<u64>) -> Result<(), GraftErr> {
+        let commits = self
+            .runtime
+            .storage()
+            .read()
+            .commits_since_checkpoint(&self.snapshot)?;
+        // this commit is one more commit since the last checkpoint
+        if !self.runtime.checkpoint_due(&self.vid, commits, threshold) {
+            return Ok(());
+        }
+        let unwritten = self
+            .page_count()
+            .iter()
+            .filter(|pageidx| !self.pages.contains_key(pageidx));
+        let Some(pages) = self
+            .runtime
+            .read_local_pages(&self.vid, &self.snapshot, unwritten)?
+        else {
+            tracing::debug!(
+                vid = ?self.vid,
+                "skipping automatic checkpoint; pages are missing locally"
+            );
+            self.runtime
+                .record_checkpoint_skipped(&self.vid, Some(commits));
+            return Ok(());
+        };
+        self.runtime.record_checkpoint_skipped(&self.vid, None);
+        tracing::debug!(vid = ?self.vid, commits, %threshold, "checkpointing volume");
+        self.pages.extend(pages);
+        Ok(())
+    }
+
+    fn commit_inner(mut self, note: Option<ByteString>) -> Result<VolumeReader, GraftErr> {
+        self.maybe_checkpoint();
+        let page_count = self.snapshot.page_count;
+        let pages = self.pages.len();
+        let evict = self.runtime.pages_to_evict(&self.pages);
+        let result = match self.runtime.mirror() {
+            Some(mirror) => {
+                mirror.commit_with_note(&self.vid, self.snapshot, page_count, self.pages, note)
+            }
+            None => self.runtime.storage().read_write().commit_with_note(
+                &self.vid,
+                self.snapshot,
+                page_count,
+                self.pages,
+                note,
+            ),
+        }
+        .map_err(GraftErr::from);
+        self.runtime.record_commit_conflict(&self.vid, &result);
+        let snapshot = result?;
+        self.runtime
+            .record_commit(&self.vid, &snapshot, pages, evict);
+        Ok(VolumeReader::new(self.runtime, self.vid, snapshot))
     }
 }
 
@@ -60,19 +231,39 @@ impl VolumeRead for VolumeWriter {
         } else if let Some(page) = self.pages.get(&pageidx) {
             Ok(page.clone())
         } else {
-            self.runtime.read_page(&self.snapshot, pageidx)
+            self.runtime.read_page(&self.vid, &self.snapshot, pageidx)
+        }
+    }
+
+    fn read_pages(&self, range: RangeInclusive<PageIdx>) -> Result<Vec<Page>, GraftErr> {
+        if self.pages.contains_key(range.start()) {
+            // the first page may not exist locally; don't fetch it
+            return Ok(vec![self.read_page(*range.start())?]);
+        }
+        let mut pages =
+            read_snapshot_pages(&self.runtime, &self.vid, &self.snapshot, range.clone())?;
+        // overlay the pages written by this writer
+        for (pageidx, page) in self.pages.range(range.clone()) {
+            let offset = (pageidx.to_u32() - range.start().to_u32()) as usize;
+            match pages.get_mut(offset) {
+                Some(slot) => *slot = page.clone(),
+                None => break,
+            }
         }
+        Ok(pages)
     }
 }
 
 impl VolumeWrite for VolumeWriter {
     fn write_page(&mut self, pageidx: PageIdx, page: Page) -> Result<(), GraftErr> {
+        self.check_growth(pageidx.pages())?;
         self.snapshot.page_count = self.snapshot.page_count.max(pageidx.pages());
         self.pages.insert(pageidx, page);
         Ok(())
     }
 
     fn soft_truncate(&mut self, page_count: PageCount) -> Result<(), GraftErr> {
+        self.check_growth(page_count)?;
         if page_count < self.page_count() {
             self.pages.retain(|&k, _| page_count.contains(k));
         }
@@ -81,13 +272,182 @@ impl VolumeWrite for VolumeWriter {
     }
 
     fn commit(self) -> Result<VolumeReader, GraftErr> {
-        let page_count = self.snapshot.page_count;
-        let snapshot = self.runtime.storage().read_write().commit(
-            &self.vid,
-            self.snapshot,
-            page_count,
-            self.pages,
-        )?;
-        Ok(VolumeReader::new(self.runtime, self.vid, snapshot))
+        self.commit_inner(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZero;
+
+    use assert_matches::assert_matches;
+
+    use crate::{
+        GraftErr, LogicalErr,
+        core::{PageCount, PageIdx, page::Page},
+        pageidx,
+        testutil::{memory_remote, test_runtime, test_tokio_rt},
+        volume_reader::VolumeRead,
+        volume_writer::VolumeWrite,
+    };
+
+    #[test]
+    fn validate_matches_commit() {
+        let tokio_rt = test_tokio_rt();
+
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+
+        let mut stale = runtime.volume_writer(vid.clone()).unwrap();
+        stale.write_page(pageidx!(1), Page::test_filled(1)).unwrap();
+        stale.validate().unwrap();
+
+        // advance the volume with another writer
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(1), Page::test_filled(2))
+            .unwrap();
+        writer.validate().unwrap();
+        writer.commit().unwrap();
+
+        // validate reports the same error as commit, without writing anything
+        let snapshot = runtime.volume_snapshot(&vid).unwrap();
+        assert_matches!(
+            stale.validate(),
+            Err(GraftErr::Logical(LogicalErr::VolumeConcurrentWrite(_)))
+        );
+        assert!(
+            runtime
+                .volume_snapshot(&vid)
+                .unwrap()
+                .iter()
+                .eq(snapshot.iter())
+        );
+        assert_matches!(
+            stale.commit(),
+            Err(GraftErr::Logical(LogicalErr::VolumeConcurrentWrite(_)))
+        );
+    }
+
+    #[test]
+    fn allocate_pages() {
+        let tokio_rt = test_tokio_rt();
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer
+            .write_page(pageidx!(1), Page::test_filled(1))
+            .unwrap();
+        writer
+            .write_page(pageidx!(2), Page::test_filled(2))
+            .unwrap();
+        writer.commit().unwrap();
+
+        // hide page 2 behind a soft truncation
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer.soft_truncate(PageCount::new(1)).unwrap();
+
+        let first = writer.allocate_pages(NonZero::new(3).unwrap()).unwrap();
+        assert_eq!(first, pageidx!(2)..=pageidx!(4));
+        let second = writer.allocate_pages(NonZero::new(2).unwrap()).unwrap();
+        assert_eq!(second, pageidx!(5)..=pageidx!(6));
+        assert_eq!(writer.page_count(), 6);
+
+        // allocated pages are fresh, even if they were previously hidden
+        assert!(writer.read_page(pageidx!(2)).unwrap().is_empty());
+
+        for pageidx in [pageidx!(3), pageidx!(4), pageidx!(5), pageidx!(6)] {
+            writer
+                .write_page(pageidx, Page::test_filled(pageidx.to_u32() as u8))
+                .unwrap();
+        }
+        let reader = writer.commit().unwrap();
+        assert_eq!(reader.page_count(), 6);
+        assert!(reader.read_page(pageidx!(2)).unwrap().is_empty());
+        assert_eq!(reader.read_page(pageidx!(6)).unwrap(), Page::test_filled(6));
+
+        // allocated pages are written with the Volume's fill page
+        runtime.set_fill_byte(&vid, Some(7)).unwrap();
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        writer.soft_truncate(PageCount::new(1)).unwrap();
+        writer.allocate_pages(NonZero::new(1).unwrap()).unwrap();
+        let reader = writer.commit().unwrap();
+        assert_eq!(reader.read_page(pageidx!(2)).unwrap(), Page::test_filled(7));
+
+        // allocation can't grow the Volume past the maximum page count
+        let mut writer = runtime.volume_writer(vid).unwrap();
+        writer.soft_truncate(PageCount::MAX).unwrap();
+        assert_matches!(
+            writer.allocate_pages(NonZero::new(1).unwrap()),
+            Err(GraftErr::Logical(LogicalErr::VolumeTooLarge {
+                pages: 1,
+                ..
+            }))
+        );
+    }
+
+    #[test]
+    fn max_pages() {
+        let tokio_rt = test_tokio_rt();
+        let runtime = test_runtime(&tokio_rt, memory_remote());
+        let vid = runtime.volume_open(None, None, None).unwrap().vid;
+        runtime.set_default_max_pages(Some(PageCount::new(8)));
+        runtime
+            .set_max_pages(&vid, Some(PageCount::new(4)))
+            .unwrap();
+        assert_eq!(runtime.max_pages(&vid).unwrap(), Some(PageCount::new(4)));
+
+        // writing up to the limit succeeds
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        for i in 1..=4 {
+            writer
+                .write_page(PageIdx::must_new(i), Page::test_filled(i as u8))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+
+        // growing beyond the limit fails without changing the writer
+        let mut writer = runtime.volume_writer(vid.clone()).unwrap();
+        assert_matches!(
+            writer.write_page(pageidx!(6), Page::test_filled(6)),
+            Err(GraftErr::Logical(LogicalErr::VolumeTooLarge {
+                pages: 2,
+                ..
+            }))
+        );
+        assert_matches!(
+            writer.soft_truncate(PageCount::new(5)),
+            Err(GraftErr::Logical(LogicalErr::VolumeTooLarge {
+                pages: 1,
+                ..
+            }))
+        );
+        assert_matches!(
+            writer.allocate_pages(NonZero::new(1).unwrap()),
+            Err(GraftErr::Logical(LogicalErr::VolumeTooLarge { .. }))
+        );
+        assert_eq!(writer.page_count(), 4);
+
+        // truncating below the limit and growing back within it succeeds
+        writer.soft_truncate(PageCount::new(2)).unwrap();
+        writer
+            .write_page(pageidx!(3), Page::test_filled(9))
+            .unwrap();
+        let reader = writer.commit().unwrap();
+        assert_eq!(reader.page_count(), 3);
+        assert_eq!(reader.read_page(pageidx!(3)).unwrap(), Page::test_filled(9));
+
+        // removing the override falls back to the default limit
+        runtime.set_max_pages(&vid, None).unwrap();
+        let mut writer = runtime.volume_writer(vid).unwrap();
+        writer
+            .write_page(pageidx!(8), Page::test_filled(8))
+            .unwrap();
+        assert_matches!(
+            writer.write_page(pageidx!(9), Page::test_filled(9)),
+            Err(GraftErr::Logical(LogicalErr::VolumeTooLarge { .. }))
+        );
+        writer.commit().unwrap();
     }
 }